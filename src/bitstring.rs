@@ -1,64 +1,51 @@
+//! Growable and fixed size strings of bits.
+//!
+//! Bit `i` is stored in word `i / W::BITS` at bit `i % W::BITS` of that word (see [`BitWord`]),
+//! so the order of the bits is the same on every target.
+
 use alloc::alloc::Global;
 use alloc::vec::Vec;
-use core::alloc::Allocator;
-use core::fmt::{Binary, Debug, Formatter};
-use core::ops::*;
-
-// used to work with endianness
-const SHIFT_OP: &dyn Fn(u8, usize) -> u8 = if cfg!(target_endian = "little") {
-    &core::ops::Shl::shl
-} else {
-    &core::ops::Shr::shr
-};
-#[repr(transparent)]
-#[derive(Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
-struct BitContainer(u8);
-
-impl BitContainer {
-    /// sets the bit at `idx` to whatever
-    #[inline]
-    pub fn set(&mut self, idx: usize, val: bool) {
-        if val {
-            self.0 |= SHIFT_OP(1, idx % 8)
-        } else {
-            self.0 &= !SHIFT_OP(1, idx % 8)
-        };
-    }
-    #[inline]
-    pub fn get(&self, idx: usize) -> bool {
-        self.0 & SHIFT_OP(0x01, idx % 8) != 0
-    }
-    #[inline]
-    pub fn toggle(&mut self, index: usize) {
-        self.0 ^= SHIFT_OP(1, index % 8)
-    }
-}
-/*
-impl Debug for BitContainer {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{:?}", self.0)
-    }
-} */
-impl Binary for BitContainer {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{:b}", self.0)
-    }
-}
+use core::alloc::{AllocError, Allocator};
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{Hash, Hasher};
+use core::ops::{Index, RangeBounds};
 
-impl Deref for BitContainer {
-    type Target = u8;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
+use crate::traits::collection::{Capacity, Clear, Collection, Contains};
+use crate::util::resolve_range;
+
+mod io;
+mod iters;
+mod ops;
+mod rank;
+mod slice;
+mod word;
+
+pub use io::{BitOrder, BitReader, BitWriter};
+pub use iters::*;
+pub use rank::{Ones, RankSelect};
+pub use slice::*;
+pub use word::BitWord;
+use word::*;
+
+type Word = usize;
+const WORD_BITS: usize = Word::BITS as usize;
+
+/// A growable string of bits backed by `usize` words.
 pub struct BitString<A: Allocator = Global> {
-    data: Vec<BitContainer, A>,
+    data: Vec<Word, A>,
+    /// the number of bits. every bit past `len` in the last word is always zero.
+    len: usize,
 }
 
 impl BitString {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Creates an empty bit string that can hold `bits` bits without reallocating.
+    pub fn with_capacity(bits: usize) -> Self {
+        Self::with_capacity_in(bits, Global).expect("failed to allocate")
+    }
 }
 // clippy is wrong. this cant be derived because of `A`
 #[allow(clippy::derivable_impls)]
@@ -66,34 +53,419 @@ impl Default for BitString {
     fn default() -> Self {
         Self {
             data: Default::default(),
+            len: 0,
         }
     }
 }
 
 impl<A: Allocator> BitString<A> {
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            data: Vec::new_in(alloc),
+            len: 0,
+        }
+    }
+
+    pub fn with_capacity_in(bits: usize, alloc: A) -> Result<Self, AllocError> {
+        let mut s = Self::new_in(alloc);
+        s.try_reserve(bits)?;
+        Ok(s)
+    }
+
+    /// Makes sure that `additional` more bits can be pushed without reallocating.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocError> {
+        let needed = words_for::<Word>(self.len + additional).saturating_sub(self.data.len());
+        self.data.try_reserve(needed).map_err(|_| AllocError)
+    }
+
+    /// Returns the number of bits in the string.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns how many bits the string can hold before it needs to reallocate.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity() * WORD_BITS
+    }
+
+    /// Returns a reference to the underlying allocator.
+    pub fn allocator(&self) -> &A {
+        self.data.allocator()
+    }
+
+    /// Appends a bit to the end of the string.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitString;
+    /// let mut bits = BitString::new();
+    /// bits.push(true);
+    /// bits.push(false);
+    /// assert_eq!(bits.len(), 2);
+    /// assert_eq!(bits.get(0), Some(true));
+    /// ```
+    pub fn push(&mut self, bit: bool) {
+        self.try_push(bit).expect("failed to allocate")
+    }
+
+    pub fn try_push(&mut self, bit: bool) -> Result<(), AllocError> {
+        if self.len % WORD_BITS == 0 {
+            self.try_reserve(1)?;
+            self.data.push(0);
+        }
+        self.data[self.len / WORD_BITS].set_bit(self.len % WORD_BITS, bit);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes the last bit from the string.
+    pub fn pop(&mut self) -> Option<bool> {
+        let idx = self.len.checked_sub(1)?;
+        let word = &mut self.data[idx / WORD_BITS];
+        let bit = word.get_bit(idx % WORD_BITS);
+        word.set_bit(idx % WORD_BITS, false);
+        self.len = idx;
+        if idx % WORD_BITS == 0 {
+            self.data.pop();
+        }
+        Some(bit)
+    }
+
+    #[inline]
+    pub fn get(&self, idx: usize) -> Option<bool> {
+        (idx < self.len).then(|| self.data[idx / WORD_BITS].get_bit(idx % WORD_BITS))
+    }
+
+    /// Sets the bit at `idx` to `val`.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    #[inline]
+    pub fn set(&mut self, idx: usize, val: bool) {
+        assert!(idx < self.len, "index out of bounds");
+        self.data[idx / WORD_BITS].set_bit(idx % WORD_BITS, val)
+    }
+
+    /// Flips the bit at `idx`.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    #[inline]
+    pub fn toggle(&mut self, idx: usize) {
+        assert!(idx < self.len, "index out of bounds");
+        self.data[idx / WORD_BITS].toggle_bit(idx % WORD_BITS)
+    }
+
+    /// Resizes the string to `new_len` bits, filling any new bits with `val`.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitString;
+    /// let mut bits = BitString::new();
+    /// bits.resize(100, true);
+    /// assert_eq!(bits.len(), 100);
+    /// assert!(bits.iter().all(|b| b));
+    /// bits.resize(10, false);
+    /// assert_eq!(bits.len(), 10);
+    /// ```
+    pub fn resize(&mut self, new_len: usize, val: bool) {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return;
+        }
+        self.try_reserve(new_len - self.len)
+            .expect("failed to allocate");
+        self.data.resize(words_for::<Word>(new_len), 0);
+        fill(&mut self.data, self.len, new_len, val);
+        self.len = new_len;
+    }
+
+    /// Shortens the string to `len` bits. Does nothing if the string is already shorter.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        self.data.truncate(words_for::<Word>(len));
+        if len % WORD_BITS != 0 {
+            *self.data.last_mut().unwrap() &= Word::mask(len % WORD_BITS);
+        }
+        self.len = len;
+    }
+
+    /// Removes every bit from the string.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.len = 0;
+    }
+
+    /// Appends every bit in `other` to the end of the string.
+    pub fn extend_from_bitslice<W: BitWord>(&mut self, other: BitSlice<'_, W>) {
+        let old_len = self.len;
+        let new_len = old_len + other.len();
+        self.try_reserve(other.len()).expect("failed to allocate");
+        self.data.resize(words_for::<Word>(new_len), 0);
+        copy(
+            other.words,
+            other.start,
+            &mut self.data,
+            old_len,
+            other.len(),
+        );
+        self.len = new_len;
+    }
+
+    /// Moves every bit in `other` to the end of `self`, leaving `other` empty.
+    pub fn append<B: Allocator>(&mut self, other: &mut BitString<B>) {
+        self.extend_from_bitslice(other.as_bitslice());
+        other.clear();
+    }
+
+    /// Borrows the whole string as a [`BitSlice`].
+    pub fn as_bitslice(&self) -> BitSlice<'_> {
+        BitSlice {
+            words: &self.data,
+            start: 0,
+            len: self.len,
+        }
+    }
+
+    /// Borrows the whole string as a [`BitSliceMut`].
+    pub fn as_mut_bitslice(&mut self) -> BitSliceMut<'_> {
+        BitSliceMut {
+            words: &mut self.data,
+            start: 0,
+            len: self.len,
+        }
+    }
+
+    /// Borrows the bits in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BitSlice<'_> {
+        self.as_bitslice().slice(range)
+    }
+
+    /// Mutably borrows the bits in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn slice_mut<R: RangeBounds<usize>>(&mut self, range: R) -> BitSliceMut<'_> {
+        let range = resolve_range(range, self.len).expect("range out of bounds");
+        BitSliceMut {
+            words: &mut self.data,
+            start: range.start,
+            len: range.len(),
+        }
+    }
+
+    /// Counts the set bits.
+    pub fn count_ones(&self) -> usize {
+        self.as_bitslice().count_ones()
+    }
+
+    /// Counts the unset bits.
+    pub fn count_zeros(&self) -> usize {
+        self.as_bitslice().count_zeros()
+    }
+
+    /// Counts the zeros at the end of the string (the highest indices).
+    pub fn leading_zeros(&self) -> usize {
+        self.as_bitslice().leading_zeros()
+    }
+
+    /// Counts the zeros at the start of the string (the lowest indices).
+    pub fn trailing_zeros(&self) -> usize {
+        self.as_bitslice().trailing_zeros()
+    }
+
+    /// Returns the index of the first set bit.
+    pub fn first_one(&self) -> Option<usize> {
+        self.as_bitslice().first_one()
+    }
+
+    /// Returns the index of the first set bit at or after `from`.
+    pub fn next_one(&self, from: usize) -> Option<usize> {
+        self.as_bitslice().next_one(from)
+    }
+
+    /// Iterates over the indices of the set bits in increasing order.
+    pub fn ones(&self) -> Ones<'_> {
+        self.as_bitslice().ones()
+    }
+
+    /// Counts the set bits in `0..idx`. See [`BitSlice::rank`].
+    pub fn rank(&self, idx: usize) -> usize {
+        self.as_bitslice().rank(idx)
+    }
+
+    /// Returns the index of the `k`th set bit. See [`BitSlice::select`].
+    pub fn select(&self, k: usize) -> Option<usize> {
+        self.as_bitslice().select(k)
+    }
+
+    /// Builds an index for fast `rank` and `select` queries.
+    pub fn rank_select(&self) -> RankSelect<'_> {
+        RankSelect::new(self.as_bitslice())
+    }
+
+    /// The words backing the string. Bits past `len` in the last word are always zero.
+    pub fn as_words(&self) -> &[Word] {
+        &self.data
+    }
+
     pub fn iter(&self) -> BitStringIterator<'_> {
         BitStringIterator {
-            inner: self.data.as_slice(),
+            inner: &self.data,
             idx: 0,
+            end: self.len,
         }
     }
+
     pub fn iter_mut(&mut self) -> BitStringIteratorMut<'_> {
-        BitStringIteratorMut {
-            inner: self.data.as_mut_slice(),
-            idx: 0,
+        let len = self.len;
+        BitStringIteratorMut::new(&mut self.data, 0, len)
+    }
+}
+
+/// Bits aren't addressable so there's no `Iterable`, use [`BitString::iter`] instead.
+impl<A: Allocator> Collection for BitString<A> {
+    type Item = bool;
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<A: Allocator> Clear for BitString<A> {
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<A: Allocator> Contains for BitString<A> {
+    fn contains(&self, item: &bool) -> bool {
+        self.iter().any(|bit| bit == *item)
+    }
+}
+
+impl<A: Allocator> Capacity for BitString<A> {
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<A: Allocator + Clone> BitString<A> {
+    /// Splits the string in two at `at`. `self` keeps the bits in `0..at` and the rest are returned.
+    ///
+    /// # Panics
+    /// Panics if `at > len`.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitString;
+    /// let mut bits: BitString = [true, false, true, true].into_iter().collect();
+    /// let tail = bits.split_off(1);
+    /// assert_eq!(bits.iter().collect::<Vec<_>>(), [true]);
+    /// assert_eq!(tail.iter().collect::<Vec<_>>(), [false, true, true]);
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "`at` out of bounds");
+        let mut other = Self::with_capacity_in(self.len - at, self.allocator().clone())
+            .expect("failed to allocate");
+        other.extend_from_bitslice(BitSlice {
+            words: &self.data,
+            start: at,
+            len: self.len - at,
+        });
+        self.truncate(at);
+        other
+    }
+}
+
+impl<A: Allocator + Clone> Clone for BitString<A> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<A: Allocator, B: Allocator> PartialEq<BitString<B>> for BitString<A> {
+    fn eq(&self, other: &BitString<B>) -> bool {
+        // the bits past `len` are always zero so the words can be compared directly
+        self.len == other.len && self.data[..] == other.data[..]
+    }
+}
+
+impl<A: Allocator> Eq for BitString<A> {}
+
+impl<A: Allocator> Hash for BitString<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        self.data[..].hash(state);
+    }
+}
+
+impl<A: Allocator> Debug for BitString<A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "BitString {{ len: {}, bits: ", self.len)?;
+        for bit in self.iter() {
+            write!(f, "{}", bit as u8)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+impl<A: Allocator> Extend<bool> for BitString<A> {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.try_reserve(iter.size_hint().0)
+            .expect("failed to allocate");
+        for bit in iter {
+            self.push(bit);
         }
     }
 }
 
+impl FromIterator<bool> for BitString {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<A: Allocator> Index<usize> for BitString<A> {
+    type Output = bool;
+    fn index(&self, index: usize) -> &Self::Output {
+        if self.get(index).expect("index out of bounds") {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+impl<'a, A: Allocator> IntoIterator for &'a BitString<A> {
+    type Item = bool;
+    type IntoIter = BitStringIterator<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A string of `N * 8` bits stored inline.
 pub struct FixedBitString<const N: usize> {
-    inner: [BitContainer; N],
+    inner: [u8; N],
 }
 
 impl<const N: usize> Default for FixedBitString<N> {
     fn default() -> Self {
-        FixedBitString {
-            inner: [BitContainer::default(); N],
-        }
+        FixedBitString { inner: [0; N] }
     }
 }
 
@@ -103,148 +475,200 @@ impl<const N: usize> FixedBitString<N> {
         Default::default()
     }
 
+    /// Returns the number of bits in the string, this is always `N * 8`.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        N * 8
+    }
+
     #[inline]
     pub fn toggle(&mut self, index: usize) {
-        self.inner[index.div_floor(8)].toggle(index)
+        self.inner[index / 8].toggle_bit(index % 8)
     }
 
     #[inline]
     pub fn reset(&mut self) {
-        self.inner = [BitContainer::default(); N];
+        self.inner = [0; N];
     }
 
+    /// Checks if no bits are set.
     #[inline]
     pub fn is_empty(&self) -> bool {
-        self.inner.iter().all(|f| f.deref() == &0)
+        self.inner.iter().all(|f| *f == 0)
     }
 
     /// sets the bit at `idx` to whatever
     #[inline]
     pub fn set(&mut self, idx: usize, val: bool) {
-        self.inner[idx.div_floor(8)].set(idx, val)
+        self.inner[idx / 8].set_bit(idx % 8, val)
     }
 
     #[inline]
     pub fn get(&self, idx: usize) -> Option<bool> {
-        (idx < N * 8).then(|| self.inner[idx.div_floor(8)].get(idx))
+        (idx < N * 8).then(|| self.inner[idx / 8].get_bit(idx % 8))
     }
 
     #[inline]
-    pub fn iter(&self) -> BitStringIterator<'_> {
+    pub fn iter(&self) -> BitStringIterator<'_, u8> {
         BitStringIterator {
             inner: self.inner.as_slice(),
             idx: 0,
+            end: N * 8,
         }
     }
 
-    pub fn copy_from_bool_slice(&mut self, idx: usize, slice: &[bool]) {
-        BitStringIteratorMut {
-            idx,
-            inner: self.inner.as_mut_slice(),
-        }
-        .zip(slice.iter())
-        .for_each(|(mut a, b)| a.set(*b));
+    #[inline]
+    pub fn iter_mut(&mut self) -> BitStringIteratorMut<'_, u8> {
+        BitStringIteratorMut::new(self.inner.as_mut_slice(), 0, N * 8)
     }
-    pub fn copy_from_slice(&mut self, idx: usize, slice: &[u8]) {
-        // not sure if this is right :/. too tired.
-        if N - idx > slice.len() {
-            panic!("out of bounds");
-        }
-        // this transmute should be fine since Bitcontainer is `#[repr(transparent)]`
-        let slice: &[BitContainer] = unsafe { core::mem::transmute(slice) };
-        self.inner[idx..idx + slice.len()].copy_from_slice(slice);
+
+    /// Borrows the whole string as a [`BitSlice`].
+    pub fn as_bitslice(&self) -> BitSlice<'_, u8> {
+        BitSlice::from_words(&self.inner)
     }
-}
 
-impl<const N: usize> Debug for FixedBitString<N> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        let Self { inner } = self;
-        write!(f, "FixedBitString<{N}> {{ data: [")?;
-        let mut iter = inner.iter();
-        if let Some(i) = iter.next() {
-            write!(f, "{:b}", *i)?;
-        }
-        for i in iter {
-            write!(f, ",{:b}", *i)?;
-        }
-        write!(f, "] }}")
+    /// Borrows the whole string as a [`BitSliceMut`].
+    pub fn as_mut_bitslice(&mut self) -> BitSliceMut<'_, u8> {
+        BitSliceMut::from_words(&mut self.inner)
     }
-}
 
-pub struct BitStringIterator<'a> {
-    inner: &'a [BitContainer],
-    idx: usize,
-}
-impl<'a> Iterator for BitStringIterator<'a> {
-    type Item = bool;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.idx > self.inner.len() * 8 {
-            return None;
+    /// Borrows the bits in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BitSlice<'_, u8> {
+        self.as_bitslice().slice(range)
+    }
+
+    /// Mutably borrows the bits in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn slice_mut<R: RangeBounds<usize>>(&mut self, range: R) -> BitSliceMut<'_, u8> {
+        let range = resolve_range(range, N * 8).expect("range out of bounds");
+        BitSliceMut {
+            words: &mut self.inner,
+            start: range.start,
+            len: range.len(),
         }
-        let r = self.inner[self.idx.div_floor(8)].get(self.idx);
-        self.idx += 1;
-        Some(r)
     }
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = (self.inner.len() * 8) - self.idx;
-        (len, Some(len))
+
+    /// Counts the set bits.
+    pub fn count_ones(&self) -> usize {
+        self.as_bitslice().count_ones()
+    }
+
+    /// Counts the unset bits.
+    pub fn count_zeros(&self) -> usize {
+        self.as_bitslice().count_zeros()
     }
-}
 
-impl<'a> ExactSizeIterator for BitStringIterator<'a> {}
+    /// Counts the zeros at the end of the string (the highest indices).
+    pub fn leading_zeros(&self) -> usize {
+        self.as_bitslice().leading_zeros()
+    }
 
-pub struct BitStringIteratorMut<'a> {
-    idx: usize,
-    inner: &'a mut [BitContainer],
-}
+    /// Counts the zeros at the start of the string (the lowest indices).
+    pub fn trailing_zeros(&self) -> usize {
+        self.as_bitslice().trailing_zeros()
+    }
 
-impl<'a> Iterator for BitStringIteratorMut<'a> {
-    type Item = BitRefMut<'a>;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.idx > self.inner.len() * 8 {
-            return None;
-        }
-        let inner: &'a mut BitContainer =
-            unsafe { core::mem::transmute(self.inner.get_mut(self.idx).unwrap()) };
+    /// Returns the index of the first set bit.
+    pub fn first_one(&self) -> Option<usize> {
+        self.as_bitslice().first_one()
+    }
 
-        let r = BitRefMut {
-            inner,
-            index: (self.idx % 8) as u8,
-        };
-        self.idx += 1;
-        Some(r)
+    /// Returns the index of the first set bit at or after `from`.
+    pub fn next_one(&self, from: usize) -> Option<usize> {
+        self.as_bitslice().next_one(from)
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = (self.inner.len() * 8) - self.idx;
-        (len, Some(len))
+    /// Iterates over the indices of the set bits in increasing order.
+    pub fn ones(&self) -> Ones<'_, u8> {
+        self.as_bitslice().ones()
+    }
+
+    /// Counts the set bits in `0..idx`. See [`BitSlice::rank`].
+    pub fn rank(&self, idx: usize) -> usize {
+        self.as_bitslice().rank(idx)
+    }
+
+    /// Returns the index of the `k`th set bit. See [`BitSlice::select`].
+    pub fn select(&self, k: usize) -> Option<usize> {
+        self.as_bitslice().select(k)
+    }
+
+    /// Builds an index for fast `rank` and `select` queries.
+    pub fn rank_select(&self) -> RankSelect<'_, u8> {
+        RankSelect::new(self.as_bitslice())
+    }
+
+    /// The bytes backing the string, bit `i` is bit `i % 8` of byte `i / 8`.
+    pub fn as_bytes(&self) -> &[u8; N] {
+        &self.inner
+    }
+
+    pub fn copy_from_bool_slice(&mut self, idx: usize, slice: &[bool]) {
+        BitStringIteratorMut::new(self.inner.as_mut_slice(), idx, N * 8)
+            .zip(slice.iter())
+            .for_each(|(mut a, b)| a.set(*b));
+    }
+
+    /// Copies `slice` into the bytes starting at byte `idx`.
+    ///
+    /// # Panics
+    /// Panics if `idx + slice.len() > N`.
+    pub fn copy_from_slice(&mut self, idx: usize, slice: &[u8]) {
+        if idx + slice.len() > N {
+            panic!("out of bounds");
+        }
+        self.inner[idx..idx + slice.len()].copy_from_slice(slice);
     }
 }
-impl<'a> ExactSizeIterator for BitStringIteratorMut<'a> {}
 
-pub struct BitRefMut<'a> {
-    inner: &'a mut BitContainer,
-    index: u8,
+impl<const N: usize> Index<usize> for FixedBitString<N> {
+    type Output = bool;
+    fn index(&self, index: usize) -> &Self::Output {
+        if self.get(index).expect("index out of bounds") {
+            &true
+        } else {
+            &false
+        }
+    }
 }
 
-impl<'a> BitRefMut<'a> {
-    #[inline]
-    pub fn get(&self) -> bool {
-        self.inner.get(self.index as usize)
+impl<const N: usize> Clone for FixedBitString<N> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner }
     }
-    #[inline]
-    pub fn set(&mut self, val: bool) {
-        self.inner.set(self.index as usize, val)
+}
+
+impl<const N: usize> PartialEq for FixedBitString<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
     }
-    #[inline]
-    pub fn toggle(&mut self) {
-        self.inner.toggle(self.index as usize)
+}
+
+impl<const N: usize> Eq for FixedBitString<N> {}
+
+impl<const N: usize> Hash for FixedBitString<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
     }
 }
 
-impl<'a> From<BitRefMut<'a>> for bool {
-    fn from(x: BitRefMut<'a>) -> Self {
-        x.get()
+impl<const N: usize> Debug for FixedBitString<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let Self { inner } = self;
+        write!(f, "FixedBitString<{N}> {{ data: [")?;
+        let mut iter = inner.iter();
+        if let Some(i) = iter.next() {
+            write!(f, "{:b}", *i)?;
+        }
+        for i in iter {
+            write!(f, ",{:b}", *i)?;
+        }
+        write!(f, "] }}")
     }
 }
 
@@ -286,4 +710,106 @@ mod tests {
         println!("{a:?}");
         assert!(!a.get(2).unwrap());
     }
+
+    #[test]
+    fn fixed_bit_string_order() {
+        let mut a = FixedBitString::<2>::new();
+        a.set(0, true);
+        a.set(9, true);
+        assert_eq!(a.as_bytes(), &[0b1, 0b10]);
+        assert_eq!(a.iter().len(), 16);
+        for mut bit in a.iter_mut() {
+            bit.toggle();
+        }
+        assert_eq!(a.as_bytes(), &[!0b1, !0b10]);
+    }
+
+    #[test]
+    fn push_pop() {
+        let mut bits = BitString::new();
+        let v = (0..300).map(|i| i % 3 == 0).collect::<Vec<_>>();
+        for b in v.iter() {
+            bits.push(*b);
+        }
+        assert_eq!(bits.len(), 300);
+        assert_eq!(bits.iter().collect::<Vec<_>>(), v);
+        for b in v.iter().rev() {
+            assert_eq!(bits.pop(), Some(*b));
+        }
+        assert_eq!(bits.pop(), None);
+        assert!(bits.as_words().is_empty());
+    }
+
+    #[test]
+    fn set_toggle() {
+        let mut bits = BitString::new();
+        bits.resize(130, false);
+        bits.set(129, true);
+        bits.toggle(64);
+        assert_eq!(bits.get(129), Some(true));
+        assert_eq!(bits.get(64), Some(true));
+        assert_eq!(bits.get(130), None);
+        assert_eq!(bits.iter().filter(|b| *b).count(), 2);
+    }
+
+    #[test]
+    fn resize_truncate_keeps_tail_clear() {
+        let mut bits = BitString::new();
+        bits.resize(100, true);
+        bits.truncate(70);
+        bits.resize(100, false);
+        assert_eq!(bits.iter().filter(|b| *b).count(), 70);
+        let mut other = BitString::new();
+        other.resize(70, true);
+        other.resize(100, false);
+        assert_eq!(bits, other);
+    }
+
+    #[test]
+    fn split_off_append() {
+        let v = (0..200).map(|i| i % 7 < 3).collect::<Vec<_>>();
+        let mut bits: BitString = v.iter().copied().collect();
+        let mut tail = bits.split_off(77);
+        assert_eq!(bits.iter().collect::<Vec<_>>(), v[..77]);
+        assert_eq!(tail.iter().collect::<Vec<_>>(), v[77..]);
+        bits.append(&mut tail);
+        assert!(tail.is_empty());
+        assert_eq!(bits.iter().collect::<Vec<_>>(), v);
+    }
+
+    #[test]
+    fn extend_from_fixed() {
+        let mut fixed = FixedBitString::<3>::new();
+        fixed.copy_from_slice(0, &[0xAB, 0xCD, 0xEF]);
+        let mut bits = BitString::new();
+        bits.push(true);
+        bits.extend_from_bitslice(fixed.as_bitslice());
+        assert_eq!(bits.len(), 25);
+        assert_eq!(
+            bits.iter().skip(1).collect::<Vec<_>>(),
+            fixed.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(bits.get(0), Some(true));
+    }
+
+    #[test]
+    fn failed_push_keeps_bits() {
+        use crate::fault::{Fault, FaultyAlloc};
+        let alloc = FaultyAlloc::with_fault(Fault::Nth(0));
+        assert!(BitString::with_capacity_in(10, alloc.clone()).is_err());
+
+        let mut bits = BitString::new_in(alloc.clone());
+        bits.try_push(true).unwrap();
+        let full = bits.capacity();
+        for i in 1..full {
+            bits.try_push(i % 3 == 0).unwrap();
+        }
+        // the next push needs another word
+        alloc.set_fault(Fault::From(0));
+        assert!(bits.try_push(true).is_err());
+        assert_eq!(bits.len(), full);
+        assert!((0..full).all(|i| bits.get(i) == Some(i % 3 == 0)));
+        drop(bits);
+        assert_eq!(alloc.live(), 0);
+    }
 }
@@ -0,0 +1,408 @@
+//! Reading and writing integers bit by bit, along with a few universal codes.
+//!
+//! A [`BitWriter`] appends to a [`BitString`] and a [`BitReader`] consumes a [`BitSlice`] from
+//! the front. Both of them have a [`BitOrder`] that decides which end of an integer is written
+//! first, a reader has to use the same order as the writer that produced the bits.
+//!
+//! Every code is built on top of `write_bits`/`read_bits` so it is laid out according to the
+//! order as well:
+//! - unary: `n` zeros followed by a one
+//! - Elias-gamma (`x >= 1`): `floor(log2(x))` in unary, then every bit of `x` below the top one
+//! - Elias-delta (`x >= 1`): `floor(log2(x)) + 1` in Elias-gamma, then every bit of `x` below the top one
+//! - Golomb-Rice with parameter `k`: `x >> k` in unary, then the lowest `k` bits of `x`
+//!
+//! With [`BitOrder::MsbFirst`] these are exactly the textbook codes.
+
+use super::{word::*, BitSlice, BitString, Word};
+use alloc::alloc::Global;
+use core::alloc::{AllocError, Allocator};
+
+/// Which bit of an integer is written first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BitOrder {
+    /// The least significant bit goes first, so `write_bits` stores integers the same way
+    /// [`BitSliceMut::store`](super::BitSliceMut::store) does.
+    #[default]
+    LsbFirst,
+    /// The most significant bit goes first, like reading the integer in binary from left to right.
+    MsbFirst,
+}
+
+/// Appends integers to the end of a [`BitString`].
+///
+/// # Examples
+/// ```
+/// use hash_table::bitstring::{BitOrder, BitReader, BitString, BitWriter};
+/// let mut bits = BitString::new();
+/// let mut writer = BitWriter::with_order(&mut bits, BitOrder::MsbFirst);
+/// writer.write_bits(0b101, 3);
+/// writer.write_gamma(9);
+/// writer.write_rice(21, 2);
+///
+/// let mut reader = BitReader::with_order(bits.as_bitslice(), BitOrder::MsbFirst);
+/// assert_eq!(reader.read_bits(3), Some(0b101));
+/// assert_eq!(reader.read_gamma(), Some(9));
+/// assert_eq!(reader.read_rice(2), Some(21));
+/// assert!(reader.is_empty());
+/// ```
+pub struct BitWriter<'a, A: Allocator = Global> {
+    bits: &'a mut BitString<A>,
+    order: BitOrder,
+}
+
+impl<'a, A: Allocator> BitWriter<'a, A> {
+    /// Creates a writer that writes the least significant bit first.
+    pub fn new(bits: &'a mut BitString<A>) -> Self {
+        Self::with_order(bits, BitOrder::LsbFirst)
+    }
+
+    pub fn with_order(bits: &'a mut BitString<A>, order: BitOrder) -> Self {
+        Self { bits, order }
+    }
+
+    #[inline]
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Returns the bits written so far, including the ones that were in the string before.
+    pub fn bits(&self) -> &BitString<A> {
+        self.bits
+    }
+
+    pub fn write_bit(&mut self, bit: bool) {
+        self.bits.push(bit)
+    }
+
+    /// Writes the lowest `width` bits of `value`, the other bits are ignored.
+    ///
+    /// # Panics
+    /// Panics if `width > 64` or if allocating fails.
+    pub fn write_bits(&mut self, value: u64, width: usize) {
+        self.try_write_bits(value, width)
+            .expect("failed to allocate")
+    }
+
+    /// Writes the lowest `width` bits of `value`, leaving the string untouched if allocating fails.
+    ///
+    /// # Panics
+    /// Panics if `width > 64`.
+    pub fn try_write_bits(&mut self, value: u64, width: usize) -> Result<(), AllocError> {
+        assert!(width <= 64, "`width` is larger than 64");
+        let value = match self.order {
+            BitOrder::LsbFirst => value,
+            BitOrder::MsbFirst => reverse_low(value, width),
+        };
+        let start = self.bits.len;
+        self.bits.try_reserve(width)?;
+        self.bits.data.resize(words_for::<Word>(start + width), 0);
+        store(&mut self.bits.data, start, width, value);
+        self.bits.len = start + width;
+        Ok(())
+    }
+
+    /// Writes `n` zeros followed by a one.
+    pub fn write_unary(&mut self, n: u64) {
+        let len = self.bits.len;
+        self.bits.resize(len + n as usize, false);
+        self.bits.push(true);
+    }
+
+    /// Writes `x` in the Elias-gamma code, which takes `2 * floor(log2(x)) + 1` bits.
+    ///
+    /// # Panics
+    /// Panics if `x == 0`.
+    pub fn write_gamma(&mut self, x: u64) {
+        assert!(x != 0, "zero can't be Elias-gamma coded");
+        let n = 63 - x.leading_zeros() as usize;
+        self.write_unary(n as u64);
+        self.write_bits(x, n);
+    }
+
+    /// Writes `x` in the Elias-delta code, which is shorter than Elias-gamma for large values.
+    ///
+    /// # Panics
+    /// Panics if `x == 0`.
+    pub fn write_delta(&mut self, x: u64) {
+        assert!(x != 0, "zero can't be Elias-delta coded");
+        let n = 63 - x.leading_zeros() as usize;
+        self.write_gamma(n as u64 + 1);
+        self.write_bits(x, n);
+    }
+
+    /// Writes `x` in the Golomb-Rice code with divisor `2^k`.
+    ///
+    /// # Panics
+    /// Panics if `k > 64`.
+    pub fn write_rice(&mut self, x: u64, k: u32) {
+        assert!(k <= 64, "`k` is larger than 64");
+        self.write_unary(x.checked_shr(k).unwrap_or(0));
+        self.write_bits(x, k as usize);
+    }
+}
+
+/// Reads integers from the front of a [`BitSlice`].
+///
+/// Every read returns `None` and doesn't move the reader if there aren't enough bits left, or if
+/// a code doesn't fit in a `u64`.
+pub struct BitReader<'a, W: BitWord = usize> {
+    bits: BitSlice<'a, W>,
+    pos: usize,
+    order: BitOrder,
+}
+
+impl<'a, W: BitWord> BitReader<'a, W> {
+    /// Creates a reader that expects the least significant bit first.
+    pub fn new(bits: BitSlice<'a, W>) -> Self {
+        Self::with_order(bits, BitOrder::LsbFirst)
+    }
+
+    pub fn with_order(bits: BitSlice<'a, W>, order: BitOrder) -> Self {
+        Self {
+            bits,
+            pos: 0,
+            order,
+        }
+    }
+
+    #[inline]
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Returns how many bits have been read.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns how many bits are left.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.bits.len - self.pos
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Returns the bits that haven't been read yet.
+    pub fn rest(&self) -> BitSlice<'a, W> {
+        self.bits.slice(self.pos..)
+    }
+
+    /// Skips `n` bits, returns `None` if there aren't enough left.
+    pub fn skip(&mut self, n: usize) -> Option<()> {
+        (n <= self.remaining()).then(|| self.pos += n)
+    }
+
+    pub fn read_bit(&mut self) -> Option<bool> {
+        let bit = self.bits.get(self.pos)?;
+        self.pos += 1;
+        Some(bit)
+    }
+
+    /// Reads the next `width` bits without consuming them.
+    ///
+    /// # Panics
+    /// Panics if `width > 64`.
+    pub fn peek_bits(&self, width: usize) -> Option<u64> {
+        assert!(width <= 64, "`width` is larger than 64");
+        if width > self.remaining() {
+            return None;
+        }
+        let value = load(self.bits.words, self.bits.start + self.pos, width);
+        Some(match self.order {
+            BitOrder::LsbFirst => value,
+            BitOrder::MsbFirst => reverse_low(value, width),
+        })
+    }
+
+    /// Reads `width` bits written by [`BitWriter::write_bits`].
+    ///
+    /// # Panics
+    /// Panics if `width > 64`.
+    pub fn read_bits(&mut self, width: usize) -> Option<u64> {
+        let value = self.peek_bits(width)?;
+        self.pos += width;
+        Some(value)
+    }
+
+    /// runs `f`, rewinding the reader if it fails
+    fn atomic<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let pos = self.pos;
+        let r = f(self);
+        if r.is_none() {
+            self.pos = pos;
+        }
+        r
+    }
+
+    /// Reads a number written by [`BitWriter::write_unary`].
+    pub fn read_unary(&mut self) -> Option<u64> {
+        let one = self.bits.next_one(self.pos)?;
+        let n = (one - self.pos) as u64;
+        self.pos = one + 1;
+        Some(n)
+    }
+
+    /// Reads a number written by [`BitWriter::write_gamma`].
+    pub fn read_gamma(&mut self) -> Option<u64> {
+        self.atomic(|r| {
+            let n = r.read_unary()?;
+            if n >= 64 {
+                return None;
+            }
+            Some((1 << n) | r.read_bits(n as usize)?)
+        })
+    }
+
+    /// Reads a number written by [`BitWriter::write_delta`].
+    pub fn read_delta(&mut self) -> Option<u64> {
+        self.atomic(|r| {
+            let n = r.read_gamma()? - 1;
+            if n >= 64 {
+                return None;
+            }
+            Some((1 << n) | r.read_bits(n as usize)?)
+        })
+    }
+
+    /// Reads a number written by [`BitWriter::write_rice`] with the same `k`.
+    ///
+    /// # Panics
+    /// Panics if `k > 64`.
+    pub fn read_rice(&mut self, k: u32) -> Option<u64> {
+        assert!(k <= 64, "`k` is larger than 64");
+        self.atomic(|r| {
+            let q = r.read_unary()?;
+            let low = r.read_bits(k as usize)?;
+            if q != 0 && (k >= 64 || q.leading_zeros() < k) {
+                return None;
+            }
+            Some(q.checked_shl(k).unwrap_or(0) | low)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::FixedBitString;
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    fn to_str(b: &BitString) -> String {
+        b.iter().map(|b| if b { '1' } else { '0' }).collect()
+    }
+
+    #[test]
+    fn bit_orders() {
+        let mut bits = BitString::new();
+        BitWriter::new(&mut bits).write_bits(0b1101, 4);
+        BitWriter::with_order(&mut bits, BitOrder::MsbFirst).write_bits(0b1101, 4);
+        assert_eq!(to_str(&bits), "10111101");
+
+        let mut lsb = BitReader::new(bits.as_bitslice());
+        assert_eq!(lsb.read_bits(4), Some(0b1101));
+        assert_eq!(lsb.read_bits(4), Some(0b1011));
+        assert_eq!(lsb.read_bits(1), None);
+        let mut msb = BitReader::with_order(bits.as_bitslice(), BitOrder::MsbFirst);
+        assert_eq!(msb.read_bits(8), Some(0b1011_1101));
+    }
+
+    #[test]
+    fn textbook_codes() {
+        let mut bits = BitString::new();
+        let mut w = BitWriter::with_order(&mut bits, BitOrder::MsbFirst);
+        w.write_gamma(9);
+        assert_eq!(to_str(w.bits()), "0001001");
+        bits.clear();
+        let mut w = BitWriter::with_order(&mut bits, BitOrder::MsbFirst);
+        w.write_delta(9);
+        assert_eq!(to_str(w.bits()), "00100001");
+        bits.clear();
+        let mut w = BitWriter::with_order(&mut bits, BitOrder::MsbFirst);
+        w.write_rice(9, 2);
+        assert_eq!(to_str(w.bits()), "00101");
+    }
+
+    #[test]
+    fn round_trip() {
+        let values = (1..300u64)
+            .chain([u32::MAX as u64, u64::MAX - 1, u64::MAX, 1 << 63])
+            .collect::<Vec<_>>();
+        for order in [BitOrder::LsbFirst, BitOrder::MsbFirst] {
+            let mut bits = BitString::new();
+            let mut w = BitWriter::with_order(&mut bits, order);
+            for &x in &values {
+                w.write_gamma(x);
+                w.write_delta(x);
+                if x < 1 << 20 {
+                    w.write_rice(x, 3);
+                }
+                w.write_bits(x, 64);
+                w.write_bits(x, 7);
+            }
+            w.write_rice(u64::MAX, 64);
+            w.write_unary(100);
+
+            let mut r = BitReader::with_order(bits.as_bitslice(), order);
+            for &x in &values {
+                assert_eq!(r.read_gamma(), Some(x));
+                assert_eq!(r.read_delta(), Some(x));
+                if x < 1 << 20 {
+                    assert_eq!(r.read_rice(3), Some(x));
+                }
+                assert_eq!(r.read_bits(64), Some(x));
+                assert_eq!(r.read_bits(7), Some(x & 0x7f));
+            }
+            assert_eq!(r.read_rice(64), Some(u64::MAX));
+            assert_eq!(r.read_unary(), Some(100));
+            assert!(r.is_empty());
+        }
+    }
+
+    #[test]
+    fn truncated_reads_dont_move() {
+        let mut bits = BitString::new();
+        BitWriter::new(&mut bits).write_gamma(1000);
+        bits.pop();
+        let mut r = BitReader::new(bits.as_bitslice());
+        assert_eq!(r.read_gamma(), None);
+        assert_eq!(r.position(), 0);
+        assert_eq!(r.read_rice(0), Some(9));
+
+        let zeros = FixedBitString::<16>::new();
+        let mut r = BitReader::new(zeros.as_bitslice());
+        assert_eq!(r.read_unary(), None);
+        assert_eq!(r.read_delta(), None);
+        assert_eq!(r.remaining(), 128);
+    }
+
+    #[test]
+    fn failed_write_keeps_bits() {
+        use crate::fault::{Fault, FaultyAlloc};
+        let alloc = FaultyAlloc::new();
+        let mut bits = BitString::new_in(alloc.clone());
+        let mut writer = BitWriter::with_order(&mut bits, BitOrder::MsbFirst);
+        writer.try_write_bits(0b1011, 4).unwrap();
+        let full = writer.bits().capacity();
+        while writer.bits().len() + 8 <= full {
+            writer.try_write_bits(0xa5, 8).unwrap();
+        }
+        let before = writer.bits().clone();
+        // the next write needs more room
+        alloc.set_fault(Fault::Nth(0));
+        assert!(writer.try_write_bits(u64::MAX, 64).is_err());
+        assert!(writer.bits().iter().eq(before.iter()));
+
+        alloc.set_fault(Fault::Never);
+        writer.try_write_bits(u64::MAX, 64).unwrap();
+        assert_eq!(writer.bits().len(), before.len() + 64);
+        drop(before);
+        drop(bits);
+        assert_eq!(alloc.live(), 0);
+    }
+}
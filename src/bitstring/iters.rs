@@ -0,0 +1,127 @@
+use super::word::BitWord;
+use core::{iter::FusedIterator, marker::PhantomData, ptr::NonNull};
+
+pub struct BitStringIterator<'a, W: BitWord = usize> {
+    pub(crate) inner: &'a [W],
+    pub(crate) idx: usize,
+    pub(crate) end: usize,
+}
+
+impl<'a, W: BitWord> Iterator for BitStringIterator<'a, W> {
+    type Item = bool;
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.idx < self.end).then(|| {
+            let r = self.inner[self.idx / W::BITS].get_bit(self.idx % W::BITS);
+            self.idx += 1;
+            r
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.idx;
+        (len, Some(len))
+    }
+}
+
+impl<'a, W: BitWord> DoubleEndedIterator for BitStringIterator<'a, W> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        (self.idx < self.end).then(|| {
+            self.end -= 1;
+            self.inner[self.end / W::BITS].get_bit(self.end % W::BITS)
+        })
+    }
+}
+
+impl<'a, W: BitWord> ExactSizeIterator for BitStringIterator<'a, W> {}
+impl<'a, W: BitWord> FusedIterator for BitStringIterator<'a, W> {}
+
+/// Hands out a [`BitRefMut`] for every bit.
+///
+/// Several `BitRefMut`s can point into the same word so they only ever go through raw pointers.
+pub struct BitStringIteratorMut<'a, W: BitWord = usize> {
+    pub(crate) ptr: NonNull<W>,
+    pub(crate) idx: usize,
+    pub(crate) end: usize,
+    pub(crate) marker: PhantomData<&'a mut [W]>,
+}
+
+impl<'a, W: BitWord> BitStringIteratorMut<'a, W> {
+    pub(crate) fn new(words: &'a mut [W], idx: usize, end: usize) -> Self {
+        debug_assert!(end <= words.len() * W::BITS);
+        Self {
+            // a slice pointer is never null
+            ptr: unsafe { NonNull::new_unchecked(words.as_mut_ptr()) },
+            idx,
+            end,
+            marker: PhantomData,
+        }
+    }
+
+    fn bit_ref(&self, idx: usize) -> BitRefMut<'a, W> {
+        BitRefMut {
+            // idx is always less than `end` so the word is in bounds
+            word: unsafe { NonNull::new_unchecked(self.ptr.as_ptr().add(idx / W::BITS)) },
+            index: (idx % W::BITS) as u8,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, W: BitWord> Iterator for BitStringIteratorMut<'a, W> {
+    type Item = BitRefMut<'a, W>;
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.idx < self.end).then(|| {
+            let r = self.bit_ref(self.idx);
+            self.idx += 1;
+            r
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.idx;
+        (len, Some(len))
+    }
+}
+
+impl<'a, W: BitWord> DoubleEndedIterator for BitStringIteratorMut<'a, W> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        (self.idx < self.end).then(|| {
+            self.end -= 1;
+            self.bit_ref(self.end)
+        })
+    }
+}
+
+impl<'a, W: BitWord> ExactSizeIterator for BitStringIteratorMut<'a, W> {}
+impl<'a, W: BitWord> FusedIterator for BitStringIteratorMut<'a, W> {}
+
+/// A mutable reference to a single bit.
+pub struct BitRefMut<'a, W: BitWord = usize> {
+    word: NonNull<W>,
+    index: u8,
+    marker: PhantomData<&'a mut W>,
+}
+
+impl<'a, W: BitWord> BitRefMut<'a, W> {
+    #[inline]
+    pub fn get(&self) -> bool {
+        unsafe { self.word.as_ptr().read() }.get_bit(self.index as usize)
+    }
+    #[inline]
+    pub fn set(&mut self, val: bool) {
+        let mut word = unsafe { self.word.as_ptr().read() };
+        word.set_bit(self.index as usize, val);
+        unsafe { self.word.as_ptr().write(word) }
+    }
+    #[inline]
+    pub fn toggle(&mut self) {
+        let mut word = unsafe { self.word.as_ptr().read() };
+        word.toggle_bit(self.index as usize);
+        unsafe { self.word.as_ptr().write(word) }
+    }
+}
+
+impl<'a, W: BitWord> From<BitRefMut<'a, W>> for bool {
+    fn from(x: BitRefMut<'a, W>) -> Self {
+        x.get()
+    }
+}
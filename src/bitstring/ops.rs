@@ -0,0 +1,357 @@
+//! Boolean and shift operators for [`BitString`] and [`FixedBitString`].
+//!
+//! The operators treat bit strings like sets of indices, so any bit past the end of the shorter
+//! string counts as zero. `&` keeps the length of the left hand side while `|` and `^` grow it to
+//! the longer of the two strings.
+//!
+//! Shifts keep the length of the string. `<<` moves bit `i` to `i + n` and `>>` moves it to
+//! `i - n`, just like shifting an integer where bit `0` is the least significant bit.
+
+use super::{word::BitWord, BitString, FixedBitString};
+use core::alloc::Allocator;
+use core::ops::*;
+
+/// Moves bit `i` to bit `i + k`, shifting in zeros.
+pub(crate) fn shl_words<W: BitWord>(words: &mut [W], k: usize) {
+    let (ws, bs) = (k / W::BITS, k % W::BITS);
+    if ws >= words.len() {
+        words.fill(W::ZERO);
+        return;
+    }
+    for i in (0..words.len()).rev() {
+        words[i] = if i < ws {
+            W::ZERO
+        } else if bs == 0 {
+            words[i - ws]
+        } else if i > ws {
+            (words[i - ws] << bs) | (words[i - ws - 1] >> (W::BITS - bs))
+        } else {
+            words[i - ws] << bs
+        };
+    }
+}
+
+/// Moves bit `i` to bit `i - k`, shifting in zeros.
+pub(crate) fn shr_words<W: BitWord>(words: &mut [W], k: usize) {
+    let (ws, bs) = (k / W::BITS, k % W::BITS);
+    let n = words.len();
+    if ws >= n {
+        words.fill(W::ZERO);
+        return;
+    }
+    for i in 0..n {
+        words[i] = if i + ws >= n {
+            W::ZERO
+        } else if bs == 0 {
+            words[i + ws]
+        } else if i + ws + 1 < n {
+            (words[i + ws] >> bs) | (words[i + ws + 1] << (W::BITS - bs))
+        } else {
+            words[i + ws] >> bs
+        };
+    }
+}
+
+impl<A: Allocator> BitString<A> {
+    /// clears the bits in the last word that are past `len`
+    fn clear_tail(&mut self) {
+        let rem = self.len % usize::BITS as usize;
+        if rem != 0 {
+            if let Some(last) = self.data.last_mut() {
+                *last &= usize::mask(rem);
+            }
+        }
+    }
+}
+
+impl<'a, A: Allocator, B: Allocator> BitAndAssign<&'a BitString<B>> for BitString<A> {
+    fn bitand_assign(&mut self, rhs: &'a BitString<B>) {
+        let shared = core::cmp::min(self.data.len(), rhs.data.len());
+        for (a, b) in self.data.iter_mut().zip(rhs.data.iter()) {
+            *a &= *b;
+        }
+        self.data[shared..].fill(0);
+    }
+}
+
+impl<'a, A: Allocator, B: Allocator> BitOrAssign<&'a BitString<B>> for BitString<A> {
+    fn bitor_assign(&mut self, rhs: &'a BitString<B>) {
+        if rhs.len > self.len {
+            self.resize(rhs.len, false);
+        }
+        for (a, b) in self.data.iter_mut().zip(rhs.data.iter()) {
+            *a |= *b;
+        }
+    }
+}
+
+impl<'a, A: Allocator, B: Allocator> BitXorAssign<&'a BitString<B>> for BitString<A> {
+    fn bitxor_assign(&mut self, rhs: &'a BitString<B>) {
+        if rhs.len > self.len {
+            self.resize(rhs.len, false);
+        }
+        for (a, b) in self.data.iter_mut().zip(rhs.data.iter()) {
+            *a ^= *b;
+        }
+    }
+}
+
+// every other combination of owned and borrowed operands is built on top of the `*Assign<&_>` impls
+macro_rules! bitstring_binop {
+    ($($op:ident, $f:ident, $op_assign:ident, $f_assign:ident);*) => {
+        $(
+            impl<A: Allocator, B: Allocator> $op_assign<BitString<B>> for BitString<A> {
+                #[inline]
+                fn $f_assign(&mut self, rhs: BitString<B>) {
+                    self.$f_assign(&rhs)
+                }
+            }
+
+            impl<'a, A: Allocator, B: Allocator> $op<&'a BitString<B>> for BitString<A> {
+                type Output = BitString<A>;
+                #[inline]
+                fn $f(mut self, rhs: &'a BitString<B>) -> Self::Output {
+                    self.$f_assign(rhs);
+                    self
+                }
+            }
+
+            impl<A: Allocator, B: Allocator> $op<BitString<B>> for BitString<A> {
+                type Output = BitString<A>;
+                #[inline]
+                fn $f(mut self, rhs: BitString<B>) -> Self::Output {
+                    self.$f_assign(&rhs);
+                    self
+                }
+            }
+
+            impl<'a, 'b, A: Allocator + Clone, B: Allocator> $op<&'b BitString<B>> for &'a BitString<A> {
+                type Output = BitString<A>;
+                #[inline]
+                fn $f(self, rhs: &'b BitString<B>) -> Self::Output {
+                    let mut out = self.clone();
+                    out.$f_assign(rhs);
+                    out
+                }
+            }
+
+            impl<'a, const N: usize> $op_assign<&'a FixedBitString<N>> for FixedBitString<N> {
+                #[inline]
+                fn $f_assign(&mut self, rhs: &'a FixedBitString<N>) {
+                    for (a, b) in self.inner.iter_mut().zip(rhs.inner.iter()) {
+                        a.$f_assign(*b);
+                    }
+                }
+            }
+
+            impl<const N: usize> $op_assign for FixedBitString<N> {
+                #[inline]
+                fn $f_assign(&mut self, rhs: FixedBitString<N>) {
+                    self.$f_assign(&rhs)
+                }
+            }
+
+            impl<'a, const N: usize> $op<&'a FixedBitString<N>> for FixedBitString<N> {
+                type Output = FixedBitString<N>;
+                #[inline]
+                fn $f(mut self, rhs: &'a FixedBitString<N>) -> Self::Output {
+                    self.$f_assign(rhs);
+                    self
+                }
+            }
+
+            impl<const N: usize> $op for FixedBitString<N> {
+                type Output = FixedBitString<N>;
+                #[inline]
+                fn $f(mut self, rhs: FixedBitString<N>) -> Self::Output {
+                    self.$f_assign(&rhs);
+                    self
+                }
+            }
+
+            impl<'a, 'b, const N: usize> $op<&'b FixedBitString<N>> for &'a FixedBitString<N> {
+                type Output = FixedBitString<N>;
+                #[inline]
+                fn $f(self, rhs: &'b FixedBitString<N>) -> Self::Output {
+                    let mut out = self.clone();
+                    out.$f_assign(rhs);
+                    out
+                }
+            }
+        )*
+    };
+}
+
+bitstring_binop!(
+    BitAnd, bitand, BitAndAssign, bitand_assign;
+    BitOr, bitor, BitOrAssign, bitor_assign;
+    BitXor, bitxor, BitXorAssign, bitxor_assign
+);
+
+impl<A: Allocator> Not for BitString<A> {
+    type Output = Self;
+    fn not(mut self) -> Self::Output {
+        self.data.iter_mut().for_each(|w| *w = !*w);
+        self.clear_tail();
+        self
+    }
+}
+
+impl<'a, A: Allocator + Clone> Not for &'a BitString<A> {
+    type Output = BitString<A>;
+    fn not(self) -> Self::Output {
+        !self.clone()
+    }
+}
+
+impl<const N: usize> Not for FixedBitString<N> {
+    type Output = Self;
+    fn not(mut self) -> Self::Output {
+        self.inner.iter_mut().for_each(|w| *w = !*w);
+        self
+    }
+}
+
+impl<'a, const N: usize> Not for &'a FixedBitString<N> {
+    type Output = FixedBitString<N>;
+    fn not(self) -> Self::Output {
+        !self.clone()
+    }
+}
+
+impl<A: Allocator> ShlAssign<usize> for BitString<A> {
+    fn shl_assign(&mut self, rhs: usize) {
+        shl_words(&mut self.data, rhs);
+        self.clear_tail();
+    }
+}
+
+impl<A: Allocator> ShrAssign<usize> for BitString<A> {
+    fn shr_assign(&mut self, rhs: usize) {
+        // the bits past `len` are zero so they are exactly what should be shifted in
+        shr_words(&mut self.data, rhs);
+    }
+}
+
+impl<const N: usize> ShlAssign<usize> for FixedBitString<N> {
+    fn shl_assign(&mut self, rhs: usize) {
+        shl_words(&mut self.inner, rhs);
+    }
+}
+
+impl<const N: usize> ShrAssign<usize> for FixedBitString<N> {
+    fn shr_assign(&mut self, rhs: usize) {
+        shr_words(&mut self.inner, rhs);
+    }
+}
+
+macro_rules! bitstring_shift {
+    ($($op:ident, $f:ident, $f_assign:ident);*) => {
+        $(
+            impl<A: Allocator> $op<usize> for BitString<A> {
+                type Output = Self;
+                #[inline]
+                fn $f(mut self, rhs: usize) -> Self::Output {
+                    self.$f_assign(rhs);
+                    self
+                }
+            }
+
+            impl<'a, A: Allocator + Clone> $op<usize> for &'a BitString<A> {
+                type Output = BitString<A>;
+                #[inline]
+                fn $f(self, rhs: usize) -> Self::Output {
+                    let mut out = self.clone();
+                    out.$f_assign(rhs);
+                    out
+                }
+            }
+
+            impl<const N: usize> $op<usize> for FixedBitString<N> {
+                type Output = Self;
+                #[inline]
+                fn $f(mut self, rhs: usize) -> Self::Output {
+                    self.$f_assign(rhs);
+                    self
+                }
+            }
+
+            impl<'a, const N: usize> $op<usize> for &'a FixedBitString<N> {
+                type Output = FixedBitString<N>;
+                #[inline]
+                fn $f(self, rhs: usize) -> Self::Output {
+                    let mut out = self.clone();
+                    out.$f_assign(rhs);
+                    out
+                }
+            }
+        )*
+    };
+}
+
+bitstring_shift!(
+    Shl, shl, shl_assign;
+    Shr, shr, shr_assign
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn bits(s: &str) -> BitString {
+        s.chars().map(|c| c == '1').collect()
+    }
+
+    fn to_str(b: &BitString) -> alloc::string::String {
+        b.iter().map(|b| if b { '1' } else { '0' }).collect()
+    }
+
+    #[test]
+    fn and_or_xor_different_lengths() {
+        let a = bits("1100");
+        let b = bits("101010");
+        assert_eq!(to_str(&(&a & &b)), "1000");
+        assert_eq!(to_str(&(&a | &b)), "111010");
+        assert_eq!(to_str(&(&a ^ &b)), "011010");
+        assert_eq!(to_str(&(b & a)), "100000");
+    }
+
+    #[test]
+    fn not_keeps_tail_clear() {
+        let a = bits("10110");
+        let b = !&a;
+        assert_eq!(to_str(&b), "01001");
+        assert_eq!(b.as_words(), &[0b10010]);
+    }
+
+    #[test]
+    fn shifts_keep_length() {
+        let v = (0..150).map(|i| i % 5 == 1).collect::<Vec<_>>();
+        let a: BitString = v.iter().copied().collect();
+        for k in [0, 1, 7, 63, 64, 65, 130, 150, 200] {
+            let shl = (&a << k).iter().collect::<Vec<_>>();
+            let shr = (&a >> k).iter().collect::<Vec<_>>();
+            let expected_shl = (0..150).map(|i| i >= k && v[i - k]).collect::<Vec<_>>();
+            let expected_shr = (0..150)
+                .map(|i| i + k < 150 && v[i + k])
+                .collect::<Vec<_>>();
+            assert_eq!(shl, expected_shl, "shl {k}");
+            assert_eq!(shr, expected_shr, "shr {k}");
+        }
+    }
+
+    #[test]
+    fn fixed_ops() {
+        let mut a = FixedBitString::<2>::new();
+        a.copy_from_slice(0, &[0b1111_0000, 0b0000_0001]);
+        let mut b = FixedBitString::<2>::new();
+        b.copy_from_slice(0, &[0b1010_1010, 0b1000_0001]);
+        assert_eq!((&a & &b).as_bytes(), &[0b1010_0000, 0b0000_0001]);
+        assert_eq!((&a | &b).as_bytes(), &[0b1111_1010, 0b1000_0001]);
+        assert_eq!((&a ^ &b).as_bytes(), &[0b0101_1010, 0b1000_0000]);
+        assert_eq!((!&a).as_bytes(), &[0b0000_1111, 0b1111_1110]);
+        assert_eq!((&a << 4).as_bytes(), &[0b0000_0000, 0b0001_1111]);
+        assert_eq!((a >> 9).as_bytes(), &[0b0000_0000, 0]);
+    }
+}
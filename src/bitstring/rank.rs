@@ -0,0 +1,332 @@
+//! Counting, searching and succinct rank/select queries.
+
+use super::{word::*, BitSlice};
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+/// returns the index of the `k`th (starting at 0) set bit in `x`, `k` must be less than `x.count_ones()`
+#[inline]
+fn select_in_word(mut x: u64, k: u32) -> usize {
+    debug_assert!(k < x.count_ones());
+    for _ in 0..k {
+        x &= x - 1;
+    }
+    x.trailing_zeros() as usize
+}
+
+impl<'a, W: BitWord> BitSlice<'a, W> {
+    /// loads the `n <= 64` bits starting at `pos`
+    #[inline]
+    fn chunk(&self, pos: usize, n: usize) -> u64 {
+        load(self.words, self.start + pos, n)
+    }
+
+    /// Counts the set bits.
+    pub fn count_ones(&self) -> usize {
+        let mut count = 0;
+        let mut pos = 0;
+        while pos < self.len {
+            let n = core::cmp::min(64, self.len - pos);
+            count += self.chunk(pos, n).count_ones() as usize;
+            pos += n;
+        }
+        count
+    }
+
+    /// Counts the unset bits.
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.count_ones()
+    }
+
+    /// Counts the zeros at the end of the slice (the highest indices).
+    pub fn leading_zeros(&self) -> usize {
+        let mut zeros = 0;
+        let mut end = self.len;
+        while end > 0 {
+            let n = core::cmp::min(64, end);
+            let chunk = self.chunk(end - n, n);
+            if chunk != 0 {
+                return zeros + chunk.leading_zeros() as usize - (64 - n);
+            }
+            zeros += n;
+            end -= n;
+        }
+        zeros
+    }
+
+    /// Counts the zeros at the start of the slice (the lowest indices).
+    pub fn trailing_zeros(&self) -> usize {
+        self.first_one().unwrap_or(self.len)
+    }
+
+    /// Returns the index of the first set bit.
+    pub fn first_one(&self) -> Option<usize> {
+        self.next_one(0)
+    }
+
+    /// Returns the index of the first set bit at or after `from`.
+    pub fn next_one(&self, from: usize) -> Option<usize> {
+        let mut pos = from;
+        while pos < self.len {
+            let n = core::cmp::min(64, self.len - pos);
+            let chunk = self.chunk(pos, n);
+            if chunk != 0 {
+                return Some(pos + chunk.trailing_zeros() as usize);
+            }
+            pos += n;
+        }
+        None
+    }
+
+    /// Iterates over the indices of the set bits in increasing order.
+    pub fn ones(&self) -> Ones<'a, W> {
+        Ones {
+            bits: *self,
+            pos: 0,
+        }
+    }
+
+    /// Counts the set bits in `0..idx`.
+    ///
+    /// This scans the slice, use a [`RankSelect`] index if it needs to be called a lot.
+    ///
+    /// # Panics
+    /// Panics if `idx > len`.
+    pub fn rank(&self, idx: usize) -> usize {
+        assert!(idx <= self.len, "index out of bounds");
+        BitSlice {
+            words: self.words,
+            start: self.start,
+            len: idx,
+        }
+        .count_ones()
+    }
+
+    /// Returns the index of the `k`th set bit, counting from zero.
+    ///
+    /// This scans the slice, use a [`RankSelect`] index if it needs to be called a lot.
+    pub fn select(&self, k: usize) -> Option<usize> {
+        let mut remaining = k;
+        let mut pos = 0;
+        while pos < self.len {
+            let n = core::cmp::min(64, self.len - pos);
+            let chunk = self.chunk(pos, n);
+            let ones = chunk.count_ones() as usize;
+            if remaining < ones {
+                return Some(pos + select_in_word(chunk, remaining as u32));
+            }
+            remaining -= ones;
+            pos += n;
+        }
+        None
+    }
+}
+
+/// An iterator over the indices of the set bits of a [`BitSlice`].
+pub struct Ones<'a, W: BitWord = usize> {
+    bits: BitSlice<'a, W>,
+    pos: usize,
+}
+
+impl<'a, W: BitWord> Iterator for Ones<'a, W> {
+    type Item = usize;
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.bits.next_one(self.pos);
+        self.pos = next.map_or(self.bits.len, |i| i + 1);
+        next
+    }
+}
+
+impl<'a, W: BitWord> FusedIterator for Ones<'a, W> {}
+
+/// the number of bits covered by a single entry in `RankSelect::blocks`
+const BLOCK_BITS: usize = 512;
+/// the number of set bits between entries in `RankSelect::samples`
+const SAMPLE_RATE: usize = 512;
+
+/// A precomputed index over a [`BitSlice`] answering `rank` in O(1) and `select` in (nearly) O(1).
+///
+/// The index stores the number of set bits before every 512 bit block and the block containing
+/// every 512th set bit, which costs roughly an eighth of the size of the bits themselves.
+/// It borrows the bits so they can't change while the index exists.
+///
+/// # Examples
+/// ```
+/// use hash_table::bitstring::{BitString, RankSelect};
+/// let bits: BitString = (0..1000).map(|i| i % 3 == 0).collect();
+/// let index = RankSelect::new(bits.as_bitslice());
+/// assert_eq!(index.rank(10), 4);
+/// assert_eq!(index.select(4), Some(12));
+/// ```
+pub struct RankSelect<'a, W: BitWord = usize> {
+    bits: BitSlice<'a, W>,
+    /// `blocks[i]` is the number of set bits in `0..i * BLOCK_BITS`, with one extra entry for the total
+    blocks: Vec<usize>,
+    /// `samples[i]` is the block that contains the `i * SAMPLE_RATE`th set bit
+    samples: Vec<usize>,
+}
+
+impl<'a, W: BitWord> RankSelect<'a, W> {
+    pub fn new(bits: BitSlice<'a, W>) -> Self {
+        let block_count = bits.len.div_ceil(BLOCK_BITS);
+        let mut blocks = Vec::with_capacity(block_count + 1);
+        let mut samples = Vec::new();
+        let mut total = 0;
+        for block in 0..block_count {
+            blocks.push(total);
+            let start = block * BLOCK_BITS;
+            let end = core::cmp::min(start + BLOCK_BITS, bits.len);
+            let mut pos = start;
+            let mut ones = 0;
+            while pos < end {
+                let n = core::cmp::min(64, end - pos);
+                ones += bits.chunk(pos, n).count_ones() as usize;
+                pos += n;
+            }
+            // every multiple of SAMPLE_RATE in `total..total + ones` lands in this block
+            while samples.len() * SAMPLE_RATE < total + ones {
+                samples.push(block);
+            }
+            total += ones;
+        }
+        blocks.push(total);
+        Self {
+            bits,
+            blocks,
+            samples,
+        }
+    }
+
+    /// The bits the index was built over.
+    pub fn bits(&self) -> BitSlice<'a, W> {
+        self.bits
+    }
+
+    /// The total number of set bits.
+    pub fn count_ones(&self) -> usize {
+        // there is always at least one entry
+        *self.blocks.last().unwrap()
+    }
+
+    /// Counts the set bits in `0..idx`.
+    ///
+    /// # Panics
+    /// Panics if `idx > len`.
+    pub fn rank(&self, idx: usize) -> usize {
+        assert!(idx <= self.bits.len, "index out of bounds");
+        let block = idx / BLOCK_BITS;
+        let mut rank = self.blocks[block];
+        let mut pos = block * BLOCK_BITS;
+        while pos < idx {
+            let n = core::cmp::min(64, idx - pos);
+            rank += self.bits.chunk(pos, n).count_ones() as usize;
+            pos += n;
+        }
+        rank
+    }
+
+    /// Returns the index of the `k`th set bit, counting from zero.
+    pub fn select(&self, k: usize) -> Option<usize> {
+        if k >= self.count_ones() {
+            return None;
+        }
+        // the answer is somewhere between this sample and the next one
+        let sample = k / SAMPLE_RATE;
+        let lo = self.samples[sample];
+        let hi = self
+            .samples
+            .get(sample + 1)
+            .map_or(self.blocks.len() - 1, |b| b + 1);
+        // the last block in lo..hi with fewer than `k + 1` ones before it
+        let block = lo + self.blocks[lo..hi].partition_point(|&r| r <= k) - 1;
+
+        let mut remaining = k - self.blocks[block];
+        let mut pos = block * BLOCK_BITS;
+        loop {
+            let n = core::cmp::min(64, self.bits.len - pos);
+            let chunk = self.bits.chunk(pos, n);
+            let ones = chunk.count_ones() as usize;
+            if remaining < ones {
+                return Some(pos + select_in_word(chunk, remaining as u32));
+            }
+            remaining -= ones;
+            pos += n;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BitString, FixedBitString};
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn random_bits(len: usize, density: f64, seed: u64) -> BitString {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..len).map(|_| rng.gen_bool(density)).collect()
+    }
+
+    #[test]
+    fn counts() {
+        let bits: BitString = (0..200)
+            .map(|i| (10..150).contains(&i) && i % 2 == 0)
+            .collect();
+        assert_eq!(bits.count_ones(), 70);
+        assert_eq!(bits.count_zeros(), 130);
+        assert_eq!(bits.trailing_zeros(), 10);
+        assert_eq!(bits.leading_zeros(), 51);
+        assert_eq!(bits.first_one(), Some(10));
+        assert_eq!(bits.next_one(11), Some(12));
+        assert_eq!(bits.next_one(149), None);
+        assert_eq!(
+            bits.ones().collect::<Vec<_>>(),
+            (10..150).step_by(2).collect::<Vec<_>>()
+        );
+
+        let empty = BitString::new();
+        assert_eq!(empty.leading_zeros(), 0);
+        assert_eq!(empty.first_one(), None);
+    }
+
+    #[test]
+    fn fixed_counts() {
+        let mut a = FixedBitString::<4>::new();
+        a.set(3, true);
+        a.set(20, true);
+        assert_eq!(a.count_ones(), 2);
+        assert_eq!(a.trailing_zeros(), 3);
+        assert_eq!(a.leading_zeros(), 11);
+        assert_eq!(a.select(1), Some(20));
+        assert_eq!(a.rank(20), 1);
+    }
+
+    #[test]
+    fn rank_select_matches_naive() {
+        for (len, density, seed) in [
+            (0, 0.5, 1),
+            (5000, 0.5, 2),
+            (20000, 0.01, 3),
+            (3000, 0.99, 4),
+        ] {
+            let bits = random_bits(len, density, seed);
+            let index = RankSelect::new(bits.as_bitslice());
+            let ones = bits.ones().collect::<Vec<_>>();
+            assert_eq!(index.count_ones(), ones.len());
+            for (k, i) in ones.iter().enumerate() {
+                assert_eq!(index.select(k), Some(*i));
+                assert_eq!(bits.select(k), Some(*i));
+            }
+            assert_eq!(index.select(ones.len()), None);
+            let mut rank = 0;
+            for i in 0..=len {
+                assert_eq!(index.rank(i), rank);
+                if i % 97 == 0 {
+                    assert_eq!(bits.rank(i), rank);
+                }
+                if bits.get(i) == Some(true) {
+                    rank += 1;
+                }
+            }
+        }
+    }
+}
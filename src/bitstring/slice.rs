@@ -0,0 +1,532 @@
+use super::{iters::*, word::*};
+use crate::util::resolve_range;
+use core::fmt::{self, Debug, Formatter};
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::ops::{Index, RangeBounds};
+
+/// A borrowed view of `len` bits starting at bit `start` of `words`.
+///
+/// The view doesn't have to start or end on a word boundary.
+#[derive(Clone, Copy)]
+pub struct BitSlice<'a, W: BitWord = usize> {
+    pub(crate) words: &'a [W],
+    pub(crate) start: usize,
+    pub(crate) len: usize,
+}
+
+/// A mutable view of `len` bits starting at bit `start` of `words`.
+///
+/// Bits of `words` outside of the view are never changed.
+pub struct BitSliceMut<'a, W: BitWord = usize> {
+    pub(crate) words: &'a mut [W],
+    pub(crate) start: usize,
+    pub(crate) len: usize,
+}
+
+impl<'a, W: BitWord> BitSlice<'a, W> {
+    /// Creates a view over every bit of `words`.
+    pub fn from_words(words: &'a [W]) -> Self {
+        Self {
+            words,
+            start: 0,
+            len: words.len() * W::BITS,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn get(&self, idx: usize) -> Option<bool> {
+        (idx < self.len).then(|| {
+            let idx = self.start + idx;
+            self.words[idx / W::BITS].get_bit(idx % W::BITS)
+        })
+    }
+
+    /// Returns a view of the bits in `range`, or `None` if it is out of bounds.
+    pub fn get_range<R: RangeBounds<usize>>(&self, range: R) -> Option<BitSlice<'a, W>> {
+        let range = resolve_range(range, self.len)?;
+        Some(BitSlice {
+            words: self.words,
+            start: self.start + range.start,
+            len: range.len(),
+        })
+    }
+
+    /// Returns a view of the bits in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitString;
+    /// let bits: BitString = (0..100).map(|i| i % 10 == 0).collect();
+    /// let middle = bits.slice(5..25);
+    /// assert_eq!(middle.len(), 20);
+    /// assert_eq!(middle.ones().collect::<Vec<_>>(), [5, 15]);
+    /// ```
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BitSlice<'a, W> {
+        self.get_range(range).expect("range out of bounds")
+    }
+
+    /// Splits the view in two at `mid`.
+    ///
+    /// # Panics
+    /// Panics if `mid > len`.
+    pub fn split_at(&self, mid: usize) -> (BitSlice<'a, W>, BitSlice<'a, W>) {
+        (self.slice(..mid), self.slice(mid..))
+    }
+
+    /// Reads `width` bits starting at `idx` as an integer. Bit `idx` becomes the least
+    /// significant bit of the result.
+    ///
+    /// # Panics
+    /// Panics if `width > I::BITS` or if the bits are out of bounds.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitSlice;
+    /// let bytes = [0b1011_0000u8, 0b0000_0110];
+    /// let bits = BitSlice::from_words(&bytes);
+    /// assert_eq!(bits.load::<u8>(4, 7), 0b110_1011);
+    /// ```
+    pub fn load<I: BitWord>(&self, idx: usize, width: usize) -> I {
+        assert!(width <= I::BITS, "`width` is larger than the integer");
+        assert!(
+            idx.checked_add(width).is_some_and(|end| end <= self.len),
+            "out of bounds"
+        );
+        let pos = self.start + idx;
+        if width <= 64 {
+            I::from_u64(load(self.words, pos, width))
+        } else {
+            let lo = I::from_u64(load(self.words, pos, 64));
+            let hi = I::from_u64(load(self.words, pos + 64, width - 64));
+            lo | (hi << 64)
+        }
+    }
+
+    /// Iterates over the bits in chunks of `I::BITS` bits, each read like [`load`](Self::load).
+    /// The last chunk only holds the remaining bits if the length isn't a multiple of `I::BITS`.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitSlice;
+    /// let bytes = [0x34u8, 0x12, 0x05];
+    /// let bits = BitSlice::from_words(&bytes);
+    /// assert_eq!(bits.chunks::<u16>().collect::<Vec<_>>(), [0x1234, 0x05]);
+    /// ```
+    pub fn chunks<I: BitWord>(&self) -> Chunks<'a, W, I> {
+        Chunks {
+            bits: *self,
+            pos: 0,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter(&self) -> BitStringIterator<'a, W> {
+        BitStringIterator {
+            inner: self.words,
+            idx: self.start,
+            end: self.start + self.len,
+        }
+    }
+}
+
+impl<'a, W: BitWord> BitSliceMut<'a, W> {
+    /// Creates a mutable view over every bit of `words`.
+    pub fn from_words(words: &'a mut [W]) -> Self {
+        let len = words.len() * W::BITS;
+        Self {
+            words,
+            start: 0,
+            len,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Borrows the view immutably, every read only method lives on [`BitSlice`].
+    #[inline]
+    pub fn as_bitslice(&self) -> BitSlice<'_, W> {
+        BitSlice {
+            words: self.words,
+            start: self.start,
+            len: self.len,
+        }
+    }
+
+    /// Consumes the view, turning it into an immutable one with the same lifetime.
+    #[inline]
+    pub fn into_bitslice(self) -> BitSlice<'a, W> {
+        BitSlice {
+            words: self.words,
+            start: self.start,
+            len: self.len,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, idx: usize) -> Option<bool> {
+        self.as_bitslice().get(idx)
+    }
+
+    /// Sets the bit at `idx` to `val`.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    #[inline]
+    pub fn set(&mut self, idx: usize, val: bool) {
+        assert!(idx < self.len, "index out of bounds");
+        let idx = self.start + idx;
+        self.words[idx / W::BITS].set_bit(idx % W::BITS, val)
+    }
+
+    /// Flips the bit at `idx`.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    #[inline]
+    pub fn toggle(&mut self, idx: usize) {
+        assert!(idx < self.len, "index out of bounds");
+        let idx = self.start + idx;
+        self.words[idx / W::BITS].toggle_bit(idx % W::BITS)
+    }
+
+    /// Swaps the bits at `a` and `b`.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        let (x, y) = (
+            self.get(a).expect("index out of bounds"),
+            self.get(b).expect("index out of bounds"),
+        );
+        self.set(a, y);
+        self.set(b, x);
+    }
+
+    /// Returns a mutable view of the bits in `range`, or `None` if it is out of bounds.
+    pub fn get_range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> Option<BitSliceMut<'_, W>> {
+        let range = resolve_range(range, self.len)?;
+        Some(BitSliceMut {
+            words: self.words,
+            start: self.start + range.start,
+            len: range.len(),
+        })
+    }
+
+    /// Returns a mutable view of the bits in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn slice_mut<R: RangeBounds<usize>>(&mut self, range: R) -> BitSliceMut<'_, W> {
+        self.get_range_mut(range).expect("range out of bounds")
+    }
+
+    /// Sets every bit to `val`.
+    pub fn fill(&mut self, val: bool) {
+        fill(self.words, self.start, self.start + self.len, val)
+    }
+
+    /// Copies every bit from `src` into the view.
+    ///
+    /// # Panics
+    /// Panics if the lengths are different.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitString;
+    /// let mut bits = BitString::new();
+    /// bits.resize(20, false);
+    /// let src: BitString = [true, true, false, true].into_iter().collect();
+    /// bits.slice_mut(3..7).copy_from_bitslice(src.as_bitslice());
+    /// assert_eq!(bits.ones().collect::<Vec<_>>(), [3, 4, 6]);
+    /// ```
+    pub fn copy_from_bitslice<V: BitWord>(&mut self, src: BitSlice<'_, V>) {
+        assert_eq!(self.len, src.len, "length mismatch");
+        copy(src.words, src.start, self.words, self.start, self.len);
+    }
+
+    /// Reverses the order of the bits.
+    pub fn reverse(&mut self) {
+        let (mut lo, mut hi) = (self.start, self.start + self.len);
+        while hi - lo >= 2 {
+            let n = core::cmp::min(64, (hi - lo) / 2);
+            let front = load(self.words, lo, n);
+            let back = load(self.words, hi - n, n);
+            store(self.words, lo, n, reverse_low(back, n));
+            store(self.words, hi - n, n, reverse_low(front, n));
+            lo += n;
+            hi -= n;
+        }
+    }
+
+    /// Rotates the bits so that bit `mid` becomes the first bit, like [`slice::rotate_left`].
+    ///
+    /// # Panics
+    /// Panics if `mid > len`.
+    pub fn rotate_left(&mut self, mid: usize) {
+        assert!(mid <= self.len, "`mid` out of bounds");
+        self.slice_mut(..mid).reverse();
+        self.slice_mut(mid..).reverse();
+        self.reverse();
+    }
+
+    /// Rotates the bits so that the last `k` bits come first, like [`slice::rotate_right`].
+    ///
+    /// # Panics
+    /// Panics if `k > len`.
+    pub fn rotate_right(&mut self, k: usize) {
+        assert!(k <= self.len, "`k` out of bounds");
+        self.rotate_left(self.len - k)
+    }
+
+    /// Reads `width` bits starting at `idx`. See [`BitSlice::load`].
+    pub fn load<I: BitWord>(&self, idx: usize, width: usize) -> I {
+        self.as_bitslice().load(idx, width)
+    }
+
+    /// Writes the lowest `width` bits of `value` starting at `idx`, the opposite of [`load`](Self::load).
+    ///
+    /// # Panics
+    /// Panics if `width > I::BITS` or if the bits are out of bounds.
+    /// # Examples
+    /// ```
+    /// use hash_table::bitstring::BitSliceMut;
+    /// let mut bytes = [0u8; 3];
+    /// BitSliceMut::from_words(&mut bytes).store::<u16>(6, 12, 0xABC);
+    /// assert_eq!(bytes, [0b0000_0000, 0b1010_1111, 0b0000_0010]);
+    /// ```
+    pub fn store<I: BitWord>(&mut self, idx: usize, width: usize, value: I) {
+        assert!(width <= I::BITS, "`width` is larger than the integer");
+        assert!(
+            idx.checked_add(width).is_some_and(|end| end <= self.len),
+            "out of bounds"
+        );
+        let pos = self.start + idx;
+        if width <= 64 {
+            store(self.words, pos, width, value.to_u64());
+        } else {
+            store(self.words, pos, 64, value.to_u64());
+            store(self.words, pos + 64, width - 64, (value >> 64).to_u64());
+        }
+    }
+
+    pub fn iter(&self) -> BitStringIterator<'_, W> {
+        self.as_bitslice().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> BitStringIteratorMut<'_, W> {
+        BitStringIteratorMut::new(self.words, self.start, self.start + self.len)
+    }
+}
+
+/// An iterator over a [`BitSlice`] that reads `I::BITS` bits at a time.
+pub struct Chunks<'a, W: BitWord, I: BitWord> {
+    bits: BitSlice<'a, W>,
+    pos: usize,
+    marker: PhantomData<I>,
+}
+
+impl<'a, W: BitWord, I: BitWord> Iterator for Chunks<'a, W, I> {
+    type Item = I;
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.pos < self.bits.len).then(|| {
+            let n = core::cmp::min(I::BITS, self.bits.len - self.pos);
+            let chunk = self.bits.load(self.pos, n);
+            self.pos += n;
+            chunk
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.bits.len - self.pos).div_ceil(I::BITS);
+        (len, Some(len))
+    }
+}
+
+impl<'a, W: BitWord, I: BitWord> ExactSizeIterator for Chunks<'a, W, I> {}
+impl<'a, W: BitWord, I: BitWord> FusedIterator for Chunks<'a, W, I> {}
+
+impl<'a, W: BitWord> IntoIterator for BitSlice<'a, W> {
+    type Item = bool;
+    type IntoIter = BitStringIterator<'a, W>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, W: BitWord> Index<usize> for BitSlice<'a, W> {
+    type Output = bool;
+    fn index(&self, index: usize) -> &Self::Output {
+        if self.get(index).expect("index out of bounds") {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+impl<'a, 'b, V: BitWord, W: BitWord> PartialEq<BitSlice<'b, W>> for BitSlice<'a, V> {
+    fn eq(&self, other: &BitSlice<'b, W>) -> bool {
+        if self.len != other.len {
+            return false;
+        }
+        let mut idx = 0;
+        while idx < self.len {
+            let n = core::cmp::min(64, self.len - idx);
+            if load(self.words, self.start + idx, n) != load(other.words, other.start + idx, n) {
+                return false;
+            }
+            idx += n;
+        }
+        true
+    }
+}
+
+impl<'a, W: BitWord> Eq for BitSlice<'a, W> {}
+
+impl<'a, W: BitWord> Debug for BitSlice<'a, W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "BitSlice {{ len: {}, bits: ", self.len)?;
+        for bit in self.iter() {
+            write!(f, "{}", bit as u8)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+impl<'a, W: BitWord> Debug for BitSliceMut<'a, W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "BitSliceMut {{ len: {}, bits: ", self.len)?;
+        for bit in self.iter() {
+            write!(f, "{}", bit as u8)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BitString, FixedBitString};
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn pattern(len: usize) -> Vec<bool> {
+        (0..len).map(|i| (i * 7 + i / 3) % 5 < 2).collect()
+    }
+
+    #[test]
+    fn unaligned_slices() {
+        let v = pattern(300);
+        let bits: BitString = v.iter().copied().collect();
+        for (start, end) in [
+            (0, 300),
+            (3, 70),
+            (63, 65),
+            (64, 128),
+            (100, 100),
+            (250, 300),
+        ] {
+            let s = bits.slice(start..end);
+            assert_eq!(s.iter().collect::<Vec<_>>(), v[start..end]);
+            if end > start {
+                assert_eq!(s.slice(1..).iter().collect::<Vec<_>>(), v[start + 1..end]);
+            }
+        }
+        assert!(bits.as_bitslice().get_range(10..301).is_none());
+        assert_eq!(bits[3], v[3]);
+        assert_eq!(bits[4], v[4]);
+    }
+
+    #[test]
+    fn fill_leaves_neighbours() {
+        let mut bits = BitString::new();
+        bits.resize(200, false);
+        bits.slice_mut(60..140).fill(true);
+        assert_eq!(
+            bits.ones().collect::<Vec<_>>(),
+            (60..140).collect::<Vec<_>>()
+        );
+        bits.slice_mut(61..139).fill(false);
+        assert_eq!(bits.ones().collect::<Vec<_>>(), [60, 139]);
+    }
+
+    #[test]
+    fn reverse_and_rotate() {
+        let v = pattern(203);
+        for (start, end) in [(0, 203), (5, 150), (64, 130), (7, 8), (9, 9)] {
+            for mid in [0, 1, (end - start) / 3, end - start] {
+                if mid > end - start {
+                    continue;
+                }
+                let mut bits: BitString = v.iter().copied().collect();
+                bits.slice_mut(start..end).rotate_left(mid);
+                let mut expected = v.clone();
+                expected[start..end].rotate_left(mid);
+                assert_eq!(
+                    bits.iter().collect::<Vec<_>>(),
+                    expected,
+                    "{start}..{end} {mid}"
+                );
+            }
+            let mut bits: BitString = v.iter().copied().collect();
+            bits.slice_mut(start..end).reverse();
+            let mut expected = v.clone();
+            expected[start..end].reverse();
+            assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn copy_between_strings() {
+        let v = pattern(150);
+        let src: BitString = v.iter().copied().collect();
+        let mut dst = FixedBitString::<32>::new();
+        dst.slice_mut(13..113)
+            .copy_from_bitslice(src.slice(27..127));
+        assert_eq!(dst.slice(13..113), src.slice(27..127));
+        assert_eq!(dst.slice(..13).count_ones(), 0);
+        assert_eq!(dst.slice(113..).count_ones(), 0);
+    }
+
+    #[test]
+    fn load_store_wide() {
+        let mut words = [0usize; 4];
+        let mut bits = BitSliceMut::from_words(&mut words);
+        let value = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210u128;
+        bits.store(37, 128, value);
+        assert_eq!(bits.load::<u128>(37, 128), value);
+        assert_eq!(bits.load::<u128>(37, 100), value & ((1 << 100) - 1));
+        bits.store::<u8>(37, 3, 0xff);
+        assert_eq!(bits.load::<u8>(37, 4), 0b0111);
+        assert_eq!(bits.as_bitslice().slice(..37).count_ones(), 0);
+    }
+
+    #[test]
+    fn chunks() {
+        let v = pattern(70);
+        let bits: BitString = v.iter().copied().collect();
+        let chunks = bits.as_bitslice().chunks::<u16>().collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 5);
+        for (i, c) in chunks.iter().enumerate() {
+            for b in 0..16 {
+                if let Some(bit) = v.get(i * 16 + b) {
+                    assert_eq!(c.get_bit(b), *bit);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use core::{
+    fmt::{Binary, Debug},
+    hash::Hash,
+    ops::*,
+};
+
+/// An unsigned integer that bits can be stored in.
+///
+/// Bit `i` of a word is the bit with the value `1 << i`, so bit `0` is the least significant bit.
+/// Everything in [`bitstring`](super) is defined in terms of this ordering which means that
+/// the layout of a bit string never depends on the endianness of the target.
+pub trait BitWord:
+    Copy
+    + Default
+    + Eq
+    + Ord
+    + Hash
+    + Debug
+    + Binary
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<usize, Output = Self>
+    + Shr<usize, Output = Self>
+    + BitAndAssign
+    + BitOrAssign
+    + BitXorAssign
+{
+    /// The number of bits in the word.
+    const BITS: usize;
+    const ZERO: Self;
+    const ONE: Self;
+    /// A word with every bit set.
+    const MAX: Self;
+
+    fn count_ones(self) -> u32;
+    fn leading_zeros(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+    fn reverse_bits(self) -> Self;
+
+    /// Truncates the word to its lowest 64 bits.
+    fn to_u64(self) -> u64;
+    /// Truncates `value` to fit in the word.
+    fn from_u64(value: u64) -> Self;
+
+    /// A word with the lowest `n` bits set. `n` can be anything from `0` to `Self::BITS`.
+    #[inline]
+    fn mask(n: usize) -> Self {
+        if n >= Self::BITS {
+            Self::MAX
+        } else {
+            !(Self::MAX << n)
+        }
+    }
+
+    #[inline]
+    fn get_bit(self, idx: usize) -> bool {
+        (self >> idx) & Self::ONE != Self::ZERO
+    }
+
+    #[inline]
+    fn set_bit(&mut self, idx: usize, val: bool) {
+        if val {
+            *self |= Self::ONE << idx;
+        } else {
+            *self &= !(Self::ONE << idx);
+        }
+    }
+
+    #[inline]
+    fn toggle_bit(&mut self, idx: usize) {
+        *self ^= Self::ONE << idx;
+    }
+}
+
+macro_rules! impl_bit_word {
+    ($($t:ty),*) => {
+        $(
+            impl BitWord for $t {
+                const BITS: usize = <$t>::BITS as usize;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$t>::MAX;
+
+                #[inline(always)]
+                fn count_ones(self) -> u32 {
+                    <$t>::count_ones(self)
+                }
+                #[inline(always)]
+                fn leading_zeros(self) -> u32 {
+                    <$t>::leading_zeros(self)
+                }
+                #[inline(always)]
+                fn trailing_zeros(self) -> u32 {
+                    <$t>::trailing_zeros(self)
+                }
+                #[inline(always)]
+                fn reverse_bits(self) -> Self {
+                    <$t>::reverse_bits(self)
+                }
+                #[inline(always)]
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+                #[inline(always)]
+                fn from_u64(value: u64) -> Self {
+                    value as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_bit_word!(u8, u16, u32, u64, u128, usize);
+
+/// The number of words needed to hold `bits` bits.
+#[inline(always)]
+pub(crate) const fn words_for<W: BitWord>(bits: usize) -> usize {
+    bits.div_ceil(W::BITS)
+}
+
+/// Reads `n <= 64` bits starting at bit `start`. Bit `start` ends up as the lsb of the result.
+///
+/// The caller has to make sure that `start + n` is in bounds of `words`.
+#[inline]
+pub(crate) fn load<W: BitWord>(words: &[W], start: usize, n: usize) -> u64 {
+    debug_assert!(n <= 64);
+    let mut out = 0u64;
+    let mut read = 0;
+    while read < n {
+        let pos = start + read;
+        let bit = pos % W::BITS;
+        let take = core::cmp::min(W::BITS - bit, n - read);
+        let chunk = (words[pos / W::BITS] >> bit).to_u64() & u64::mask(take);
+        out |= chunk << read;
+        read += take;
+    }
+    out
+}
+
+/// Writes the lowest `n <= 64` bits of `value` starting at bit `start`. Bits outside of
+/// `start..start + n` are left alone.
+///
+/// The caller has to make sure that `start + n` is in bounds of `words`.
+#[inline]
+pub(crate) fn store<W: BitWord>(words: &mut [W], start: usize, n: usize, value: u64) {
+    debug_assert!(n <= 64);
+    let mut written = 0;
+    while written < n {
+        let pos = start + written;
+        let bit = pos % W::BITS;
+        let take = core::cmp::min(W::BITS - bit, n - written);
+        let mask = W::mask(take) << bit;
+        let chunk = W::from_u64(value >> written) << bit;
+        let word = &mut words[pos / W::BITS];
+        *word = (*word & !mask) | (chunk & mask);
+        written += take;
+    }
+}
+
+/// Reverses the lowest `n <= 64` bits of `x`, the other bits are cleared.
+#[inline]
+pub(crate) fn reverse_low(x: u64, n: usize) -> u64 {
+    if n == 0 {
+        0
+    } else {
+        x.reverse_bits() >> (64 - n)
+    }
+}
+
+/// Sets every bit in `start..end` to `val`.
+pub(crate) fn fill<W: BitWord>(words: &mut [W], start: usize, end: usize, val: bool) {
+    let fill = if val { W::MAX } else { W::ZERO };
+    let mut pos = start;
+    while pos < end {
+        let bit = pos % W::BITS;
+        let take = core::cmp::min(W::BITS - bit, end - pos);
+        let mask = W::mask(take) << bit;
+        let word = &mut words[pos / W::BITS];
+        *word = (*word & !mask) | (fill & mask);
+        pos += take;
+    }
+}
+
+/// Copies `len` bits from `src` starting at `src_start` into `dst` starting at `dst_start`.
+pub(crate) fn copy<S: BitWord, D: BitWord>(
+    src: &[S],
+    src_start: usize,
+    dst: &mut [D],
+    dst_start: usize,
+    len: usize,
+) {
+    let mut done = 0;
+    while done < len {
+        let n = core::cmp::min(64, len - done);
+        store(dst, dst_start + done, n, load(src, src_start + done, n));
+        done += n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_store_across_words() {
+        let mut words = [0u8; 4];
+        store(&mut words, 5, 13, 0b1_0110_1100_1011);
+        assert_eq!(load(&words, 5, 13), 0b1_0110_1100_1011);
+        // nothing outside of the range was touched
+        assert_eq!(words[0] & 0b1_1111, 0);
+        assert_eq!(words[2] & !0b11, 0);
+    }
+
+    #[test]
+    fn load_store_u128() {
+        let mut words = [0u128; 2];
+        store(&mut words, 100, 64, u64::MAX - 7);
+        assert_eq!(load(&words, 100, 64), u64::MAX - 7);
+        assert_eq!(load(&words, 99, 1), 0);
+    }
+
+    #[test]
+    fn copy_between_word_sizes() {
+        let src: [u8; 3] = [0b1010_1010, 0b1111_0000, 0b0000_1111];
+        let mut dst = [0usize; 1];
+        copy(&src, 3, &mut dst, 7, 17);
+        assert_eq!(load(&dst, 7, 17), load(&src, 3, 17));
+    }
+}
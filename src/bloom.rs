@@ -0,0 +1,430 @@
+//! Bloom filters built on top of [`BitString`].
+//!
+//! Every item is hashed once with the filter's [`BuildHasher`], the same abstraction the hash
+//! tables use, and the `k` bit positions are derived from that with double hashing
+//! (`h1 + i * h2`). Two filters can only be combined or compared if they have the same number of
+//! bits, the same number of hashes and hashers that produce the same hashes.
+
+use crate::bitstring::{BitSlice, BitString};
+use alloc::alloc::Global;
+use alloc::vec::Vec;
+use core::{
+    alloc::{AllocError, Allocator},
+    fmt::{self, Debug, Formatter},
+    hash::{BuildHasher, Hash, Hasher},
+    marker::PhantomData,
+};
+
+mod counting;
+pub use counting::CountingBloomFilter;
+
+const LN_2: f64 = core::f64::consts::LN_2;
+
+/// natural logarithm of a positive finite `x`, core doesn't have one
+fn ln(x: f64) -> f64 {
+    debug_assert!(x > 0.0 && x.is_finite());
+    // x = m * 2^e with m in [1, 2)
+    let bits = x.to_bits();
+    let mut e = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let mut m = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    if e == -1023 {
+        // subnormal, normalize it first
+        let y = x * (1u64 << 54) as f64;
+        return ln(y) - 54.0 * LN_2;
+    }
+    if m > core::f64::consts::SQRT_2 {
+        m /= 2.0;
+        e += 1;
+    }
+    // ln(m) = 2 * atanh((m - 1) / (m + 1)) and |z| < 0.18 so the series converges quickly
+    let z = (m - 1.0) / (m + 1.0);
+    let z2 = z * z;
+    let mut term = z;
+    let mut sum = 0.0;
+    for i in 0..20 {
+        sum += term / (2 * i + 1) as f64;
+        term *= z2;
+    }
+    2.0 * sum + e as f64 * LN_2
+}
+
+fn ceil(x: f64) -> usize {
+    let c = x as usize;
+    if (c as f64) < x {
+        c + 1
+    } else {
+        c
+    }
+}
+
+/// Returns the number of bits and hashes that keep the false positive rate at `fp_rate` after
+/// `expected_items` insertions.
+///
+/// # Panics
+/// Panics if `fp_rate` isn't strictly between 0 and 1.
+pub fn optimal_parameters(expected_items: usize, fp_rate: f64) -> (usize, u32) {
+    assert!(
+        fp_rate > 0.0 && fp_rate < 1.0,
+        "the false positive rate has to be between 0 and 1"
+    );
+    let n = core::cmp::max(expected_items, 1) as f64;
+    let bits = core::cmp::max(ceil(-n * ln(fp_rate) / (LN_2 * LN_2)), 1);
+    let hashes = (bits as f64 / n * LN_2 + 0.5) as u32;
+    (bits, hashes.clamp(1, 32))
+}
+
+/// the two halves of the double hashing scheme, `h2` is odd so it never gets stuck on one bit
+fn double_hash<T: Hash + ?Sized, S: BuildHasher>(hash_builder: &S, item: &T) -> (u64, u64) {
+    let mut hasher = hash_builder.build_hasher();
+    item.hash(&mut hasher);
+    let h1 = hasher.finish();
+    hasher.write_u64(0x9e37_79b9_7f4a_7c15);
+    let h2 = hasher.finish() | 1;
+    (h1, h2)
+}
+
+/// The bit (or counter) positions of `item` in a filter with `len` slots.
+fn indexes<T: Hash + ?Sized, S: BuildHasher>(
+    hash_builder: &S,
+    item: &T,
+    hashes: u32,
+    len: usize,
+) -> impl Iterator<Item = usize> {
+    let (h1, h2) = double_hash(hash_builder, item);
+    (0..hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len as u64) as usize)
+}
+
+/// A set that can have false positives but never false negatives.
+///
+/// # Examples
+/// ```
+/// use hash_table::bloom::BloomFilter;
+/// use std::collections::hash_map::RandomState;
+/// let mut seen = BloomFilter::<str, _>::with_rate_and_hasher(1000, 0.01, RandomState::new());
+/// seen.insert("hello");
+/// assert!(seen.contains("hello"));
+/// assert!(!seen.contains("world"));
+/// ```
+pub struct BloomFilter<T: ?Sized, S, A: Allocator = Global> {
+    bits: BitString<A>,
+    hashes: u32,
+    hash_builder: S,
+    marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized, S: BuildHasher> BloomFilter<T, S> {
+    /// Creates a filter sized for `expected_items` items at a false positive rate of `fp_rate`.
+    ///
+    /// # Panics
+    /// Panics if `fp_rate` isn't strictly between 0 and 1 or if allocating fails.
+    pub fn with_rate_and_hasher(expected_items: usize, fp_rate: f64, hash_builder: S) -> Self {
+        Self::with_rate_and_hasher_in(expected_items, fp_rate, hash_builder, Global)
+            .expect("failed to allocate")
+    }
+
+    /// Creates a filter with `bits` bits that sets `hashes` bits for every item.
+    ///
+    /// # Panics
+    /// Panics if `bits` or `hashes` is zero or if allocating fails.
+    pub fn with_bits_and_hasher(bits: usize, hashes: u32, hash_builder: S) -> Self {
+        Self::with_bits_and_hasher_in(bits, hashes, hash_builder, Global)
+            .expect("failed to allocate")
+    }
+
+    /// Reads a filter written by [`to_bytes`](Self::to_bytes), returns `None` if `bytes` isn't one.
+    pub fn from_bytes(bytes: &[u8], hash_builder: S) -> Option<Self> {
+        Self::from_bytes_in(bytes, hash_builder, Global)
+            .ok()
+            .flatten()
+    }
+}
+
+impl<T: Hash + ?Sized, S: BuildHasher + Default> BloomFilter<T, S> {
+    /// Like [`with_rate_and_hasher`](Self::with_rate_and_hasher) with the default hasher.
+    pub fn with_rate(expected_items: usize, fp_rate: f64) -> Self {
+        Self::with_rate_and_hasher(expected_items, fp_rate, S::default())
+    }
+}
+
+impl<T: Hash + ?Sized, S: BuildHasher, A: Allocator> BloomFilter<T, S, A> {
+    pub fn with_rate_and_hasher_in(
+        expected_items: usize,
+        fp_rate: f64,
+        hash_builder: S,
+        allocator: A,
+    ) -> Result<Self, AllocError> {
+        let (bits, hashes) = optimal_parameters(expected_items, fp_rate);
+        Self::with_bits_and_hasher_in(bits, hashes, hash_builder, allocator)
+    }
+
+    pub fn with_bits_and_hasher_in(
+        bits: usize,
+        hashes: u32,
+        hash_builder: S,
+        allocator: A,
+    ) -> Result<Self, AllocError> {
+        assert!(bits != 0, "a bloom filter needs at least one bit");
+        assert!(hashes != 0, "a bloom filter needs at least one hash");
+        let mut string = BitString::with_capacity_in(bits, allocator)?;
+        string.resize(bits, false);
+        Ok(Self {
+            bits: string,
+            hashes,
+            hash_builder,
+            marker: PhantomData,
+        })
+    }
+
+    /// The outer error is an allocation failure and the inner `None` means `bytes` isn't a filter.
+    pub fn from_bytes_in(
+        bytes: &[u8],
+        hash_builder: S,
+        allocator: A,
+    ) -> Result<Option<Self>, AllocError> {
+        if bytes.len() < 12 {
+            return Ok(None);
+        }
+        let (header, body) = bytes.split_at(12);
+        let len = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let hashes = u32::from_le_bytes(header[8..].try_into().unwrap());
+        let Ok(len) = usize::try_from(len) else {
+            return Ok(None);
+        };
+        if len == 0 || hashes == 0 || body.len() != len.div_ceil(8) {
+            return Ok(None);
+        }
+        let body = BitSlice::from_words(body);
+        // the padding in the last byte has to be zero
+        if body.slice(len..).count_ones() != 0 {
+            return Ok(None);
+        }
+        let mut bits = BitString::with_capacity_in(len, allocator)?;
+        bits.extend_from_bitslice(body.slice(..len));
+        Ok(Some(Self {
+            bits,
+            hashes,
+            hash_builder,
+            marker: PhantomData,
+        }))
+    }
+
+    /// The number of bits in the filter.
+    #[inline]
+    pub fn num_bits(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// The number of bits every item sets.
+    #[inline]
+    pub fn num_hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// The underlying bits.
+    pub fn bits(&self) -> &BitString<A> {
+        &self.bits
+    }
+
+    /// Returns `true` if nothing has been inserted since the filter was created or cleared.
+    pub fn is_empty(&self) -> bool {
+        self.bits.first_one().is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.as_mut_bitslice().fill(false);
+    }
+
+    /// Adds `item` to the set. Returns `false` if the item might have already been in the set.
+    pub fn insert(&mut self, item: &T) -> bool {
+        let mut new = false;
+        for i in indexes(&self.hash_builder, item, self.hashes, self.bits.len()) {
+            new |= !self.bits[i];
+            self.bits.set(i, true);
+        }
+        new
+    }
+
+    /// Returns `false` if `item` was never inserted, `true` means it probably was.
+    pub fn contains(&self, item: &T) -> bool {
+        indexes(&self.hash_builder, item, self.hashes, self.bits.len()).all(|i| self.bits[i])
+    }
+
+    /// Returns `true` if the filters have the same shape, the hashers have to be checked by hand.
+    pub fn is_compatible<B: Allocator>(&self, other: &BloomFilter<T, S, B>) -> bool {
+        self.bits.len() == other.bits.len() && self.hashes == other.hashes
+    }
+
+    /// Adds every item in `other` to `self`.
+    ///
+    /// # Panics
+    /// Panics if the filters aren't [compatible](Self::is_compatible).
+    pub fn union<B: Allocator>(&mut self, other: &BloomFilter<T, S, B>) {
+        assert!(self.is_compatible(other), "incompatible bloom filters");
+        self.bits |= &other.bits;
+    }
+
+    /// Keeps only the bits that are set in both filters. Every item in both sets is still
+    /// reported, though the false positive rate can be higher than that of a new filter.
+    ///
+    /// # Panics
+    /// Panics if the filters aren't [compatible](Self::is_compatible).
+    pub fn intersect<B: Allocator>(&mut self, other: &BloomFilter<T, S, B>) {
+        assert!(self.is_compatible(other), "incompatible bloom filters");
+        self.bits &= &other.bits;
+    }
+
+    /// Estimates the number of distinct items inserted from the number of set bits.
+    pub fn estimated_len(&self) -> usize {
+        let m = self.bits.len() as f64;
+        let ones = self.bits.count_ones() as f64;
+        if ones >= m {
+            return usize::MAX;
+        }
+        (-m / self.hashes as f64 * ln(1.0 - ones / m) + 0.5) as usize
+    }
+
+    /// Serializes the filter as the number of bits (`u64`, little endian), the number of hashes
+    /// (`u32`, little endian) and then the bits packed into bytes, bit `i` in byte `i / 8`.
+    ///
+    /// The hasher isn't included, it has to be passed to [`from_bytes`](BloomFilter::from_bytes).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(12 + self.bits.len().div_ceil(8));
+        out.extend_from_slice(&(self.bits.len() as u64).to_le_bytes());
+        out.extend_from_slice(&self.hashes.to_le_bytes());
+        out.extend(self.bits.as_bitslice().chunks::<u8>());
+        out
+    }
+}
+
+impl<T: ?Sized, S: Clone, A: Allocator + Clone> Clone for BloomFilter<T, S, A> {
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits.clone(),
+            hashes: self.hashes,
+            hash_builder: self.hash_builder.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator> Debug for BloomFilter<T, S, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BloomFilter")
+            .field("bits", &self.bits.len())
+            .field("hashes", &self.hashes)
+            .field("ones", &self.bits.count_ones())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::RandomState;
+
+    #[test]
+    fn ln_matches_std() {
+        for x in [1e-300, 0.001, 0.5, 1.0, 1.5, 2.0, 10.0, 12345.678, 1e300] {
+            assert!(
+                (ln(x) - x.ln()).abs() < 1e-12 * x.ln().abs().max(1.0),
+                "{x}"
+            );
+        }
+    }
+
+    #[test]
+    fn parameters() {
+        // the usual numbers, ~9.6 bits and 7 hashes per item for 1%
+        let (bits, hashes) = optimal_parameters(1000, 0.01);
+        assert_eq!(bits, 9586);
+        assert_eq!(hashes, 7);
+    }
+
+    #[test]
+    fn no_false_negatives_and_few_positives() {
+        let mut f = BloomFilter::<u32, _>::with_rate_and_hasher(10_000, 0.01, RandomState::new());
+        for i in 0..10_000 {
+            f.insert(&i);
+        }
+        assert!((0..10_000).all(|i| f.contains(&i)));
+        let fps = (10_000..110_000).filter(|i| f.contains(i)).count();
+        assert!(fps < 2000, "{fps} false positives");
+        let est = f.estimated_len();
+        assert!((9_000..11_000).contains(&est), "{est}");
+    }
+
+    #[test]
+    fn union_intersect() {
+        let s = RandomState::new();
+        let mut a = BloomFilter::<u32, _>::with_bits_and_hasher(4096, 4, s.clone());
+        let mut b = BloomFilter::<u32, _>::with_bits_and_hasher(4096, 4, s);
+        for i in 0..100 {
+            a.insert(&i);
+        }
+        for i in 50..150 {
+            b.insert(&i);
+        }
+        let mut both = a.clone();
+        both.intersect(&b);
+        assert!((50..100).all(|i| both.contains(&i)));
+        a.union(&b);
+        assert!((0..150).all(|i| a.contains(&i)));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let s = RandomState::new();
+        let mut f = BloomFilter::<str, _>::with_bits_and_hasher(1001, 3, s.clone());
+        f.insert("a");
+        f.insert("b");
+        let bytes = f.to_bytes();
+        assert_eq!(bytes.len(), 12 + 126);
+        let g = BloomFilter::<str, _>::from_bytes(&bytes, s.clone()).unwrap();
+        assert_eq!(g.bits(), f.bits());
+        assert!(g.contains("a") && g.contains("b"));
+        assert!(BloomFilter::<str, _>::from_bytes(&bytes[..100], s.clone()).is_none());
+        let mut padded = bytes.clone();
+        *padded.last_mut().unwrap() |= 0x80;
+        assert!(BloomFilter::<str, _>::from_bytes(&padded, s).is_none());
+    }
+
+    #[test]
+    fn failed_allocations_dont_leak() {
+        use crate::fault::{Fault, FaultyAlloc};
+        use crate::hash::FxState;
+        let alloc = FaultyAlloc::with_fault(Fault::Nth(0));
+        let new = |alloc: &FaultyAlloc| {
+            BloomFilter::<u32, _, _>::with_bits_and_hasher_in(
+                100,
+                3,
+                FxState::default(),
+                alloc.clone(),
+            )
+        };
+        assert!(new(&alloc).is_err());
+        assert_eq!(alloc.live(), 0);
+
+        // the bits are allocated once up front, filling them in doesn't need more
+        alloc.set_fault(Fault::From(1));
+        let mut f = new(&alloc).unwrap();
+        f.insert(&7);
+        let bytes = f.to_bytes();
+        alloc.set_fault(Fault::Nth(0));
+        let read =
+            BloomFilter::<u32, _, _>::from_bytes_in(&bytes, FxState::default(), alloc.clone());
+        assert!(read.is_err());
+        assert!(f.contains(&7));
+
+        alloc.set_fault(Fault::Never);
+        let read =
+            BloomFilter::<u32, _, _>::from_bytes_in(&bytes, FxState::default(), alloc.clone());
+        assert!(read.unwrap().unwrap().contains(&7));
+        drop(f);
+        assert_eq!(alloc.live(), 0);
+    }
+}
@@ -0,0 +1,255 @@
+use super::{indexes, optimal_parameters};
+use crate::bitstring::BitString;
+use alloc::alloc::Global;
+use core::{
+    alloc::{AllocError, Allocator},
+    fmt::{self, Debug, Formatter},
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+
+/// the width of every counter
+const COUNTER_BITS: usize = 4;
+const SATURATED: u8 = (1 << COUNTER_BITS) - 1;
+
+/// A bloom filter that supports [`remove`](Self::remove) by keeping a small counter per slot
+/// instead of a single bit.
+///
+/// The counters are 4 bits wide and saturate at 15. A saturated counter is never decremented
+/// again since it doesn't know how many items it stands for, which keeps `remove` from ever
+/// causing false negatives.
+///
+/// # Examples
+/// ```
+/// use hash_table::bloom::CountingBloomFilter;
+/// use std::collections::hash_map::RandomState;
+/// let mut f = CountingBloomFilter::<u32, _>::with_rate_and_hasher(100, 0.01, RandomState::new());
+/// f.insert(&1);
+/// f.insert(&2);
+/// assert!(f.remove(&1));
+/// assert!(!f.contains(&1));
+/// assert!(f.contains(&2));
+/// ```
+pub struct CountingBloomFilter<T: ?Sized, S, A: Allocator = Global> {
+    /// `len * COUNTER_BITS` bits of packed counters
+    counters: BitString<A>,
+    len: usize,
+    hashes: u32,
+    hash_builder: S,
+    marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized, S: BuildHasher> CountingBloomFilter<T, S> {
+    /// Creates a filter sized for `expected_items` items at a false positive rate of `fp_rate`.
+    ///
+    /// # Panics
+    /// Panics if `fp_rate` isn't strictly between 0 and 1 or if allocating fails.
+    pub fn with_rate_and_hasher(expected_items: usize, fp_rate: f64, hash_builder: S) -> Self {
+        Self::with_rate_and_hasher_in(expected_items, fp_rate, hash_builder, Global)
+            .expect("failed to allocate")
+    }
+
+    /// Creates a filter with `counters` counters that touches `hashes` of them for every item.
+    ///
+    /// # Panics
+    /// Panics if `counters` or `hashes` is zero or if allocating fails.
+    pub fn with_counters_and_hasher(counters: usize, hashes: u32, hash_builder: S) -> Self {
+        Self::with_counters_and_hasher_in(counters, hashes, hash_builder, Global)
+            .expect("failed to allocate")
+    }
+}
+
+impl<T: Hash + ?Sized, S: BuildHasher, A: Allocator> CountingBloomFilter<T, S, A> {
+    pub fn with_rate_and_hasher_in(
+        expected_items: usize,
+        fp_rate: f64,
+        hash_builder: S,
+        allocator: A,
+    ) -> Result<Self, AllocError> {
+        let (counters, hashes) = optimal_parameters(expected_items, fp_rate);
+        Self::with_counters_and_hasher_in(counters, hashes, hash_builder, allocator)
+    }
+
+    pub fn with_counters_and_hasher_in(
+        counters: usize,
+        hashes: u32,
+        hash_builder: S,
+        allocator: A,
+    ) -> Result<Self, AllocError> {
+        assert!(counters != 0, "a bloom filter needs at least one counter");
+        assert!(hashes != 0, "a bloom filter needs at least one hash");
+        let bits = counters
+            .checked_mul(COUNTER_BITS)
+            .expect("capacity overflow");
+        let mut string = BitString::with_capacity_in(bits, allocator)?;
+        string.resize(bits, false);
+        Ok(Self {
+            counters: string,
+            len: counters,
+            hashes,
+            hash_builder,
+            marker: PhantomData,
+        })
+    }
+
+    /// The number of counters in the filter.
+    #[inline]
+    pub fn num_counters(&self) -> usize {
+        self.len
+    }
+
+    /// The number of counters every item touches.
+    #[inline]
+    pub fn num_hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Returns the value of counter `idx`.
+    #[inline]
+    pub fn counter(&self, idx: usize) -> u8 {
+        self.counters
+            .as_bitslice()
+            .load(idx * COUNTER_BITS, COUNTER_BITS)
+    }
+
+    #[inline]
+    fn set_counter(&mut self, idx: usize, val: u8) {
+        self.counters
+            .as_mut_bitslice()
+            .store(idx * COUNTER_BITS, COUNTER_BITS, val)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.first_one().is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.counters.as_mut_bitslice().fill(false);
+    }
+
+    /// Adds `item` to the set.
+    pub fn insert(&mut self, item: &T) {
+        for i in indexes(&self.hash_builder, item, self.hashes, self.len) {
+            let c = self.counter(i);
+            if c != SATURATED {
+                self.set_counter(i, c + 1);
+            }
+        }
+    }
+
+    /// Returns `false` if `item` isn't in the set, `true` means it probably is.
+    pub fn contains(&self, item: &T) -> bool {
+        indexes(&self.hash_builder, item, self.hashes, self.len).all(|i| self.counter(i) != 0)
+    }
+
+    /// Removes one copy of `item`. Returns `false` and leaves the filter untouched if `item`
+    /// definitely isn't in the set.
+    ///
+    /// Removing an item that was never inserted but is reported by [`contains`](Self::contains)
+    /// can cause false negatives for the items it collided with.
+    pub fn remove(&mut self, item: &T) -> bool {
+        if !self.contains(item) {
+            return false;
+        }
+        for i in indexes(&self.hash_builder, item, self.hashes, self.len) {
+            let c = self.counter(i);
+            // the same counter can come up twice, so check again
+            if c != SATURATED && c != 0 {
+                self.set_counter(i, c - 1);
+            }
+        }
+        true
+    }
+}
+
+impl<T: ?Sized, S: Clone, A: Allocator + Clone> Clone for CountingBloomFilter<T, S, A> {
+    fn clone(&self) -> Self {
+        Self {
+            counters: self.counters.clone(),
+            len: self.len,
+            hashes: self.hashes,
+            hash_builder: self.hash_builder.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator> Debug for CountingBloomFilter<T, S, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountingBloomFilter")
+            .field("counters", &self.len)
+            .field("hashes", &self.hashes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::RandomState;
+
+    #[test]
+    fn insert_remove() {
+        let mut f =
+            CountingBloomFilter::<u32, _>::with_rate_and_hasher(1000, 0.01, RandomState::new());
+        for i in 0..1000 {
+            f.insert(&i);
+        }
+        for i in 0..500 {
+            assert!(f.remove(&i));
+        }
+        assert!((500..1000).all(|i| f.contains(&i)));
+        let leftovers = (0..500).filter(|i| f.contains(i)).count();
+        assert!(leftovers < 50, "{leftovers}");
+        for i in 500..1000 {
+            f.remove(&i);
+        }
+        assert!(f.is_empty());
+    }
+
+    #[test]
+    fn saturates() {
+        let mut f =
+            CountingBloomFilter::<u32, _>::with_counters_and_hasher(8, 1, RandomState::new());
+        for _ in 0..20 {
+            f.insert(&7);
+        }
+        for _ in 0..20 {
+            assert!(f.remove(&7));
+        }
+        // the counter got stuck at the top so the item never goes away
+        assert!(f.contains(&7));
+        assert_eq!((0..8).map(|i| f.counter(i)).max(), Some(SATURATED));
+    }
+
+    #[test]
+    fn failed_allocations_dont_leak() {
+        use crate::fault::{Fault, FaultyAlloc};
+        use crate::hash::FxState;
+        let alloc = FaultyAlloc::with_fault(Fault::Nth(0));
+        let new = |alloc: &FaultyAlloc| {
+            CountingBloomFilter::<u32, _, _>::with_counters_and_hasher_in(
+                100,
+                3,
+                FxState::default(),
+                alloc.clone(),
+            )
+        };
+        assert!(new(&alloc).is_err());
+        assert_eq!(alloc.live(), 0);
+
+        // the counters are allocated once up front, zeroing them doesn't need more
+        alloc.set_fault(Fault::From(1));
+        let mut f = new(&alloc).unwrap();
+        f.insert(&7);
+        assert!(f.remove(&7));
+        assert!(f.is_empty());
+        drop(f);
+        assert_eq!(alloc.live(), 0);
+    }
+}
@@ -0,0 +1,326 @@
+//! An [`Allocator`] that fails when it's told to, for exercising the `AllocError` paths.
+//!
+//! Clones share their state, so a container and the test holding on to the allocator see the
+//! same counts and the fault can be changed after the container is built. Every live block is
+//! tracked by address: freeing something that wasn't handed out, or with a different layout,
+//! panics, and whatever is still in [`live`](FaultyAlloc::live) after a drop was leaked.
+
+use alloc::{alloc::Global, collections::BTreeMap, rc::Rc};
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::{Cell, RefCell},
+    fmt::{self, Debug, Formatter},
+    ptr::NonNull,
+};
+
+/// When a [`FaultyAlloc`] fails. Counting starts over whenever the fault is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    Never,
+    /// only the `n`th allocation, 0 being the next one
+    Nth(usize),
+    /// the `n`th allocation and everything after it
+    From(usize),
+    /// any allocation that would take the total bytes allocated past `n`
+    AfterBytes(usize),
+    /// every allocation fails with probability `chance`, the same ones for the same seed
+    Random {
+        seed: u64,
+        chance: f64,
+    },
+}
+
+struct State<A> {
+    inner: A,
+    fault: Cell<Fault>,
+    rng: Cell<u64>,
+    // since the fault was set
+    attempts: Cell<usize>,
+    bytes: Cell<usize>,
+    failures: Cell<usize>,
+    // address to layout, zero sized blocks aren't tracked since they all share an address
+    live: RefCell<BTreeMap<usize, Layout>>,
+}
+
+/// Wraps another allocator, failing according to its [`Fault`] and tracking what's live.
+pub struct FaultyAlloc<A: Allocator = Global> {
+    state: Rc<State<A>>,
+}
+
+impl FaultyAlloc {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+
+    pub fn with_fault(fault: Fault) -> Self {
+        let alloc = Self::new();
+        alloc.set_fault(fault);
+        alloc
+    }
+}
+
+impl Default for FaultyAlloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Allocator> FaultyAlloc<A> {
+    pub fn new_in(inner: A) -> Self {
+        Self {
+            state: Rc::new(State {
+                inner,
+                fault: Cell::new(Fault::Never),
+                rng: Cell::new(0),
+                attempts: Cell::new(0),
+                bytes: Cell::new(0),
+                failures: Cell::new(0),
+                live: RefCell::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// Changes the fault for this allocator and all its clones.
+    pub fn set_fault(&self, fault: Fault) {
+        let state = &self.state;
+        if let Fault::Random { seed, .. } = fault {
+            state.rng.set(seed);
+        }
+        state.fault.set(fault);
+        state.attempts.set(0);
+        state.bytes.set(0);
+    }
+
+    pub fn fault(&self) -> Fault {
+        self.state.fault.get()
+    }
+
+    /// how many allocations have been refused, ever
+    pub fn failures(&self) -> usize {
+        self.state.failures.get()
+    }
+
+    /// how many blocks are currently allocated
+    pub fn live(&self) -> usize {
+        self.state.live.borrow().len()
+    }
+
+    pub fn live_bytes(&self) -> usize {
+        self.state.live.borrow().values().map(Layout::size).sum()
+    }
+
+    // splitmix64
+    fn next_random(&self) -> f64 {
+        let x = self.state.rng.get().wrapping_add(0x9e3779b97f4a7c15);
+        self.state.rng.set(x);
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// counts an allocation of `size` more bytes, refusing it if the fault says so
+    fn attempt(&self, size: usize) -> Result<(), AllocError> {
+        let state = &self.state;
+        let n = state.attempts.get();
+        state.attempts.set(n + 1);
+        let bytes = state.bytes.get().saturating_add(size);
+        let fail = match state.fault.get() {
+            Fault::Never => false,
+            Fault::Nth(k) => n == k,
+            Fault::From(k) => n >= k,
+            Fault::AfterBytes(limit) => bytes > limit,
+            Fault::Random { chance, .. } => self.next_random() < chance,
+        };
+        if fail {
+            state.failures.set(state.failures.get() + 1);
+            Err(AllocError)
+        } else {
+            state.bytes.set(bytes);
+            Ok(())
+        }
+    }
+
+    fn track(&self, block: NonNull<[u8]>, layout: Layout) {
+        if layout.size() != 0 {
+            let addr = block.cast::<u8>().as_ptr() as usize;
+            self.state.live.borrow_mut().insert(addr, layout);
+        }
+    }
+
+    /// panics if `ptr` isn't a live block with `layout`
+    fn untrack(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+        let addr = ptr.as_ptr() as usize;
+        match self.state.live.borrow_mut().remove(&addr) {
+            Some(actual) if actual == layout => {}
+            Some(actual) => panic!(
+                "block at {:#x} was allocated as {:?} but freed as {:?}",
+                addr, actual, layout
+            ),
+            None => panic!("freed {:#x} which isn't allocated", addr),
+        }
+    }
+}
+
+impl<A: Allocator> Clone for FaultyAlloc<A> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<A: Allocator> Debug for FaultyAlloc<A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultyAlloc")
+            .field("fault", &self.fault())
+            .field("failures", &self.failures())
+            .field("live", &self.live())
+            .field("live_bytes", &self.live_bytes())
+            .finish()
+    }
+}
+
+// every block comes from `inner` and is handed back to it, the tracking is on the side
+unsafe impl<A: Allocator> Allocator for FaultyAlloc<A> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.attempt(layout.size())?;
+        let block = self.state.inner.allocate(layout)?;
+        self.track(block, layout);
+        Ok(block)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.untrack(ptr, layout);
+        self.state.inner.deallocate(ptr, layout)
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.attempt(new_layout.size() - old_layout.size())?;
+        let block = self.state.inner.grow(ptr, old_layout, new_layout)?;
+        self.untrack(ptr, old_layout);
+        self.track(block, new_layout);
+        Ok(block)
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.attempt(new_layout.size() - old_layout.size())?;
+        let block = self.state.inner.grow_zeroed(ptr, old_layout, new_layout)?;
+        self.untrack(ptr, old_layout);
+        self.track(block, new_layout);
+        Ok(block)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.attempt(0)?;
+        let block = self.state.inner.shrink(ptr, old_layout, new_layout)?;
+        self.untrack(ptr, old_layout);
+        self.track(block, new_layout);
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use std::vec::Vec;
+
+    fn outcomes(alloc: &FaultyAlloc, n: usize) -> Vec<bool> {
+        (0..n)
+            .map(|_| Box::try_new_in(0u64, alloc.clone()).is_ok())
+            .collect()
+    }
+
+    #[test]
+    fn nth_only_fails_once() {
+        let alloc = FaultyAlloc::with_fault(Fault::Nth(2));
+        assert_eq!(outcomes(&alloc, 5), [true, true, false, true, true]);
+        assert_eq!(alloc.failures(), 1);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn from_keeps_failing() {
+        let alloc = FaultyAlloc::with_fault(Fault::From(3));
+        assert_eq!(outcomes(&alloc, 5), [true, true, true, false, false]);
+        // setting it again starts the count over
+        alloc.set_fault(Fault::From(1));
+        assert_eq!(outcomes(&alloc, 2), [true, false]);
+    }
+
+    #[test]
+    fn after_bytes() {
+        let alloc = FaultyAlloc::with_fault(Fault::AfterBytes(20));
+        let kept: Vec<_> = (0..4)
+            .map(|_| Box::try_new_in(0u64, alloc.clone()))
+            .collect();
+        assert!(kept[0].is_ok() && kept[1].is_ok());
+        assert!(kept[2].is_err() && kept[3].is_err());
+        assert_eq!(alloc.live_bytes(), 16);
+    }
+
+    #[test]
+    fn random_is_seeded() {
+        let fault = Fault::Random {
+            seed: 48,
+            chance: 0.5,
+        };
+        let a = outcomes(&FaultyAlloc::with_fault(fault), 200);
+        let b = outcomes(&FaultyAlloc::with_fault(fault), 200);
+        assert_eq!(a, b);
+        let failed = a.iter().filter(|ok| !**ok).count();
+        assert!((50..150).contains(&failed), "{} failures", failed);
+    }
+
+    #[test]
+    fn tracks_live_blocks() {
+        let alloc = FaultyAlloc::new();
+        let a = Box::new_in([0u8; 10], alloc.clone());
+        let b = Box::new_in([0u8; 6], alloc.clone());
+        assert_eq!((alloc.live(), alloc.live_bytes()), (2, 16));
+        drop(a);
+        assert_eq!((alloc.live(), alloc.live_bytes()), (1, 6));
+        drop(b);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't allocated")]
+    fn double_free_panics() {
+        let alloc = FaultyAlloc::new();
+        let layout = Layout::new::<u64>();
+        let block = alloc.allocate(layout).unwrap().cast();
+        unsafe {
+            alloc.deallocate(block, layout);
+            alloc.deallocate(block, layout);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "freed as")]
+    fn wrong_layout_panics() {
+        let alloc = FaultyAlloc::new();
+        let block = alloc.allocate(Layout::new::<u64>()).unwrap().cast();
+        unsafe { alloc.deallocate(block, Layout::new::<u32>()) };
+    }
+}
@@ -0,0 +1,205 @@
+//! [`BuildHasher`]s that take their seed explicitly.
+//!
+//! std's `RandomState` picks its keys from the OS, which is what you want from a table facing
+//! the outside world but not from a test that has to hash the same way every time it's replayed.
+//! Everything here is `no_std` and the same seed always gives the same hashes, on any run.
+//!
+//! - [`Sip13State`] / [`Sip24State`]: SipHash, the 1-3 variant is what std's `DefaultHasher` uses
+//! - [`FxState`]: rustc's multiply-rotate hash, fast and not at all DoS resistant
+//! - [`WyState`]: wyhash, fast with decent quality
+//! - [`LowEntropyState`]: deliberately terrible, only a few bits of the hash vary so tables get
+//!   long collision chains
+
+use core::hash::BuildHasher;
+
+mod fx;
+mod sip;
+mod weak;
+mod wy;
+
+pub use fx::{FxHasher, FxState};
+pub use sip::{Sip13State, Sip24State, SipHasher, SipHasher13, SipHasher24, SipState};
+pub use weak::{LowEntropyHasher, LowEntropyState};
+pub use wy::{wyhash, WyHasher, WyState};
+
+/// A [`BuildHasher`] made from a seed, like `rand`'s `SeedableRng` but for hashers.
+pub trait SeedableState: BuildHasher + Sized {
+    fn from_seed(seed: [u8; 16]) -> Self;
+
+    /// [`from_seed`](SeedableState::from_seed) with the seed spread over both halves
+    fn from_u64(seed: u64) -> Self {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&seed.to_le_bytes());
+        bytes[8..].copy_from_slice(&(!seed).rotate_left(32).to_le_bytes());
+        Self::from_seed(bytes)
+    }
+}
+
+/// the two little endian halves of a seed
+fn seed_words(seed: [u8; 16]) -> (u64, u64) {
+    let (lo, hi) = seed.split_at(8);
+    (
+        u64::from_le_bytes(lo.try_into().unwrap()),
+        u64::from_le_bytes(hi.try_into().unwrap()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_table::SCHashTable;
+    use crate::traits::hash_table::HashTable;
+    use core::hash::{Hash, Hasher};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::{alloc::Global, collections::HashMap, vec::Vec};
+
+    fn hash<S: BuildHasher, T: Hash>(state: &S, value: T) -> u64 {
+        let mut hasher = state.build_hasher();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn key_bytes() -> [u8; 16] {
+        core::array::from_fn(|i| i as u8)
+    }
+
+    #[test]
+    fn sip24_reference_vector() {
+        // from the end of the SipHash paper, key 00..0f and message 00..0e
+        let message: Vec<u8> = (0..15).collect();
+        let mut hasher = Sip24State::from_seed(key_bytes()).build_hasher();
+        hasher.write(&message);
+        assert_eq!(hasher.finish(), 0xa129ca6149be45e5);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn sip_matches_core() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for len in 0..70 {
+            let message: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let (k0, k1) = (rng.gen(), rng.gen());
+
+            let mut ours = SipHasher24::new_with_keys(k0, k1);
+            let mut theirs = core::hash::SipHasher::new_with_keys(k0, k1);
+            ours.write(&message);
+            theirs.write(&message);
+            assert_eq!(ours.finish(), theirs.finish(), "2-4, length {}", len);
+
+            let mut ours = SipHasher13::new_with_keys(k0, k1);
+            let mut theirs = core::hash::SipHasher13::new_with_keys(k0, k1);
+            ours.write(&message);
+            theirs.write(&message);
+            assert_eq!(ours.finish(), theirs.finish(), "1-3, length {}", len);
+        }
+    }
+
+    #[test]
+    fn split_writes_are_the_same_as_one() {
+        let message: Vec<u8> = (0..40).collect();
+        let state = Sip13State::from_seed(key_bytes());
+        let mut whole = state.build_hasher();
+        whole.write(&message);
+        for split in [0, 1, 7, 8, 9, 23, 40] {
+            let mut parts = state.build_hasher();
+            parts.write(&message[..split]);
+            parts.write(&message[split..]);
+            assert_eq!(parts.finish(), whole.finish(), "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn fx_known_values() {
+        // seed 0 is plain FxHash, a single word is just multiplied by the constant
+        let state = FxState::default();
+        assert_eq!(hash(&state, 1u64), 0x517cc1b727220a95);
+        assert_eq!(hash(&state, 0u64), 0);
+    }
+
+    #[test]
+    fn wyhash_lengths() {
+        // every branch of the length handling, none of them should collide with the others
+        let message: Vec<u8> = (0..100).collect();
+        let mut seen: Vec<u64> = (0..=100).map(|len| wyhash(&message[..len], 0)).collect();
+        assert_eq!(wyhash(&message[..33], 0), wyhash(&message[..33], 0));
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), 101);
+    }
+
+    #[test]
+    fn wyhash_reference_vectors() {
+        // the test vectors published with the final version 4 reference, seeded by index
+        let vectors: [(&str, u64); 7] = [
+            ("", 0x93228a4de0eec5a2),
+            ("a", 0xc5bac3db178713c4),
+            ("abc", 0xa97f2f7b1d9b3314),
+            ("message digest", 0x786d1f1df3801df4),
+            ("abcdefghijklmnopqrstuvwxyz", 0xdca5a8138ad37c87),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                0xb9e734f117cfaf70,
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                0x6cc5eab49a92d617,
+            ),
+        ];
+        for (seed, (message, expected)) in vectors.into_iter().enumerate() {
+            let hash = wyhash(message.as_bytes(), seed as u64);
+            assert_eq!(hash, expected, "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn seeds_are_deterministic_and_matter() {
+        fn check<S: SeedableState>() {
+            let a = S::from_u64(1);
+            let b = S::from_u64(2);
+            assert_eq!(hash(&a, "some key"), hash(&S::from_u64(1), "some key"));
+            assert_ne!(hash(&a, "some key"), hash(&b, "some key"));
+        }
+        check::<Sip13State>();
+        check::<Sip24State>();
+        check::<FxState>();
+        check::<WyState>();
+        check::<LowEntropyState>();
+    }
+
+    #[test]
+    fn low_entropy_only_uses_its_bits() {
+        let state = LowEntropyState::new(0x10, 2);
+        let mut hashes: Vec<u64> = (0..1000u32).map(|i| hash(&state, i)).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        assert_eq!(hashes, [0x10, 0x11, 0x12, 0x13]);
+
+        let constant = LowEntropyState::new(7, 0);
+        assert!((0..100u32).all(|i| hash(&constant, i) == 7));
+    }
+
+    #[test]
+    fn table_with_colliding_hashes() {
+        // every key lands in one of two buckets, so chains get long and removes hit the middle of them
+        let mut table = SCHashTable::<u16, u32, _, Global>::with_capacity_and_hasher_in(
+            16,
+            LowEntropyState::new(0, 1),
+            Global,
+        )
+        .unwrap();
+        let mut model = HashMap::new();
+        let mut rng = StdRng::seed_from_u64(49);
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..200);
+            match rng.gen_range(0..3) {
+                0 => assert_eq!(
+                    table.insert(key, key as u32).unwrap(),
+                    model.insert(key, key as u32)
+                ),
+                1 => assert_eq!(table.remove(&key), model.remove(&key)),
+                _ => assert_eq!(table.get(&key), model.get(&key)),
+            }
+            assert_eq!(table.len(), model.len());
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::{seed_words, SeedableState};
+use core::hash::{BuildHasher, Hasher};
+
+const K: u64 = 0x517cc1b727220a95;
+
+/// The hash rustc uses internally, one rotate, xor and multiply per word. Seed 0 gives the
+/// same hashes as the `rustc-hash` crate on 64 bit targets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { hash: seed }
+    }
+
+    #[inline]
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(K);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.add(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let mut rest = words.remainder();
+        if rest.len() >= 4 {
+            self.add(u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64);
+            rest = &rest[4..];
+        }
+        if rest.len() >= 2 {
+            self.add(u16::from_le_bytes(rest[..2].try_into().unwrap()) as u64);
+            rest = &rest[2..];
+        }
+        if let Some(&b) = rest.first() {
+            self.add(b as u64);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Builds [`FxHasher`]s starting from a fixed seed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FxState {
+    seed: u64,
+}
+
+impl FxState {
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for FxState {
+    type Hasher = FxHasher;
+    fn build_hasher(&self) -> FxHasher {
+        FxHasher::with_seed(self.seed)
+    }
+}
+
+impl SeedableState for FxState {
+    fn from_seed(seed: [u8; 16]) -> Self {
+        let (lo, hi) = seed_words(seed);
+        Self::with_seed(lo ^ hi)
+    }
+}
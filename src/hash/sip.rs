@@ -0,0 +1,129 @@
+use super::{seed_words, SeedableState};
+use core::hash::{BuildHasher, Hasher};
+
+/// SipHash with `C` compression rounds per word and `D` finalization rounds.
+#[derive(Debug, Clone, Copy)]
+pub struct SipHasher<const C: usize, const D: usize> {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    // bytes that haven't made a whole word yet, little endian in the low bytes
+    tail: u64,
+    ntail: usize,
+    // only the low byte ends up in the hash
+    length: usize,
+}
+
+pub type SipHasher13 = SipHasher<1, 3>;
+pub type SipHasher24 = SipHasher<2, 4>;
+
+impl<const C: usize, const D: usize> SipHasher<C, D> {
+    pub const fn new_with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            v0: k0 ^ 0x736f6d6570736575,
+            v1: k1 ^ 0x646f72616e646f6d,
+            v2: k0 ^ 0x6c7967656e657261,
+            v3: k1 ^ 0x7465646279746573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    #[inline]
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    #[inline]
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        for _ in 0..C {
+            self.round();
+        }
+        self.v0 ^= word;
+    }
+}
+
+impl<const C: usize, const D: usize> Hasher for SipHasher<C, D> {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len());
+
+        // top up the tail first
+        if self.ntail != 0 {
+            let take = (8 - self.ntail).min(bytes.len());
+            for (i, &b) in bytes[..take].iter().enumerate() {
+                self.tail |= (b as u64) << (8 * (self.ntail + i));
+            }
+            self.ntail += take;
+            bytes = &bytes[take..];
+            if self.ntail < 8 {
+                return;
+            }
+            self.compress(self.tail);
+            self.tail = 0;
+            self.ntail = 0;
+        }
+
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.compress(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        for (i, &b) in words.remainder().iter().enumerate() {
+            self.tail |= (b as u64) << (8 * i);
+        }
+        self.ntail = words.remainder().len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = *self;
+        let last = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(last);
+        state.v2 ^= 0xff;
+        for _ in 0..D {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
+
+/// Builds [`SipHasher`]s with a fixed pair of keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SipState<const C: usize, const D: usize> {
+    k0: u64,
+    k1: u64,
+}
+
+pub type Sip13State = SipState<1, 3>;
+pub type Sip24State = SipState<2, 4>;
+
+impl<const C: usize, const D: usize> SipState<C, D> {
+    pub const fn with_keys(k0: u64, k1: u64) -> Self {
+        Self { k0, k1 }
+    }
+}
+
+impl<const C: usize, const D: usize> BuildHasher for SipState<C, D> {
+    type Hasher = SipHasher<C, D>;
+    fn build_hasher(&self) -> Self::Hasher {
+        SipHasher::new_with_keys(self.k0, self.k1)
+    }
+}
+
+impl<const C: usize, const D: usize> SeedableState for SipState<C, D> {
+    /// the seed is the 128 bit key, little endian like the reference implementation
+    fn from_seed(seed: [u8; 16]) -> Self {
+        let (k0, k1) = seed_words(seed);
+        Self::with_keys(k0, k1)
+    }
+}
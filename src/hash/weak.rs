@@ -0,0 +1,69 @@
+use super::{seed_words, SeedableState};
+use core::hash::{BuildHasher, Hasher};
+
+/// FNV-1a folded down to a handful of bits and xored onto the seed. Only for tests: with
+/// `bits` bits of hash at most `2^bits` buckets are ever used, and with 0 every key collides.
+#[derive(Debug, Clone, Copy)]
+pub struct LowEntropyHasher {
+    hash: u64,
+    seed: u64,
+    bits: u32,
+}
+
+impl Hasher for LowEntropyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.hash = (self.hash ^ b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mask = 1u64.checked_shl(self.bits).map_or(u64::MAX, |bit| bit - 1);
+        self.seed ^ (self.hash & mask)
+    }
+}
+
+/// Builds [`LowEntropyHasher`]s, see there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LowEntropyState {
+    seed: u64,
+    bits: u32,
+}
+
+impl LowEntropyState {
+    /// how many bits come out when [`from_seed`](SeedableState::from_seed) makes one
+    pub const DEFAULT_BITS: u32 = 2;
+
+    pub const fn new(seed: u64, bits: u32) -> Self {
+        Self { seed, bits }
+    }
+
+    /// a hasher that returns `value` for everything
+    pub const fn constant(value: u64) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl Default for LowEntropyState {
+    fn default() -> Self {
+        Self::new(0, Self::DEFAULT_BITS)
+    }
+}
+
+impl BuildHasher for LowEntropyState {
+    type Hasher = LowEntropyHasher;
+    fn build_hasher(&self) -> LowEntropyHasher {
+        LowEntropyHasher {
+            hash: 0xcbf29ce484222325,
+            seed: self.seed,
+            bits: self.bits,
+        }
+    }
+}
+
+impl SeedableState for LowEntropyState {
+    fn from_seed(seed: [u8; 16]) -> Self {
+        let (lo, hi) = seed_words(seed);
+        Self::new(lo ^ hi, Self::DEFAULT_BITS)
+    }
+}
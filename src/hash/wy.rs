@@ -0,0 +1,127 @@
+use super::{seed_words, SeedableState};
+use core::hash::{BuildHasher, Hasher};
+
+// `_wyp`, the default secret of the final version 4 reference implementation
+const SECRET: [u64; 4] = [
+    0x2d358dccaa6c78a5,
+    0x8bb84b93962eacc9,
+    0x4b33a62ed433d4a3,
+    0x4d5a2da51de1aa47,
+];
+
+#[inline]
+fn mum(a: u64, b: u64) -> (u64, u64) {
+    let r = a as u128 * b as u128;
+    (r as u64, (r >> 64) as u64)
+}
+
+#[inline]
+fn mix(a: u64, b: u64) -> u64 {
+    let (lo, hi) = mum(a, b);
+    lo ^ hi
+}
+
+#[inline]
+fn r8(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+#[inline]
+fn r4(bytes: &[u8], at: usize) -> u64 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as u64
+}
+
+/// One-shot wyhash of `bytes`, the final version 4 algorithm with the `_wyp` secret.
+pub fn wyhash(bytes: &[u8], seed: u64) -> u64 {
+    let len = bytes.len();
+    let mut seed = seed ^ mix(seed ^ SECRET[0], SECRET[1]);
+    let (a, b) = if len <= 16 {
+        if len >= 4 {
+            let quarter = (len >> 3) << 2;
+            (
+                (r4(bytes, 0) << 32) | r4(bytes, quarter),
+                (r4(bytes, len - 4) << 32) | r4(bytes, len - 4 - quarter),
+            )
+        } else if len > 0 {
+            let a =
+                ((bytes[0] as u64) << 16) | ((bytes[len >> 1] as u64) << 8) | bytes[len - 1] as u64;
+            (a, 0)
+        } else {
+            (0, 0)
+        }
+    } else {
+        let mut p = bytes;
+        if p.len() > 48 {
+            let (mut see1, mut see2) = (seed, seed);
+            while p.len() > 48 {
+                seed = mix(r8(p, 0) ^ SECRET[1], r8(p, 8) ^ seed);
+                see1 = mix(r8(p, 16) ^ SECRET[2], r8(p, 24) ^ see1);
+                see2 = mix(r8(p, 32) ^ SECRET[3], r8(p, 40) ^ see2);
+                p = &p[48..];
+            }
+            seed ^= see1 ^ see2;
+        }
+        while p.len() > 16 {
+            seed = mix(r8(p, 0) ^ SECRET[1], r8(p, 8) ^ seed);
+            p = &p[16..];
+        }
+        // the last 16 bytes of the whole input, which can overlap what was already mixed in
+        (r8(bytes, len - 16), r8(bytes, len - 8))
+    };
+    let (a, b) = mum(a ^ SECRET[1], b ^ seed);
+    mix(a ^ SECRET[0] ^ len as u64, b ^ SECRET[1])
+}
+
+/// A streaming wyhash. Every write is hashed with the state so far as its seed, so writing
+/// `ab` and writing `a` then `b` hash differently, which `Hash` impls never rely on anyway.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WyHasher {
+    state: u64,
+    length: u64,
+}
+
+impl WyHasher {
+    pub const fn with_seed(seed: u64) -> Self {
+        Self {
+            state: seed,
+            length: 0,
+        }
+    }
+}
+
+impl Hasher for WyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.state = wyhash(bytes, self.state);
+        self.length = self.length.wrapping_add(bytes.len() as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        mix(self.state ^ SECRET[0], self.length ^ SECRET[1])
+    }
+}
+
+/// Builds [`WyHasher`]s starting from a fixed seed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WyState {
+    seed: u64,
+}
+
+impl WyState {
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for WyState {
+    type Hasher = WyHasher;
+    fn build_hasher(&self) -> WyHasher {
+        WyHasher::with_seed(self.seed)
+    }
+}
+
+impl SeedableState for WyState {
+    fn from_seed(seed: [u8; 16]) -> Self {
+        let (lo, hi) = seed_words(seed);
+        Self::with_seed(lo ^ hi)
+    }
+}
@@ -7,13 +7,19 @@ pub mod seperate_chaining;
 use alloc::alloc::Global;
 
 use core::{
-    alloc::Allocator,
+    alloc::{AllocError, Allocator},
     //collections::hash_map::RandomState,
     fmt::Debug,
     hash::{BuildHasher, Hash},
     marker::PhantomData,
+    mem,
 };
 
+use crate::prelude::{Drainable, DrainableBy, Iterable, IterableMut};
+use crate::rc::{Trace, Tracer};
+use crate::traits::adt::{Creatable, DynamicSet, MultiSet, Set, SetSize, StaticSet};
+use crate::traits::collection::{Capacity, Clear, Collection};
+use crate::traits::hash_table::HashTable as HashTableTrait;
 use crate::traits::hash_table::*;
 
 pub type SCHashTable<K, V, S /*= RandomState*/, A = Global> =
@@ -43,6 +49,18 @@ where
     }
 }
 
+unsafe impl<K, V, S, A, T> Trace for HashTable<K, V, S, A, T>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+    T: HashTableImpl<K, V, S, A> + Trace,
+{
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.inner.trace(tracer)
+    }
+}
+
 impl<K, V, S, T> Default for HashTable<K, V, S, Global, T>
 where
     K: Eq + Hash,
@@ -58,6 +76,354 @@ where
     }
 }
 
+impl<K, V, S, A> SCHashTable<K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)
+    }
+
+    /// The entries in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.inner.iter()
+    }
+
+    /// Keeps the entries `f` returns `true` for, `f` can also change the values it keeps.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.inner.retain(f)
+    }
+
+    /// Moves every entry of `other` into `self`, keys in both get `f(ours, theirs)`.
+    ///
+    /// Keys `self` already has are merged in place, only new keys allocate. So on `Err` `self`
+    /// still has every entry it started with, merged if `other` got to them first, plus the new
+    /// ones moved over before the failure. The entry that failed and the rest of `other` are
+    /// dropped.
+    pub fn union_with<F>(&mut self, mut other: Self, mut f: F) -> Result<(), AllocError>
+    where
+        F: FnMut(V, V) -> V,
+    {
+        for (k, theirs) in other.inner.drain_by(|_| true) {
+            let merging = Unlink {
+                table: &mut self.inner,
+                key: &k,
+            };
+            match merging.table.get_mut(&k).map(|ours| ours as *mut V) {
+                Some(ours) => {
+                    unsafe { ours.write(f(ours.read(), theirs)) };
+                    mem::forget(merging);
+                }
+                None => {
+                    mem::forget(merging);
+                    self.inner.insert(k, theirs)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// While a value is moved out of the table for `f` in `union_with`. If `f` panics the entry
+/// is unlinked and its value forgotten, since `f` already has it.
+struct Unlink<'a, K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    table: &'a mut seperate_chaining::SLLHashTableImpl<K, V, S, A>,
+    key: &'a K,
+}
+
+impl<K, V, S, A> Drop for Unlink<'_, K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        mem::forget(self.table.remove(self.key));
+    }
+}
+
+impl<K, V, S, A> DrainableBy for SCHashTable<K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+    type DrainBy<'a, F> = seperate_chaining::DrainBy<'a, K, V, A, F>
+    where
+        Self: 'a, F: FnMut(&(K, V)) -> bool + 'a;
+    fn drain_by<'a, F>(&'a mut self, pred: F) -> Self::DrainBy<'a, F>
+    where
+        F: FnMut(&(K, V)) -> bool + 'a,
+    {
+        self.inner.drain_by(pred)
+    }
+}
+
+impl<K, V, S, A> Drainable for SCHashTable<K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+    type Drain<'a> = seperate_chaining::DrainBy<'a, K, V, A, fn(&(K, V)) -> bool>
+    where
+        Self: 'a;
+    fn drain(&mut self) -> Self::Drain<'_> {
+        let everything: fn(&(K, V)) -> bool = |_| true;
+        self.inner.drain_by(everything)
+    }
+}
+
+impl<K, V, S, A> Iterable for SCHashTable<K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = V;
+    type Iter<'a> = seperate_chaining::Values<'a, K, V, A>
+    where
+        Self: 'a;
+    fn iter(&self) -> Self::Iter<'_> {
+        self.inner.values()
+    }
+}
+
+impl<K, V, S, A> IterableMut for SCHashTable<K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = V;
+    type IterMut<'a> = seperate_chaining::ValuesMut<'a, K, V, A>
+    where
+        Self: 'a;
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.inner.values_mut()
+    }
+}
+
+impl<K, V, S, A, T> Collection for HashTable<K, V, S, A, T>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+    T: HashTableImpl<K, V, S, A>,
+{
+    type Item = V;
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<K, V, S, A, T> Clear for HashTable<K, V, S, A, T>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+    T: HashTableImpl<K, V, S, A>,
+{
+    fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+impl<K, V, S, A, T> Capacity for HashTable<K, V, S, A, T>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+    T: HashTableImpl<K, V, S, A>,
+{
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<K, V, S> Creatable for SCHashTable<K, V, S, Global>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn new() -> Self {
+        HashTableTrait::with_capacity_and_hasher_in(0, S::default(), Global)
+            .expect("failed to allocate")
+    }
+}
+
+/// A set is a table of `()`s.
+impl<K, S, A> Set<K> for SCHashTable<K, (), S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn union(mut self, other: Self) -> Self {
+        self.union_with(other, |a, _| a)
+            .expect("failed to allocate");
+        self
+    }
+    fn intersection(mut self, other: Self) -> Self {
+        self.retain(|k, _| other.inner.get(k).is_some());
+        self
+    }
+    fn difference(mut self, other: Self) -> Self {
+        self.retain(|k, _| other.inner.get(k).is_none());
+        self
+    }
+    fn is_subset(&self, other: &Self) -> bool {
+        self.iter().all(|(k, _)| other.inner.get(k).is_some())
+    }
+}
+
+impl<K, S, A> StaticSet<K> for SCHashTable<K, (), S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn is_element_of(&self, elem: &K) -> bool {
+        self.inner.get(elem).is_some()
+    }
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+    fn size(&self) -> SetSize {
+        SetSize::Finite(self.inner.len())
+    }
+}
+
+impl<K, S> DynamicSet<K> for SCHashTable<K, (), S, Global>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn with_capacity(capacity: usize) -> Self {
+        HashTableTrait::with_capacity_and_hasher_in(capacity, S::default(), Global)
+            .expect("failed to allocate")
+    }
+    fn add(&mut self, elem: K) -> Result<bool, AllocError> {
+        Ok(self.inner.insert(elem, ())?.is_none())
+    }
+    fn remove(&mut self, elem: &K) -> bool {
+        self.inner.remove(elem).is_some()
+    }
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+/// A multiset is a table of counts, entries never hold a zero.
+impl<K, S, A> Set<K> for SCHashTable<K, usize, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn union(mut self, other: Self) -> Self {
+        self.union_with(other, usize::max)
+            .expect("failed to allocate");
+        self
+    }
+    fn intersection(mut self, other: Self) -> Self {
+        self.retain(|k, n| {
+            *n = (*n).min(other.inner.get(k).copied().unwrap_or(0));
+            *n != 0
+        });
+        self
+    }
+    fn difference(mut self, other: Self) -> Self {
+        self.retain(|k, n| {
+            *n = n.saturating_sub(other.inner.get(k).copied().unwrap_or(0));
+            *n != 0
+        });
+        self
+    }
+    fn is_subset(&self, other: &Self) -> bool {
+        self.iter()
+            .all(|(k, n)| other.inner.get(k).is_some_and(|m| n <= m))
+    }
+}
+
+impl<K, S, A> StaticSet<K> for SCHashTable<K, usize, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn is_element_of(&self, elem: &K) -> bool {
+        self.inner.get(elem).is_some()
+    }
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+    /// counts every copy
+    fn size(&self) -> SetSize {
+        SetSize::Finite(self.iter().map(|(_, n)| n).sum())
+    }
+}
+
+impl<K, S> DynamicSet<K> for SCHashTable<K, usize, S, Global>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn with_capacity(capacity: usize) -> Self {
+        HashTableTrait::with_capacity_and_hasher_in(capacity, S::default(), Global)
+            .expect("failed to allocate")
+    }
+    fn add(&mut self, elem: K) -> Result<bool, AllocError> {
+        if let Some(n) = self.get_mut(&elem) {
+            *n += 1;
+            return Ok(false);
+        }
+        self.inner.insert(elem, 1)?;
+        Ok(true)
+    }
+    fn remove(&mut self, elem: &K) -> bool {
+        match self.get_mut(elem) {
+            Some(1) => {
+                self.inner.remove(elem);
+                true
+            }
+            Some(n) => {
+                *n -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<K, S> MultiSet<K> for SCHashTable<K, usize, S, Global>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn count(&self, elem: &K) -> usize {
+        self.inner.get(elem).copied().unwrap_or(0)
+    }
+    fn remove_all(&mut self, elem: &K) -> usize {
+        self.inner.remove(elem).unwrap_or(0)
+    }
+}
+
 impl<K, V, S, A, T> crate::traits::hash_table::HashTable<K, V, S, A> for HashTable<K, V, S, A, T>
 where
     K: Eq + Hash,
@@ -100,3 +466,180 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fault::{Fault, FaultyAlloc};
+    use crate::hash::{FxState, LowEntropyState};
+    use alloc::boxed::Box;
+    use std::collections::HashMap;
+
+    type Table<S> = SCHashTable<u32, Box<u32, FaultyAlloc>, S, FaultyAlloc>;
+
+    #[test]
+    fn failed_grow_keeps_entries() {
+        let alloc = FaultyAlloc::new();
+        let boxed = |i| Box::new_in(i, alloc.clone());
+        let mut table =
+            Table::with_capacity_and_hasher_in(4, FxState::default(), alloc.clone()).unwrap();
+        for i in 0..4 {
+            table.insert(i, boxed(i)).unwrap();
+        }
+
+        // the new bucket array can't be allocated
+        let value = boxed(4);
+        alloc.set_fault(Fault::Nth(0));
+        assert!(table.insert(4, value).is_err());
+        assert_eq!((Collection::len(&table), Capacity::capacity(&table)), (4, 4));
+
+        // the grow works but the node for the new entry doesn't
+        let value = boxed(4);
+        alloc.set_fault(Fault::Nth(1));
+        assert!(table.insert(4, value).is_err());
+        assert_eq!((Collection::len(&table), Capacity::capacity(&table)), (4, 8));
+        for i in 0..4 {
+            assert_eq!(table.get(&i).map(|b| **b), Some(i));
+        }
+        assert_eq!(table.get(&4), None);
+
+        alloc.set_fault(Fault::Never);
+        table.insert(4, boxed(4)).unwrap();
+        assert_eq!(table.get(&4).map(|b| **b), Some(4));
+        drop(table);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn failed_union_keeps_our_entries() {
+        let alloc = FaultyAlloc::new();
+        let table = |keys: &[u32]| {
+            let mut table =
+                Table::with_capacity_and_hasher_in(8, FxState::default(), alloc.clone()).unwrap();
+            for &i in keys {
+                table.insert(i, Box::new_in(i, alloc.clone())).unwrap();
+            }
+            table
+        };
+        let mut ours = table(&[0, 1, 2, 3]);
+        let theirs = table(&[2, 3, 4]);
+        // only the node for 4 needs allocating, the shared keys are merged where they are
+        alloc.set_fault(Fault::Nth(0));
+        let merge = |mut a: Box<u32, FaultyAlloc>, b: Box<u32, FaultyAlloc>| {
+            *a += *b * 10;
+            a
+        };
+        assert!(ours.union_with(theirs, merge).is_err());
+        assert_eq!(Collection::len(&ours), 4);
+        for i in 0..4 {
+            let value = **ours.get(&i).unwrap();
+            assert!(value == i || (i >= 2 && value == i * 11), "{}: {}", i, value);
+        }
+        assert_eq!(ours.get(&4), None);
+        drop(ours);
+        assert_eq!(alloc.live(), 0);
+
+        // a panicking merge loses that entry but doesn't drop it twice
+        alloc.set_fault(Fault::Never);
+        let mut ours = table(&[0, 1]);
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ours.union_with(table(&[1]), |_, _| panic!("merging"))
+        }));
+        assert!(caught.is_err());
+        assert_eq!(Collection::len(&ours), 1);
+        assert_eq!(ours.get(&0).map(|b| **b), Some(0));
+        drop(ours);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn borrowed_keys_union_and_drain() {
+        let words = std::string::String::from("a b c d");
+        let table = |keys: &[usize]| {
+            let mut table = SCHashTable::<&str, usize, FxState>::new();
+            for &i in keys {
+                table.insert(&words[2 * i..2 * i + 1], i).unwrap();
+            }
+            table
+        };
+        let mut a = table(&[0, 1]);
+        a.union_with(table(&[1, 2, 3]), |x, y| x + y).unwrap();
+        assert_eq!((a.get(&"b"), a.get(&"d")), (Some(&2), Some(&3)));
+
+        // the count goes down with every entry taken, stopping early keeps the rest
+        a.drain().next().unwrap();
+        assert_eq!(Collection::len(&a), 3);
+        assert_eq!(a.iter().count(), 3);
+    }
+
+    #[test]
+    fn retain_survives_a_panicking_predicate() {
+        let state = LowEntropyState::new(0, 1);
+        let mut table =
+            SCHashTable::<u32, u32, _>::with_capacity_and_hasher_in(4, state, Global).unwrap();
+        for i in 0..20 {
+            table.insert(i, i).unwrap();
+        }
+        let mut seen = 0;
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            table.retain(|_, v| {
+                seen += 1;
+                assert!(seen < 12, "enough");
+                *v += 100;
+                *v % 2 == 0
+            })
+        }));
+        assert!(caught.is_err());
+        assert_eq!(Collection::len(&table), table.iter().count());
+        let left: Vec<_> = table.iter().map(|(k, _)| *k).collect();
+        for k in left {
+            assert!(table.remove(&k).is_some());
+        }
+        assert_eq!(Collection::len(&table), 0);
+
+        let mut set = SCHashTable::<u32, usize, FxState>::new();
+        let mut other = SCHashTable::<u32, usize, FxState>::new();
+        for i in 0..10 {
+            set.add(i).unwrap();
+            set.add(i).unwrap();
+            other.add(i * 2).unwrap();
+        }
+        let set = set.intersection(other);
+        assert!(matches!(set.size(), SetSize::Finite(5)));
+        assert_eq!((set.count(&4), set.count(&5)), (1, 0));
+    }
+
+    #[test]
+    fn random_faults_dont_leak() {
+        let alloc = FaultyAlloc::new();
+        // only 4 different hashes so the chains are long
+        let mut table =
+            Table::with_capacity_and_hasher_in(2, LowEntropyState::default(), alloc.clone())
+                .unwrap();
+        let mut model = HashMap::new();
+        alloc.set_fault(Fault::Random {
+            seed: 50,
+            chance: 0.1,
+        });
+        for i in 0..3000u32 {
+            let key = (i * 7919) % 500;
+            if i % 3 == 2 {
+                assert_eq!(table.remove(&key).map(|b| *b), model.remove(&key));
+                continue;
+            }
+            let Ok(value) = Box::try_new_in(i, alloc.clone()) else {
+                continue;
+            };
+            if let Ok(old) = table.insert(key, value) {
+                assert_eq!(old.map(|b| *b), model.insert(key, i));
+            }
+            assert_eq!(Collection::len(&table), model.len());
+        }
+        assert!(alloc.failures() > 0);
+        for (key, value) in &model {
+            assert_eq!(table.get(key).map(|b| **b), Some(*value));
+        }
+        drop(table);
+        assert_eq!(alloc.live(), 0);
+    }
+}
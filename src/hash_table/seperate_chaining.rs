@@ -1,16 +1,19 @@
 use core::{
     alloc::{AllocError, Allocator, Layout},
     hash::{BuildHasher, Hash, Hasher},
+    iter::Flatten,
     marker::PhantomData,
     mem::MaybeUninit,
     ptr::{self, *},
     slice,
 };
-type ElementsPtr<K, V, A> = NonNull<SLLBucket<K, V, A>>;
 
 mod buckets;
 pub use buckets::*;
 
+use crate::linked_lists::{unlink_next, unlink_next_mut, Link};
+use crate::prelude::Iterable;
+use crate::rc::{Trace, Tracer};
 use crate::traits::hash_table::seperate_chaining::*;
 use crate::traits::hash_table::*;
 
@@ -37,7 +40,7 @@ where
 impl<K, V, S, B, A> HashTableImpl<K, V, S, A> for SCHashTableImpl<K, V, S, B, A>
 where
     S: BuildHasher,
-    B: Bucket<K, V, A> + for<'a> BucketDrain<'a, K, V, A>,
+    B: Bucket<K, V, A>,
     K: Eq + Hash,
     A: Allocator + Clone,
 {
@@ -63,7 +66,7 @@ where
     }
 
     fn clear(&mut self) {
-        for bucket in unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len()) } {
+        for bucket in unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.capacity) } {
             bucket.clear();
         }
 
@@ -83,23 +86,17 @@ where
         self.ptr = new_ptr;
         self.capacity = new_capacity;
 
-        // move elements from old area to new area and dealloc old area
+        // move elements from old area to new area and dealloc old area. the nodes are relinked
+        // rather than reinserted so this can't fail once the new area exists
         if old_capacity != 0 {
             unsafe {
-                let new_arr = slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len());
+                let new_arr = slice::from_raw_parts_mut(self.ptr.as_ptr(), self.capacity);
                 for bucket in slice::from_raw_parts_mut(old_ptr.as_ptr(), old_capacity) {
-                    if !bucket.is_empty() {
-                        for (k, v) in bucket.drain() {
-                            new_arr
-                                .get_unchecked_mut(self.key_index(&k))
-                                .insert_unchecked(k, v)?;
-                        }
-                    }
+                    bucket.move_into(new_arr, |k| self.key_index(k));
+                    ptr::drop_in_place(bucket as *mut B);
                 }
-                self.allocator.deallocate(
-                    old_ptr.cast(),
-                    Layout::array::<NonNull<SLLBucket<K, V, A>>>(old_capacity).unwrap(),
-                );
+                self.allocator
+                    .deallocate(old_ptr.cast(), Layout::array::<B>(old_capacity).unwrap());
             }
         }
         Ok(())
@@ -126,6 +123,9 @@ where
     }
 
     fn remove(&mut self, key: &K) -> Option<V> {
+        if self.capacity == 0 {
+            return None;
+        }
         let bucket = unsafe {
             self.ptr
                 .as_ptr()
@@ -138,6 +138,9 @@ where
         Some(res)
     }
     fn get(&self, key: &K) -> Option<&V> {
+        if self.capacity == 0 {
+            return None;
+        }
         unsafe {
             self.ptr
                 .as_ptr()
@@ -178,6 +181,61 @@ where
     }
 }
 
+impl<K: Eq + Hash, V, S: BuildHasher, A: Allocator + Clone> SLLHashTableImpl<K, V, S, A> {
+    pub(crate) fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let index = self.key_index(key);
+        let bucket = unsafe { &mut *self.ptr.as_ptr().add(index) };
+        bucket.get_mut_by(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub(crate) fn values(&self) -> Values<'_, K, V, A> {
+        Values {
+            inner: self.buckets().iter().flatten(),
+        }
+    }
+
+    pub(crate) fn values_mut(&mut self) -> ValuesMut<'_, K, V, A> {
+        let buckets = unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.capacity) };
+        ValuesMut {
+            inner: buckets.iter_mut().flatten(),
+        }
+    }
+
+    /// Unlinks the entries `f` returns `false` for, bucket by bucket. `len` is right after every
+    /// entry, so a panic in `f` leaves a smaller but consistent table.
+    pub(crate) fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let mut remove = |(k, v): &mut (K, V)| !f(k, v);
+        for index in 0..self.capacity {
+            let mut cursor = unsafe { (*self.ptr.as_ptr().add(index)).head_link() };
+            while let Some(entry) = unsafe { unlink_next_mut(&mut cursor, &mut remove) } {
+                // counted before the entry is dropped, which can panic as well
+                self.len -= 1;
+                drop(entry);
+            }
+        }
+    }
+
+    pub(crate) fn drain_by<F>(&mut self, pred: F) -> DrainBy<'_, K, V, A, F>
+    where
+        F: FnMut(&(K, V)) -> bool,
+    {
+        DrainBy {
+            buckets: self.ptr,
+            capacity: self.capacity,
+            index: 0,
+            cursor: None,
+            len: &mut self.len,
+            pred,
+        }
+    }
+}
+
 impl<K: Eq + Hash, V, S: BuildHasher, B: Bucket<K, V, A>, A: Allocator + Clone>
     SCHashTableImpl<K, V, S, B, A>
 {
@@ -195,6 +253,19 @@ impl<K: Eq + Hash, V, S: BuildHasher, B: Bucket<K, V, A>, A: Allocator + Clone>
         hasher.finish() as usize % self.capacity
     }
 
+    pub(crate) fn buckets(&self) -> &[B] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.capacity) }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)>
+    where
+        B: Iterable<Item = (K, V)>,
+    {
+        self.buckets()
+            .iter()
+            .flat_map(|b| b.iter().map(|(k, v)| (k, v)))
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -204,6 +275,18 @@ impl<K: Eq + Hash, V, S: BuildHasher, B: Bucket<K, V, A>, A: Allocator + Clone>
     }
 }
 
+unsafe impl<K, V, S, B, A> Trace for SCHashTableImpl<K, V, S, B, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    B: Bucket<K, V, A> + Trace,
+    A: Allocator + Clone,
+{
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.buckets().iter().for_each(|b| b.trace(tracer))
+    }
+}
+
 /*impl<K,V,S: Default + BuildHasher, A: Allocator> Default for SCHashTable<K,V,S, A> {
     fn default() -> Self {
         Self::with_capacity_and_hasher_in(50, S::default(), De).unwrap()
@@ -246,13 +329,76 @@ impl<K: Eq, V, S, B: Bucket<K, V, A>, A: Allocator + Clone> Drop
                         self.allocator.deallocate(s.cast(), layout);
                     })*/
                 }
-                let self_layout = Layout::array::<ElementsPtr<K, V, A>>(self.capacity).unwrap();
+                let self_layout = Layout::array::<B>(self.capacity).unwrap();
                 self.allocator.deallocate(self.ptr.cast(), self_layout);
             }
         }
     }
 }
 // ------------------------------------------ ITER ---------------------------------------------
+
+pub struct Values<'a, K, V, A: Allocator + Clone> {
+    inner: Flatten<slice::Iter<'a, SLLBucket<K, V, A>>>,
+}
+
+impl<'a, K, V, A: Allocator + Clone> Iterator for Values<'a, K, V, A> {
+    type Item = &'a V;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+}
+
+pub struct ValuesMut<'a, K, V, A: Allocator + Clone> {
+    inner: Flatten<slice::IterMut<'a, SLLBucket<K, V, A>>>,
+}
+
+impl<'a, K, V, A: Allocator + Clone> Iterator for ValuesMut<'a, K, V, A> {
+    type Item = &'a mut V;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+}
+
+/// Walks the buckets in order unlinking the entries `pred` picks, anything it hasn't reached
+/// stays in the table if it's dropped early.
+pub struct DrainBy<'a, K, V, A: Allocator + Clone, F> {
+    buckets: NonNull<SLLBucket<K, V, A>>,
+    capacity: usize,
+    index: usize,
+    cursor: Option<NonNull<Link<(K, V), A>>>,
+    // only the count is borrowed, the buckets are reached through the pointer
+    len: &'a mut usize,
+    pred: F,
+}
+
+impl<'a, K, V, A, F> Iterator for DrainBy<'a, K, V, A, F>
+where
+    A: Allocator + Clone,
+    F: FnMut(&(K, V)) -> bool,
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.capacity {
+            let cursor = match &mut self.cursor {
+                Some(cursor) => cursor,
+                None => {
+                    let bucket = unsafe { &mut *self.buckets.as_ptr().add(self.index) };
+                    self.cursor.insert(bucket.head_link())
+                }
+            };
+            if let Some(entry) = unsafe { unlink_next(cursor, &mut self.pred) } {
+                *self.len -= 1;
+                return Some(entry);
+            }
+            self.cursor = None;
+            self.index += 1;
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(*self.len))
+    }
+}
 /*
 pub struct HashTableInnerIter<'a, K, V, A: Allocator+Clone> {
     table: &'a [SLLBucket<K,V,A>],
@@ -3,20 +3,8 @@ use core::cmp::{PartialEq, Eq, Ord, Ordering::*};
 pub mod binary_heap;
 
 
-trait Heap<T> {
-    fn new() -> Self;
-    //fn heapify(arr: Vec<T>) -> Self;
-    //fn merge(&mut self, other: Self);
-    fn peek(&self) -> Option<&T>;
-    fn insert(&mut self, val: T);
-    fn extract(&mut self) -> Option<T>;
-    //fn meld(&self, other: &Self) -> Self;
-    //fn pushpop(&mut self, val: T) -> Option<T>;
-    fn len(&self) -> usize;
-    fn is_empty(&self) -> bool {
-        self.len() == 0
-    }
-}
+pub use crate::traits::adt::{Heap, HeapKind, MaxHeap, MinHeap};
+
 /*
 trait MergableHeap<T>: Heap<T>{
 
@@ -1,9 +1,13 @@
-use alloc::vec::Vec;
+use alloc::{slice, vec::{self, Vec}};
 
 use core::cmp::Ord;
 
 
-use super::Heap;
+use super::{Heap, MinHeap};
+use crate::traits::adt::Creatable;
+use crate::traits::collection::{Capacity, Clear, Collection, Contains};
+use crate::traits::iter::{Drainable, Iterable};
+use crate::traits::fp::Foldable;
 
 //pub type MinBinaryHeap<T> = BinaryHeap<T, {HeapType::Min}>;
 //pub type MaxBinaryHeap<T> = BinaryHeap<T, {HeapType::Max}>;
@@ -83,12 +87,85 @@ impl<T: Ord> BinaryHeap<T> {
     }
 }
 
-impl<T: Ord> Heap<T> for BinaryHeap<T> {
-    fn new() -> BinaryHeap<T>{
-        Self {
-            ombga: Vec::new(),
+impl<T> BinaryHeap<T> {
+    pub const fn new() -> Self {
+        Self { ombga: Vec::new() }
+    }
+
+    /// Maps every element and rebuilds the heap, `f` doesn't have to keep the order so this is
+    /// all a heap gets instead of a full `Functor`.
+    pub fn map<B: Ord, F: FnMut(T) -> B>(self, f: F) -> BinaryHeap<B> {
+        let mut heap = BinaryHeap {
+            ombga: self.ombga.into_iter().map(f).collect(),
+        };
+        for i in (0..heap.ombga.len() / 2).rev() {
+            heap.downheap(i);
         }
+        heap
+    }
+}
+
+/// Folds in storage order, not in heap order.
+impl<T> Foldable for BinaryHeap<T> {
+    type Item = T;
+    fn foldl<B, F: FnMut(B, T) -> B>(self, init: B, f: F) -> B {
+        self.ombga.into_iter().fold(init, f)
+    }
+    fn foldr<B, F: FnMut(T, B) -> B>(self, init: B, mut f: F) -> B {
+        self.ombga.into_iter().rev().fold(init, |b, a| f(a, b))
+    }
+}
+
+/// Storage order, not heap order.
+impl<T> Iterable for BinaryHeap<T> {
+    type Item = T;
+    type Iter<'a> = slice::Iter<'a, T> where T: 'a;
+    fn iter(&self) -> Self::Iter<'_> {
+        self.ombga.iter()
     }
+}
+
+impl<T> Drainable for BinaryHeap<T> {
+    type Item = T;
+    type Drain<'a> = vec::Drain<'a, T> where T: 'a;
+    fn drain(&mut self) -> Self::Drain<'_> {
+        self.ombga.drain(..)
+    }
+}
+
+impl<T> Collection for BinaryHeap<T> {
+    type Item = T;
+    fn len(&self) -> usize {
+        self.ombga.len()
+    }
+}
+
+impl<T> Clear for BinaryHeap<T> {
+    fn clear(&mut self) {
+        self.ombga.clear()
+    }
+}
+
+impl<T: PartialEq> Contains for BinaryHeap<T> {
+    fn contains(&self, item: &T) -> bool {
+        self.ombga.contains(item)
+    }
+}
+
+impl<T> Capacity for BinaryHeap<T> {
+    fn capacity(&self) -> usize {
+        self.ombga.capacity()
+    }
+}
+
+impl<T: Ord> Creatable for BinaryHeap<T> {
+    fn new() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> Heap<T> for BinaryHeap<T> {
+    type Kind = MinHeap;
     fn extract(&mut self) -> Option<T> {
         if self.ombga.len() == 0 {
             return None;
@@ -126,6 +203,15 @@ impl<T: Ord> Heap<T> for BinaryHeap<T> {
     fn peek(&self) -> Option<&T> {
         self.ombga.get(0)
     }
+    fn replace(&mut self, item: T) -> Option<T> {
+        if self.ombga.is_empty() {
+            self.ombga.push(item);
+            return None;
+        }
+        let top = core::mem::replace(&mut self.ombga[0], item);
+        self.downheap(0);
+        Some(top)
+    }
     fn len(&self) -> usize {
         self.ombga.len()
     }
@@ -173,4 +259,18 @@ mod tests {
             println!("{bheap:?}");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn map_rebuilds() {
+        let mut bheap: BinaryHeap<i32> = BinaryHeap::new();
+        for i in 0..50 {
+            bheap.insert(i);
+        }
+        // flips the order, so everything has to move
+        let mut neg = bheap.clone().map(|x| -x);
+        for i in (0..50).rev() {
+            assert_eq!(neg.extract(), Some(-i));
+        }
+        assert_eq!(bheap.foldl(0, |a, x| a + x), (0..50).sum());
+    }
+}
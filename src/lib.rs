@@ -1,5 +1,7 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![allow(incomplete_features)]
+// core's own siphashers, to check ours against
+#![cfg_attr(test, feature(hashmap_internals))]
 #![feature(
     test,
     variant_count,
@@ -20,10 +22,14 @@
     let_chains,
     step_trait,
     ptr_as_uninit,
-    adt_const_params
+    adt_const_params,
+    thread_local,
+    layout_for_ptr,
+    coerce_unsized,
+    unsize
 )]
 
-#[cfg(test)]
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 #[cfg(test)]
 extern crate test;
@@ -35,13 +41,17 @@ extern crate alloc;
 pub mod prelude;
 
 pub mod bitstring;
+pub mod bloom;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fault;
+pub mod hash;
 pub mod hash_table;
 pub mod linked_lists;
 pub mod rc;
-//pub mod persistent;
+pub mod persistent;
 pub mod queue;
 pub mod traits;
-mod util;
+pub mod util;
 //mod map;
 pub mod heap;
 
@@ -5,3 +5,4 @@ mod doubly_linked_list;
 pub use doubly_linked_list::DoublyLinkedList;
 pub use singly_linked_list::r#unsafe::UnsafeSinglyLinkedList;
 pub use singly_linked_list::SinglyLinkedList;
+pub(crate) use singly_linked_list::{unlink_next, unlink_next_mut, Link};
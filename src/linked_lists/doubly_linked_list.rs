@@ -1,7 +1,16 @@
 #![allow(dead_code)]
+use crate::rc::{Trace, Tracer};
+use crate::traits::adt::{Creatable, List};
+use crate::traits::collection::{Clear, Collection, Contains};
+use crate::traits::iter::{
+    DrainFilter, Drainable, DrainableBy, DrainableRange, IndexFilter, Iterable, IterableMut,
+    PopDrain, RetainRange,
+};
 use alloc::alloc::Global;
 use core::{
     alloc::{AllocError, Allocator, Layout},
+    marker::PhantomData,
+    ops::RangeBounds,
     ptr::{self, drop_in_place, NonNull},
 };
 
@@ -80,15 +89,13 @@ impl<T, A: Allocator + Clone> DoublyLinkedList<T, A> {
     pub fn pop_front(&mut self) -> Option<T> {
         let r = match self.head {
             Some(node) => unsafe {
-                let v: T = core::ptr::read(&node.as_ref().value);
                 self.head = node.as_ref().next;
                 if let Some(mut node) = self.head {
                     node.as_mut().prev = None;
                 }
-                DoublyLinkedListNode::drop(node);
-                Some(v)
+                Some(DoublyLinkedListNode::unwrap(node))
             },
-            None => None,
+            None => return None,
         };
         self.len -= 1;
         if self.len == 0 {
@@ -116,15 +123,13 @@ impl<T, A: Allocator + Clone> DoublyLinkedList<T, A> {
     pub fn pop_back(&mut self) -> Option<T> {
         let r = match self.tail {
             Some(node) => unsafe {
-                let v: T = core::ptr::read(&node.as_ref().value);
                 self.tail = node.as_ref().prev;
                 if let Some(mut node) = self.tail {
                     node.as_mut().next = None;
                 }
-                DoublyLinkedListNode::drop(node);
-                Some(v)
+                Some(DoublyLinkedListNode::unwrap(node))
             },
-            None => None,
+            None => return None,
         };
         self.len -= 1;
         if self.len == 0 {
@@ -173,10 +178,28 @@ impl<T, A: Allocator + Clone> DoublyLinkedList<T, A> {
         node.map(|mut x| unsafe { &mut x.as_mut().value })
     }
 
-    //pub fn iter(&self) -> Iter<'_, T,A> {todo!()}
-    //pub fn iter_mut(&self) -> IterMut<'_, T,A> {todo!()}
-    //pub fn drain(&self) -> Drain<'_, T,A> {todo!()}
-    //pub fn iter(&self) -> IntoIter<T,A> {todo!()}
+    pub fn iter(&self) -> Iter<'_, T, A> {
+        Iter {
+            list: self,
+            node: self.head,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, A> {
+        IterMut {
+            node: self.head,
+            marker: PhantomData,
+        }
+    }
+
+    /// Pops from the front, whatever isn't taken stays in the list.
+    pub fn drain(&mut self) -> PopDrain<'_, Self, T> {
+        PopDrain::new(self, Self::pop_front)
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
 }
 
 pub struct Cursor<'a, T, A: Allocator + Clone> {
@@ -314,6 +337,266 @@ impl<'a, T, A: Allocator + Clone> Iterator for Iter<'a, T, A> {
     }
 }
 
+/// Unlinks nodes as it yields them, so stopping early just leaves the rest in the list.
+pub struct DrainBy<'a, T, P: DrainFilter<T>, A: Allocator + Clone = Global> {
+    list: &'a mut DoublyLinkedList<T, A>,
+    node: Option<NodePtr<T, A>>,
+    pred: P,
+}
+
+impl<'a, T, P: DrainFilter<T>, A: Allocator + Clone> Iterator for DrainBy<'a, T, P, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.node {
+            if self.pred.done() {
+                return None;
+            }
+            let r = unsafe { node.as_ref() };
+            if !self.pred.matches(&r.value) {
+                self.node = r.next;
+                continue;
+            }
+            let (prev, next) = (r.prev, r.next);
+            match prev {
+                Some(mut p) => unsafe { p.as_mut() }.next = next,
+                None => self.list.head = next,
+            }
+            match next {
+                Some(mut n) => unsafe { n.as_mut() }.prev = prev,
+                None => self.list.tail = prev,
+            }
+            self.list.len -= 1;
+            self.node = next;
+            return Some(unsafe { DoublyLinkedListNode::unwrap(node) });
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.list.len))
+    }
+}
+
+impl<'a, T, P: DrainFilter<T>, A: Allocator + Clone> Drop for DrainBy<'a, T, P, A> {
+    fn drop(&mut self) {
+        if P::FINISH_ON_DROP {
+            self.for_each(drop);
+        }
+    }
+}
+
+impl<T, A: Allocator + Clone> DoublyLinkedList<T, A> {
+    fn drain_with<P: DrainFilter<T>>(&mut self, pred: P) -> DrainBy<'_, T, P, A> {
+        DrainBy {
+            node: self.head,
+            list: self,
+            pred,
+        }
+    }
+}
+
+impl<T, A: Allocator + Clone> DrainableBy for DoublyLinkedList<T, A> {
+    type Item = T;
+    type DrainBy<'a, F> = DrainBy<'a, T, F, A> where T: 'a, A: 'a, F: FnMut(&T) -> bool + 'a;
+    fn drain_by<'a, F>(&'a mut self, pred: F) -> Self::DrainBy<'a, F>
+    where
+        F: FnMut(&T) -> bool + 'a,
+    {
+        self.drain_with(pred)
+    }
+}
+
+impl<T, A: Allocator + Clone> DrainableRange for DoublyLinkedList<T, A> {
+    type Item = T;
+    type DrainRange<'a> = DrainBy<'a, T, IndexFilter, A> where T: 'a, A: 'a;
+    fn drain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::DrainRange<'_> {
+        let filter = IndexFilter::inside(range, self.len);
+        self.drain_with(filter)
+    }
+}
+
+impl<T, A: Allocator + Clone> RetainRange for DoublyLinkedList<T, A> {
+    type Item = T;
+    type Retain<'a> = DrainBy<'a, T, IndexFilter, A> where T: 'a, A: 'a;
+    fn retain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::Retain<'_> {
+        let filter = IndexFilter::outside(range, self.len);
+        self.drain_with(filter)
+    }
+}
+
+pub struct IterMut<'a, T, A: Allocator + Clone> {
+    node: Option<NodePtr<T, A>>,
+    marker: PhantomData<&'a mut DoublyLinkedList<T, A>>,
+}
+
+impl<'a, T, A: Allocator + Clone> Iterator for IterMut<'a, T, A> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.node.map(|mut s| {
+            let r: &'a mut DoublyLinkedListNode<T, A> = unsafe { s.as_mut() };
+            self.node = r.next;
+            &mut r.value
+        })
+    }
+}
+
+impl<T, A: Allocator + Clone> Iterable for DoublyLinkedList<T, A> {
+    type Item = T;
+    type Iter<'a> = Iter<'a, T, A> where T: 'a, A: 'a;
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+impl<T, A: Allocator + Clone> IterableMut for DoublyLinkedList<T, A> {
+    type Item = T;
+    type IterMut<'a> = IterMut<'a, T, A> where T: 'a, A: 'a;
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.iter_mut()
+    }
+}
+
+impl<T, A: Allocator + Clone> Drainable for DoublyLinkedList<T, A> {
+    type Item = T;
+    type Drain<'a> = PopDrain<'a, Self, T> where T: 'a, A: 'a;
+    fn drain(&mut self) -> Self::Drain<'_> {
+        self.drain()
+    }
+}
+
+impl<T, A: Allocator + Clone> Collection for DoublyLinkedList<T, A> {
+    type Item = T;
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<T, A: Allocator + Clone> Clear for DoublyLinkedList<T, A> {
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<T: PartialEq, A: Allocator + Clone> Contains for DoublyLinkedList<T, A> {
+    fn contains(&self, item: &T) -> bool {
+        self.iter().any(|x| x == item)
+    }
+}
+
+impl<T> Creatable for DoublyLinkedList<T> {
+    fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, A: Allocator + Clone> List<T> for DoublyLinkedList<T, A> {
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn try_append(&mut self, item: T) -> Result<(), AllocError> {
+        self.push_back(item)
+    }
+    fn try_prepend(&mut self, item: T) -> Result<(), AllocError> {
+        self.push_front(item)
+    }
+    fn head(&self) -> Option<&T> {
+        self.front()
+    }
+    fn tail(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+    fn get(&self, index: usize) -> Option<&T> {
+        self.get(index)
+    }
+}
+
+/// Pops from the front, so whatever is left gets cleaned up with the list.
+pub struct IntoIter<T, A: Allocator + Clone> {
+    list: DoublyLinkedList<T, A>,
+}
+
+impl<T, A: Allocator + Clone> Iterator for IntoIter<T, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.list.pop_front()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T, A: Allocator + Clone> DoubleEndedIterator for IntoIter<T, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.list.pop_back()
+    }
+}
+
+impl<T, A: Allocator + Clone> ExactSizeIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator + Clone> IntoIterator for DoublyLinkedList<T, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T, A: Allocator + Clone> IntoIterator for &'a DoublyLinkedList<T, A> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, A>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, A: Allocator + Clone> Extend<T> for DoublyLinkedList<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push_back(i).expect("failed to allocate");
+        }
+    }
+}
+
+impl<T> FromIterator<T> for DoublyLinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for DoublyLinkedList<T, A> {
+    fn clone(&self) -> Self {
+        let mut list = Self::new_in(self.alloc.clone());
+        list.extend(self.iter().cloned());
+        list
+    }
+}
+
+impl<T: PartialEq, A: Allocator + Clone> PartialEq for DoublyLinkedList<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, A: Allocator + Clone> Eq for DoublyLinkedList<T, A> {}
+
+unsafe impl<T: Trace, A: Allocator + Clone> Trace for DoublyLinkedList<T, A> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        let mut cur = self.head;
+        while let Some(s) = cur {
+            let r = unsafe { s.as_ref() };
+            cur = r.next;
+            r.value.trace(tracer);
+        }
+    }
+}
+
+impl<T, A: Allocator + Clone> Drop for DoublyLinkedList<T, A> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 impl<T> Default for DoublyLinkedList<T> {
     fn default() -> Self {
         Self::new_in(Global)
@@ -399,6 +682,8 @@ mod iters {}
 #[cfg(test)]
 mod tests {
     use super::DoublyLinkedList;
+    use crate::fault::{Fault, FaultyAlloc};
+    use alloc::boxed::Box;
 
     #[test]
     fn push_front() {
@@ -416,4 +701,37 @@ mod tests {
         }
         list.pop_back();
     }
+
+    #[test]
+    fn iter_and_pop_empty() {
+        let list: DoublyLinkedList<i32> = (0..5).collect();
+        assert!(list.iter().copied().eq(0..5));
+        let mut rest = list.into_iter();
+        assert!(rest.by_ref().rev().take(2).eq([4, 3]));
+        assert!(rest.eq(0..3));
+        let mut list = DoublyLinkedList::<i32>::new();
+        assert_eq!((list.pop_front(), list.pop_back(), list.len()), (None, None, 0));
+    }
+
+    #[test]
+    fn failed_push_and_drop_dont_leak() {
+        let alloc = FaultyAlloc::new();
+        let boxed = |i| Box::new_in(i, alloc.clone());
+        let mut list = DoublyLinkedList::new_in(alloc.clone());
+        for i in 1..4 {
+            list.push_back(boxed(i)).unwrap();
+        }
+        let (front, back) = (boxed(0), boxed(4));
+        alloc.set_fault(Fault::From(0));
+        assert!(list.push_front(front).is_err());
+        assert!(list.push_back(back).is_err());
+        assert!(list.iter().map(|b| **b).eq(1..4));
+
+        alloc.set_fault(Fault::Never);
+        list.push_front(boxed(0)).unwrap();
+        // popped values are dropped exactly once
+        assert_eq!(list.pop_back().map(|b| *b), Some(3));
+        drop(list);
+        assert_eq!(alloc.live(), 0);
+    }
 }
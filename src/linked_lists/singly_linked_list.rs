@@ -6,14 +6,19 @@ use core::{
     fmt::{self, Debug, Display, Formatter},
     hint::unreachable_unchecked,
     iter::Extend,
-    ops::{Deref, DerefMut},
+    marker::PhantomData,
+    ops::{Deref, DerefMut, RangeBounds},
+    ptr::NonNull,
 };
 
+use crate::rc::{Trace, Tracer};
+use crate::traits::adt::{Creatable, List};
+use crate::traits::collection::{Clear, Collection, Contains};
 use crate::traits::hash_table::seperate_chaining::*;
 
 pub mod r#unsafe;
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct SinglyLinkedList<T, A: Allocator + Clone = alloc::alloc::Global> {
     head: Option<Box<SinglyLinkedListNode<T, A>, A>>,
     alloc: A, // A should be a zst
@@ -27,6 +32,12 @@ impl<T> SinglyLinkedList<T> {
     pub fn push(&mut self, item: T) {
         self.try_push(item).expect("failed_to_push")
     }
+}
+
+impl<T, A: Allocator + Clone> SinglyLinkedList<T, A> {
+    pub fn new_in(alloc: A) -> Self {
+        Self { head: None, alloc }
+    }
 
     pub fn len(&self) -> usize {
         self.iter().count()
@@ -42,20 +53,17 @@ impl<T> SinglyLinkedList<T> {
     pub fn contains<Q: PartialEq<T>>(&self, item: &Q) -> bool {
         self.iter().any(|s| item.eq(s))
     }
-}
-
-impl<T, A: Allocator + Clone> SinglyLinkedList<T, A> {
-    pub fn new_in(alloc: A) -> Self {
-        Self { head: None, alloc }
-    }
 
     /// Inserts an element at the beginning of the list
     pub fn try_push(&mut self, item: T) -> Result<(), AllocError> {
+        // the head is only moved once the node exists, a failed push can't lose the list
         let node = SinglyLinkedListNode {
             value: item,
-            next: self.head.take(),
+            next: None,
         };
-        self.head = Some(Box::try_new_in(node, self.alloc.clone())?);
+        let mut node = Box::try_new_in(node, self.alloc.clone())?;
+        node.next = self.head.take();
+        self.head = Some(node);
         Ok(())
     }
 
@@ -128,6 +136,14 @@ impl<T, A: Allocator + Clone> SinglyLinkedList<T, A> {
     }
 }
 
+impl<T: PartialEq, A: Allocator + Clone> PartialEq for SinglyLinkedList<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, A: Allocator + Clone> Eq for SinglyLinkedList<T, A> {}
+
 impl<T> Default for SinglyLinkedList<T> {
     fn default() -> Self {
         Self {
@@ -162,9 +178,10 @@ impl<T> FromIterator<T> for SinglyLinkedList<T> {
     }
 }
 
-use iters::*;
+pub(crate) use iters::*;
 mod iters {
     use super::*;
+    use alloc::alloc::Global;
     use core::alloc::Allocator;
 
     impl<T, A: Allocator + Clone> IntoIterator for SinglyLinkedList<T, A> {
@@ -175,6 +192,22 @@ mod iters {
         }
     }
 
+    impl<'a, T, A: Allocator + Clone> IntoIterator for &'a SinglyLinkedList<T, A> {
+        type Item = &'a T;
+        type IntoIter = Iter<'a, T, A>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T, A: Allocator + Clone> IntoIterator for &'a mut SinglyLinkedList<T, A> {
+        type Item = &'a mut T;
+        type IntoIter = IterMut<'a, T, A>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter_mut()
+        }
+    }
+
     pub struct IntoIter<T, A: Allocator + Clone> {
         pub(crate) head: Option<Box<SinglyLinkedListNode<T, A>, A>>,
     }
@@ -252,6 +285,74 @@ mod iters {
             *self.head_ref = self.item_ref.take();
         }
     }
+
+    pub(crate) type Link<T, A> = Option<Box<SinglyLinkedListNode<T, A>, A>>;
+
+    /// Walks `cursor` forward to the next node `pred` picks, unlinks it and returns its value.
+    /// Non matching nodes are stepped over so each element is only tested once.
+    ///
+    /// # Safety
+    /// `cursor` must point to a live link that nothing else is borrowing.
+    pub(crate) unsafe fn unlink_next<T, A: Allocator + Clone, P: DrainFilter<T>>(
+        cursor: &mut NonNull<Link<T, A>>,
+        pred: &mut P,
+    ) -> Option<T> {
+        loop {
+            if pred.done() {
+                return None;
+            }
+            let link = &mut *cursor.as_ptr();
+            let node = link.as_mut()?;
+            if pred.matches(&node.value) {
+                let SinglyLinkedListNode { value, next } = Box::into_inner(link.take()?);
+                *link = next;
+                return Some(value);
+            }
+            *cursor = NonNull::from(&mut node.next);
+        }
+    }
+
+    /// [`unlink_next`] for predicates that change the values they step over.
+    ///
+    /// # Safety
+    /// Same as [`unlink_next`].
+    pub(crate) unsafe fn unlink_next_mut<T, A: Allocator + Clone, F: FnMut(&mut T) -> bool>(
+        cursor: &mut NonNull<Link<T, A>>,
+        pred: &mut F,
+    ) -> Option<T> {
+        loop {
+            let link = &mut *cursor.as_ptr();
+            let node = link.as_mut()?;
+            if pred(&mut node.value) {
+                let SinglyLinkedListNode { value, next } = Box::into_inner(link.take()?);
+                *link = next;
+                return Some(value);
+            }
+            *cursor = NonNull::from(&mut node.next);
+        }
+    }
+
+    /// Nodes are unlinked as they are yielded, so stopping early just leaves the rest in place.
+    pub struct DrainBy<'a, T, P: DrainFilter<T>, A: Allocator + Clone = Global> {
+        pub(crate) cursor: NonNull<Link<T, A>>,
+        pub(crate) pred: P,
+        pub(crate) marker: PhantomData<&'a mut SinglyLinkedList<T, A>>,
+    }
+
+    impl<'a, T, P: DrainFilter<T>, A: Allocator + Clone> Iterator for DrainBy<'a, T, P, A> {
+        type Item = T;
+        fn next(&mut self) -> Option<T> {
+            unsafe { unlink_next(&mut self.cursor, &mut self.pred) }
+        }
+    }
+
+    impl<'a, T, P: DrainFilter<T>, A: Allocator + Clone> Drop for DrainBy<'a, T, P, A> {
+        fn drop(&mut self) {
+            if P::FINISH_ON_DROP {
+                self.for_each(drop);
+            }
+        }
+    }
 }
 
 impl<T, A: Allocator + Clone> Extend<T> for SinglyLinkedList<T, A> {
@@ -262,6 +363,69 @@ impl<T, A: Allocator + Clone> Extend<T> for SinglyLinkedList<T, A> {
     }
 }
 
+impl<T, A: Allocator + Clone> Collection for SinglyLinkedList<T, A> {
+    type Item = T;
+    fn len(&self) -> usize {
+        self.iter().count()
+    }
+    fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+}
+
+impl<T, A: Allocator + Clone> Clear for SinglyLinkedList<T, A> {
+    fn clear(&mut self) {
+        // popping one at a time so long lists don't blow the stack with recursive drops
+        while self.pop().is_some() {}
+    }
+}
+
+impl<T: PartialEq, A: Allocator + Clone> Contains for SinglyLinkedList<T, A> {
+    fn contains(&self, item: &T) -> bool {
+        self.iter().any(|x| x == item)
+    }
+}
+
+impl<T> Creatable for SinglyLinkedList<T> {
+    fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The head is the front, so `append` has to walk the whole list.
+impl<T, A: Allocator + Clone> List<T> for SinglyLinkedList<T, A> {
+    fn len(&self) -> usize {
+        self.iter().count()
+    }
+    fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+    fn try_append(&mut self, item: T) -> Result<(), AllocError> {
+        let mut current = &mut self.head;
+        while let Some(node) = current {
+            current = &mut node.next;
+        }
+        let node = SinglyLinkedListNode {
+            value: item,
+            next: None,
+        };
+        *current = Some(Box::try_new_in(node, self.alloc.clone())?);
+        Ok(())
+    }
+    fn try_prepend(&mut self, item: T) -> Result<(), AllocError> {
+        self.try_push(item)
+    }
+    fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|n| &n.value)
+    }
+    fn tail(&mut self) -> Option<T> {
+        self.pop()
+    }
+    fn get(&self, index: usize) -> Option<&T> {
+        self.iter().nth(index)
+    }
+}
+
 impl<K: Eq, V, A: Allocator + Clone> Bucket<K, V, A> for SinglyLinkedList<(K, V), A> {
     fn new_in(alloc: A) -> Self {
         Self::new_in(alloc)
@@ -291,6 +455,15 @@ impl<K: Eq, V, A: Allocator + Clone> Bucket<K, V, A> for SinglyLinkedList<(K, V)
     fn remove(&mut self, key: &K) -> Option<(K, V)> {
         self.remove_by(|(k, _)| k == key)
     }
+    fn move_into<F: Fn(&K) -> usize>(&mut self, buckets: &mut [Self], index: F) {
+        // relinks the boxes so nothing has to be allocated halfway through a grow
+        while let Some(mut node) = self.head.take() {
+            self.head = node.next.take();
+            let bucket = &mut buckets[index(&node.value.0)];
+            node.next = bucket.head.take();
+            bucket.head = Some(node);
+        }
+    }
 }
 
 impl<T, A: Allocator + Clone> Iterable for SinglyLinkedList<T, A> {
@@ -315,6 +488,12 @@ impl<T, A: Allocator + Clone> IterableMut for SinglyLinkedList<T, A> {
     }
 }
 
+unsafe impl<T: Trace, A: Allocator + Clone> Trace for SinglyLinkedList<T, A> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.iter().for_each(|v| v.trace(tracer))
+    }
+}
+
 impl<T, A: Allocator + Clone> Drainable for SinglyLinkedList<T, A> {
     type Item = T;
     type Drain<'a> = iters::Drain<'a, T,A>
@@ -329,7 +508,56 @@ impl<T, A: Allocator + Clone> Drainable for SinglyLinkedList<T, A> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+impl<T, A: Allocator + Clone> SinglyLinkedList<T, A> {
+    pub(crate) fn head_link(&mut self) -> NonNull<Link<T, A>> {
+        NonNull::from(&mut self.head)
+    }
+
+    fn drain_with<P: DrainFilter<T>>(&mut self, pred: P) -> iters::DrainBy<'_, T, P, A> {
+        DrainBy {
+            cursor: self.head_link(),
+            pred,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, A: Allocator + Clone> DrainableBy for SinglyLinkedList<T, A> {
+    type Item = T;
+    type DrainBy<'a, F> = iters::DrainBy<'a, T, F, A>
+    where
+        T: 'a, A: 'a, F: FnMut(&T) -> bool + 'a;
+    fn drain_by<'a, F>(&'a mut self, pred: F) -> Self::DrainBy<'a, F>
+    where
+        F: FnMut(&T) -> bool + 'a,
+    {
+        self.drain_with(pred)
+    }
+}
+
+impl<T, A: Allocator + Clone> DrainableRange for SinglyLinkedList<T, A> {
+    type Item = T;
+    type DrainRange<'a> = iters::DrainBy<'a, T, IndexFilter, A>
+    where
+        T: 'a, A: 'a;
+    fn drain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::DrainRange<'_> {
+        let filter = IndexFilter::inside(range, self.len());
+        self.drain_with(filter)
+    }
+}
+
+impl<T, A: Allocator + Clone> RetainRange for SinglyLinkedList<T, A> {
+    type Item = T;
+    type Retain<'a> = iters::DrainBy<'a, T, IndexFilter, A>
+    where
+        T: 'a, A: 'a;
+    fn retain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::Retain<'_> {
+        let filter = IndexFilter::outside(range, self.len());
+        self.drain_with(filter)
+    }
+}
+
+#[derive(Clone)]
 pub struct SinglyLinkedListNode<T, A: Allocator> {
     value: T,
     next: Option<Box<SinglyLinkedListNode<T, A>, A>>,
@@ -366,6 +594,7 @@ mod bench {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fault::{Fault, FaultyAlloc};
     use alloc::alloc::Global;
 
     #[test]
@@ -418,4 +647,25 @@ mod tests {
         );
         assert_eq!((50..100).rev().collect::<Vec<_>>(), b);
     }
+
+    #[test]
+    fn failed_push_keeps_the_list() {
+        let alloc = FaultyAlloc::new();
+        let boxed = |i| Box::new_in(i, alloc.clone());
+        let mut lst = SinglyLinkedList::new_in(alloc.clone());
+        for i in 0..5 {
+            lst.try_push(boxed(i)).unwrap();
+        }
+        let (front, back) = (boxed(5), boxed(6));
+        alloc.set_fault(Fault::From(0));
+        assert!(lst.try_push(front).is_err());
+        assert!(lst.try_append(back).is_err());
+        assert!(lst.iter().map(|b| **b).eq((0..5).rev()));
+
+        alloc.set_fault(Fault::Never);
+        lst.try_append(boxed(6)).unwrap();
+        assert_eq!(lst.iter().last().map(|b| **b), Some(6));
+        drop(lst);
+        assert_eq!(alloc.live(), 0);
+    }
 }
@@ -7,6 +7,7 @@ use core::{
     ptr::{self, drop_in_place, NonNull},
 };
 
+use crate::traits::collection::{Clear, Collection, Contains};
 use crate::traits::hash_table::seperate_chaining::*;
 
 pub type ElementPtr<T> = NonNull<SinglyLinkedListNode<T>>;
@@ -76,7 +77,8 @@ where
             head = unsafe { ptr.as_ref().next };
             unsafe {
                 core::ptr::drop_in_place(ptr.as_ptr());
-                self.alloc.deallocate(ptr.cast(), Layout::new::<Self>())
+                self.alloc
+                    .deallocate(ptr.cast(), Layout::new::<SinglyLinkedListNode<(K, V)>>())
             }
         }
         self.head = None;
@@ -85,6 +87,17 @@ where
     fn is_empty(&self) -> bool {
         self.head.is_none()
     }
+
+    fn move_into<F: Fn(&K) -> usize>(&mut self, buckets: &mut [Self], index: F) {
+        while let Some(mut node) = self.head {
+            unsafe {
+                self.head = node.as_ref().next;
+                let bucket = &mut buckets[index(&node.as_ref().val.0)];
+                node.as_mut().next = bucket.head;
+                bucket.head = Some(node);
+            }
+        }
+    }
 }
 
 impl<T, A: Allocator + Clone> Drop for UnsafeSinglyLinkedList<T, A> {
@@ -129,6 +142,42 @@ impl<T, A: Allocator + Clone> Iterable for UnsafeSinglyLinkedList<T, A> {
         }
     }
 }
+impl<T, A: Allocator + Clone> IterableMut for UnsafeSinglyLinkedList<T, A> {
+    type Item = T;
+    type IterMut<'a> = SLLBucketIterMut<'a, T, A>
+    where
+        T: 'a, A: 'a
+    ;
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        SLLBucketIterMut {
+            marker: PhantomData,
+            head: self.head,
+        }
+    }
+}
+
+impl<T, A: Allocator + Clone> Collection for UnsafeSinglyLinkedList<T, A> {
+    type Item = T;
+    fn len(&self) -> usize {
+        self.iter().count()
+    }
+    fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+}
+
+impl<T, A: Allocator + Clone> Clear for UnsafeSinglyLinkedList<T, A> {
+    fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+}
+
+impl<T: PartialEq, A: Allocator + Clone> Contains for UnsafeSinglyLinkedList<T, A> {
+    fn contains(&self, item: &T) -> bool {
+        self.iter().any(|x| x == item)
+    }
+}
+
 impl<T, A: Allocator + Clone> Drainable for UnsafeSinglyLinkedList<T, A> {
     type Item = T;
     type Drain<'a> = SLLBucketDrain<'a, T, A>
@@ -177,6 +226,22 @@ impl<'a, T: 'a, A: Allocator + Clone> Iterator for SLLBucketIter<'a, T, A> {
     }
 }
 
+pub struct SLLBucketIterMut<'a, T, A: Allocator + Clone> {
+    head: Option<ElementPtr<T>>,
+    marker: PhantomData<(&'a mut UnsafeSinglyLinkedList<T, A>, A)>,
+}
+
+impl<'a, T: 'a, A: Allocator + Clone> Iterator for SLLBucketIterMut<'a, T, A> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.head.map(|mut s| unsafe {
+            let node = s.as_mut();
+            self.head = node.next;
+            &mut node.val
+        })
+    }
+}
+
 pub struct SLLBucketDrain<'a, T, A: Allocator + Clone> {
     head: &'a mut Option<ElementPtr<T>>,
     alloc: A,
@@ -228,7 +293,9 @@ impl<T> SinglyLinkedListNode<T> {
 #[cfg(test)]
 mod pub_api_tests {
     use super::*;
+    use crate::fault::{Fault, FaultyAlloc};
     use std::alloc::Global;
+    use std::boxed::Box;
 
     #[test]
     fn insert_drop() {
@@ -251,6 +318,27 @@ mod pub_api_tests {
         assert_eq!(i.1, 1);
     }
 
+    #[test]
+    fn failed_inserts_dont_leak() {
+        let alloc = FaultyAlloc::new();
+        let mut a = UnsafeSinglyLinkedList::<(i32, Box<i32, _>), _>::new_in(alloc.clone());
+        for i in 0..4 {
+            a.insert(i, Box::new_in(i, alloc.clone())).unwrap();
+        }
+        // the value is allocated before the fault is set, so only the node can fail
+        let value = Box::new_in(10, alloc.clone());
+        alloc.set_fault(Fault::From(0));
+        assert!(a.insert(1, value).is_err());
+        assert_eq!(a.get(&1).map(|b| **b), Some(1));
+        assert_eq!(a.len(), 4);
+
+        alloc.set_fault(Fault::Never);
+        assert_eq!(a.remove(&2).map(|(_, b)| *b), Some(2));
+        assert_eq!(a.drain().next().map(|(k, _)| k), Some(3));
+        drop(a);
+        assert_eq!(alloc.live(), 0);
+    }
+
     /*
     #[test]
     fn insert_and_remove() {
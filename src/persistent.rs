@@ -1 +1,96 @@
-pub mod linked_list;
+//! Immutable data structures, every update returns a new version that shares most of its nodes
+//! with the old one. The nodes are [`Rc`](crate::rc::Rc)s of the flavor picked by an
+//! [`RcStub`](crate::rc::RcStub), so `StRc` for a single thread and `AtRc` to share across threads.
+
+mod finger_tree;
+mod hash_map;
+mod linked_list;
+mod ord_map;
+mod ord_set;
+mod vector;
+
+use crate::rc::RcStub;
+use crate::traits::collection::{Collection, Contains};
+use crate::traits::iter::Iterable;
+
+pub use finger_tree::{FingerTree, Measured, Size};
+pub use hash_map::{PersistentHashMap, TransientHashMap, Values as HashMapValues};
+pub use linked_list::ConsList;
+pub use ord_map::{PersistentOrdMap, Values as OrdMapValues};
+pub use ord_set::PersistentOrdSet;
+pub use vector::{PersistentVec, TransientVec};
+
+// everything here already has an inherent `len` and iterator, the traits only forward to them.
+// maps iterate their values, so that's their item too
+macro_rules! collection_impls {
+    ($(impl[$($gen:tt)*] $ty:ty $(where [$($bound:tt)*])? {
+        Item = $item:ty;
+        Iter<'a> = $iter:ty, from $iter_fn:ident;
+    })*) => {$(
+        impl<$($gen)*> Collection for $ty $(where $($bound)*)? {
+            type Item = $item;
+            fn len(&self) -> usize {
+                self.len()
+            }
+        }
+
+        impl<$($gen)*> Iterable for $ty {
+            type Item = $item;
+            type Iter<'a> = $iter where Self: 'a;
+            fn iter(&self) -> Self::Iter<'_> {
+                self.$iter_fn()
+            }
+        }
+    )*};
+}
+
+/// Sequences without a faster way to find an element.
+macro_rules! contains_by_scan {
+    ($(impl[$($gen:tt)*] $ty:ty;)*) => {$(
+        impl<$($gen)*> Contains for $ty {
+            fn contains(&self, item: &Self::Item) -> bool {
+                self.iter().any(|x| x == item)
+            }
+        }
+    )*};
+}
+
+collection_impls! {
+    impl[T, S: RcStub] ConsList<T, S> {
+        Item = T;
+        Iter<'a> = linked_list::Iter<'a, T, S>, from iter;
+    }
+    impl[T, S: RcStub] PersistentVec<T, S> {
+        Item = T;
+        Iter<'a> = vector::Iter<'a, T, S>, from iter;
+    }
+    // only trees measured by `Size` know their length without walking the whole thing
+    impl[T: Measured, R: RcStub] FingerTree<T, R> where [T: Measured<Measure = Size> + Clone] {
+        Item = T;
+        Iter<'a> = finger_tree::Iter<'a, T, R>, from iter;
+    }
+    impl[T, R: RcStub] PersistentOrdSet<T, R> {
+        Item = T;
+        Iter<'a> = ord_set::Iter<'a, T, R>, from iter;
+    }
+    impl[K, V, S, R: RcStub] PersistentHashMap<K, V, S, R> {
+        Item = V;
+        Iter<'a> = HashMapValues<'a, K, V, R>, from values;
+    }
+    impl[K, V, R: RcStub] PersistentOrdMap<K, V, R> {
+        Item = V;
+        Iter<'a> = OrdMapValues<'a, K, V, R>, from values;
+    }
+}
+
+contains_by_scan! {
+    impl[T: PartialEq, S: RcStub] ConsList<T, S>;
+    impl[T: PartialEq, S: RcStub] PersistentVec<T, S>;
+    impl[T: Measured<Measure = Size> + Clone + PartialEq, R: RcStub] FingerTree<T, R>;
+}
+
+impl<T: Ord, R: RcStub> Contains for PersistentOrdSet<T, R> {
+    fn contains(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+}
@@ -0,0 +1,680 @@
+//! 2-3 finger trees, after Hinze and Paterson, "Finger trees: a simple general-purpose data
+//! structure".
+//!
+//! Every subtree caches the [`Monoid`] sum of the measures of its elements, `split` walks down
+//! to where a predicate on the running sum flips. With [`Size`] as the measure that gives an
+//! indexed sequence, with a max it's a priority queue, and so on.
+//!
+//! The nested trees of the paper would need polymorphic recursion, so here every level holds
+//! the same `Item` type and nodes only ever show up below the top level.
+
+use crate::rc::{Rc, RcStub, StRc};
+use crate::traits::fp::{Monoid, Semigroup};
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    slice,
+};
+
+/// Something a [`FingerTree`] can hold.
+pub trait Measured {
+    type Measure: Monoid + Clone;
+    fn measure(&self) -> Self::Measure;
+}
+
+/// Counts the elements, for indexing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size(pub usize);
+
+impl Semigroup for Size {
+    fn append(self, rhs: Self) -> Self {
+        Size(self.0 + rhs.0)
+    }
+}
+
+impl Monoid for Size {
+    fn mempty() -> Self {
+        Size(0)
+    }
+}
+
+enum Item<T: Measured, R: RcStub> {
+    Leaf(T),
+    /// two or three items of the level below
+    Node(Rc<Node<T, R>, R>),
+}
+
+struct Node<T: Measured, R: RcStub> {
+    measure: T::Measure,
+    items: Vec<Item<T, R>>,
+}
+
+enum Tree<T: Measured, R: RcStub> {
+    Empty,
+    Single(Item<T, R>),
+    Deep(Rc<Deep<T, R>, R>),
+}
+
+struct Deep<T: Measured, R: RcStub> {
+    measure: T::Measure,
+    /// one to four items on each side
+    prefix: Vec<Item<T, R>>,
+    middle: Tree<T, R>,
+    suffix: Vec<Item<T, R>>,
+}
+
+impl<T: Measured + Clone, R: RcStub> Clone for Item<T, R> {
+    fn clone(&self) -> Self {
+        match self {
+            Item::Leaf(v) => Item::Leaf(v.clone()),
+            Item::Node(n) => Item::Node(n.clone()),
+        }
+    }
+}
+
+impl<T: Measured + Clone, R: RcStub> Clone for Tree<T, R> {
+    fn clone(&self) -> Self {
+        match self {
+            Tree::Empty => Tree::Empty,
+            Tree::Single(x) => Tree::Single(x.clone()),
+            Tree::Deep(d) => Tree::Deep(d.clone()),
+        }
+    }
+}
+
+#[inline]
+fn add<M: Monoid + Clone>(a: &M, b: M) -> M {
+    a.clone().append(b)
+}
+
+fn measure_all<T: Measured, R: RcStub>(items: &[Item<T, R>]) -> T::Measure {
+    items
+        .iter()
+        .fold(T::Measure::mempty(), |acc, x| acc.append(x.measure()))
+}
+
+impl<T: Measured, R: RcStub> Item<T, R> {
+    fn measure(&self) -> T::Measure {
+        match self {
+            Item::Leaf(v) => v.measure(),
+            Item::Node(n) => n.measure.clone(),
+        }
+    }
+
+    fn node(items: Vec<Self>) -> Self {
+        Item::Node(Rc::new(Node {
+            measure: measure_all(&items),
+            items,
+        }))
+    }
+}
+
+impl<T: Measured + Clone, R: RcStub> Tree<T, R> {
+    fn measure(&self) -> T::Measure {
+        match self {
+            Tree::Empty => T::Measure::mempty(),
+            Tree::Single(x) => x.measure(),
+            Tree::Deep(d) => d.measure.clone(),
+        }
+    }
+
+    fn deep(prefix: Vec<Item<T, R>>, middle: Tree<T, R>, suffix: Vec<Item<T, R>>) -> Self {
+        let measure = measure_all(&prefix)
+            .append(middle.measure())
+            .append(measure_all(&suffix));
+        Tree::Deep(Rc::new(Deep {
+            measure,
+            prefix,
+            middle,
+            suffix,
+        }))
+    }
+
+    fn from_items(items: Vec<Item<T, R>>) -> Self {
+        items.into_iter().fold(Tree::Empty, |t, x| t.push_back(x))
+    }
+
+    fn push_front(&self, x: Item<T, R>) -> Self {
+        match self {
+            Tree::Empty => Tree::Single(x),
+            Tree::Single(y) => Self::deep(vec![x], Tree::Empty, vec![y.clone()]),
+            Tree::Deep(d) if d.prefix.len() == 4 => {
+                // push the last three down a level
+                let node = Item::node(d.prefix[1..].to_vec());
+                let prefix = vec![x, d.prefix[0].clone()];
+                Self::deep(prefix, d.middle.push_front(node), d.suffix.clone())
+            }
+            Tree::Deep(d) => {
+                let mut prefix = Vec::with_capacity(d.prefix.len() + 1);
+                prefix.push(x);
+                prefix.extend_from_slice(&d.prefix);
+                Self::deep(prefix, d.middle.clone(), d.suffix.clone())
+            }
+        }
+    }
+
+    fn push_back(&self, x: Item<T, R>) -> Self {
+        match self {
+            Tree::Empty => Tree::Single(x),
+            Tree::Single(y) => Self::deep(vec![y.clone()], Tree::Empty, vec![x]),
+            Tree::Deep(d) if d.suffix.len() == 4 => {
+                let node = Item::node(d.suffix[..3].to_vec());
+                let suffix = vec![d.suffix[3].clone(), x];
+                Self::deep(d.prefix.clone(), d.middle.push_back(node), suffix)
+            }
+            Tree::Deep(d) => {
+                let mut suffix = d.suffix.clone();
+                suffix.push(x);
+                Self::deep(d.prefix.clone(), d.middle.clone(), suffix)
+            }
+        }
+    }
+
+    fn view_front(&self) -> Option<(Item<T, R>, Self)> {
+        match self {
+            Tree::Empty => None,
+            Tree::Single(x) => Some((x.clone(), Tree::Empty)),
+            Tree::Deep(d) => Some((
+                d.prefix[0].clone(),
+                Self::deep_l(d.prefix[1..].to_vec(), &d.middle, d.suffix.clone()),
+            )),
+        }
+    }
+
+    fn view_back(&self) -> Option<(Self, Item<T, R>)> {
+        match self {
+            Tree::Empty => None,
+            Tree::Single(x) => Some((Tree::Empty, x.clone())),
+            Tree::Deep(d) => {
+                let (last, init) = d.suffix.split_last().unwrap();
+                let tree = Self::deep_r(d.prefix.clone(), &d.middle, init.to_vec());
+                Some((tree, last.clone()))
+            }
+        }
+    }
+
+    /// `deep` where the prefix might be empty
+    fn deep_l(prefix: Vec<Item<T, R>>, middle: &Self, suffix: Vec<Item<T, R>>) -> Self {
+        if !prefix.is_empty() {
+            return Self::deep(prefix, middle.clone(), suffix);
+        }
+        match middle.view_front() {
+            None => Self::from_items(suffix),
+            Some((Item::Node(n), middle)) => Self::deep(n.items.clone(), middle, suffix),
+            Some((Item::Leaf(_), _)) => unreachable!("leaves only live on the top level"),
+        }
+    }
+
+    /// `deep` where the suffix might be empty
+    fn deep_r(prefix: Vec<Item<T, R>>, middle: &Self, suffix: Vec<Item<T, R>>) -> Self {
+        if !suffix.is_empty() {
+            return Self::deep(prefix, middle.clone(), suffix);
+        }
+        match middle.view_back() {
+            None => Self::from_items(prefix),
+            Some((middle, Item::Node(n))) => Self::deep(prefix, middle, n.items.clone()),
+            Some((_, Item::Leaf(_))) => unreachable!("leaves only live on the top level"),
+        }
+    }
+
+    /// `a`, then the items in `mid`, then `b`.
+    fn concat(a: &Self, mid: Vec<Item<T, R>>, b: &Self) -> Self {
+        match (a, b) {
+            (Tree::Empty, _) => mid
+                .into_iter()
+                .rev()
+                .fold(b.clone(), |t, x| t.push_front(x)),
+            (_, Tree::Empty) => mid.into_iter().fold(a.clone(), |t, x| t.push_back(x)),
+            (Tree::Single(x), _) => Self::concat(&Tree::Empty, mid, b).push_front(x.clone()),
+            (_, Tree::Single(y)) => Self::concat(a, mid, &Tree::Empty).push_back(y.clone()),
+            (Tree::Deep(a), Tree::Deep(b)) => {
+                let mut items = a.suffix.clone();
+                items.extend(mid);
+                items.extend_from_slice(&b.prefix);
+                let middle = Self::concat(&a.middle, nodes(items), &b.middle);
+                Self::deep(a.prefix.clone(), middle, b.suffix.clone())
+            }
+        }
+    }
+
+    /// Splits a non empty tree around the item where `pred` of the running measure, starting at
+    /// `acc`, turns `true`.
+    fn split<P>(&self, pred: &P, acc: T::Measure) -> (Self, Item<T, R>, Self)
+    where
+        P: Fn(&T::Measure) -> bool,
+    {
+        let d = match self {
+            Tree::Empty => unreachable!("splitting an empty tree"),
+            Tree::Single(x) => return (Tree::Empty, x.clone(), Tree::Empty),
+            Tree::Deep(d) => d,
+        };
+        let after_prefix = add(&acc, measure_all(&d.prefix));
+        if pred(&after_prefix) {
+            let (l, x, r) = split_items(&d.prefix, pred, acc);
+            return (
+                Self::from_items(l),
+                x,
+                Self::deep_l(r, &d.middle, d.suffix.clone()),
+            );
+        }
+        let after_middle = add(&after_prefix, d.middle.measure());
+        if pred(&after_middle) {
+            let (ml, node, mr) = d.middle.split(pred, after_prefix.clone());
+            let Item::Node(node) = node else {
+                unreachable!("leaves only live on the top level")
+            };
+            let acc = add(&after_prefix, ml.measure());
+            let (l, x, r) = split_items(&node.items, pred, acc);
+            return (
+                Self::deep_r(d.prefix.clone(), &ml, l),
+                x,
+                Self::deep_l(r, &mr, d.suffix.clone()),
+            );
+        }
+        let (l, x, r) = split_items(&d.suffix, pred, after_middle);
+        (
+            Self::deep_r(d.prefix.clone(), &d.middle, l),
+            x,
+            Self::from_items(r),
+        )
+    }
+}
+
+/// groups 2 to 12 items into nodes of two or three
+fn nodes<T: Measured + Clone, R: RcStub>(mut items: Vec<Item<T, R>>) -> Vec<Item<T, R>> {
+    let mut out = Vec::with_capacity(items.len() / 2);
+    while items.len() > 4 {
+        out.push(Item::node(items.drain(..3).collect()));
+    }
+    match items.len() {
+        4 => {
+            let b = items.split_off(2);
+            out.push(Item::node(items));
+            out.push(Item::node(b));
+        }
+        _ => out.push(Item::node(items)),
+    }
+    out
+}
+
+type SplitItems<T, R> = (Vec<Item<T, R>>, Item<T, R>, Vec<Item<T, R>>);
+
+fn split_items<T, R, P>(items: &[Item<T, R>], pred: &P, mut acc: T::Measure) -> SplitItems<T, R>
+where
+    T: Measured + Clone,
+    R: RcStub,
+    P: Fn(&T::Measure) -> bool,
+{
+    for (i, x) in items.iter().enumerate() {
+        acc = acc.append(x.measure());
+        if pred(&acc) || i + 1 == items.len() {
+            return (items[..i].to_vec(), x.clone(), items[i + 1..].to_vec());
+        }
+    }
+    unreachable!("digits are never empty")
+}
+
+/// An immutable sequence with amortized O(1) access to both ends and O(log n) `concat` and
+/// `split`, see the [module docs](self).
+///
+/// # Examples
+/// ```
+/// use hash_table::persistent::{FingerTree, Measured, Size};
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Elem(char);
+/// impl Measured for Elem {
+///     type Measure = Size;
+///     fn measure(&self) -> Size {
+///         Size(1)
+///     }
+/// }
+/// let t: FingerTree<Elem> = "hello".chars().map(Elem).collect();
+/// let (l, r) = t.split(|s| s.0 > 2);
+/// assert_eq!((l.measure(), r.front()), (Size(2), Some(&Elem('l'))));
+/// ```
+pub struct FingerTree<T: Measured, R: RcStub = StRc> {
+    tree: Tree<T, R>,
+}
+
+impl<T: Measured, R: RcStub> FingerTree<T, R> {
+    pub const fn new() -> Self {
+        Self { tree: Tree::Empty }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        matches!(self.tree, Tree::Empty)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, R> {
+        Iter {
+            stack: vec![Frame::Tree(&self.tree)],
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        let mut x = match &self.tree {
+            Tree::Empty => return None,
+            Tree::Single(x) => x,
+            Tree::Deep(d) => &d.prefix[0],
+        };
+        loop {
+            match x {
+                Item::Leaf(v) => return Some(v),
+                Item::Node(n) => x = &n.items[0],
+            }
+        }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        let mut x = match &self.tree {
+            Tree::Empty => return None,
+            Tree::Single(x) => x,
+            Tree::Deep(d) => d.suffix.last().unwrap(),
+        };
+        loop {
+            match x {
+                Item::Leaf(v) => return Some(v),
+                Item::Node(n) => x = n.items.last().unwrap(),
+            }
+        }
+    }
+}
+
+impl<T: Measured + Clone, R: RcStub> FingerTree<T, R> {
+    /// The sum of the measures of all elements.
+    pub fn measure(&self) -> T::Measure {
+        self.tree.measure()
+    }
+
+    pub fn push_front(&self, value: T) -> Self {
+        Self {
+            tree: self.tree.push_front(Item::Leaf(value)),
+        }
+    }
+
+    pub fn push_back(&self, value: T) -> Self {
+        Self {
+            tree: self.tree.push_back(Item::Leaf(value)),
+        }
+    }
+
+    /// The first element and the rest.
+    pub fn pop_front(&self) -> Option<(T, Self)> {
+        match self.tree.view_front()? {
+            (Item::Leaf(v), tree) => Some((v, Self { tree })),
+            _ => unreachable!("leaves only live on the top level"),
+        }
+    }
+
+    /// The rest and the last element.
+    pub fn pop_back(&self) -> Option<(Self, T)> {
+        match self.tree.view_back()? {
+            (tree, Item::Leaf(v)) => Some((Self { tree }, v)),
+            _ => unreachable!("leaves only live on the top level"),
+        }
+    }
+
+    /// The elements of `self` followed by those of `other`.
+    pub fn concat(&self, other: &Self) -> Self {
+        Self {
+            tree: Tree::concat(&self.tree, Vec::new(), &other.tree),
+        }
+    }
+
+    /// Splits before the first element where `pred` of the measure of everything up to and
+    /// including it is `true`. `pred` has to flip from `false` to `true` at most once; if it
+    /// never does the right side is empty.
+    pub fn split<P: Fn(&T::Measure) -> bool>(&self, pred: P) -> (Self, Self) {
+        if self.is_empty() || !pred(&self.measure()) {
+            return (self.clone(), Self::new());
+        }
+        let (l, x, r) = self.tree.split(&pred, T::Measure::mempty());
+        (
+            Self { tree: l },
+            Self {
+                tree: r.push_front(x),
+            },
+        )
+    }
+
+    /// The element [`split`](Self::split) would put first on the right side.
+    pub fn find<P: Fn(&T::Measure) -> bool>(&self, pred: P) -> Option<T> {
+        if self.is_empty() || !pred(&self.measure()) {
+            return None;
+        }
+        match self.tree.split(&pred, T::Measure::mempty()).1 {
+            Item::Leaf(v) => Some(v),
+            _ => unreachable!("leaves only live on the top level"),
+        }
+    }
+}
+
+impl<T: Measured<Measure = Size> + Clone, R: RcStub> FingerTree<T, R> {
+    /// The number of elements, for trees that count them.
+    pub fn len(&self) -> usize {
+        self.measure().0
+    }
+
+    pub fn get(&self, i: usize) -> Option<T> {
+        self.find(|s| s.0 > i)
+    }
+
+    /// The first `i` elements and the rest.
+    pub fn split_at(&self, i: usize) -> (Self, Self) {
+        self.split(|s| s.0 > i)
+    }
+}
+
+impl<T: Measured + Clone, R: RcStub> Clone for FingerTree<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T: Measured, R: RcStub> Default for FingerTree<T, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Measured + Debug, R: RcStub> Debug for FingerTree<T, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Measured + PartialEq, R: RcStub> PartialEq for FingerTree<T, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Measured + Eq, R: RcStub> Eq for FingerTree<T, R> {}
+
+impl<T: Measured + Clone, R: RcStub> FromIterator<T> for FingerTree<T, R> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::new(), |t, v| t.push_back(v))
+    }
+}
+
+impl<'a, T: Measured, R: RcStub> IntoIterator for &'a FingerTree<T, R> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, R>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+enum Frame<'a, T: Measured, R: RcStub> {
+    Items(slice::Iter<'a, Item<T, R>>),
+    Tree(&'a Tree<T, R>),
+}
+
+pub struct Iter<'a, T: Measured, R: RcStub> {
+    /// what's left, innermost last
+    stack: Vec<Frame<'a, T, R>>,
+}
+
+impl<'a, T: Measured, R: RcStub> Iterator for Iter<'a, T, R> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Frame::Items(mut items) => {
+                    let Some(x) = items.next() else { continue };
+                    self.stack.push(Frame::Items(items));
+                    match x {
+                        Item::Leaf(v) => return Some(v),
+                        Item::Node(n) => self.stack.push(Frame::Items(n.items.iter())),
+                    }
+                }
+                Frame::Tree(Tree::Empty) => {}
+                Frame::Tree(Tree::Single(x)) => {
+                    self.stack.push(Frame::Items(slice::from_ref(x).iter()))
+                }
+                Frame::Tree(Tree::Deep(d)) => {
+                    self.stack.push(Frame::Items(d.suffix.iter()));
+                    self.stack.push(Frame::Tree(&d.middle));
+                    self.stack.push(Frame::Items(d.prefix.iter()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::AtRc;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::VecDeque;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Elem(u32);
+
+    impl Measured for Elem {
+        type Measure = Size;
+        fn measure(&self) -> Size {
+            Size(1)
+        }
+    }
+
+    fn check<R: RcStub>(t: &FingerTree<Elem, R>, model: &VecDeque<u32>) {
+        assert_eq!(t.len(), model.len());
+        assert!(t.iter().map(|e| e.0).eq(model.iter().copied()));
+        assert_eq!(t.front().map(|e| e.0), model.front().copied());
+        assert_eq!(t.back().map(|e| e.0), model.back().copied());
+    }
+
+    #[test]
+    fn deque() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut t = FingerTree::<Elem>::new();
+        let mut model = VecDeque::new();
+        let mut old = Vec::new();
+        for i in 0..5000 {
+            match rng.gen_range(0..4) {
+                0 => {
+                    t = t.push_front(Elem(i));
+                    model.push_front(i);
+                }
+                1 => {
+                    t = t.push_back(Elem(i));
+                    model.push_back(i);
+                }
+                2 => {
+                    if let Some((v, rest)) = t.pop_front() {
+                        assert_eq!(Some(v.0), model.pop_front());
+                        t = rest;
+                    }
+                }
+                _ => {
+                    if let Some((rest, v)) = t.pop_back() {
+                        assert_eq!(Some(v.0), model.pop_back());
+                        t = rest;
+                    }
+                }
+            }
+            if i % 1000 == 0 {
+                old.push((t.clone(), model.clone()));
+            }
+        }
+        check(&t, &model);
+        for (t, model) in &old {
+            check(t, model);
+        }
+    }
+
+    #[test]
+    fn indexed() {
+        let t: FingerTree<Elem> = (0..3000).map(Elem).collect();
+        for i in (0..3000).step_by(7) {
+            assert_eq!(t.get(i), Some(Elem(i as u32)));
+            let (l, r) = t.split_at(i);
+            assert_eq!((l.len(), r.front()), (i, Some(&Elem(i as u32))));
+            assert_eq!(l.concat(&r), t);
+        }
+        assert_eq!(t.get(3000), None);
+        let (l, r) = t.split_at(5000);
+        assert_eq!((l.len(), r.len()), (3000, 0));
+
+        // glue lots of pieces of different sizes together, on both sides
+        let mut model = VecDeque::new();
+        let mut whole = FingerTree::new();
+        for n in 0..60 {
+            let part: FingerTree<Elem> = (0..n).map(Elem).collect();
+            if n % 2 == 0 {
+                whole = whole.concat(&part);
+                model.extend(0..n);
+            } else {
+                whole = part.concat(&whole);
+                (0..n).rev().for_each(|i| model.push_front(i));
+            }
+        }
+        check(&whole, &model);
+    }
+
+    /// the largest priority in a subtree
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct MaxPrio(Option<u32>);
+
+    impl Semigroup for MaxPrio {
+        fn append(self, rhs: Self) -> Self {
+            MaxPrio(self.0.max(rhs.0))
+        }
+    }
+
+    impl Monoid for MaxPrio {
+        fn mempty() -> Self {
+            MaxPrio(None)
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Task(u32);
+
+    impl Measured for Task {
+        type Measure = MaxPrio;
+        fn measure(&self) -> MaxPrio {
+            MaxPrio(Some(self.0))
+        }
+    }
+
+    #[test]
+    fn priority_queue() {
+        let mut q: FingerTree<Task, AtRc> = [3, 9, 1, 9, 4].into_iter().map(Task).collect();
+        let mut out = Vec::new();
+        while !q.is_empty() {
+            let max = q.measure();
+            // the first task with the highest priority
+            let (l, r) = q.split(|m| *m == max);
+            let (task, r) = r.pop_front().unwrap();
+            out.push(task.0);
+            q = l.concat(&r);
+        }
+        assert_eq!(out, [9, 9, 4, 3, 1]);
+    }
+}
@@ -0,0 +1,611 @@
+use crate::rc::{Rc, RcStub, StRc};
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    hash::{BuildHasher, Hash, Hasher},
+    iter::FusedIterator,
+    mem, slice,
+};
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+enum Node<K, V, R: RcStub> {
+    /// one bit for every one of the 32 hash fragments that has an entry, the entries are kept in
+    /// the order of their bits
+    Bitmap {
+        bitmap: u32,
+        entries: Vec<Entry<K, V, R>>,
+    },
+    /// keys whose whole hashes are equal
+    Collision { hash: u64, pairs: Vec<(K, V)> },
+}
+
+enum Entry<K, V, R: RcStub> {
+    Leaf(u64, K, V),
+    Node(Rc<Node<K, V, R>, R>),
+}
+
+impl<K: Clone, V: Clone, R: RcStub> Clone for Node<K, V, R> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Bitmap { bitmap, entries } => Node::Bitmap {
+                bitmap: *bitmap,
+                entries: entries.clone(),
+            },
+            Node::Collision { hash, pairs } => Node::Collision {
+                hash: *hash,
+                pairs: pairs.clone(),
+            },
+        }
+    }
+}
+
+impl<K: Clone, V: Clone, R: RcStub> Clone for Entry<K, V, R> {
+    fn clone(&self) -> Self {
+        match self {
+            Entry::Leaf(h, k, v) => Entry::Leaf(*h, k.clone(), v.clone()),
+            Entry::Node(n) => Entry::Node(n.clone()),
+        }
+    }
+}
+
+#[inline]
+fn bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+impl<K, V, R: RcStub> Node<K, V, R> {
+    const EMPTY: Self = Node::Bitmap {
+        bitmap: 0,
+        entries: Vec::new(),
+    };
+
+    fn get(&self, mut shift: u32, hash: u64, key: &K) -> Option<&V>
+    where
+        K: Eq,
+    {
+        let mut node = self;
+        loop {
+            match node {
+                Node::Bitmap { bitmap, entries } => {
+                    let bit = bit(hash, shift);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    match &entries[(bitmap & (bit - 1)).count_ones() as usize] {
+                        Entry::Leaf(h, k, v) => return (*h == hash && k == key).then_some(v),
+                        Entry::Node(n) => (node, shift) = (n, shift + BITS),
+                    }
+                }
+                Node::Collision { pairs, .. } => {
+                    return pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+                }
+            }
+        }
+    }
+
+    /// A node holding two entries with different hashes, or a collision of two leaves.
+    fn pair(shift: u32, a: (u64, Entry<K, V, R>), b: (u64, Entry<K, V, R>)) -> Self {
+        if a.0 == b.0 {
+            match (a.1, b.1) {
+                (Entry::Leaf(_, ka, va), Entry::Leaf(_, kb, vb)) => {
+                    return Node::Collision {
+                        hash: a.0,
+                        pairs: vec![(ka, va), (kb, vb)],
+                    }
+                }
+                _ => unreachable!("a collision node never meets a key with its hash"),
+            }
+        }
+        let (bit_a, bit_b) = (bit(a.0, shift), bit(b.0, shift));
+        if bit_a == bit_b {
+            let child = Self::pair(shift + BITS, a, b);
+            return Node::Bitmap {
+                bitmap: bit_a,
+                entries: vec![Entry::Node(Rc::new(child))],
+            };
+        }
+        let entries = if bit_a < bit_b {
+            vec![a.1, b.1]
+        } else {
+            vec![b.1, a.1]
+        };
+        Node::Bitmap {
+            bitmap: bit_a | bit_b,
+            entries,
+        }
+    }
+}
+
+impl<K: Eq + Clone, V: Clone, R: RcStub> Node<K, V, R> {
+    /// Returns the old value if the key was there already.
+    fn insert(this: &mut Rc<Self, R>, shift: u32, hash: u64, key: K, value: V) -> Option<V> {
+        match Rc::make_mut(this) {
+            Node::Bitmap { bitmap, entries } => {
+                let bit = bit(hash, shift);
+                let idx = (*bitmap & (bit - 1)).count_ones() as usize;
+                if *bitmap & bit == 0 {
+                    *bitmap |= bit;
+                    entries.insert(idx, Entry::Leaf(hash, key, value));
+                    return None;
+                }
+                match &mut entries[idx] {
+                    Entry::Leaf(h, k, v) if *h == hash && *k == key => Some(mem::replace(v, value)),
+                    Entry::Leaf(h, ..) => {
+                        let h = *h;
+                        let old = entries.remove(idx);
+                        let node = Self::pair(
+                            shift + BITS,
+                            (h, old),
+                            (hash, Entry::Leaf(hash, key, value)),
+                        );
+                        entries.insert(idx, Entry::Node(Rc::new(node)));
+                        None
+                    }
+                    Entry::Node(child) => Self::insert(child, shift + BITS, hash, key, value),
+                }
+            }
+            Node::Collision { hash: h, pairs } if *h == hash => {
+                if let Some((_, v)) = pairs.iter_mut().find(|(k, _)| *k == key) {
+                    return Some(mem::replace(v, value));
+                }
+                pairs.push((key, value));
+                None
+            }
+            Node::Collision { hash: h, .. } => {
+                // only shares a prefix with the colliding keys, push them down a level
+                let h = *h;
+                let old = mem::replace(this, Rc::new(Self::EMPTY));
+                *this = Rc::new(Self::pair(
+                    shift,
+                    (h, Entry::Node(old)),
+                    (hash, Entry::Leaf(hash, key, value)),
+                ));
+                None
+            }
+        }
+    }
+
+    fn remove(this: &mut Rc<Self, R>, shift: u32, hash: u64, key: &K) -> Option<V> {
+        // don't copy the path if the key isn't there
+        this.get(shift, hash, key)?;
+        match Rc::make_mut(this) {
+            Node::Bitmap { bitmap, entries } => {
+                let bit = bit(hash, shift);
+                let idx = (*bitmap & (bit - 1)).count_ones() as usize;
+                let Entry::Node(child) = &mut entries[idx] else {
+                    *bitmap &= !bit;
+                    let Entry::Leaf(_, _, v) = entries.remove(idx) else {
+                        unreachable!()
+                    };
+                    return Some(v);
+                };
+                let value = Self::remove(child, shift + BITS, hash, key);
+                // keep the tree canonical: nodes with a single leaf are replaced by the leaf
+                let collapse = match Rc::make_mut(child) {
+                    Node::Bitmap { entries: e, .. } => match e.as_slice() {
+                        [Entry::Leaf(..)] => e.pop(),
+                        _ => None,
+                    },
+                    Node::Collision { hash, pairs } if pairs.len() == 1 => {
+                        let (k, v) = pairs.pop().unwrap();
+                        Some(Entry::Leaf(*hash, k, v))
+                    }
+                    Node::Collision { .. } => None,
+                };
+                if let Some(leaf) = collapse {
+                    entries[idx] = leaf;
+                }
+                value
+            }
+            Node::Collision { pairs, .. } => {
+                let i = pairs.iter().position(|(k, _)| k == key)?;
+                Some(pairs.swap_remove(i).1)
+            }
+        }
+    }
+}
+
+/// An immutable hash array mapped trie.
+///
+/// Every level of the trie uses 5 more bits of the hash, so updates copy O(log32 n) nodes and
+/// share the rest. The hasher is any [`BuildHasher`], like for the other hash tables of the
+/// crate.
+///
+/// # Examples
+/// ```
+/// use hash_table::persistent::PersistentHashMap;
+/// use std::collections::hash_map::RandomState;
+/// let a = PersistentHashMap::<&str, i32, RandomState>::new().insert("a", 1);
+/// let b = a.insert("b", 2).remove(&"a");
+/// assert_eq!((a.get(&"a"), b.get(&"a"), b.get(&"b")), (Some(&1), None, Some(&2)));
+/// ```
+pub struct PersistentHashMap<K, V, S, R: RcStub = StRc> {
+    root: Rc<Node<K, V, R>, R>,
+    len: usize,
+    hash_builder: S,
+}
+
+impl<K, V, S: Default, R: RcStub> PersistentHashMap<K, V, S, R> {
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S, R: RcStub> PersistentHashMap<K, V, S, R> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            root: Rc::new(Node::EMPTY),
+            len: 0,
+            hash_builder,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V, R> {
+        let Node::Bitmap { entries, .. } = &*self.root else {
+            unreachable!("the root is never a collision")
+        };
+        Iter {
+            stack: vec![entries.iter()],
+            pairs: [].iter(),
+            len: self.len,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> Values<'_, K, V, R> {
+        Values(self.iter())
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher, R: RcStub> PersistentHashMap<K, V, S, R> {
+    fn hash(&self, key: &K) -> u64 {
+        let mut hasher = self.hash_builder.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.root.get(0, self.hash(key), key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl<K, V, S, R> PersistentHashMap<K, V, S, R>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+    R: RcStub,
+{
+    /// A new version with `key` set to `value`.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let mut out = self.clone();
+        out.insert_mut(key, value);
+        out
+    }
+
+    /// A new version without `key`.
+    pub fn remove(&self, key: &K) -> Self {
+        let mut out = self.clone();
+        out.remove_mut(key);
+        out
+    }
+
+    pub fn transient(&self) -> TransientHashMap<K, V, S, R> {
+        TransientHashMap { map: self.clone() }
+    }
+
+    fn insert_mut(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hash(&key);
+        let old = Node::insert(&mut self.root, 0, hash, key, value);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    fn remove_mut(&mut self, key: &K) -> Option<V> {
+        let hash = self.hash(key);
+        let old = Node::remove(&mut self.root, 0, hash, key);
+        if old.is_some() {
+            self.len -= 1;
+        }
+        old
+    }
+}
+
+impl<K, V, S: Clone, R: RcStub> Clone for PersistentHashMap<K, V, S, R> {
+    /// Only clones the pointer to the root and the hasher.
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+}
+
+impl<K, V, S: Default, R: RcStub> Default for PersistentHashMap<K, V, S, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug, V: Debug, S, R: RcStub> Debug for PersistentHashMap<K, V, S, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, S: BuildHasher, R: RcStub> PartialEq
+    for PersistentHashMap<K, V, S, R>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && (Rc::ptr_eq(&self.root, &other.root)
+                || self.iter().all(|(k, v)| other.get(k) == Some(v)))
+    }
+}
+
+impl<K: Eq + Hash, V: Eq, S: BuildHasher, R: RcStub> Eq for PersistentHashMap<K, V, S, R> {}
+
+impl<K, V, S, R> FromIterator<(K, V)> for PersistentHashMap<K, V, S, R>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone + Default,
+    R: RcStub,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut t = Self::new().transient();
+        t.extend(iter);
+        t.persistent()
+    }
+}
+
+impl<'a, K, V, S, R: RcStub> IntoIterator for &'a PersistentHashMap<K, V, S, R> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, R>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A [`PersistentHashMap`] that is changed in place, the nodes it shares with other versions
+/// are copied the first time they are touched.
+pub struct TransientHashMap<K, V, S, R: RcStub = StRc> {
+    map: PersistentHashMap<K, V, S, R>,
+}
+
+impl<K, V, S, R> TransientHashMap<K, V, S, R>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+    R: RcStub,
+{
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    /// Returns the old value if there was one.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert_mut(key, value)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove_mut(key)
+    }
+
+    /// Freezes it again.
+    pub fn persistent(self) -> PersistentHashMap<K, V, S, R> {
+        self.map
+    }
+}
+
+impl<K, V, S, R> Extend<(K, V)> for TransientHashMap<K, V, S, R>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+    R: RcStub,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|(k, v)| {
+            self.insert(k, v);
+        })
+    }
+}
+
+pub struct Iter<'a, K, V, R: RcStub> {
+    stack: Vec<slice::Iter<'a, Entry<K, V, R>>>,
+    /// the rest of the collision node being walked
+    pairs: slice::Iter<'a, (K, V)>,
+    len: usize,
+}
+
+impl<'a, K, V, R: RcStub> Iterator for Iter<'a, K, V, R> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.pairs.next() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+            match self.stack.last_mut()?.next() {
+                Some(Entry::Leaf(_, k, v)) => {
+                    self.len -= 1;
+                    return Some((k, v));
+                }
+                Some(Entry::Node(n)) => match &**n {
+                    Node::Bitmap { entries, .. } => self.stack.push(entries.iter()),
+                    Node::Collision { pairs, .. } => self.pairs = pairs.iter(),
+                },
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V, R: RcStub> ExactSizeIterator for Iter<'_, K, V, R> {}
+impl<K, V, R: RcStub> FusedIterator for Iter<'_, K, V, R> {}
+
+/// The values in no particular order.
+pub struct Values<'a, K, V, R: RcStub>(Iter<'a, K, V, R>);
+
+impl<'a, K, V, R: RcStub> Iterator for Values<'a, K, V, R> {
+    type Item = &'a V;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V, R: RcStub> ExactSizeIterator for Values<'_, K, V, R> {}
+impl<K, V, R: RcStub> FusedIterator for Values<'_, K, V, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::AtRc;
+    use core::hash::BuildHasherDefault;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::{hash_map::RandomState, HashMap};
+
+    /// only keeps a few bits of the key, so there are lots of full and partial collisions
+    #[derive(Default)]
+    struct Poor(u64);
+
+    impl Hasher for Poor {
+        fn finish(&self) -> u64 {
+            // the same low bits for everything, the top bits tell some keys apart
+            (self.0 % 5) << 62 | 0x1234
+        }
+        fn write(&mut self, bytes: &[u8]) {
+            for &b in bytes {
+                self.0 = self.0.wrapping_mul(31).wrapping_add(b as u64);
+            }
+        }
+    }
+
+    fn random_ops<S: BuildHasher + Clone + Default>(seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut model = HashMap::new();
+        let mut map = PersistentHashMap::<u16, u32, S>::new();
+        let mut versions = Vec::new();
+        for i in 0..3000 {
+            let k = rng.gen_range(0..500);
+            if rng.gen_ratio(2, 3) {
+                model.insert(k, i);
+                map = map.insert(k, i);
+            } else {
+                model.remove(&k);
+                map = map.remove(&k);
+            }
+            if i % 500 == 0 {
+                versions.push((map.clone(), model.clone()));
+            }
+        }
+        versions.push((map, model));
+        for (map, model) in &versions {
+            assert_eq!(map.len(), model.len());
+            assert_eq!(map.iter().count(), model.len());
+            for k in 0..500 {
+                assert_eq!(map.get(&k), model.get(&k));
+            }
+        }
+        // removing everything leaves an empty root behind
+        let (map, model) = versions.pop().unwrap();
+        let empty = model.keys().fold(map, |m, k| m.remove(k));
+        assert!(empty.is_empty() && empty == PersistentHashMap::new());
+        let Node::Bitmap { entries, .. } = &*empty.root else {
+            panic!()
+        };
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn matches_hashmap() {
+        random_ops::<RandomState>(1);
+    }
+
+    #[test]
+    fn collisions() {
+        random_ops::<BuildHasherDefault<Poor>>(2);
+    }
+
+    #[test]
+    fn transient_and_equality() {
+        let a: PersistentHashMap<i32, i32, RandomState> = (0..1000).map(|i| (i, i)).collect();
+        let mut t = a.transient();
+        for i in 0..1000 {
+            assert_eq!(t.insert(i, i * 2), Some(i));
+        }
+        assert_eq!(t.remove(&0), Some(0));
+        assert_eq!(t.remove(&0), None);
+        let b = t.persistent();
+        assert_eq!(
+            (b.len(), b.get(&10), a.get(&10)),
+            (999, Some(&20), Some(&10))
+        );
+        assert_ne!(a, b);
+        let c = (1..1000).fold(a.remove(&0), |m, i| m.insert(i, i * 2));
+        assert_eq!(b, c);
+        assert_eq!(c.remove(&5).insert(5, 10), c);
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let a: PersistentHashMap<i32, i32, RandomState, AtRc> = (0..100).map(|i| (i, i)).collect();
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let a = a.clone();
+                std::thread::spawn(move || a.insert(i, -1).values().sum::<i32>())
+            })
+            .collect();
+        for (i, h) in handles.into_iter().enumerate() {
+            assert_eq!(h.join().unwrap(), 4950 - i as i32 - 1);
+        }
+    }
+}
@@ -1,59 +1,346 @@
-/*
-use alloc::sync::Arc;
-use core::fmt::{Debug, Formatter};
+use crate::rc::{Rc, RcStub, StRc};
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+};
 
-pub struct SinglyLinkedList<T: Clone> {
-    head: Arc<Option<SinglyLinkedListNode<T>>>,
+struct Node<T, S: RcStub> {
+    value: T,
+    next: Link<T, S>,
 }
 
-impl<T: Clone> SinglyLinkedList<T> {
-    pub fn new() -> SinglyLinkedList<T> {
-        SinglyLinkedList { head: Arc::new(None) }
+type Link<T, S> = Option<Rc<Node<T, S>, S>>;
+
+/// An immutable singly linked list, [`cons`](ConsList::cons) and [`tail`](ConsList::tail) share
+/// the rest of the list instead of copying it.
+///
+/// # Examples
+/// ```
+/// use hash_table::persistent::ConsList;
+/// let a: ConsList<i32> = [2, 3].into_iter().collect();
+/// let b = a.cons(1);
+/// assert_eq!(b.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+/// assert_eq!(b.tail(), Some(a));
+/// ```
+pub struct ConsList<T, S: RcStub = StRc> {
+    head: Link<T, S>,
+    len: usize,
+}
+
+impl<T, S: RcStub> ConsList<T, S> {
+    pub const fn new() -> Self {
+        Self { head: None, len: 0 }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A new list with `value` in front of this one.
+    ///
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn cons(&self, value: T) -> Self {
+        Self {
+            head: Some(Rc::new(Node {
+                value,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    /// Puts `value` in front of this version only.
+    pub fn push_front(&mut self, value: T) {
+        let next = self.head.take();
+        self.head = Some(Rc::new(Node { value, next }));
+        self.len += 1;
+    }
+
+    #[inline]
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|n| &n.value)
+    }
+
+    /// The list without its first element, `None` if it's empty.
+    pub fn tail(&self) -> Option<Self> {
+        let node = self.head.as_ref()?;
+        Some(Self {
+            head: node.next.clone(),
+            len: self.len - 1,
+        })
+    }
+
+    /// Splits off the first element.
+    pub fn uncons(&self) -> Option<(&T, Self)> {
+        Some((self.head()?, self.tail()?))
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            node: self.head.as_deref(),
+            len: self.len,
+        }
+    }
+
+    /// A new list with the elements in reverse order, nothing is shared with this one.
+    pub fn rev(&self) -> Self
+    where
+        T: Clone,
+    {
+        let mut out = Self::new();
+        for v in self.iter() {
+            out.push_front(v.clone());
+        }
+        out
+    }
+
+    /// Returns `true` if both lists are the same version.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T, S: RcStub> Drop for ConsList<T, S> {
+    fn drop(&mut self) {
+        // unlink the nodes nobody else holds one by one, so a long list doesn't recurse. when two
+        // lists sharing a tail drop at the same time exactly one of them gets to carry on
+        let mut head = self.head.take();
+        while let Some(node) = head {
+            match Rc::into_inner(node) {
+                Some(mut node) => head = node.next.take(),
+                None => break,
+            }
+        }
+    }
+}
+
+impl<T, S: RcStub> Clone for ConsList<T, S> {
+    /// Only clones the pointer to the first node.
+    fn clone(&self) -> Self {
+        Self {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T, S: RcStub> Default for ConsList<T, S> {
+    fn default() -> Self {
+        Self::new()
     }
-    pub fn push(&mut self, val: T) {
-        let mut n = SinglyLinkedListNode::new(val);
-        n.next = self.head.clone();
-        *Arc::make_mut(&mut self.head) = Some(n);
+}
+
+impl<T: Debug, S: RcStub> Debug for ConsList<T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, S: RcStub> PartialEq for ConsList<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        // shared tails are equal without looking at them
+        let (mut a, mut b) = (self.head.as_ref(), other.head.as_ref());
+        if self.len != other.len {
+            return false;
+        }
+        while let (Some(x), Some(y)) = (a, b) {
+            if Rc::ptr_eq(x, y) {
+                return true;
+            }
+            if x.value != y.value {
+                return false;
+            }
+            (a, b) = (x.next.as_ref(), y.next.as_ref());
+        }
+        true
+    }
+}
+
+impl<T: Eq, S: RcStub> Eq for ConsList<T, S> {}
+
+impl<T: Hash, S: RcStub> Hash for ConsList<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        self.iter().for_each(|v| v.hash(state))
+    }
+}
+
+impl<T, S: RcStub> FromIterator<T> for ConsList<T, S> {
+    /// Keeps the order of the iterator.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        let mut out = Self::new();
+        for v in items.into_iter().rev() {
+            out.push_front(v);
+        }
+        out
+    }
+}
+
+pub struct Iter<'a, T, S: RcStub> {
+    node: Option<&'a Node<T, S>>,
+    len: usize,
+}
+
+impl<'a, T, S: RcStub> Iterator for Iter<'a, T, S> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node?;
+        self.node = node.next.as_deref();
+        self.len -= 1;
+        Some(&node.value)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
     }
 }
 
-impl<T: Clone> Debug for SinglyLinkedList<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        write!(f, "SinglyLinkedList {{")?;
+impl<T, S: RcStub> ExactSizeIterator for Iter<'_, T, S> {}
+impl<T, S: RcStub> FusedIterator for Iter<'_, T, S> {}
+
+impl<T, S: RcStub> Clone for Iter<'_, T, S> {
+    fn clone(&self) -> Self {
+        Self { ..*self }
+    }
+}
 
-        write!(f, "}}")
+impl<'a, T, S: RcStub> IntoIterator for &'a ConsList<T, S> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, S>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
-pub struct SinglyLinkedListIterator<T: Clone> {
-    item: Arc<Option<SinglyLinkedListNode<T>>>
+/// Moves the values out of the nodes this list holds alone and clones the shared ones.
+pub struct IntoIter<T, S: RcStub> {
+    list: ConsList<T, S>,
 }
 
-impl<T: Clone> Iterator for SinglyLinkedListIterator<T> {
+impl<T: Clone, S: RcStub> Iterator for IntoIter<T, S> {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(s) = self.item.as_ref() {
-            let b = s.val.clone();
-            self.item = s.next.clone();
-            Some(b)
-        } else {
-            None
+        let node = self.list.head.take()?;
+        self.list.len -= 1;
+        match Rc::try_unwrap(node) {
+            Ok(mut node) => {
+                self.list.head = node.next.take();
+                Some(node.value)
+            }
+            Err(node) => {
+                self.list.head = node.next.clone();
+                let value = node.value.clone();
+                // the others can let go in the meantime and leave this one to drop the node, the
+                // rest of the list is held above so that doesn't go any further
+                if let Some(mut node) = Rc::into_inner(node) {
+                    node.next = None;
+                }
+                Some(value)
+            }
         }
     }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
 }
 
-use alloc::boxed::Box;
+impl<T: Clone, S: RcStub> ExactSizeIterator for IntoIter<T, S> {}
+impl<T: Clone, S: RcStub> FusedIterator for IntoIter<T, S> {}
 
-#[derive(Clone)]
-struct SinglyLinkedListNode<T> {
-    pub val: Arc<T>,
-    pub next: Option<Box<SinglyLinkedListNode<T>>>
+impl<T: Clone, S: RcStub> IntoIterator for ConsList<T, S> {
+    type Item = T;
+    type IntoIter = IntoIter<T, S>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
 }
 
-impl<T> SinglyLinkedListNode<T> {
-    pub fn new(val: T) -> SinglyLinkedListNode<T> {
-        SinglyLinkedListNode { val: Arc::new(val), next: None }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::AtRc;
+    use alloc::vec;
+    use std::thread;
+
+    #[test]
+    fn sharing() {
+        let empty: ConsList<i32> = ConsList::new();
+        assert_eq!((empty.head(), empty.tail()), (None, None));
+        let a = empty.cons(3).cons(2);
+        let b = a.cons(1);
+        let c = a.cons(0);
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(c.iter().copied().collect::<Vec<_>>(), [0, 2, 3]);
+        assert!(b.tail().unwrap().ptr_eq(&c.tail().unwrap()));
+        assert_eq!(b.uncons(), Some((&1, a.clone())));
+        assert_ne!(b, c);
+        assert_eq!(b.rev(), [3, 2, 1].into_iter().collect());
+        drop(a);
+        assert_eq!(c.into_iter().collect::<Vec<_>>(), [0, 2, 3]);
+        assert_eq!(b.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(format!("{empty:?}"), "[]");
+    }
+
+    #[test]
+    fn long_lists_drop() {
+        let a: ConsList<usize> = (0..1_000_000).collect();
+        let b = a.tail().unwrap().cons(7);
+        drop(a);
+        assert_eq!(b.iter().sum::<usize>(), 1_000_000 * 999_999 / 2 + 7);
+        drop(b);
     }
-}
 
- */
+    #[test]
+    fn shared_between_threads() {
+        let a: ConsList<Vec<i32>, AtRc> = (0..100).map(|i| vec![i]).collect();
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let a = a.clone();
+                thread::spawn(move || a.cons(vec![i]).len())
+            })
+            .collect();
+        for t in threads {
+            assert_eq!(t.join().unwrap(), 101);
+        }
+        assert_eq!(a.head(), Some(&vec![0]));
+    }
+
+    #[test]
+    fn long_shared_lists_drop_on_threads() {
+        // whichever thread lets go last has to free the whole tail without recursing
+        let tail: ConsList<usize, AtRc> = (0..200_000).collect();
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(4));
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let list = tail.cons(i);
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    if i % 2 == 0 {
+                        drop(list);
+                    } else {
+                        assert_eq!(list.into_iter().count(), 200_001);
+                    }
+                })
+            })
+            .collect();
+        drop(tail);
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,735 @@
+//! A weight balanced tree where every operation is built on `join`, after Blelloch, Ferizovic
+//! and Sun, "Just Join for Parallel Ordered Sets".
+
+use crate::rc::{Rc, RcStub, StRc};
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug, Formatter},
+    iter::FusedIterator,
+    ops::{Bound, RangeBounds},
+};
+
+struct Node<K, V, R: RcStub> {
+    key: K,
+    value: V,
+    size: usize,
+    left: Tree<K, V, R>,
+    right: Tree<K, V, R>,
+}
+
+type Tree<K, V, R> = Option<Rc<Node<K, V, R>, R>>;
+/// the keys before a key, its value and the keys after it
+type Split<'a, K, V, R> = (Tree<K, V, R>, Option<&'a V>, Tree<K, V, R>);
+
+/// a subtree is at least a quarter of its parent's weight
+const DELTA: usize = 3;
+
+#[inline]
+fn size<K, V, R: RcStub>(t: &Tree<K, V, R>) -> usize {
+    t.as_ref().map_or(0, |n| n.size)
+}
+
+#[inline]
+fn weight<K, V, R: RcStub>(t: &Tree<K, V, R>) -> usize {
+    size(t) + 1
+}
+
+#[inline]
+fn balanced(a: usize, b: usize) -> bool {
+    DELTA * a >= b && DELTA * b >= a
+}
+
+/// both trees are the same version
+fn same<K, V, R: RcStub>(a: &Tree<K, V, R>, b: &Tree<K, V, R>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn node<K, V, R: RcStub>(
+    left: Tree<K, V, R>,
+    key: K,
+    value: V,
+    right: Tree<K, V, R>,
+) -> Rc<Node<K, V, R>, R> {
+    Rc::new(Node {
+        size: size(&left) + size(&right) + 1,
+        key,
+        value,
+        left,
+        right,
+    })
+}
+
+fn get<'a, K: Ord, V, R: RcStub>(mut t: &'a Tree<K, V, R>, key: &K) -> Option<&'a Node<K, V, R>> {
+    while let Some(n) = t {
+        t = match key.cmp(&n.key) {
+            Ordering::Less => &n.left,
+            Ordering::Greater => &n.right,
+            Ordering::Equal => return Some(n),
+        }
+    }
+    None
+}
+
+impl<K: Clone, V: Clone, R: RcStub> Node<K, V, R> {
+    /// A tree with everything in `left`, then `key`, then everything in `right`.
+    fn join(left: Tree<K, V, R>, key: K, value: V, right: Tree<K, V, R>) -> Rc<Self, R> {
+        let (wl, wr) = (weight(&left), weight(&right));
+        if balanced(wl, wr) {
+            node(left, key, value, right)
+        } else if wl > wr {
+            Self::join_right(&left, key, value, right)
+        } else {
+            Self::join_left(left, key, value, &right)
+        }
+    }
+
+    /// `left` is too heavy, walk down its right spine until the weights match
+    fn join_right(left: &Tree<K, V, R>, key: K, value: V, right: Tree<K, V, R>) -> Rc<Self, R> {
+        if balanced(weight(left), weight(&right)) {
+            return node(left.clone(), key, value, right);
+        }
+        let left = left.as_ref().expect("only heavier trees get here");
+        let t = Self::join_right(&left.right, key, value, right);
+        let (l, k, v) = (&left.left, left.key.clone(), left.value.clone());
+        if balanced(weight(l), t.size + 1) {
+            return node(l.clone(), k, v, Some(t));
+        }
+        if balanced(weight(l), weight(&t.left))
+            && balanced(weight(l) + weight(&t.left), weight(&t.right))
+        {
+            // single rotation
+            let inner = node(l.clone(), k, v, t.left.clone());
+            return node(Some(inner), t.key.clone(), t.value.clone(), t.right.clone());
+        }
+        // double rotation
+        let a = t.left.as_ref().unwrap();
+        let outer = node(l.clone(), k, v, a.left.clone());
+        let inner = node(
+            a.right.clone(),
+            t.key.clone(),
+            t.value.clone(),
+            t.right.clone(),
+        );
+        node(Some(outer), a.key.clone(), a.value.clone(), Some(inner))
+    }
+
+    /// the mirror image of `join_right`
+    fn join_left(left: Tree<K, V, R>, key: K, value: V, right: &Tree<K, V, R>) -> Rc<Self, R> {
+        if balanced(weight(&left), weight(right)) {
+            return node(left, key, value, right.clone());
+        }
+        let right = right.as_ref().expect("only heavier trees get here");
+        let t = Self::join_left(left, key, value, &right.left);
+        let (r, k, v) = (&right.right, right.key.clone(), right.value.clone());
+        if balanced(t.size + 1, weight(r)) {
+            return node(Some(t), k, v, r.clone());
+        }
+        if balanced(weight(r), weight(&t.right))
+            && balanced(weight(r) + weight(&t.right), weight(&t.left))
+        {
+            let inner = node(t.right.clone(), k, v, r.clone());
+            return node(t.left.clone(), t.key.clone(), t.value.clone(), Some(inner));
+        }
+        let a = t.right.as_ref().unwrap();
+        let inner = node(
+            t.left.clone(),
+            t.key.clone(),
+            t.value.clone(),
+            a.left.clone(),
+        );
+        let outer = node(a.right.clone(), k, v, r.clone());
+        node(Some(inner), a.key.clone(), a.value.clone(), Some(outer))
+    }
+
+    /// Joins two trees without a key between them.
+    fn join2(left: Tree<K, V, R>, right: Tree<K, V, R>) -> Tree<K, V, R> {
+        match &left {
+            None => right,
+            Some(l) => {
+                let (l, k, v) = Self::split_last(l);
+                Some(Self::join(l, k, v, right))
+            }
+        }
+    }
+
+    fn split_last(this: &Rc<Self, R>) -> (Tree<K, V, R>, K, V) {
+        match &this.right {
+            None => (this.left.clone(), this.key.clone(), this.value.clone()),
+            Some(r) => {
+                let (r, k, v) = Self::split_last(r);
+                let t = Self::join(this.left.clone(), this.key.clone(), this.value.clone(), r);
+                (Some(t), k, v)
+            }
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone, R: RcStub> Node<K, V, R> {
+    /// The keys less than `key`, the value of `key` and the keys greater than it.
+    fn split<'a>(t: &'a Tree<K, V, R>, key: &K) -> Split<'a, K, V, R> {
+        let Some(n) = t else {
+            return (None, None, None);
+        };
+        match key.cmp(&n.key) {
+            Ordering::Less => {
+                let (l, found, r) = Self::split(&n.left, key);
+                let r = Self::join(r, n.key.clone(), n.value.clone(), n.right.clone());
+                (l, found, Some(r))
+            }
+            Ordering::Greater => {
+                let (l, found, r) = Self::split(&n.right, key);
+                let l = Self::join(n.left.clone(), n.key.clone(), n.value.clone(), l);
+                (Some(l), found, r)
+            }
+            Ordering::Equal => (n.left.clone(), Some(&n.value), n.right.clone()),
+        }
+    }
+
+    fn insert(t: &Tree<K, V, R>, key: K, value: V) -> Rc<Self, R> {
+        let Some(n) = t else {
+            return node(None, key, value, None);
+        };
+        let (k, v) = (n.key.clone(), n.value.clone());
+        match key.cmp(&n.key) {
+            Ordering::Less => {
+                let l = Self::insert(&n.left, key, value);
+                Self::join(Some(l), k, v, n.right.clone())
+            }
+            Ordering::Greater => {
+                let r = Self::insert(&n.right, key, value);
+                Self::join(n.left.clone(), k, v, Some(r))
+            }
+            Ordering::Equal => node(n.left.clone(), key, value, n.right.clone()),
+        }
+    }
+
+    /// The key has to be in the tree.
+    fn remove(n: &Rc<Self, R>, key: &K) -> Tree<K, V, R> {
+        let (k, v) = (n.key.clone(), n.value.clone());
+        match key.cmp(&n.key) {
+            Ordering::Less => {
+                let l = Self::remove(n.left.as_ref().unwrap(), key);
+                Some(Self::join(l, k, v, n.right.clone()))
+            }
+            Ordering::Greater => {
+                let r = Self::remove(n.right.as_ref().unwrap(), key);
+                Some(Self::join(n.left.clone(), k, v, r))
+            }
+            Ordering::Equal => Self::join2(n.left.clone(), n.right.clone()),
+        }
+    }
+
+    /// All keys of both, the values of `b` win.
+    fn union(a: &Tree<K, V, R>, b: &Tree<K, V, R>) -> Tree<K, V, R> {
+        match (a, b) {
+            (None, _) => b.clone(),
+            (_, None) => a.clone(),
+            _ if same(a, b) => a.clone(),
+            (_, Some(n)) => {
+                let (l, _, r) = Self::split(a, &n.key);
+                let (l, r) = (Self::union(&l, &n.left), Self::union(&r, &n.right));
+                Some(Self::join(l, n.key.clone(), n.value.clone(), r))
+            }
+        }
+    }
+
+    /// The keys of `a` that are in `b` too, with the values of `a`.
+    fn intersection(a: &Tree<K, V, R>, b: &Tree<K, V, R>) -> Tree<K, V, R> {
+        match (a, b) {
+            (None, _) | (_, None) => None,
+            _ if same(a, b) => a.clone(),
+            (Some(n), _) => {
+                let (l, found, r) = Self::split(b, &n.key);
+                let l = Self::intersection(&n.left, &l);
+                let r = Self::intersection(&n.right, &r);
+                match found {
+                    // nothing was dropped, keep the old node
+                    Some(_) if same(&l, &n.left) && same(&r, &n.right) => a.clone(),
+                    Some(_) => Some(Self::join(l, n.key.clone(), n.value.clone(), r)),
+                    None => Self::join2(l, r),
+                }
+            }
+        }
+    }
+
+    /// The keys of `a` that aren't in `b`.
+    fn difference(a: &Tree<K, V, R>, b: &Tree<K, V, R>) -> Tree<K, V, R> {
+        match (a, b) {
+            (None, _) => None,
+            (_, None) => a.clone(),
+            _ if same(a, b) => None,
+            (Some(n), _) => {
+                let (l, found, r) = Self::split(b, &n.key);
+                let l = Self::difference(&n.left, &l);
+                let r = Self::difference(&n.right, &r);
+                match found {
+                    Some(_) => Self::join2(l, r),
+                    None if same(&l, &n.left) && same(&r, &n.right) => a.clone(),
+                    None => Some(Self::join(l, n.key.clone(), n.value.clone(), r)),
+                }
+            }
+        }
+    }
+}
+
+/// An immutable ordered map, a weight balanced tree of [`Rc`] nodes.
+///
+/// Updates copy O(log n) nodes and share the rest. `split`, `join` and the set operations run
+/// in O(log n) for `join` and O(m log(n / m + 1)) for the others, and skip over subtrees both
+/// sides share.
+///
+/// # Examples
+/// ```
+/// use hash_table::persistent::PersistentOrdMap;
+/// let a: PersistentOrdMap<i32, char> = [(1, 'a'), (3, 'c')].into_iter().collect();
+/// let b = a.insert(2, 'b');
+/// assert_eq!(b.range(2..).map(|(k, _)| *k).collect::<Vec<_>>(), [2, 3]);
+/// assert_eq!((a.rank(&3), b.rank(&3)), (1, 2));
+/// ```
+pub struct PersistentOrdMap<K, V, R: RcStub = StRc> {
+    root: Tree<K, V, R>,
+}
+
+impl<K, V, R: RcStub> PersistentOrdMap<K, V, R> {
+    pub const fn new() -> Self {
+        Self { root: None }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V, R> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            len: self.len(),
+        };
+        iter.push_left(&self.root);
+        iter
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> Values<'_, K, V, R> {
+        Values(self.iter())
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        let mut n = self.root.as_ref()?;
+        while let Some(l) = &n.left {
+            n = l;
+        }
+        Some((&n.key, &n.value))
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        let mut n = self.root.as_ref()?;
+        while let Some(r) = &n.right {
+            n = r;
+        }
+        Some((&n.key, &n.value))
+    }
+
+    /// The `i`th smallest key, the inverse of [`rank`](Self::rank).
+    pub fn get_index(&self, mut i: usize) -> Option<(&K, &V)> {
+        let mut t = &self.root;
+        while let Some(n) = t {
+            let l = size(&n.left);
+            t = match i.cmp(&l) {
+                Ordering::Less => &n.left,
+                Ordering::Equal => return Some((&n.key, &n.value)),
+                Ordering::Greater => {
+                    i -= l + 1;
+                    &n.right
+                }
+            };
+        }
+        None
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        same(&self.root, &other.root)
+    }
+}
+
+impl<K: Ord, V, R: RcStub> PersistentOrdMap<K, V, R> {
+    pub fn get(&self, key: &K) -> Option<&V> {
+        get(&self.root, key).map(|n| &n.value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        get(&self.root, key).is_some()
+    }
+
+    /// The number of keys less than `key`.
+    pub fn rank(&self, key: &K) -> usize {
+        let (mut t, mut rank) = (&self.root, 0);
+        while let Some(n) = t {
+            t = match key.cmp(&n.key) {
+                Ordering::Less => &n.left,
+                Ordering::Equal => return rank + size(&n.left),
+                Ordering::Greater => {
+                    rank += size(&n.left) + 1;
+                    &n.right
+                }
+            };
+        }
+        rank
+    }
+
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Iter<'_, K, V, R> {
+        let below = |key: &K, inclusive: bool| {
+            self.rank(key) + (inclusive && self.contains_key(key)) as usize
+        };
+        let start = match range.start_bound() {
+            Bound::Included(s) => below(s, false),
+            Bound::Excluded(s) => below(s, true),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => below(e, true),
+            Bound::Excluded(e) => below(e, false),
+            Bound::Unbounded => self.len(),
+        };
+        let mut iter = Iter {
+            stack: Vec::new(),
+            len: end.saturating_sub(start),
+        };
+        // push the path to the first key in range
+        let mut t = &self.root;
+        while let Some(n) = t {
+            let after_start = match range.start_bound() {
+                Bound::Included(s) => n.key >= *s,
+                Bound::Excluded(s) => n.key > *s,
+                Bound::Unbounded => true,
+            };
+            if after_start {
+                iter.stack.push(n);
+                t = &n.left;
+            } else {
+                t = &n.right;
+            }
+        }
+        iter
+    }
+}
+
+impl<K: Ord + Clone, V: Clone, R: RcStub> PersistentOrdMap<K, V, R> {
+    /// A new version with `key` set to `value`.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        Self {
+            root: Some(Node::insert(&self.root, key, value)),
+        }
+    }
+
+    /// A new version without `key`.
+    pub fn remove(&self, key: &K) -> Self {
+        match &self.root {
+            Some(n) if get(&self.root, key).is_some() => Self {
+                root: Node::remove(n, key),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// The keys less than `key`, the value of `key` and the keys greater than it.
+    pub fn split(&self, key: &K) -> (Self, Option<&V>, Self) {
+        let (l, found, r) = Node::split(&self.root, key);
+        (Self { root: l }, found, Self { root: r })
+    }
+
+    /// The keys of `self` followed by those of `other`.
+    ///
+    /// # Panics
+    /// Panics if the last key of `self` isn't less than the first of `other`.
+    pub fn join(&self, other: &Self) -> Self {
+        if let (Some((a, _)), Some((b, _))) = (self.last(), other.first()) {
+            assert!(a < b, "joined maps overlap");
+        }
+        Self {
+            root: Node::join2(self.root.clone(), other.root.clone()),
+        }
+    }
+
+    /// All keys of both, the values of `other` win.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            root: Node::union(&self.root, &other.root),
+        }
+    }
+
+    /// The keys that are in both, with the values of `self`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            root: Node::intersection(&self.root, &other.root),
+        }
+    }
+
+    /// The keys of `self` that aren't in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            root: Node::difference(&self.root, &other.root),
+        }
+    }
+}
+
+impl<K, V, R: RcStub> Clone for PersistentOrdMap<K, V, R> {
+    /// Only clones the pointer to the root.
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<K, V, R: RcStub> Default for PersistentOrdMap<K, V, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug, V: Debug, R: RcStub> Debug for PersistentOrdMap<K, V, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: PartialEq, V: PartialEq, R: RcStub> PartialEq for PersistentOrdMap<K, V, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || (self.len() == other.len() && self.iter().eq(other.iter()))
+    }
+}
+
+impl<K: Eq, V: Eq, R: RcStub> Eq for PersistentOrdMap<K, V, R> {}
+
+impl<K: Ord + Clone, V: Clone, R: RcStub> FromIterator<(K, V)> for PersistentOrdMap<K, V, R> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |map, (k, v)| map.insert(k, v))
+    }
+}
+
+impl<'a, K, V, R: RcStub> IntoIterator for &'a PersistentOrdMap<K, V, R> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, R>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// In order iterator over (a range of) a [`PersistentOrdMap`].
+pub struct Iter<'a, K, V, R: RcStub> {
+    /// the nodes left to visit on the path down to the next one
+    stack: Vec<&'a Node<K, V, R>>,
+    /// how many are left in the range
+    len: usize,
+}
+
+impl<'a, K, V, R: RcStub> Iter<'a, K, V, R> {
+    fn push_left(&mut self, mut t: &'a Tree<K, V, R>) {
+        while let Some(n) = t {
+            self.stack.push(n);
+            t = &n.left;
+        }
+    }
+}
+
+impl<'a, K, V, R: RcStub> Iterator for Iter<'a, K, V, R> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let n = self.stack.pop()?;
+        self.push_left(&n.right);
+        Some((&n.key, &n.value))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V, R: RcStub> ExactSizeIterator for Iter<'_, K, V, R> {}
+impl<K, V, R: RcStub> FusedIterator for Iter<'_, K, V, R> {}
+
+/// The values in key order.
+pub struct Values<'a, K, V, R: RcStub>(Iter<'a, K, V, R>);
+
+impl<'a, K, V, R: RcStub> Iterator for Values<'a, K, V, R> {
+    type Item = &'a V;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V, R: RcStub> ExactSizeIterator for Values<'_, K, V, R> {}
+impl<K, V, R: RcStub> FusedIterator for Values<'_, K, V, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::AtRc;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    impl<K: Ord, V, R: RcStub> PersistentOrdMap<K, V, R> {
+        fn check(&self) {
+            fn walk<K: Ord, V, R: RcStub>(t: &Tree<K, V, R>) -> usize {
+                let Some(n) = t else { return 0 };
+                let (l, r) = (walk(&n.left), walk(&n.right));
+                assert!(balanced(l + 1, r + 1), "unbalanced: {l} {r}");
+                assert_eq!(n.size, l + r + 1);
+                n.size
+            }
+            walk(&self.root);
+            assert!(self.keys().zip(self.keys().skip(1)).all(|(a, b)| a < b));
+        }
+    }
+
+    fn random(
+        rng: &mut StdRng,
+        n: usize,
+        keys: i32,
+    ) -> (PersistentOrdMap<i32, i32>, BTreeMap<i32, i32>) {
+        let mut map = PersistentOrdMap::new();
+        let mut model = BTreeMap::new();
+        for _ in 0..n {
+            let (k, v) = (rng.gen_range(0..keys), rng.gen());
+            if rng.gen_ratio(3, 4) {
+                map = map.insert(k, v);
+                model.insert(k, v);
+            } else {
+                map = map.remove(&k);
+                model.remove(&k);
+            }
+        }
+        (map, model)
+    }
+
+    fn same_as(map: &PersistentOrdMap<i32, i32>, model: &BTreeMap<i32, i32>) {
+        map.check();
+        assert_eq!(map.len(), model.len());
+        assert!(map.iter().eq(model.iter()));
+    }
+
+    #[test]
+    fn matches_btreemap() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (map, model) = random(&mut rng, 5000, 1000);
+        same_as(&map, &model);
+        for k in -1..1001 {
+            assert_eq!(map.get(&k), model.get(&k));
+            assert_eq!(map.rank(&k), model.range(..k).count());
+        }
+        for (i, kv) in model.iter().enumerate() {
+            assert_eq!(map.get_index(i), Some(kv));
+        }
+        assert!(map.range(100..=200).eq(model.range(100..=200)));
+        assert!(map
+            .range((Bound::Excluded(5), Bound::Excluded(50)))
+            .eq(model.range(6..50)));
+        assert_eq!(
+            (map.first(), map.last()),
+            (model.iter().next(), model.iter().last())
+        );
+
+        let (l, v, r) = map.split(&500);
+        assert_eq!(v, model.get(&500));
+        same_as(&l, &model.range(..500).map(|(a, b)| (*a, *b)).collect());
+        same_as(&r, &model.range(501..).map(|(a, b)| (*a, *b)).collect());
+        let joined = l.join(&r);
+        joined.check();
+        assert_eq!(joined.len(), model.len() - v.is_some() as usize);
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for round in 0..20 {
+            let (a, ma) = random(&mut rng, round * 50, 300);
+            // share part of the tree
+            let (b, mb) = if round % 2 == 0 {
+                random(&mut rng, round * 30, 300)
+            } else {
+                let b = (0..20).fold(a.clone(), |m, k| m.insert(k * 7, -1));
+                let mut mb = ma.clone();
+                (0..20).for_each(|k| {
+                    mb.insert(k * 7, -1);
+                });
+                (b, mb)
+            };
+            let mut union = ma.clone();
+            union.extend(mb.iter());
+            same_as(&a.union(&b), &union);
+            let inter = ma
+                .iter()
+                .filter(|(k, _)| mb.contains_key(k))
+                .map(|(k, v)| (*k, *v));
+            same_as(&a.intersection(&b), &inter.collect());
+            let diff = ma
+                .iter()
+                .filter(|(k, _)| !mb.contains_key(k))
+                .map(|(k, v)| (*k, *v));
+            same_as(&a.difference(&b), &diff.collect());
+        }
+        let (a, _) = random(&mut rng, 100, 100);
+        assert!(a.intersection(&a).ptr_eq(&a) && a.union(&a).ptr_eq(&a));
+        assert!(a.difference(&a).is_empty());
+        assert!(a.difference(&PersistentOrdMap::new()).ptr_eq(&a));
+    }
+
+    #[test]
+    fn joins_unbalanced() {
+        let small: PersistentOrdMap<i32, ()> = (0..3).map(|k| (k, ())).collect();
+        let big: PersistentOrdMap<i32, ()> = (10..10_000).map(|k| (k, ())).collect();
+        for m in [
+            small.join(&big),
+            big.join(&PersistentOrdMap::new()),
+            PersistentOrdMap::new().join(&small),
+        ] {
+            m.check();
+        }
+        let tiny: PersistentOrdMap<i32, ()> = [(20_000, ())].into_iter().collect();
+        let m = big.join(&tiny);
+        m.check();
+        assert_eq!(m.len(), 9991);
+    }
+
+    #[test]
+    #[should_panic]
+    fn join_overlapping() {
+        let a: PersistentOrdMap<i32, ()> = (0..10).map(|k| (k, ())).collect();
+        a.join(&a);
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let a: PersistentOrdMap<i32, i32, AtRc> = (0..100).map(|k| (k, k)).collect();
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let a = a.clone();
+                std::thread::spawn(move || a.remove(&i).values().sum::<i32>())
+            })
+            .collect();
+        for (i, h) in handles.into_iter().enumerate() {
+            assert_eq!(h.join().unwrap(), 4950 - i as i32);
+        }
+    }
+}
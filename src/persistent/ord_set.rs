@@ -0,0 +1,205 @@
+use super::ord_map::{self, PersistentOrdMap};
+use crate::rc::{RcStub, StRc};
+use core::{
+    fmt::{self, Debug, Formatter},
+    iter::FusedIterator,
+    ops::RangeBounds,
+};
+
+/// An immutable ordered set, a [`PersistentOrdMap`] without values.
+///
+/// # Examples
+/// ```
+/// use hash_table::persistent::PersistentOrdSet;
+/// let a: PersistentOrdSet<i32> = (0..10).collect();
+/// let b: PersistentOrdSet<i32> = (5..15).collect();
+/// assert_eq!(a.intersection(&b), (5..10).collect());
+/// assert_eq!(a.difference(&b).len(), 5);
+/// ```
+pub struct PersistentOrdSet<T, R: RcStub = StRc> {
+    map: PersistentOrdMap<T, (), R>,
+}
+
+impl<T, R: RcStub> PersistentOrdSet<T, R> {
+    pub const fn new() -> Self {
+        Self {
+            map: PersistentOrdMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, R> {
+        Iter(self.map.iter())
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.map.first().map(|(k, _)| k)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.map.last().map(|(k, _)| k)
+    }
+
+    /// The `i`th smallest value.
+    pub fn get_index(&self, i: usize) -> Option<&T> {
+        self.map.get_index(i).map(|(k, _)| k)
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.map.ptr_eq(&other.map)
+    }
+}
+
+impl<T: Ord, R: RcStub> PersistentOrdSet<T, R> {
+    pub fn contains(&self, value: &T) -> bool {
+        self.map.contains_key(value)
+    }
+
+    /// The number of values less than `value`.
+    pub fn rank(&self, value: &T) -> usize {
+        self.map.rank(value)
+    }
+
+    pub fn range<B: RangeBounds<T>>(&self, range: B) -> Iter<'_, T, R> {
+        Iter(self.map.range(range))
+    }
+}
+
+impl<T: Ord + Clone, R: RcStub> PersistentOrdSet<T, R> {
+    pub fn insert(&self, value: T) -> Self {
+        Self {
+            map: self.map.insert(value, ()),
+        }
+    }
+
+    pub fn remove(&self, value: &T) -> Self {
+        Self {
+            map: self.map.remove(value),
+        }
+    }
+
+    /// The values less than `value`, whether it was there and the values greater than it.
+    pub fn split(&self, value: &T) -> (Self, bool, Self) {
+        let (l, found, r) = self.map.split(value);
+        (Self { map: l }, found.is_some(), Self { map: r })
+    }
+
+    /// The values of `self` followed by those of `other`.
+    ///
+    /// # Panics
+    /// Panics if the last value of `self` isn't less than the first of `other`.
+    pub fn join(&self, other: &Self) -> Self {
+        Self {
+            map: self.map.join(&other.map),
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            map: self.map.union(&other.map),
+        }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            map: self.map.intersection(&other.map),
+        }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            map: self.map.difference(&other.map),
+        }
+    }
+}
+
+impl<T, R: RcStub> Clone for PersistentOrdSet<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<T, R: RcStub> Default for PersistentOrdSet<T, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug, R: RcStub> Debug for PersistentOrdSet<T, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, R: RcStub> PartialEq for PersistentOrdSet<T, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<T: Eq, R: RcStub> Eq for PersistentOrdSet<T, R> {}
+
+impl<T: Ord + Clone, R: RcStub> FromIterator<T> for PersistentOrdSet<T, R> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().map(|v| (v, ())).collect(),
+        }
+    }
+}
+
+impl<'a, T, R: RcStub> IntoIterator for &'a PersistentOrdSet<T, R> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, R>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T, R: RcStub>(ord_map::Iter<'a, T, (), R>);
+
+impl<'a, T, R: RcStub> Iterator for Iter<'a, T, R> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T, R: RcStub> ExactSizeIterator for Iter<'_, T, R> {}
+impl<T, R: RcStub> FusedIterator for Iter<'_, T, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let a: PersistentOrdSet<i32> = (0..100).step_by(2).collect();
+        let b = a.insert(1).remove(&0);
+        assert!(a.contains(&0) && !b.contains(&0) && b.contains(&1));
+        assert_eq!(b.first(), Some(&1));
+        assert_eq!((b.rank(&10), b.get_index(5)), (5, Some(&10)));
+        let (l, found, r) = b.split(&50);
+        assert!(found);
+        assert_eq!(l.len() + r.len() + 1, b.len());
+        assert_eq!(l.join(&r).insert(50), b);
+        assert!(b.range(90..).copied().eq((90..100).step_by(2)));
+        assert_eq!(a.union(&b).len(), 51);
+        assert_eq!(a.intersection(&b).len(), 49);
+        assert_eq!(a.difference(&b).iter().collect::<Vec<_>>(), [&0]);
+        assert!(l.range(..5).copied().eq([1, 2, 4]));
+    }
+}
@@ -0,0 +1,806 @@
+use crate::rc::{Rc, RcStub, StRc};
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    iter::FusedIterator,
+    ops::Index,
+    slice,
+};
+
+const BITS: usize = 5;
+/// children per branch and values per leaf
+const BRANCH: usize = 1 << BITS;
+/// how many more nodes than the minimum a level may have after a concat
+const EXTRA: usize = 2;
+
+enum Node<T, S: RcStub> {
+    Leaf(Vec<T>),
+    Branch(Branch<T, S>),
+}
+
+struct Branch<T, S: RcStub> {
+    children: Vec<Rc<Node<T, S>, S>>,
+    /// cumulative sizes of the children, `None` if all of them but the last are full so the
+    /// index can be computed from the bits
+    sizes: Option<Vec<usize>>,
+}
+
+use Node::*;
+
+impl<T: Clone, S: RcStub> Clone for Node<T, S> {
+    fn clone(&self) -> Self {
+        match self {
+            Leaf(items) => Leaf(items.clone()),
+            Branch(b) => Branch(Branch {
+                children: b.children.clone(),
+                sizes: b.sizes.clone(),
+            }),
+        }
+    }
+}
+
+// a node at `shift` holds up to `1 << (shift + BITS)` values, leaves are at 0
+
+impl<T, S: RcStub> Node<T, S> {
+    fn len(&self, shift: usize) -> usize {
+        match self {
+            Leaf(items) => items.len(),
+            Branch(b) => match (&b.sizes, b.children.last()) {
+                (Some(sizes), _) => sizes.last().copied().unwrap_or(0),
+                (None, Some(last)) => ((b.children.len() - 1) << shift) + last.len(shift - BITS),
+                (None, None) => 0,
+            },
+        }
+    }
+
+    /// the number of values or children directly in the node
+    fn slots(&self) -> usize {
+        match self {
+            Leaf(items) => items.len(),
+            Branch(b) => b.children.len(),
+        }
+    }
+
+    fn branch(children: Vec<Rc<Self, S>>, shift: usize) -> Self {
+        let full = 1 << shift;
+        let strict = children
+            .iter()
+            .rev()
+            .skip(1)
+            .all(|c| c.len(shift - BITS) == full);
+        Branch(Branch {
+            sizes: (!strict).then(|| cumulative_sizes(&children, shift)),
+            children,
+        })
+    }
+
+    /// a path down to a leaf holding only `value`
+    fn path(shift: usize, value: T) -> Self {
+        if shift == 0 {
+            Leaf(vec![value])
+        } else {
+            Branch(Branch {
+                children: vec![Rc::new(Self::path(shift - BITS, value))],
+                sizes: None,
+            })
+        }
+    }
+
+    fn get(&self, mut shift: usize, mut i: usize) -> &T {
+        let mut node = self;
+        loop {
+            match node {
+                Leaf(items) => return &items[i],
+                Branch(b) => {
+                    let (c, rest) = b.position(shift, i);
+                    (node, i, shift) = (&b.children[c], rest, shift - BITS);
+                }
+            }
+        }
+    }
+}
+
+fn cumulative_sizes<T, S: RcStub>(children: &[Rc<Node<T, S>, S>], shift: usize) -> Vec<usize> {
+    children
+        .iter()
+        .scan(0, |acc, c| {
+            *acc += c.len(shift - BITS);
+            Some(*acc)
+        })
+        .collect()
+}
+
+impl<T, S: RcStub> Branch<T, S> {
+    /// the child holding index `i` and the index inside of it
+    #[inline]
+    fn position(&self, shift: usize, i: usize) -> (usize, usize) {
+        // children are never bigger than in a strict node, so the radix guess is a lower bound
+        let mut c = i >> shift;
+        match &self.sizes {
+            None => (c, i - (c << shift)),
+            Some(sizes) => {
+                while sizes[c] <= i {
+                    c += 1;
+                }
+                (c, if c == 0 { i } else { i - sizes[c - 1] })
+            }
+        }
+    }
+}
+
+impl<T: Clone, S: RcStub> Node<T, S> {
+    /// Appends `value` to the rightmost leaf, gives it back if the subtree is full.
+    fn push(this: &mut Rc<Self, S>, shift: usize, value: T) -> Result<(), T> {
+        match Rc::make_mut(this) {
+            Leaf(items) if items.len() < BRANCH => {
+                items.push(value);
+                Ok(())
+            }
+            Leaf(_) => Err(value),
+            Branch(b) => {
+                let last = b.children.len() - 1;
+                match Self::push(&mut b.children[last], shift - BITS, value) {
+                    Ok(()) => {
+                        if let Some(sizes) = &mut b.sizes {
+                            sizes[last] += 1;
+                        }
+                        Ok(())
+                    }
+                    Err(value) if b.children.len() < BRANCH => {
+                        if b.sizes.is_none() && b.children[last].len(shift - BITS) != 1 << shift {
+                            b.sizes = Some(cumulative_sizes(&b.children, shift));
+                        }
+                        b.children.push(Rc::new(Self::path(shift - BITS, value)));
+                        if let Some(sizes) = &mut b.sizes {
+                            sizes.push(sizes[last] + 1);
+                        }
+                        Ok(())
+                    }
+                    Err(value) => Err(value),
+                }
+            }
+        }
+    }
+
+    /// Removes the last value, the node must not be empty.
+    fn pop(this: &mut Rc<Self, S>) -> T {
+        match Rc::make_mut(this) {
+            Leaf(items) => items.pop().unwrap(),
+            Branch(b) => {
+                let last = b.children.len() - 1;
+                let value = Self::pop(&mut b.children[last]);
+                if b.children[last].slots() == 0 {
+                    b.children.pop();
+                    if let Some(sizes) = &mut b.sizes {
+                        sizes.pop();
+                    }
+                } else if let Some(sizes) = &mut b.sizes {
+                    sizes[last] -= 1;
+                }
+                value
+            }
+        }
+    }
+
+    fn set(this: &mut Rc<Self, S>, mut shift: usize, mut i: usize, value: T) {
+        let mut node = Rc::make_mut(this);
+        loop {
+            match node {
+                Leaf(items) => {
+                    items[i] = value;
+                    return;
+                }
+                Branch(b) => {
+                    let (c, rest) = b.position(shift, i);
+                    (i, shift) = (rest, shift - BITS);
+                    node = Rc::make_mut(&mut b.children[c]);
+                }
+            }
+        }
+    }
+
+    /// The first `n` values, `0 < n <= len`.
+    fn take(this: &Rc<Self, S>, shift: usize, n: usize) -> Rc<Self, S> {
+        if n == this.len(shift) {
+            return this.clone();
+        }
+        Rc::new(match &**this {
+            Leaf(items) => Leaf(items[..n].to_vec()),
+            Branch(b) => {
+                let (c, rest) = b.position(shift, n - 1);
+                let mut children = b.children[..c].to_vec();
+                children.push(Self::take(&b.children[c], shift - BITS, rest + 1));
+                Self::branch(children, shift)
+            }
+        })
+    }
+
+    /// Everything but the first `n` values, `n < len`.
+    fn skip(this: &Rc<Self, S>, shift: usize, n: usize) -> Rc<Self, S> {
+        if n == 0 {
+            return this.clone();
+        }
+        Rc::new(match &**this {
+            Leaf(items) => Leaf(items[n..].to_vec()),
+            Branch(b) => {
+                let (c, rest) = b.position(shift, n);
+                let mut children = vec![Self::skip(&b.children[c], shift - BITS, rest)];
+                children.extend_from_slice(&b.children[c + 1..]);
+                Self::branch(children, shift)
+            }
+        })
+    }
+
+    /// Joins two trees into one or two nodes at the level of the taller one.
+    fn concat(l: &Rc<Self, S>, ls: usize, r: &Rc<Self, S>, rs: usize) -> Vec<Rc<Self, S>> {
+        match (&**l, &**r) {
+            (Branch(lb), _) if ls > rs => {
+                let (last, init) = lb.children.split_last().unwrap();
+                let mid = Self::concat(last, ls - BITS, r, rs);
+                Self::rebalance(init, mid, &[], ls)
+            }
+            (_, Branch(rb)) if ls < rs => {
+                let (first, tail) = rb.children.split_first().unwrap();
+                let mid = Self::concat(l, ls, first, rs - BITS);
+                Self::rebalance(&[], mid, tail, rs)
+            }
+            (Leaf(a), Leaf(b)) => {
+                if a.len() + b.len() <= BRANCH {
+                    vec![Rc::new(Leaf(a.iter().chain(b).cloned().collect()))]
+                } else {
+                    vec![l.clone(), r.clone()]
+                }
+            }
+            (Branch(lb), Branch(rb)) => {
+                let (last, init) = lb.children.split_last().unwrap();
+                let (first, tail) = rb.children.split_first().unwrap();
+                let mid = Self::concat(last, ls - BITS, first, rs - BITS);
+                Self::rebalance(init, mid, tail, ls)
+            }
+            _ => unreachable!("trees of the same height"),
+        }
+    }
+
+    /// Redistributes the children of the merged nodes at `shift` until there are at most
+    /// `EXTRA` more of them than needed, then packs them into one or two nodes.
+    fn rebalance(
+        left: &[Rc<Self, S>],
+        mid: Vec<Rc<Self, S>>,
+        right: &[Rc<Self, S>],
+        shift: usize,
+    ) -> Vec<Rc<Self, S>> {
+        let all: Vec<_> = left
+            .iter()
+            .cloned()
+            .chain(mid)
+            .chain(right.iter().cloned())
+            .collect();
+        let plan = plan(all.iter().map(|n| n.slots()).collect());
+        let children = pour(&all, &plan, shift - BITS);
+        children
+            .chunks(BRANCH)
+            .map(|c| Rc::new(Self::branch(c.to_vec(), shift)))
+            .collect()
+    }
+}
+
+/// New slot counts for a row of nodes, the concatenation plan of the RRB paper: nodes that are
+/// too empty get spread over the ones that follow them.
+fn plan(mut sizes: Vec<usize>) -> Vec<usize> {
+    let optimal = sizes.iter().sum::<usize>().div_ceil(BRANCH);
+    let mut i = 0;
+    while sizes.len() > optimal + EXTRA {
+        while sizes[i] > BRANCH - EXTRA / 2 {
+            i += 1;
+        }
+        let mut rest = sizes[i];
+        while rest > 0 {
+            let n = (rest + sizes[i + 1]).min(BRANCH);
+            rest = rest + sizes[i + 1] - n;
+            sizes[i] = n;
+            i += 1;
+        }
+        // whatever was at `i` has moved into the nodes before it
+        sizes.remove(i);
+        i -= 1;
+    }
+    sizes
+}
+
+/// Refills the slots of `nodes` (at `shift`) in order into nodes of the sizes in `plan`,
+/// keeping the ones that don't change.
+fn pour<T: Clone, S: RcStub>(
+    nodes: &[Rc<Node<T, S>, S>],
+    plan: &[usize],
+    shift: usize,
+) -> Vec<Rc<Node<T, S>, S>> {
+    let (mut src, mut offset) = (0, 0);
+    let mut out = Vec::with_capacity(plan.len());
+    for &want in plan {
+        if offset == 0 && nodes[src].slots() == want {
+            out.push(nodes[src].clone());
+            src += 1;
+            continue;
+        }
+        let (mut items, mut children) = (Vec::new(), Vec::new());
+        let mut got = 0;
+        while got < want {
+            let n = (nodes[src].slots() - offset).min(want - got);
+            match &*nodes[src] {
+                Leaf(v) => items.extend_from_slice(&v[offset..offset + n]),
+                Branch(b) => children.extend_from_slice(&b.children[offset..offset + n]),
+            }
+            got += n;
+            offset += n;
+            if offset == nodes[src].slots() {
+                (src, offset) = (src + 1, 0);
+            }
+        }
+        out.push(Rc::new(if shift == 0 {
+            Leaf(items)
+        } else {
+            Node::branch(children, shift)
+        }));
+    }
+    out
+}
+
+/// An immutable vector, a relaxed radix balanced tree with 32 way nodes.
+///
+/// Updates copy the path to the changed leaf and share the rest, [`concat`](Self::concat) and
+/// [`split_at`](Self::split_at) take O(log n) too. Use a [`TransientVec`] to build or change
+/// a lot at once, it changes the nodes it doesn't share in place.
+///
+/// # Examples
+/// ```
+/// use hash_table::persistent::PersistentVec;
+/// let a: PersistentVec<i32> = (0..100).collect();
+/// let b = a.set(10, -1).push_back(100);
+/// assert_eq!((a[10], b[10], b.len()), (10, -1, 101));
+/// let (l, r) = b.split_at(50);
+/// assert_eq!(l.concat(&r), b);
+/// ```
+pub struct PersistentVec<T, S: RcStub = StRc> {
+    root: Rc<Node<T, S>, S>,
+    shift: usize,
+    len: usize,
+}
+
+impl<T, S: RcStub> PersistentVec<T, S> {
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new() -> Self {
+        Self {
+            root: Rc::new(Leaf(Vec::new())),
+            shift: 0,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<&T> {
+        (i < self.len).then(|| self.root.get(self.shift, i))
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len.wrapping_sub(1))
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        let (stack, leaf) = match &*self.root {
+            Leaf(items) => (Vec::new(), items.iter()),
+            Branch(b) => (vec![b.children.iter()], [].iter()),
+        };
+        Iter {
+            stack,
+            leaf,
+            len: self.len,
+        }
+    }
+
+    /// drops the levels that only have a single child
+    fn shrink(&mut self) {
+        while let Branch(b) = &*self.root {
+            if b.children.len() != 1 {
+                break;
+            }
+            self.root = b.children[0].clone();
+            self.shift -= BITS;
+        }
+    }
+}
+
+impl<T: Clone, S: RcStub> PersistentVec<T, S> {
+    /// # Panics
+    /// Panics if `i` is out of bounds.
+    pub fn set(&self, i: usize, value: T) -> Self {
+        let mut out = self.clone();
+        out.set_mut(i, value);
+        out
+    }
+
+    pub fn push_back(&self, value: T) -> Self {
+        let mut out = self.clone();
+        out.push_back_mut(value);
+        out
+    }
+
+    /// The vector without its last value, and that value.
+    pub fn pop_back(&self) -> Option<(Self, T)> {
+        let mut out = self.clone();
+        let value = out.pop_back_mut()?;
+        Some((out, value))
+    }
+
+    /// The values of `self` followed by those of `other`.
+    pub fn concat(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        }
+        if other.is_empty() {
+            return self.clone();
+        }
+        let shift = self.shift.max(other.shift) + BITS;
+        let nodes = Node::concat(&self.root, self.shift, &other.root, other.shift);
+        let mut out = Self {
+            root: Rc::new(Node::branch(nodes, shift)),
+            shift,
+            len: self.len + other.len,
+        };
+        out.shrink();
+        out
+    }
+
+    /// The first `i` values and the rest.
+    ///
+    /// # Panics
+    /// Panics if `i > len`.
+    pub fn split_at(&self, i: usize) -> (Self, Self) {
+        assert!(i <= self.len, "split index out of bounds");
+        if i == 0 {
+            return (Self::new(), self.clone());
+        }
+        if i == self.len {
+            return (self.clone(), Self::new());
+        }
+        let mut left = Self {
+            root: Node::take(&self.root, self.shift, i),
+            shift: self.shift,
+            len: i,
+        };
+        let mut right = Self {
+            root: Node::skip(&self.root, self.shift, i),
+            shift: self.shift,
+            len: self.len - i,
+        };
+        left.shrink();
+        right.shrink();
+        (left, right)
+    }
+
+    pub fn transient(&self) -> TransientVec<T, S> {
+        TransientVec { vec: self.clone() }
+    }
+
+    fn set_mut(&mut self, i: usize, value: T) {
+        assert!(i < self.len, "index out of bounds");
+        Node::set(&mut self.root, self.shift, i, value)
+    }
+
+    fn push_back_mut(&mut self, value: T) {
+        if let Err(value) = Node::push(&mut self.root, self.shift, value) {
+            // full, grow a level
+            let path = Rc::new(Node::path(self.shift, value));
+            let children = vec![self.root.clone(), path];
+            self.shift += BITS;
+            self.root = Rc::new(Node::branch(children, self.shift));
+        }
+        self.len += 1;
+    }
+
+    fn pop_back_mut(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let value = Node::pop(&mut self.root);
+        self.len -= 1;
+        self.shrink();
+        Some(value)
+    }
+}
+
+impl<T, S: RcStub> Clone for PersistentVec<T, S> {
+    /// Only clones the pointer to the root.
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            shift: self.shift,
+            len: self.len,
+        }
+    }
+}
+
+impl<T, S: RcStub> Default for PersistentVec<T, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, S: RcStub> Index<usize> for PersistentVec<T, S> {
+    type Output = T;
+    fn index(&self, i: usize) -> &T {
+        self.get(i).expect("index out of bounds")
+    }
+}
+
+impl<T: Debug, S: RcStub> Debug for PersistentVec<T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, S: RcStub> PartialEq for PersistentVec<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && (Rc::ptr_eq(&self.root, &other.root) || self.iter().eq(other.iter()))
+    }
+}
+
+impl<T: Eq, S: RcStub> Eq for PersistentVec<T, S> {}
+
+impl<T: Clone, S: RcStub> FromIterator<T> for PersistentVec<T, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut t = TransientVec { vec: Self::new() };
+        iter.into_iter().for_each(|v| t.push_back(v));
+        t.persistent()
+    }
+}
+
+impl<'a, T, S: RcStub> IntoIterator for &'a PersistentVec<T, S> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, S>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A [`PersistentVec`] that is changed in place, the nodes it shares with other versions are
+/// copied the first time they are touched.
+pub struct TransientVec<T, S: RcStub = StRc> {
+    vec: PersistentVec<T, S>,
+}
+
+impl<T: Clone, S: RcStub> TransientVec<T, S> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.vec.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.vec.get(i)
+    }
+
+    /// # Panics
+    /// Panics if `i` is out of bounds.
+    pub fn set(&mut self, i: usize, value: T) {
+        self.vec.set_mut(i, value)
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        self.vec.push_back_mut(value)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.vec.pop_back_mut()
+    }
+
+    /// Freezes it again.
+    pub fn persistent(self) -> PersistentVec<T, S> {
+        self.vec
+    }
+}
+
+impl<T: Clone, S: RcStub> Extend<T> for TransientVec<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|v| self.push_back(v))
+    }
+}
+
+pub struct Iter<'a, T, S: RcStub> {
+    /// the children left on every level above the current leaf
+    stack: Vec<slice::Iter<'a, Rc<Node<T, S>, S>>>,
+    leaf: slice::Iter<'a, T>,
+    len: usize,
+}
+
+impl<'a, T, S: RcStub> Iterator for Iter<'a, T, S> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(v) = self.leaf.next() {
+                self.len -= 1;
+                return Some(v);
+            }
+            loop {
+                match self.stack.last_mut()?.next() {
+                    Some(node) => match &**node {
+                        Leaf(items) => {
+                            self.leaf = items.iter();
+                            break;
+                        }
+                        Branch(b) => self.stack.push(b.children.iter()),
+                    },
+                    None => {
+                        self.stack.pop();
+                    }
+                }
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, S: RcStub> ExactSizeIterator for Iter<'_, T, S> {}
+impl<T, S: RcStub> FusedIterator for Iter<'_, T, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::AtRc;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    impl<T, S: RcStub> PersistentVec<T, S> {
+        /// checks the cached sizes and that strict nodes really are strict
+        fn check(&self) {
+            fn node<T, S: RcStub>(n: &Node<T, S>, shift: usize) -> usize {
+                match n {
+                    Leaf(items) => {
+                        assert_eq!(shift, 0);
+                        assert!(items.len() <= BRANCH);
+                        items.len()
+                    }
+                    Branch(b) => {
+                        assert!(shift > 0 && !b.children.is_empty() && b.children.len() <= BRANCH);
+                        let lens: Vec<_> =
+                            b.children.iter().map(|c| node(c, shift - BITS)).collect();
+                        match &b.sizes {
+                            Some(sizes) => {
+                                let sums: Vec<_> = lens
+                                    .iter()
+                                    .scan(0, |a, l| {
+                                        *a += l;
+                                        Some(*a)
+                                    })
+                                    .collect();
+                                assert_eq!(sizes, &sums);
+                            }
+                            None => assert!(lens.iter().rev().skip(1).all(|&l| l == 1 << shift)),
+                        }
+                        lens.iter().sum()
+                    }
+                }
+            }
+            assert_eq!(node(&self.root, self.shift), self.len);
+            assert_eq!(self.iter().len(), self.len);
+        }
+    }
+
+    #[test]
+    fn push_pop_set() {
+        let mut versions = vec![PersistentVec::<usize>::new()];
+        for i in 0..5000 {
+            versions.push(versions[i].push_back(i));
+        }
+        for (n, v) in versions.iter().enumerate().step_by(97) {
+            v.check();
+            assert!(v.iter().copied().eq(0..n));
+        }
+        let full = versions.pop().unwrap();
+        let changed = (0..5000).step_by(7).fold(full.clone(), |v, i| v.set(i, 0));
+        changed.check();
+        assert!(full.iter().copied().eq(0..5000));
+        assert!(changed
+            .iter()
+            .enumerate()
+            .all(|(i, &v)| v == if i % 7 == 0 { 0 } else { i }));
+
+        let mut v = full;
+        for i in (0..5000).rev() {
+            let (next, x) = v.pop_back().unwrap();
+            assert_eq!(x, i);
+            v = next;
+        }
+        assert!(v.pop_back().is_none());
+        v.check();
+        assert!(versions[1000].iter().copied().eq(0..1000));
+    }
+
+    #[test]
+    fn transient() {
+        let base: PersistentVec<usize> = (0..2000).collect();
+        let mut t = base.transient();
+        for i in 0..2000 {
+            t.set(i, i * 2);
+        }
+        t.extend(2000..3000);
+        assert_eq!(t.pop_back(), Some(2999));
+        let v = t.persistent();
+        v.check();
+        assert_eq!(v.len(), 2999);
+        assert_eq!((v[1999], v[2000]), (3998, 2000));
+        assert!(base.iter().copied().eq(0..2000));
+    }
+
+    #[test]
+    fn concat_and_split() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut model = Vec::new();
+        let mut v = PersistentVec::<usize>::new();
+        for _ in 0..200 {
+            let n = rng.gen_range(0..300);
+            let part: PersistentVec<_> = (model.len()..model.len() + n).collect();
+            model.extend(part.iter().copied());
+            v = if rng.gen() {
+                v.concat(&part)
+            } else {
+                // split somewhere and glue it back together in two steps
+                let at = rng.gen_range(0..=v.len());
+                let (l, r) = v.split_at(at);
+                l.check();
+                r.check();
+                l.concat(&r.concat(&part))
+            };
+            v.check();
+            assert_eq!(v.len(), model.len());
+        }
+        assert!(v.iter().eq(model.iter()));
+        for i in (0..model.len()).step_by(333) {
+            assert_eq!(v[i], model[i]);
+            let (l, r) = v.split_at(i);
+            assert!(l.iter().eq(model[..i].iter()) && r.iter().eq(model[i..].iter()));
+        }
+        // pushing onto a relaxed tree
+        let mut t = v.transient();
+        t.extend(0..1000);
+        for _ in 0..500 {
+            t.pop_back();
+        }
+        let w = t.persistent();
+        w.check();
+        assert_eq!(w.len(), model.len() + 500);
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let v: PersistentVec<usize, AtRc> = (0..1000).collect();
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let v = v.clone();
+                std::thread::spawn(move || v.set(i, 0).iter().sum::<usize>())
+            })
+            .collect();
+        for (i, h) in handles.into_iter().enumerate() {
+            assert_eq!(h.join().unwrap(), 999 * 1000 / 2 - i);
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use crate::prelude::*;
+use crate::rc::{Trace, Tracer};
+use crate::traits::adt::{Creatable, List};
+use crate::traits::collection::{Capacity, Clear, Collection, Contains};
 use alloc::{alloc::Global, string::String, vec::Vec};
 use core::{
     alloc::{AllocError, Allocator, Layout},
     fmt::{self, Debug, Formatter},
     iter::{self, Extend, FromIterator},
     num::NonZeroUsize,
-    ops::{Index, IndexMut},
+    ops::{Index, IndexMut, RangeBounds},
     ptr::{self, drop_in_place, NonNull},
 };
 mod iters;
@@ -88,7 +91,7 @@ where
     /// First, `new_ptr` have a layout of `Layout::array::<T>(capacity)` where `capacity` must be greater than or equal to the capacity than the current capacity a
     /// Second, `new_ptr` cannot be equal to `self.ptr`
     unsafe fn copy_elements_to(&self, new_ptr: NonNull<T>) {
-        debug_assert!(new_ptr != self.ptr);
+        debug_assert!(new_ptr != self.ptr || core::mem::size_of::<T>() == 0);
         // if the current capacity is zero then do nothing
         /*if self.capacity == 0 || self.len == 0 {
             return;
@@ -100,17 +103,24 @@ where
             ptr::copy_nonoverlapping(snd.as_ptr(), new_ptr.as_ptr().add(fst.len()), snd.len());
         }
     }
-    /// This needs to be audited
+    /// Shrinks the allocation down to `size`, or to the length if that's bigger.
     pub fn shrink_to(&mut self, size: usize) -> Result<(), AllocError> {
-        let _ = self.make_contiguous();
         let size = core::cmp::max(size, self.len);
-        unsafe {
-            self.alloc.shrink(
-                self.ptr.cast(),
-                Layout::array::<T>(self.capacity).expect(""),
-                Layout::array::<T>(size).expect(""),
-            )?;
+        if size >= self.capacity {
+            return Ok(());
+        }
+        let _ = self.make_contiguous();
+        let old_layout = Layout::array::<T>(self.capacity).expect("failed to get current layout");
+        if size == 0 {
+            unsafe { self.alloc.deallocate(self.ptr.cast(), old_layout) };
+            self.ptr = NonNull::dangling();
+        } else {
+            let new_layout = Layout::array::<T>(size).expect("failed to create layout");
+            // shrinking can move the allocation, so the old pointer is gone after this
+            let new_ptr = unsafe { self.alloc.shrink(self.ptr.cast(), old_layout, new_layout)? };
+            self.ptr = new_ptr.cast();
         }
+        self.capacity = size;
         Ok(())
     }
 
@@ -122,10 +132,13 @@ where
         debug_assert!(new_capacity.get() >= self.capacity);
         unsafe {
             self.copy_elements_to(new_ptr);
-            self.alloc.deallocate(
-                self.ptr.cast(),
-                Layout::array::<T>(self.capacity).expect("failed to get current layout"),
-            );
+            // nothing was allocated yet, the pointer is just dangling
+            if self.capacity != 0 {
+                self.alloc.deallocate(
+                    self.ptr.cast(),
+                    Layout::array::<T>(self.capacity).expect("failed to get current layout"),
+                );
+            }
         }
         self.start = 0;
         self.capacity = new_capacity.get();
@@ -294,104 +307,38 @@ where
         self.start = 0;
         unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
     }
-    /// Inserts an element at the specified index. 
+    /// Inserts an element at the specified index, shifting everything after it back.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than the length.
     pub fn insert(&mut self, index: usize, value: T) -> Result<(), AllocError> {
-        debug_assert!(index <= self.len + 1, "out of bounds");
-        if self.len + 1 >= self.capacity {
-            self.grow()?;
+        assert!(index <= self.len, "out of bounds");
+        self.push_back(value)?;
+        // walk the new element down to `index`, the wrapping is handled by `ptr_to_mut`
+        for i in (index..self.len - 1).rev() {
+            unsafe { ptr::swap(self.ptr_to_mut(i), self.ptr_to_mut(i + 1)) }
         }
-        let real_index = (self.start + index) % self.capacity;
-        let ptr = self.ptr.as_ptr();
-        // 2 segments
-        // its one big unsafe block since its just copies and ifs
-        unsafe {
-            if self.start + real_index >= self.capacity {
-                // in the second segment (front of buf)
-                if real_index > (self.start + self.len) % self.capacity {
-                    // move the elems at the front forward
-                    ptr::copy(
-                        ptr,
-                        ptr.add(1),
-                        //self.len - index,
-                        (self.start + self.len) % self.capacity, // # elems at front
-                    );
-                    // this might be non overlapping
-                    // move back element to front
-                    ptr::copy_nonoverlapping(ptr.add(self.capacity - 1), ptr, 1);
-                    if real_index + 1 != self.capacity {
-                        ptr::copy(
-                            ptr.add(real_index),
-                            ptr.add(real_index + 1),
-                            self.capacity.saturating_sub(real_index).saturating_sub(1),
-                        );
-                    }
-                }
-            // one segment
-            } else {
-                ptr::copy(
-                    ptr.add(real_index),
-                    ptr.add(real_index + 1),
-                    self.len - index,
-                );
-            }
-            ptr::write(ptr.add(real_index), value);
-        }
-
-        self.len += 1;
-
         Ok(())
     }
-    /// Removes the element at the specified element. 
+    /// Removes the element at the specified element.
     /// Returns None if no element was found at the given index.
     pub fn remove(&mut self, index: usize) -> Option<T> {
-        if index > self.len {
+        if index >= self.len {
             return None;
         }
-        let elem = unsafe { ptr::read(self.ptr_to(index)) };
-        let real_index = (self.start + index) % self.capacity;
-        let ptr = self.ptr.as_ptr();
-
-        // this is only here so that the debugger can see the slice.
-        //#[cfg(test)]
-        //let a = unsafe { core::slice::from_raw_parts(ptr, self.capacity) };
-
-        // move all the other elements
-        // if there are 2 segments
-        unsafe {
-            if self.start + self.len > self.capacity {
-                // 2 segments
-                // its one big unsafe block since its just copies and ifs
-
-                // in the first segment (back of buf)
-                if self.start <= real_index {
-                    ptr::copy(
-                        ptr.add(self.start),
-                        ptr.add(self.start + 1),
-                        real_index.saturating_sub(self.start),
-                    );
-                    self.start += 1;
-                    // move elems after the real index to the real index
-
-                    // in the second segment
-                } else {
-                    ptr::copy(
-                        ptr.add(real_index + 1),
-                        ptr.add(real_index),
-                        self.len - index,
-                    );
-                }
-            } else {
-                ptr::copy(
-                    ptr.add(real_index + 1),
-                    ptr.add(real_index),
-                    self.len.saturating_sub(index).saturating_sub(1),
-                );
+        // in the first of two segments the elements before it move up, otherwise the ones
+        // after it move down, then it's at an end and can be popped off
+        if self.start + self.len > self.capacity && self.start + index < self.capacity {
+            for i in (0..index).rev() {
+                unsafe { ptr::swap(self.ptr_to_mut(i), self.ptr_to_mut(i + 1)) }
+            }
+            self.pop_front()
+        } else {
+            for i in index..self.len - 1 {
+                unsafe { ptr::swap(self.ptr_to_mut(i), self.ptr_to_mut(i + 1)) }
             }
+            self.pop_back()
         }
-
-        self.len -= 1;
-
-        Some(elem)
     }
     /// Shrinks the allocation to exactly fit the elements in the queue
     pub fn shrink_to_fit(&mut self) -> Result<(), AllocError> {
@@ -399,9 +346,8 @@ where
     }
     /// truncates all after size.
     pub fn truncate(&mut self, size: usize) {
-        if size < self.len {
-            let num_to_drop = size - self.len;
-            self.drain().rev().take(num_to_drop).for_each(|f| drop(f));
+        while self.len > size {
+            drop(self.pop_back());
         }
     }
     /// grows the queues allocation to be able to hold more elements
@@ -453,12 +399,12 @@ where
 
     /// Gets a reference to the last element in the queue
     pub fn back(&self) -> Option<&T> {
-        self.get(self.len() - 1)
+        self.get(self.len.checked_sub(1)?)
     }
 
     /// Gets a mutable reference to the last element in the queue
     pub fn back_mut(&mut self) -> Option<&mut T> {
-        self.get_mut(self.len() - 1)
+        self.get_mut(self.len.checked_sub(1)?)
     }
 
     /// Returns the length of the list
@@ -508,6 +454,12 @@ impl<T, A: Allocator> Iterable for ArrayQueue<T, A> {
     }
 }
 
+unsafe impl<T: Trace, A: Allocator> Trace for ArrayQueue<T, A> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.iter().for_each(|v| v.trace(tracer))
+    }
+}
+
 impl<T, A: Allocator> IterableMut for ArrayQueue<T, A> {
     type IterMut<'a> = IterMut<'a, T, A> where T: 'a, A: 'a;
     type Item = T;
@@ -534,6 +486,35 @@ impl<T, A: Allocator> Drainable for ArrayQueue<T, A> {
     }
 }
 
+impl<T, A: Allocator> DrainableBy for ArrayQueue<T, A> {
+    type Item = T;
+    type DrainBy<'a, F> = RotateDrainBy<'a, Self, T, F> where Self: 'a, F: FnMut(&T) -> bool + 'a;
+    fn drain_by<'a, F>(&'a mut self, pred: F) -> Self::DrainBy<'a, F>
+    where
+        F: FnMut(&T) -> bool + 'a,
+    {
+        RotateDrainBy::new(self, pred)
+    }
+}
+
+impl<T, A: Allocator> DrainableRange for ArrayQueue<T, A> {
+    type Item = T;
+    type DrainRange<'a> = RotateDrainBy<'a, Self, T, IndexFilter> where Self: 'a;
+    fn drain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::DrainRange<'_> {
+        let filter = IndexFilter::inside(range, self.len);
+        RotateDrainBy::new(self, filter)
+    }
+}
+
+impl<T, A: Allocator> RetainRange for ArrayQueue<T, A> {
+    type Item = T;
+    type Retain<'a> = RotateDrainBy<'a, Self, T, IndexFilter> where Self: 'a;
+    fn retain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::Retain<'_> {
+        let filter = IndexFilter::outside(range, self.len);
+        RotateDrainBy::new(self, filter)
+    }
+}
+
 impl<T, A: Allocator + Clone> IntoIterator for ArrayQueue<T, A> {
     type IntoIter = IntoIter<T, A>;
     type Item = T;
@@ -542,8 +523,61 @@ impl<T, A: Allocator + Clone> IntoIterator for ArrayQueue<T, A> {
     }
 }
 
+impl<T, A: Allocator> Collection for ArrayQueue<T, A> {
+    type Item = T;
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<T, A: Allocator> Clear for ArrayQueue<T, A> {
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<T: PartialEq, A: Allocator> Contains for ArrayQueue<T, A> {
+    fn contains(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+}
+
+impl<T, A: Allocator> Capacity for ArrayQueue<T, A> {
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<T> Creatable for ArrayQueue<T, Global> {
+    fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, A: Allocator> List<T> for ArrayQueue<T, A> {
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn try_append(&mut self, item: T) -> Result<(), AllocError> {
+        self.push_back(item)
+    }
+    fn try_prepend(&mut self, item: T) -> Result<(), AllocError> {
+        self.push_front(item)
+    }
+    fn head(&self) -> Option<&T> {
+        self.front()
+    }
+    fn tail(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+    fn get(&self, index: usize) -> Option<&T> {
+        self.get(index)
+    }
+}
+
 impl<T, A: Allocator> Drop for ArrayQueue<T, A> {
     fn drop(&mut self) {
+        self.clear();
         if self.capacity != 0 {
             // this should never happen since the layout needs to be created in `grow` before it can be recreated here.
             unsafe {
@@ -602,7 +636,7 @@ impl<T: PartialEq + Eq, A: Allocator> Eq for ArrayQueue<T, A> {}
 
 impl<T: PartialEq, A: Allocator> PartialEq for ArrayQueue<T, A> {
     fn eq(&self, other: &Self) -> bool {
-        self.len != other.len && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+        self.len == other.len && self.iter().zip(other.iter()).all(|(a, b)| a == b)
     }
 }
 
@@ -679,6 +713,8 @@ mod bench {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fault::{Fault, FaultyAlloc};
+    use alloc::boxed::Box;
     use core::ops::Range;
     #[test]
     fn queue_new() {
@@ -1168,6 +1204,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn failed_grow_keeps_elements() {
+        let alloc = FaultyAlloc::new();
+        let boxed = |i| Box::new_in(i, alloc.clone());
+        let mut queue = ArrayQueue::with_capacity_in(4, alloc.clone()).unwrap();
+        // wrapped around so the grow has two segments to copy
+        queue.push_back(boxed(2)).unwrap();
+        queue.push_back(boxed(3)).unwrap();
+        queue.push_front(boxed(1)).unwrap();
+        queue.push_front(boxed(0)).unwrap();
+
+        let item = boxed(4);
+        alloc.set_fault(Fault::Nth(0));
+        assert!(queue.push_back(item).is_err());
+        assert_eq!(queue.capacity(), 4);
+        assert!(queue.iter().map(|b| **b).eq(0..4));
+
+        alloc.set_fault(Fault::Never);
+        queue.push_back(boxed(4)).unwrap();
+        assert!(queue.iter().map(|b| **b).eq(0..5));
+        drop(queue);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn random_faults_dont_leak() {
+        let alloc = FaultyAlloc::new();
+        let mut queue = ArrayQueue::new_in(alloc.clone());
+        let mut model = std::collections::VecDeque::new();
+        alloc.set_fault(Fault::Random {
+            seed: 50,
+            chance: 0.2,
+        });
+        for i in 0..2000u32 {
+            let Ok(item) = Box::try_new_in(i, alloc.clone()) else {
+                continue;
+            };
+            match i % 5 {
+                0 | 1 => {
+                    if queue.push_back(item).is_ok() {
+                        model.push_back(i);
+                    }
+                }
+                2 => {
+                    if queue.push_front(item).is_ok() {
+                        model.push_front(i);
+                    }
+                }
+                3 => assert_eq!(queue.pop_front().map(|b| *b), model.pop_front()),
+                _ => {
+                    let _ = queue.shrink_to(queue.len() + 2);
+                    assert_eq!(queue.pop_back().map(|b| *b), model.pop_back());
+                }
+            }
+            assert!(queue.iter().map(|b| **b).eq(model.iter().copied()));
+        }
+        assert!(alloc.failures() > 0);
+        drop(queue);
+        assert_eq!(alloc.live(), 0);
+    }
+
     fn queue_starting_at(capacity: usize, start: usize, range: Range<usize>) -> ArrayQueue<usize> {
         let mut queue: ArrayQueue<usize> = ArrayQueue::with_capacity(capacity);
         queue.extend(iter::repeat(0).take(start));
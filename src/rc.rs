@@ -1,461 +1,717 @@
 //! A generic reference counter over atomic and non atomic reference counting
+//!
+//! [`Rc`] is a single pointer to an allocation holding the counts, the allocator and the value.
+//! What the counts look like is picked by the [`RcStub`] flavor:
+//! - [`StRc`]: non atomic strong and weak counts, like `alloc::rc::Rc`
+//! - [`AtRc`]: atomic strong and weak counts, like `alloc::sync::Arc`
+//! - [`StRcNoWeak`], [`AtRcNoWeak`]: the same without weak references, one word smaller
+//! - [`BiRc`]: [biased](BiasedCounts) counts, non atomic on the thread that created the value
+//! - [`CcRc`]: single threaded counts with a [cycle collector](collect_cycles)
+//!
+//! [`AtomicRc`] is a cell holding an `Rc` that threads can read and replace concurrently.
 
-use core::pin::Pin;
-use core::ops::Deref;
-//use core::ops::CoerceUnsized;
-
-
-/*
-    shared:
-    --AsRef<T>
-    --Borrow<T>
-    --Clone
-    --CoerceUnsized<Arc<U>>
-    --Debug
-    --Default
-    --Deref
-    --DispatchFromDyn<Arc<U>>
-    --Display
-    --Drop
-    --Eq
-    --From<&[T]>
-    --From<&CStr>
-    --From<&str>
-    --From<Box<T, Global>>
-    --From<CString>
-    --From<String>
-    --From<T>
-    --From<Vec<T, Global>>
-    --FromIterator<T>
-    --Hash
-    --Ord
-    --PartialEq<Arc<T>>
-    --PartialOrd<Arc<T>>
-    --Pointer
-    --TryFrom<Arc<[T]>>
-    --Unpin
-    --UnwindSafe
-    
-    arc has:
-    Error
-    From<Arc<W>>
-    From<Arc<W>>
-    From<Arc<str>>
-    From<Cow<'a, B>>
-    Send
-    Sync
-    
-    rc has:
-    From<Rc<str>>
-    RefUnwindSafe
-    !Send
-    !Sync
-*/
+use alloc::alloc::Global;
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops::Deref,
+    pin::Pin,
+    ptr::{self, NonNull, Pointee},
+};
+
+mod atomic;
+mod biased;
+pub mod counts;
+mod cycle;
+mod traits;
+mod unique;
+
+pub use atomic::{AtomicRc, LoadGuard};
+pub use biased::BiasedCounts;
+use counts::*;
+pub use cycle::{collect_cycles, CycleCounts, Trace, Tracer};
+pub use unique::UniqueRc;
+
+#[repr(C)]
+struct RcInner<C, A, T: ?Sized> {
+    counts: C,
+    alloc: A,
+    value: T,
+}
+
+/// Picks the counts an [`Rc`] uses.
+pub trait RcStub {
+    type Counts: Counts;
+}
+
+/// Single threaded strong and weak counts.
+pub enum StRc {}
+/// Atomic strong and weak counts.
+pub enum AtRc {}
+/// A single threaded strong count without weak references.
+pub enum StRcNoWeak {}
+/// An atomic strong count without weak references.
+pub enum AtRcNoWeak {}
+/// Biased strong counts without weak references: clones and drops on the thread that created the
+/// value don't need atomics.
+///
+/// Other threads that drop the count below what they added queue the allocation for the owning
+/// thread, which settles the queue whenever it drops a `BiRc` or calls
+/// [`merge_queued`](BiRc::merge_queued). Allocations still queued when their owner exits are
+/// leaked.
+pub enum BiRc {}
+/// Single threaded strong counts whose garbage cycles are reclaimed by [`collect_cycles`], for
+/// values created with [`Rc::new_traced`].
+pub enum CcRc {}
+
+impl RcStub for StRc {
+    type Counts = LocalCounts;
+}
+impl RcStub for AtRc {
+    type Counts = AtomicCounts;
+}
+impl RcStub for StRcNoWeak {
+    type Counts = LocalStrongCount;
+}
+impl RcStub for AtRcNoWeak {
+    type Counts = AtomicStrongCount;
+}
+impl RcStub for BiRc {
+    type Counts = BiasedCounts;
+}
+impl RcStub for CcRc {
+    type Counts = CycleCounts;
+}
+
+impl BiRc {
+    /// Settles the references other threads released on values created by this thread, and frees
+    /// the ones that aren't referenced anymore.
+    pub fn merge_queued() {
+        biased::merge_queued()
+    }
+}
+
+/// the layout of the allocation for a value with layout `value`, and the offset of the value
+fn inner_layout<C, A>(value: Layout) -> (Layout, usize) {
+    let header = Layout::new::<C>()
+        .extend(Layout::new::<A>())
+        .expect("capacity overflow")
+        .0;
+    let (layout, offset) = header.extend(value).expect("capacity overflow");
+    (layout.pad_to_align(), offset)
+}
+
+/// Allocates room for a value with layout `value` and writes the header, the value is left
+/// uninitialized.
+fn allocate<T: ?Sized, C: Counts, A: Allocator>(
+    value: Layout,
+    meta: <T as Pointee>::Metadata,
+    alloc: A,
+    counts: impl FnOnce(Destroy) -> C,
+) -> Result<NonNull<RcInner<C, A, T>>, AllocError> {
+    let (layout, _) = inner_layout::<C, A>(value);
+    let mem = alloc.allocate(layout)?;
+    Ok(unsafe { write_header(mem.cast(), meta, alloc, counts) })
+}
+
+/// Writes the header into `mem`, which came from `alloc` with the layout from [`inner_layout`].
+unsafe fn write_header<T: ?Sized, C: Counts, A: Allocator>(
+    mem: NonNull<u8>,
+    meta: <T as Pointee>::Metadata,
+    alloc: A,
+    counts: impl FnOnce(Destroy) -> C,
+) -> NonNull<RcInner<C, A, T>> {
+    let inner =
+        ptr::from_raw_parts_mut::<T>(mem.as_ptr() as *mut (), meta) as *mut RcInner<C, A, T>;
+    let destroy = Destroy::new::<T, C, A>(meta);
+    ptr::addr_of_mut!((*inner).counts).write(counts(destroy));
+    ptr::addr_of_mut!((*inner).alloc).write(alloc);
+    NonNull::new_unchecked(inner)
+}
+
+/// the pointer to the allocation from a pointer to its value
+unsafe fn inner_from_value<T: ?Sized, C, A>(value: *const T) -> NonNull<RcInner<C, A, T>> {
+    let (_, offset) = inner_layout::<C, A>(Layout::for_value_raw(value));
+    let data = (value as *const u8).sub(offset) as *mut ();
+    NonNull::new_unchecked(ptr::from_raw_parts_mut::<T>(data, ptr::metadata(value)) as *mut _)
+}
+
+/// the pointer to the allocation from the erased parts in `Destroy`
+unsafe fn erased_inner<T: ?Sized, C, A>(data: *mut (), meta: usize) -> NonNull<RcInner<C, A, T>> {
+    let inner = ptr::from_raw_parts_mut::<T>(data, unerase_meta(meta)) as *mut RcInner<C, A, T>;
+    NonNull::new_unchecked(inner)
+}
+
+unsafe fn erased_drop_slow<T: ?Sized, C: Counts, A: Allocator>(data: *mut (), meta: usize) {
+    drop_slow(erased_inner::<T, C, A>(data, meta))
+}
+
+unsafe fn erased_drop_value<T: ?Sized, C, A>(data: *mut (), meta: usize) {
+    let inner = erased_inner::<T, C, A>(data, meta);
+    ptr::drop_in_place(ptr::addr_of_mut!((*inner.as_ptr()).value));
+}
+
+unsafe fn erased_dealloc<T: ?Sized, C, A: Allocator>(data: *mut (), meta: usize) {
+    dealloc(erased_inner::<T, C, A>(data, meta))
+}
+
+/// drops the value after the last strong reference is gone
+unsafe fn drop_slow<T: ?Sized, C: Counts, A: Allocator>(inner: NonNull<RcInner<C, A, T>>) {
+    ptr::drop_in_place(ptr::addr_of_mut!((*inner.as_ptr()).value));
+    if (*inner.as_ptr()).counts.release_weak() {
+        dealloc(inner)
+    }
+}
+
+/// frees the allocation, the value has to be dropped or moved out already
+unsafe fn dealloc<T: ?Sized, C, A: Allocator>(inner: NonNull<RcInner<C, A, T>>) {
+    let layout = Layout::for_value_raw(inner.as_ptr());
+    let alloc = ptr::read(ptr::addr_of!((*inner.as_ptr()).alloc));
+    ptr::drop_in_place(ptr::addr_of_mut!((*inner.as_ptr()).counts));
+    alloc.deallocate(inner.cast(), layout);
+}
+
+/// A reference counted pointer, the counting scheme is picked by `S`.
 #[repr(transparent)]
-pub struct Rc<T: ?Sized, S: RcStub = StRc>(<S as RcStub>::Counter<T>);
+pub struct Rc<T: ?Sized, S: RcStub = StRc, A: Allocator = Global> {
+    ptr: NonNull<RcInner<S::Counts, A, T>>,
+    marker: PhantomData<RcInner<S::Counts, A, T>>,
+}
+
+unsafe impl<T: ?Sized + Send + Sync, S: RcStub, A: Allocator + Send + Sync> Send for Rc<T, S, A> where
+    S::Counts: Send + Sync
+{
+}
+unsafe impl<T: ?Sized + Send + Sync, S: RcStub, A: Allocator + Send + Sync> Sync for Rc<T, S, A> where
+    S::Counts: Send + Sync
+{
+}
 
-impl<T: ?Sized, S: RcStub> Rc<T, S> {
+impl<T: ?Sized, S: RcStub, A: Allocator> Rc<T, S, A> {
     #[inline(always)]
-    pub fn as_ptr(this: &Self) -> *const T {
-        S::Counter::as_ptr(&this.0)
+    unsafe fn from_inner(ptr: NonNull<RcInner<S::Counts, A, T>>) -> Self {
+        Self {
+            ptr,
+            marker: PhantomData,
+        }
+    }
+
+    /// Takes over a strong reference to an allocation whose value is now seen as a `T`.
+    unsafe fn retype(ptr: NonNull<RcInner<S::Counts, A, T>>) -> Self {
+        let meta = ptr::metadata(ptr.as_ptr() as *const T);
+        (*ptr.as_ptr())
+            .counts
+            .retype(Destroy::new::<T, S::Counts, A>(meta));
+        Self::from_inner(ptr)
     }
 
     #[inline(always)]
-    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
-        S::Counter::get_mut(&mut this.0)
+    fn counts(&self) -> &S::Counts {
+        unsafe { &(*self.ptr.as_ptr()).counts }
     }
 
     #[inline(always)]
-    pub fn into_raw(this: Self) -> *const T {
-        S::Counter::into_raw(this.0)
+    pub fn as_ptr(this: &Self) -> *const T {
+        unsafe { ptr::addr_of!((*this.ptr.as_ptr()).value) }
     }
 
     #[inline(always)]
-    pub unsafe fn from_raw(ptr: *const T) -> Self {
-        Self(S::Counter::from_raw(ptr))
+    pub fn allocator(this: &Self) -> &A {
+        unsafe { &(*this.ptr.as_ptr()).alloc }
+    }
+
+    /// Returns a mutable reference if there are no other `Rc` or weak pointers to the value.
+    #[inline(always)]
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if this.counts().is_unique() {
+            Some(unsafe { &mut this.ptr.as_mut().value })
+        } else {
+            None
+        }
     }
 
     #[inline(always)]
-    pub fn downgrade(this: &Self) -> WeakStub<T,S> {
-        WeakStub(S::Counter::downgrade(&this.0))
+    pub fn into_raw(this: Self) -> *const T {
+        let ptr = Self::as_ptr(&this);
+        mem::forget(this);
+        ptr
     }
 
+    /// # Safety
+    /// `ptr` has to come from [`into_raw`](Self::into_raw) of an `Rc` with the same `S` and `A`.
     #[inline(always)]
-    pub fn weak_count(this: &Self) -> usize {
-        S::Counter::weak_count(&this.0)
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        Self::from_inner(inner_from_value(ptr))
     }
 
     #[inline(always)]
     pub fn strong_count(this: &Self) -> usize {
-        S::Counter::strong_count(&this.0)
+        this.counts().strong()
     }
 
+    /// # Safety
+    /// `ptr` has to come from [`into_raw`](Self::into_raw) of an `Rc` with the same `S` and `A`
+    /// that is still alive.
     #[inline(always)]
     pub unsafe fn increment_strong_count(ptr: *const T) {
-        S::Counter::increment_strong_count(ptr)
+        let this = mem::ManuallyDrop::new(Self::from_raw(ptr));
+        this.counts().inc_strong();
     }
 
+    /// # Safety
+    /// `ptr` has to come from [`into_raw`](Self::into_raw) of an `Rc` with the same `S` and `A`
+    /// and gives up that reference.
     #[inline(always)]
     pub unsafe fn decrement_strong_count(ptr: *const T) {
-        S::Counter::decrement_strong_count(ptr)
+        drop(Self::from_raw(ptr))
     }
 
     #[inline(always)]
     pub fn ptr_eq(this: &Self, other: &Self) -> bool {
-        S::Counter::ptr_eq(&this.0, &other.0)
+        ptr::eq(
+            this.ptr.as_ptr() as *const (),
+            other.ptr.as_ptr() as *const (),
+        )
     }
 }
 
-impl<T, S: RcStub> Rc<T, S>
+impl<T: ?Sized, S: RcStub, A: Allocator> Rc<T, S, A>
 where
-    S::Counter<T>: RctSizedT<T> 
+    S::Counts: WeakCounts,
 {
-    pub fn new(item: T) -> Self {
-        Self(S::Counter::new(item))
+    #[inline(always)]
+    pub fn downgrade(this: &Self) -> WeakStub<T, S, A> {
+        this.counts().inc_weak();
+        WeakStub { ptr: this.ptr }
     }
 
-    pub fn new_cyclic<F>(data_fn: F) -> Self
-    where
-        F: FnOnce(&<S::Counter<T> as Rct<T>>::Weak) -> T,
-    {
-        Self(S::Counter::new_cyclic(data_fn))
+    #[inline(always)]
+    pub fn weak_count(this: &Self) -> usize {
+        this.counts().weak()
     }
-    pub fn try_unwrap(this: Self) -> Result<T, Self> {
-        S::Counter::try_unwrap(this.0).map_err(|e| Self(e))
+}
+
+impl<T, S: RcStub> Rc<T, S> {
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new(item: T) -> Self {
+        Self::new_in(item, Global).expect("failed to allocate")
+    }
+
+    pub fn try_new(item: T) -> Result<Self, AllocError> {
+        Self::new_in(item, Global)
     }
+
     pub fn pin(data: T) -> Pin<Self> {
-        unsafe {Pin::new_unchecked(Self::new(data))}
+        unsafe { Pin::new_unchecked(Self::new(data)) }
     }
-}
 
-impl<T: Clone, S: RcStub> Rc<T, S>
-where
-    S::Counter<T>: RctCloneableT<T>,
-{
-    pub fn make_mut(this: &mut Self) -> &mut T {
-        S::Counter::make_mut(&mut this.0)
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new_uninit() -> Rc<MaybeUninit<T>, S> {
+        Rc::new_uninit_in(Global).expect("failed to allocate")
     }
-}
 
-impl<T: ?Sized, S: RcStub> Deref for Rc<T, S> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        S::Counter::deref(&self.0)
+    /// Like [`new_uninit`](Rc::new_uninit), with the value's memory set to zero.
+    ///
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new_zeroed() -> Rc<MaybeUninit<T>, S> {
+        Rc::new_zeroed_in(Global).expect("failed to allocate")
+    }
+
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new_uninit_slice(len: usize) -> Rc<[MaybeUninit<T>], S> {
+        Rc::new_uninit_slice_in(len, Global).expect("failed to allocate")
+    }
+
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new_zeroed_slice(len: usize) -> Rc<[MaybeUninit<T>], S> {
+        Rc::new_zeroed_slice_in(len, Global).expect("failed to allocate")
     }
 }
 
-impl<T: ?Sized, S: RcStub> Clone for Rc<T, S> {
-    fn clone(&self) -> Self {
-        Self(self.0.clone())
+impl<T, S: RcStub> Rc<T, S>
+where
+    S::Counts: WeakCounts,
+{
+    /// Creates a value that can hold weak pointers to itself. Upgrading the weak pointer inside
+    /// `data_fn` fails.
+    ///
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new_cyclic<F>(data_fn: F) -> Self
+    where
+        F: FnOnce(&WeakStub<T, S>) -> T,
+    {
+        Self::new_cyclic_in(data_fn, Global).expect("failed to allocate")
     }
 }
 
-/*impl<T, S:RcStub> RctSizedT<T> for RcStubb<T,S> {
-    
-}*/
-#[repr(transparent)]
-pub struct WeakStub<T: ?Sized, S: RcStub = StRc>(<S as RcStub>::Weak<T>);
+impl<T, S: RcStub, A: Allocator> Rc<T, S, A> {
+    pub fn new_in(item: T, alloc: A) -> Result<Self, AllocError> {
+        let ptr = allocate::<T, _, A>(Layout::new::<T>(), (), alloc, S::Counts::new)?;
+        unsafe {
+            ptr::addr_of_mut!((*ptr.as_ptr()).value).write(item);
+            Ok(Self::from_inner(ptr))
+        }
+    }
 
-use traits::*;
-pub use arc_impl::AtRc;
-pub use rc_impl::StRc;
+    pub fn pin_in(data: T, alloc: A) -> Result<Pin<Self>, AllocError> {
+        Ok(unsafe { Pin::new_unchecked(Self::new_in(data, alloc)?) })
+    }
 
-mod traits {
-    use core::pin::Pin;
-    use core::ops::Deref;
-    pub trait RcStub {
-        type Counter<T: ?Sized>: Rct<T, Weak = Self::Weak<T>>;
-        type Weak<T: ?Sized>: Weakt<T, Parent = Self::Counter<T>>;
+    pub fn new_uninit_in(alloc: A) -> Result<Rc<MaybeUninit<T>, S, A>, AllocError> {
+        let ptr = allocate(Layout::new::<T>(), (), alloc, S::Counts::new)?;
+        Ok(unsafe { Rc::from_inner(ptr) })
     }
 
-    pub trait Rct<T: ?Sized>: Clone + AsRef<T> + Deref<Target = T>/*+ Borrow<T>*/ {
-        type Weak: Weakt<T, Parent = Self>;
-        fn as_ptr(this: &Self) -> *const T;
-        fn get_mut(this: &mut Self) -> Option<&mut T>;
-        fn into_raw(this: Self) -> *const T;
-        unsafe fn from_raw(ptr: *const T) -> Self;
-        fn downgrade(this: &Self) -> Self::Weak;
-        fn weak_count(this: &Self) -> usize;
-        fn strong_count(this: &Self) -> usize;
-        unsafe fn increment_strong_count(ptr: *const T);
-        unsafe fn decrement_strong_count(ptr: *const T);
-        fn ptr_eq(this: &Self, other: &Self) -> bool;
+    pub fn new_zeroed_in(alloc: A) -> Result<Rc<MaybeUninit<T>, S, A>, AllocError> {
+        let mut rc = Self::new_uninit_in(alloc)?;
+        unsafe { rc.ptr.as_mut().value.as_mut_ptr().write_bytes(0, 1) };
+        Ok(rc)
     }
 
-    pub trait RctSizedT<T>: Rct<T> {
-        fn new(item: T) -> Self;
-        fn try_unwrap(this: Self) -> Result<T, Self>;
-        fn pin(data: T) -> Pin<Self>;
-        fn new_cyclic<F>(data_fn: F) -> Self
-        where
-            F: FnOnce(&Self::Weak) -> T;
+    pub fn new_uninit_slice_in(
+        len: usize,
+        alloc: A,
+    ) -> Result<Rc<[MaybeUninit<T>], S, A>, AllocError> {
+        let value = Layout::array::<T>(len).map_err(|_| AllocError)?;
+        let ptr = allocate(value, len, alloc, S::Counts::new)?;
+        Ok(unsafe { Rc::from_inner(ptr) })
     }
 
-    pub trait RctCloneableT<T: Clone>: Rct<T> {
-        fn make_mut(this: &mut Self) -> &mut T;
+    pub fn new_zeroed_slice_in(
+        len: usize,
+        alloc: A,
+    ) -> Result<Rc<[MaybeUninit<T>], S, A>, AllocError> {
+        let mut rc = Self::new_uninit_slice_in(len, alloc)?;
+        unsafe { (rc.ptr.as_mut().value.as_mut_ptr() as *mut T).write_bytes(0, len) };
+        Ok(rc)
     }
 
-    pub trait Weakt<T: ?Sized> {
-        type Parent: Rct<T, Weak = Self>;
-        fn upgrade(&self) -> Option<Self::Parent>;
-        fn as_ptr(&self) -> *const T;
-        fn into_raw(self) -> *const T;
-        unsafe fn from_raw(ptr: *const T) -> Self;
-        fn strong_count(&self) -> usize;
-        fn weak_count(&self) -> usize;
-        fn ptr_eq(&self, other: &Self) -> bool;
+    /// Returns the value if this is the only strong reference.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if !this.counts().try_take() {
+            return Err(this);
+        }
+        let this = mem::ManuallyDrop::new(this);
+        unsafe {
+            let value = ptr::read(ptr::addr_of!((*this.ptr.as_ptr()).value));
+            if this.counts().release_weak() {
+                dealloc(this.ptr);
+            }
+            Ok(value)
+        }
     }
 
-    pub trait WeaktSizedT<T>: Weakt<T> {
-        fn new() -> Self;
+    /// Returns the value if this was the last strong reference, otherwise just drops it.
+    ///
+    /// Unlike `Rc::try_unwrap(this).ok()` this never loses the value when the last two references
+    /// race to call it from different threads.
+    pub fn into_inner(this: Self) -> Option<T> {
+        let this = mem::ManuallyDrop::new(this);
+        let value = if this.counts().dec_strong() {
+            unsafe {
+                let value = ptr::read(ptr::addr_of!((*this.ptr.as_ptr()).value));
+                if this.counts().release_weak() {
+                    dealloc(this.ptr);
+                }
+                Some(value)
+            }
+        } else {
+            None
+        };
+        S::Counts::poll();
+        value
     }
 }
 
+impl<T: Clone, S: RcStub, A: Allocator> Rc<T, S, A> {
+    /// Moves the value out if this is the only strong reference, otherwise clones it.
+    pub fn unwrap_or_clone(this: Self) -> T {
+        Self::try_unwrap(this).unwrap_or_else(|this| (*this).clone())
+    }
+}
 
-mod rc_impl {
-    use super::*;
-    use alloc::rc::{Rc, Weak};
-
-    pub enum StRc {}
+impl<T, S: RcStub, A: Allocator> Rc<MaybeUninit<T>, S, A> {
+    /// # Safety
+    /// The value has to be initialized.
+    pub unsafe fn assume_init(self) -> Rc<T, S, A> {
+        let this = mem::ManuallyDrop::new(self);
+        Rc::retype(this.ptr.cast())
+    }
+}
 
-    impl RcStub for StRc {
-        type Counter<T: ?Sized> = Rc<T>;
-        type Weak<T: ?Sized> = Weak<T>;
+impl<T, S: RcStub, A: Allocator> Rc<[MaybeUninit<T>], S, A> {
+    /// # Safety
+    /// Every item has to be initialized.
+    pub unsafe fn assume_init(self) -> Rc<[T], S, A> {
+        let this = mem::ManuallyDrop::new(self);
+        let len = this.len();
+        let data = this.ptr.as_ptr() as *mut ();
+        Rc::retype(NonNull::new_unchecked(
+            ptr::from_raw_parts_mut::<[T]>(data, len) as *mut _,
+        ))
     }
+}
 
-    impl<T: Clone> RctCloneableT<T> for Rc<T> {
-        #[inline(always)]
-        fn make_mut(this: &mut Self) -> &mut T {
-            Rc::make_mut(this)
-        }
+impl<T, S: RcStub> Rc<[T], S> {
+    /// Builds a slice in a single allocation.
+    ///
+    /// # Panics
+    /// Panics if allocating fails or if `iter` doesn't yield exactly as many items as its `len`
+    /// said.
+    pub fn from_exact_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::from_exact_iter_in(iter, Global).expect("failed to allocate")
     }
+}
 
-    impl<T: ?Sized> Rct<T> for Rc<T> {
-        type Weak = Weak<T>;
-        #[inline(always)]
-        fn as_ptr(this: &Self) -> *const T {
-            Rc::as_ptr(this)
-        }
-        #[inline(always)]
-        fn get_mut(this: &mut Self) -> Option<&mut T> {
-            Rc::get_mut(this)
-        }
-        #[inline(always)]
-        fn into_raw(this: Self) -> *const T {
-            Rc::into_raw(this)
-        }
-        #[inline(always)]
-        unsafe fn from_raw(ptr: *const T) -> Self {
-            Rc::from_raw(ptr)
-        }
-        #[inline(always)]
-        fn downgrade(this: &Self) -> Self::Weak {
-            Rc::downgrade(this)
-        }
-        #[inline(always)]
-        fn weak_count(this: &Self) -> usize {
-            Rc::weak_count(this)
-        }
-        #[inline(always)]
-        fn strong_count(this: &Self) -> usize {
-            Rc::strong_count(this)
-        }
-        #[inline(always)]
-        unsafe fn increment_strong_count(ptr: *const T) {
-            Rc::increment_strong_count(ptr)
-        }
-        #[inline(always)]
-        unsafe fn decrement_strong_count(ptr: *const T) {
-            Rc::decrement_strong_count(ptr)
-        }
-        #[inline(always)]
-        fn ptr_eq(this: &Self, other: &Self) -> bool {
-            Rc::ptr_eq(this, other)
-        }
-        
+impl<T, S: RcStub, A: Allocator> Rc<[T], S, A> {
+    /// # Panics
+    /// Panics if `iter` doesn't yield exactly as many items as its `len` said.
+    pub fn from_exact_iter_in<I>(iter: I, alloc: A) -> Result<Self, AllocError>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let len = iter.len();
+        Self::from_iter_len_in(iter, len, alloc)
     }
 
-    impl<T> RctSizedT<T> for Rc<T> {
-        #[inline(always)]
-        fn new(item: T) -> Self {
-            Rc::new(item)
-        }
-        #[inline(always)]
-        fn new_cyclic<F>(data_fn: F) -> Self
-        where
-            F: FnOnce(&Self::Weak) -> T,
-        {
-            Rc::new_cyclic(data_fn)
+    /// Builds a slice out of `len` items of `iter` in a single allocation.
+    ///
+    /// # Panics
+    /// Panics if `iter` doesn't yield exactly `len` items.
+    fn from_iter_len_in(
+        mut iter: impl Iterator<Item = T>,
+        len: usize,
+        alloc: A,
+    ) -> Result<Self, AllocError> {
+        /// drops what was written so far and frees the allocation if `iter` panics
+        struct Guard<C, A: Allocator, T> {
+            ptr: NonNull<RcInner<C, A, [T]>>,
+            written: usize,
         }
-        #[inline(always)]
-        fn try_unwrap(this: Self) -> Result<T, Self> {
-            Rc::try_unwrap(this)
+
+        impl<C, A: Allocator, T> Drop for Guard<C, A, T> {
+            fn drop(&mut self) {
+                unsafe {
+                    let elems = ptr::addr_of_mut!((*self.ptr.as_ptr()).value) as *mut T;
+                    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(elems, self.written));
+                    dealloc(self.ptr);
+                }
+            }
         }
-        #[inline(always)]
-        fn pin(data: T) -> Pin<Self> {
-            Rc::pin(data)
+
+        let value = Layout::array::<T>(len).map_err(|_| AllocError)?;
+        let ptr = allocate::<[T], _, A>(value, len, alloc, S::Counts::new)?;
+        let mut guard = Guard { ptr, written: 0 };
+        let elems = unsafe { ptr::addr_of_mut!((*ptr.as_ptr()).value) as *mut T };
+        while guard.written < len {
+            let item = iter.next().expect("iterator ended before its length");
+            unsafe { elems.add(guard.written).write(item) };
+            guard.written += 1;
         }
+        assert!(iter.next().is_none(), "iterator went past its length");
+        mem::forget(guard);
+        Ok(unsafe { Self::from_inner(ptr) })
     }
+}
 
-    impl<T: ?Sized> Weakt<T> for Weak<T> {
-        type Parent = Rc<T>;
-        #[inline(always)]
-        fn upgrade(&self) -> Option<Self::Parent> {
-            Weak::upgrade(&self)
-        }
-        #[inline(always)]
-        fn as_ptr(&self) -> *const T {
-            Weak::as_ptr(&self)
-        }
-        #[inline(always)]
-        fn into_raw(self) -> *const T {
-            Weak::into_raw(self)
-        }
-        #[inline(always)]
-        unsafe fn from_raw(ptr: *const T) -> Self {
-            Weak::from_raw(ptr)
-        }
-        #[inline(always)]
-        fn strong_count(&self) -> usize {
-            Weak::strong_count(self)
-        }
-        #[inline(always)]
-        fn weak_count(&self) -> usize {
-            Weak::weak_count(&self)
-        }
-        #[inline(always)]
-        fn ptr_eq(&self, other: &Self) -> bool {
-            Weak::ptr_eq(&self, other)
+impl<T, S: RcStub, A: Allocator> Rc<T, S, A>
+where
+    S::Counts: WeakCounts,
+{
+    pub fn new_cyclic_in<F>(data_fn: F, alloc: A) -> Result<Self, AllocError>
+    where
+        F: FnOnce(&WeakStub<T, S, A>) -> T,
+    {
+        let ptr = allocate::<T, _, A>(Layout::new::<T>(), (), alloc, S::Counts::new_cyclic)?;
+        // this weak pointer becomes the one shared by the strong references, if `data_fn` panics
+        // dropping it frees the allocation
+        let weak = WeakStub { ptr };
+        let value = data_fn(&weak);
+        unsafe {
+            ptr::addr_of_mut!((*ptr.as_ptr()).value).write(value);
+            (*ptr.as_ptr()).counts.init_strong();
         }
+        mem::forget(weak);
+        Ok(unsafe { Self::from_inner(ptr) })
     }
+}
 
-    impl<T> WeaktSizedT<T> for Weak<T> {
-        #[inline(always)]
-        fn new() -> Self {
-            Weak::new()
+impl<T: Clone, S: RcStub, A: Allocator + Clone> Rc<T, S, A> {
+    /// Returns a mutable reference to the value, cloning it into a new allocation first if it is
+    /// shared.
+    ///
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn make_mut(this: &mut Self) -> &mut T {
+        if !this.counts().is_unique() {
+            let alloc = Self::allocator(this).clone();
+            let clone = Self::new_in((**this).clone(), alloc).expect("failed to allocate");
+            clone.counts().cloned_from(this.counts());
+            *this = clone;
         }
+        unsafe { &mut this.ptr.as_mut().value }
     }
 }
 
-mod arc_impl {
-    use super::*;
-    pub enum AtRc {}
-    use alloc::sync::{Arc, Weak as WeakA};
+impl<T: ?Sized, S: RcStub, A: Allocator> Deref for Rc<T, S, A> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &(*self.ptr.as_ptr()).value }
+    }
+}
 
-    impl RcStub for AtRc {
-        type Counter<T: ?Sized> = Arc<T>;
-        type Weak<T: ?Sized> = WeakA<T>;
+impl<T: ?Sized, S: RcStub, A: Allocator> Clone for Rc<T, S, A> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        self.counts().inc_strong();
+        unsafe { Self::from_inner(self.ptr) }
     }
+}
 
-    impl<T: ?Sized> Rct<T> for Arc<T> {
-        type Weak = WeakA<T>;
-        #[inline(always)]
-        fn as_ptr(this: &Self) -> *const T {
-            Arc::as_ptr(this)
-        }
-        #[inline(always)]
-        fn get_mut(this: &mut Self) -> Option<&mut T> {
-            Arc::get_mut(this)
-        }
-        #[inline(always)]
-        fn into_raw(this: Self) -> *const T {
-            Arc::into_raw(this)
-        }
-        #[inline(always)]
-        unsafe fn from_raw(ptr: *const T) -> Self {
-            Arc::from_raw(ptr)
-        }
-        #[inline(always)]
-        fn downgrade(this: &Self) -> Self::Weak {
-            Arc::downgrade(this)
-        }
-        #[inline(always)]
-        fn weak_count(this: &Self) -> usize {
-            Arc::weak_count(this)
-        }
-        #[inline(always)]
-        fn strong_count(this: &Self) -> usize {
-            Arc::strong_count(this)
-        }
-        #[inline(always)]
-        unsafe fn increment_strong_count(ptr: *const T) {
-            Arc::increment_strong_count(ptr)
-        }
-        #[inline(always)]
-        unsafe fn decrement_strong_count(ptr: *const T) {
-            Arc::decrement_strong_count(ptr)
-        }
-        #[inline(always)]
-        fn ptr_eq(this: &Self, other: &Self) -> bool {
-            Arc::ptr_eq(this, other)
+impl<T: ?Sized, S: RcStub, A: Allocator> Drop for Rc<T, S, A> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.counts().dec_strong() {
+            unsafe { drop_slow(self.ptr) }
         }
+        S::Counts::poll();
     }
+}
 
-    impl<T> RctSizedT<T> for Arc<T> {
-        #[inline(always)]
-        fn new(item: T) -> Self {
-            Arc::new(item)
-        }
-        #[inline(always)]
-        fn new_cyclic<F>(data_fn: F) -> Self
-        where
-            F: FnOnce(&Self::Weak) -> T,
-        {
-            Arc::new_cyclic(data_fn)
-        }
-        #[inline(always)]
-        fn try_unwrap(this: Self) -> Result<T, Self> {
-            Arc::try_unwrap(this)
-        }
-        #[inline(always)]
-        fn pin(data: T) -> Pin<Self> {
-            Arc::pin(data)
+/// A weak pointer to the value of an [`Rc`], only exists for flavors with weak counts.
+#[repr(transparent)]
+pub struct WeakStub<T: ?Sized, S: RcStub = StRc, A: Allocator = Global> {
+    /// dangles at `usize::MAX` for [`WeakStub::new`]
+    ptr: NonNull<RcInner<S::Counts, A, T>>,
+}
+
+unsafe impl<T: ?Sized + Send + Sync, S: RcStub, A: Allocator + Send + Sync> Send
+    for WeakStub<T, S, A>
+where
+    S::Counts: Send + Sync,
+{
+}
+unsafe impl<T: ?Sized + Send + Sync, S: RcStub, A: Allocator + Send + Sync> Sync
+    for WeakStub<T, S, A>
+where
+    S::Counts: Send + Sync,
+{
+}
+
+impl<T, S: RcStub, A: Allocator> WeakStub<T, S, A>
+where
+    S::Counts: WeakCounts,
+{
+    /// A weak pointer that never upgrades, without allocating.
+    pub const fn new() -> Self {
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(ptr::invalid_mut(usize::MAX)) },
         }
     }
-    
-    impl<T: Clone> RctCloneableT<T> for Arc<T> {
-        #[inline(always)]
-        fn make_mut(this: &mut Self) -> &mut T {
-            Arc::make_mut(this)
-        }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> WeakStub<T, S, A> {
+    #[inline(always)]
+    fn is_dangling(&self) -> bool {
+        (self.ptr.as_ptr() as *mut ()).addr() == usize::MAX
     }
 
-    impl<T: ?Sized> Weakt<T> for WeakA<T> {
-        type Parent = Arc<T>;
-        #[inline(always)]
-        fn upgrade(&self) -> Option<Self::Parent> {
-            WeakA::upgrade(&self)
-        }
-        #[inline(always)]
-        fn as_ptr(&self) -> *const T {
-            WeakA::as_ptr(&self)
-        }
-        #[inline(always)]
-        fn into_raw(self) -> *const T {
-            WeakA::into_raw(self)
+    #[inline(always)]
+    fn counts(&self) -> Option<&S::Counts> {
+        if self.is_dangling() {
+            None
+        } else {
+            Some(unsafe { &(*self.ptr.as_ptr()).counts })
         }
-        #[inline(always)]
-        unsafe fn from_raw(ptr: *const T) -> Self {
-            WeakA::from_raw(ptr)
+    }
+
+    /// The pointer to the value, which may already be dropped.
+    pub fn as_ptr(&self) -> *const T {
+        if self.is_dangling() {
+            self.ptr.as_ptr() as *const T
+        } else {
+            unsafe { ptr::addr_of!((*self.ptr.as_ptr()).value) }
         }
-        #[inline(always)]
-        fn strong_count(&self) -> usize {
-            WeakA::strong_count(&self)
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.counts().map_or(0, |c| c.strong())
+    }
+
+    /// Returns `true` if both point to the same allocation, or both came from
+    /// [`new`](WeakStub::new).
+    #[inline(always)]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        ptr::eq(
+            self.ptr.as_ptr() as *const (),
+            other.ptr.as_ptr() as *const (),
+        )
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> WeakStub<T, S, A>
+where
+    S::Counts: WeakCounts,
+{
+    /// Returns a new [`Rc`] if the value hasn't been dropped yet.
+    pub fn upgrade(&self) -> Option<Rc<T, S, A>> {
+        if self.counts()?.upgrade() {
+            Some(unsafe { Rc::from_inner(self.ptr) })
+        } else {
+            None
         }
-        #[inline(always)]
-        fn weak_count(&self) -> usize {
-            WeakA::weak_count(&self)
+    }
+
+    /// The number of weak pointers, or zero once the value has been dropped.
+    pub fn weak_count(&self) -> usize {
+        match self.counts() {
+            Some(c) if c.strong() != 0 => c.weak(),
+            _ => 0,
         }
-        #[inline(always)]
-        fn ptr_eq(&self, other: &Self) -> bool {
-            WeakA::ptr_eq(&self, other)
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> Clone for WeakStub<T, S, A>
+where
+    S::Counts: WeakCounts,
+{
+    fn clone(&self) -> Self {
+        if let Some(c) = self.counts() {
+            c.inc_weak()
         }
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T, S: RcStub, A: Allocator> Default for WeakStub<T, S, A>
+where
+    S::Counts: WeakCounts,
+{
+    fn default() -> Self {
+        Self::new()
     }
-    
-    impl<T> WeaktSizedT<T> for WeakA<T> {
-        #[inline(always)]
-        fn new() -> Self {
-            WeakA::new()
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> Drop for WeakStub<T, S, A> {
+    fn drop(&mut self) {
+        if let Some(c) = self.counts() {
+            if c.release_weak() {
+                unsafe { dealloc(self.ptr) }
+            }
         }
     }
 }
@@ -501,4 +757,339 @@ mod tests {
             });
         }
     }
+
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc as StdArc;
+
+    /// bumps the counter when dropped
+    struct Dropper(StdArc<AtomicUsize>);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counts_and_frees<S: RcStub>() {
+        let drops = StdArc::new(AtomicUsize::new(0));
+        let a: Rc<_, S> = Rc::new(Dropper(drops.clone()));
+        let b = a.clone();
+        assert_eq!(Rc::strong_count(&a), 2);
+        assert!(Rc::ptr_eq(&a, &b));
+        drop(a);
+        assert_eq!(Rc::strong_count(&b), 1);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        let raw = Rc::into_raw(b);
+        unsafe {
+            Rc::<Dropper, S>::increment_strong_count(raw);
+            Rc::<Dropper, S>::decrement_strong_count(raw);
+            drop(Rc::<Dropper, S>::from_raw(raw));
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let mut a: Rc<_, S> = Rc::new(vec![1, 2]);
+        let b = a.clone();
+        assert!(Rc::get_mut(&mut a).is_none());
+        let a = Rc::try_unwrap(a).unwrap_err();
+        drop(b);
+        assert_eq!(Rc::try_unwrap(a).ok(), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn flavors() {
+        counts_and_frees::<StRc>();
+        counts_and_frees::<AtRc>();
+        counts_and_frees::<StRcNoWeak>();
+        counts_and_frees::<AtRcNoWeak>();
+        counts_and_frees::<BiRc>();
+    }
+
+    #[test]
+    fn no_weak_is_smaller() {
+        use core::mem::size_of;
+        assert_eq!(
+            size_of::<RcInner<LocalStrongCount, Global, u64>>() + 8,
+            size_of::<RcInner<LocalCounts, Global, u64>>()
+        );
+        assert_eq!(
+            size_of::<RcInner<AtomicStrongCount, Global, u64>>() + 8,
+            size_of::<RcInner<AtomicCounts, Global, u64>>()
+        );
+    }
+
+    fn weak_refs<S: RcStub>()
+    where
+        S::Counts: WeakCounts,
+    {
+        let drops = StdArc::new(AtomicUsize::new(0));
+        let mut a: Rc<_, S> = Rc::new(Dropper(drops.clone()));
+        let w = Rc::downgrade(&a);
+        let w2 = w.clone();
+        assert_eq!(Rc::weak_count(&a), 2);
+        assert!(Rc::get_mut(&mut a).is_none());
+        drop(w);
+        drop(w2);
+        assert!(Rc::get_mut(&mut a).is_some());
+        let _w = Rc::downgrade(&a);
+        drop(a);
+        // the value goes away with the last strong reference, the allocation with the last weak
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        struct Node<S: RcStub> {
+            me: WeakStub<Node<S>, S>,
+            val: i32,
+        }
+        let a: Rc<Node<S>, S> = Rc::new_cyclic(|w| Node {
+            me: w.clone(),
+            val: 5,
+        });
+        assert_eq!(Rc::weak_count(&a), 1);
+        assert_eq!(a.val, 5);
+        assert!(a.me.ptr == a.ptr);
+    }
+
+    #[test]
+    fn weak() {
+        weak_refs::<StRc>();
+        weak_refs::<AtRc>();
+    }
+
+    #[test]
+    fn make_mut_clones_shared() {
+        let mut a: Rc<_, StRc> = Rc::new(1);
+        let b = a.clone();
+        *Rc::make_mut(&mut a) += 1;
+        assert_eq!((*a, *b), (2, 1));
+        let w = Rc::downgrade(&b);
+        let mut b = b;
+        *Rc::make_mut(&mut b) += 2;
+        assert_eq!(*b, 3);
+        drop(w);
+    }
+
+    #[test]
+    fn biased_remote_drops() {
+        use std::thread::spawn;
+        let drops = StdArc::new(AtomicUsize::new(0));
+
+        // the owner outlives the other threads and settles their debts when it drops
+        let a: Rc<_, BiRc> = Rc::new(Dropper(drops.clone()));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let c = a.clone();
+                spawn(move || drop(c))
+            })
+            .collect();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        assert_eq!(Rc::strong_count(&a), 1);
+        drop(a);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        // the owner lets go first, the queue is settled on request
+        let a: Rc<_, BiRc> = Rc::new(Dropper(drops.clone()));
+        let clones: Vec<_> = (0..4).map(|_| a.clone()).collect();
+        drop(a);
+        let handles: Vec<_> = clones.into_iter().map(|c| spawn(move || drop(c))).collect();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        BiRc::merge_queued();
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        // clones made elsewhere keep it alive after the owner merged
+        let a: Rc<_, BiRc> = Rc::new(Dropper(drops.clone()));
+        let c = a.clone();
+        drop(a);
+        spawn(move || {
+            let more: Vec<_> = (0..3).map(|_| c.clone()).collect();
+            drop(c);
+            drop(more);
+        })
+        .join()
+        .unwrap();
+        BiRc::merge_queued();
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn weak_upgrade() {
+        let a: Rc<_, AtRc> = Rc::new(3);
+        let w = Rc::downgrade(&a);
+        assert_eq!(w.upgrade().as_deref(), Some(&3));
+        assert_eq!((w.strong_count(), w.weak_count()), (1, 1));
+        assert_eq!(w.as_ptr(), Rc::as_ptr(&a));
+        assert!(w.ptr_eq(&w.clone()));
+        drop(a);
+        assert!(w.upgrade().is_none());
+        assert_eq!((w.strong_count(), w.weak_count()), (0, 0));
+
+        let empty = WeakStub::<i32>::new();
+        assert!(empty.upgrade().is_none());
+        assert!(empty.ptr_eq(&WeakStub::default()));
+        assert_eq!(empty.clone().strong_count(), 0);
+    }
+
+    #[test]
+    fn conversions() {
+        use core::fmt::Debug;
+        use std::collections::BTreeSet;
+
+        let f: Rc<dyn Fn(i32) -> i32, AtRc> = Rc::new(|x| x + 1);
+        assert_eq!(f(1), 2);
+        let d: Rc<dyn Debug> = Rc::from(Box::new([1, 2]) as Box<dyn Debug>);
+        assert_eq!(format!("{d:?}"), "[1, 2]");
+
+        let s: Rc<str, StRcNoWeak> = Rc::from("hello");
+        assert_eq!(&*s, "hello");
+        assert_eq!(format!("{s}"), "hello");
+        let bytes: Rc<[u8], StRcNoWeak> = s.into();
+        assert_eq!(&*bytes, b"hello");
+
+        let v: Rc<[String], BiRc> = (0..3).map(|i| i.to_string()).collect();
+        assert_eq!(v.len(), 3);
+        let arr: Rc<[String; 3], BiRc> = v.try_into().unwrap();
+        assert_eq!(arr[2], "2");
+        let empty: Rc<[i32]> = Rc::default();
+        assert!(Rc::<[i32; 1]>::try_from(empty).is_err());
+
+        let set: BTreeSet<Rc<i32>> = [3, 1, 2, 1].into_iter().map(Rc::from).collect();
+        assert_eq!(set.iter().map(|r| **r).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(Rc::<i32>::new(1) < Rc::new(2));
+    }
+
+    #[test]
+    fn panicking_iterator_frees() {
+        let drops = StdArc::new(AtomicUsize::new(0));
+        let items = (0..4).map(|i| {
+            assert!(i < 3);
+            Dropper(drops.clone())
+        });
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Rc::<[Dropper]>::from_iter_len_in(items, 4, Global)
+        }));
+        assert!(res.is_err());
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn biased_unwrap_off_the_owner() {
+        // never merged, the owner's only reference moved to another thread
+        let u = UniqueRc::<_, BiRc>::new(vec![1, 2]);
+        let v = std::thread::spawn(move || UniqueRc::into_inner(u)).join().unwrap();
+        assert_eq!(v, [1, 2]);
+
+        // merged, the owner let go of its references first
+        let a = Rc::<_, BiRc>::new(String::from("moved"));
+        let b = a.clone();
+        let other = std::thread::spawn(move || {
+            let b = b;
+            std::thread::park();
+            Rc::try_unwrap(b)
+        });
+        drop(a);
+        other.thread().unpark();
+        assert_eq!(other.join().unwrap().ok().as_deref(), Some("moved"));
+
+        // still shared with the owner, so it has to fail
+        let a = Rc::<_, BiRc>::new(3);
+        let b = a.clone();
+        let b = std::thread::spawn(move || Rc::try_unwrap(b).err())
+            .join()
+            .unwrap()
+            .unwrap();
+        drop(b);
+        BiRc::merge_queued();
+        assert_eq!(Rc::try_unwrap(a).ok(), Some(3));
+    }
+
+    #[test]
+    fn failed_box_conversion_frees_the_box() {
+        use crate::fault::{Fault, FaultyAlloc};
+        let drops = StdArc::new(AtomicUsize::new(0));
+        let alloc = FaultyAlloc::new();
+        let boxed = Box::new_in(Dropper(drops.clone()), alloc.clone());
+        alloc.set_fault(Fault::Nth(0));
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Rc::<Dropper, StRc, _>::from(boxed)
+        }));
+        assert!(res.is_err());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn uninit_and_unwrapping() {
+        let mut u = Rc::<u64, BiRc>::new_uninit();
+        Rc::get_mut(&mut u).unwrap().write(7);
+        let u = unsafe { u.assume_init() };
+        assert_eq!(*u, 7);
+        let z = unsafe { Rc::<u32, AtRc>::new_zeroed_slice(3).assume_init() };
+        assert_eq!(*z, [0, 0, 0]);
+        let z = unsafe { Rc::<(u8, u64)>::new_zeroed().assume_init() };
+        assert_eq!(*z, (0, 0));
+
+        // the value gets dropped through the new type once it's initialized
+        let drops = StdArc::new(AtomicUsize::new(0));
+        let mut s = Rc::<Dropper, BiRc>::new_uninit_slice(2);
+        for slot in Rc::get_mut(&mut s).unwrap() {
+            slot.write(Dropper(drops.clone()));
+        }
+        let s = unsafe { s.assume_init() };
+        let c = s.clone();
+        drop(s);
+        std::thread::spawn(move || drop(c)).join().unwrap();
+        BiRc::merge_queued();
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        let a: Rc<_, AtRc> = Rc::try_new(vec![1]).unwrap();
+        let b = a.clone();
+        assert_eq!(Rc::unwrap_or_clone(a), [1]);
+        let c = b.clone();
+        assert_eq!(Rc::into_inner(b), None);
+        assert_eq!(Rc::into_inner(c), Some(vec![1]));
+    }
+
+    #[test]
+    fn no_weak_try_take() {
+        fn check<S: RcStub>() {
+            let a: Rc<u32, S> = Rc::new(1);
+            let b = a.clone();
+            assert!(!a.counts().try_take());
+            assert_eq!(a.counts().strong(), 2);
+            drop(b);
+            // taking leaves nothing for anyone else to release
+            assert!(a.counts().try_take());
+            assert_eq!(a.counts().strong(), 0);
+            a.counts().inc_strong();
+            assert_eq!(Rc::try_unwrap(a).ok(), Some(1));
+        }
+        check::<StRcNoWeak>();
+        check::<AtRcNoWeak>();
+    }
+
+    #[test]
+    fn exact_slices() {
+        let s: Rc<[String], StRcNoWeak> = Rc::from_exact_iter((0..3).map(|i| i.to_string()));
+        assert_eq!(&*s, ["0", "1", "2"]);
+        let mut u = UniqueRc::<_, AtRc>::new(vec![1]);
+        u.push(2);
+        let r = UniqueRc::into_rc(u);
+        assert_eq!(Rc::strong_count(&r), 1);
+        assert_eq!(*r, [1, 2]);
+        assert_eq!(UniqueRc::into_inner(UniqueRc::<_>::new(3)), 3);
+
+        /// claims one more item than it has
+        struct Liar(core::ops::Range<u32>);
+        impl Iterator for Liar {
+            type Item = u32;
+            fn next(&mut self) -> Option<u32> {
+                self.0.next()
+            }
+        }
+        impl ExactSizeIterator for Liar {
+            fn len(&self) -> usize {
+                self.0.len() + 1
+            }
+        }
+        let res = std::panic::catch_unwind(|| Rc::<[u32]>::from_exact_iter(Liar(0..3)));
+        assert!(res.is_err());
+    }
 }
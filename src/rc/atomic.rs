@@ -0,0 +1,392 @@
+//! A cell holding an [`Rc`] that can be read and replaced concurrently.
+//!
+//! Readers don't touch the reference count on the fast path: [`AtomicRc::load`] writes the
+//! pointer into one of its thread's debt slots and checks that the cell still holds it. The
+//! slot records that the reader borrows the reference owned by the cell. A writer that takes a
+//! pointer out of the cell goes over every slot before letting go of it, and pays the readers
+//! still borrowing it with a reference of their own.
+//!
+//! Every thread that loads gets a small record of slots, these are never freed.
+
+use super::{AtRc, Rc, RcStub};
+use alloc::boxed::Box;
+use core::{
+    cell::Cell,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    ops::Deref,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering::*},
+};
+
+/// debt slots per thread
+const SLOTS: usize = 8;
+
+/// written over a debt once a writer paid it, no value pointer is ever this low
+const PAID: *mut () = 1 as *mut ();
+
+struct Record {
+    slots: [AtomicPtr<()>; SLOTS],
+    /// only used for the moment it takes to increment the count in `load_full`
+    help: AtomicPtr<()>,
+    next: *const Record,
+}
+
+/// every record ever made
+static RECORDS: AtomicPtr<Record> = AtomicPtr::new(ptr::null_mut());
+
+#[thread_local]
+static LOCAL: Cell<*const Record> = Cell::new(ptr::null());
+
+fn local() -> &'static Record {
+    if let Some(rec) = unsafe { LOCAL.get().as_ref() } {
+        return rec;
+    }
+    let rec = Box::leak(Box::new(Record {
+        slots: core::array::from_fn(|_| AtomicPtr::default()),
+        help: AtomicPtr::default(),
+        next: ptr::null(),
+    }));
+    let mut head = RECORDS.load(Relaxed);
+    loop {
+        rec.next = head;
+        match RECORDS.compare_exchange_weak(head, rec, Release, Relaxed) {
+            Ok(_) => break,
+            Err(old) => head = old,
+        }
+    }
+    LOCAL.set(rec);
+    rec
+}
+
+/// Hands a reference to everyone still borrowing `ptr` from a cell, the caller has to own one.
+///
+/// # Safety
+/// `ptr` has to be an [`Rc::into_raw`] pointer the caller holds a reference through.
+unsafe fn pay_debts<T, S: RcStub>(ptr: *const T) {
+    let mut rec = RECORDS.load(Acquire);
+    while let Some(r) = rec.as_ref() {
+        for slot in r.slots.iter().chain([&r.help]) {
+            if slot.load(SeqCst) != ptr as *mut () {
+                continue;
+            }
+            Rc::<T, S>::increment_strong_count(ptr);
+            if slot
+                .compare_exchange(ptr as *mut (), PAID, AcqRel, Relaxed)
+                .is_err()
+            {
+                // the reader gave the slot back first
+                Rc::<T, S>::decrement_strong_count(ptr);
+            }
+        }
+        rec = r.next as *mut Record;
+    }
+}
+
+/// An [`Rc`] behind an atomic pointer, like `ArcSwap`.
+///
+/// # Examples
+/// ```
+/// use hash_table::rc::{AtomicRc, Rc};
+/// let config: AtomicRc<i32> = AtomicRc::new(Rc::new(1));
+/// assert_eq!(*config.load(), 1);
+/// let old = config.swap(Rc::new(2));
+/// assert_eq!((*old, *config.load()), (1, 2));
+/// ```
+pub struct AtomicRc<T, S: RcStub = AtRc> {
+    ptr: AtomicPtr<T>,
+    marker: PhantomData<Rc<T, S>>,
+}
+
+unsafe impl<T, S: RcStub> Send for AtomicRc<T, S> where Rc<T, S>: Send + Sync {}
+unsafe impl<T, S: RcStub> Sync for AtomicRc<T, S> where Rc<T, S>: Send + Sync {}
+
+impl<T, S: RcStub> AtomicRc<T, S> {
+    pub fn new(value: Rc<T, S>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Rc::into_raw(value) as *mut T),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> Rc<T, S> {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe { Rc::from_raw(this.ptr.load(Relaxed)) }
+    }
+
+    /// Borrows the current value without changing its count, unless the thread already holds
+    /// a lot of guards.
+    pub fn load(&self) -> LoadGuard<'_, T, S> {
+        let rec = local();
+        if let Some(slot) = rec.slots.iter().find(|s| s.load(Relaxed).is_null()) {
+            let ptr = self.ptr.load(Acquire);
+            slot.store(ptr as *mut (), SeqCst);
+            if self.ptr.load(SeqCst) == ptr {
+                // any writer taking it out now sees the slot
+                return LoadGuard {
+                    ptr,
+                    slot: Some(slot),
+                    marker: PhantomData,
+                };
+            }
+            if slot
+                .compare_exchange(ptr as *mut (), ptr::null_mut(), Acquire, Relaxed)
+                .is_err()
+            {
+                // replaced, but the writer paid for it anyway
+                slot.store(ptr::null_mut(), Relaxed);
+                return LoadGuard::owned(ptr);
+            }
+        }
+        LoadGuard::owned(Rc::into_raw(self.load_full()))
+    }
+
+    /// Clones the current value.
+    pub fn load_full(&self) -> Rc<T, S> {
+        let help = &local().help;
+        loop {
+            let ptr = self.ptr.load(Acquire);
+            help.store(ptr as *mut (), SeqCst);
+            let counted = self.ptr.load(SeqCst) == ptr;
+            if counted {
+                unsafe { Rc::<T, S>::increment_strong_count(ptr) };
+            }
+            let paid = help
+                .compare_exchange(ptr as *mut (), ptr::null_mut(), Acquire, Relaxed)
+                .is_err();
+            if paid {
+                help.store(ptr::null_mut(), Relaxed);
+            }
+            match (counted, paid) {
+                // raced with a writer and got nothing, try again
+                (false, false) => continue,
+                // got the increment or the payment
+                (true, false) | (false, true) => return unsafe { Rc::from_raw(ptr) },
+                // got both
+                (true, true) => {
+                    unsafe { Rc::<T, S>::decrement_strong_count(ptr) };
+                    return unsafe { Rc::from_raw(ptr) };
+                }
+            }
+        }
+    }
+
+    pub fn store(&self, value: Rc<T, S>) {
+        drop(self.swap(value))
+    }
+
+    /// Replaces the value and returns the old one.
+    pub fn swap(&self, value: Rc<T, S>) -> Rc<T, S> {
+        let old = self.ptr.swap(Rc::into_raw(value) as *mut T, SeqCst);
+        unsafe {
+            pay_debts::<T, S>(old);
+            Rc::from_raw(old)
+        }
+    }
+
+    /// Replaces the value if it's still the one at `current` (compared by address, like
+    /// [`Rc::as_ptr`] or [`LoadGuard::as_ptr`]). Returns the old value, or gives `new` back.
+    pub fn compare_and_swap(&self, current: *const T, new: Rc<T, S>) -> Result<Rc<T, S>, Rc<T, S>> {
+        let raw = Rc::into_raw(new) as *mut T;
+        match self
+            .ptr
+            .compare_exchange(current as *mut T, raw, SeqCst, Relaxed)
+        {
+            Ok(old) => unsafe {
+                pay_debts::<T, S>(old);
+                Ok(Rc::from_raw(old))
+            },
+            Err(_) => Err(unsafe { Rc::from_raw(raw) }),
+        }
+    }
+}
+
+impl<T, S: RcStub> Drop for AtomicRc<T, S> {
+    fn drop(&mut self) {
+        // nobody can borrow from it anymore
+        unsafe { Rc::<T, S>::decrement_strong_count(*self.ptr.get_mut()) }
+    }
+}
+
+impl<T, S: RcStub> From<Rc<T, S>> for AtomicRc<T, S> {
+    fn from(value: Rc<T, S>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Debug, S: RcStub> Debug for AtomicRc<T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicRc").field(&*self.load()).finish()
+    }
+}
+
+/// The value [`AtomicRc::load`] returned, it stays valid when the cell is changed.
+pub struct LoadGuard<'a, T, S: RcStub = AtRc> {
+    ptr: *const T,
+    /// the debt this borrows through, `None` if it owns a reference
+    slot: Option<&'static AtomicPtr<()>>,
+    marker: PhantomData<&'a AtomicRc<T, S>>,
+}
+
+impl<T, S: RcStub> LoadGuard<'_, T, S> {
+    fn owned(ptr: *const T) -> Self {
+        Self {
+            ptr,
+            slot: None,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn as_ptr(this: &Self) -> *const T {
+        this.ptr
+    }
+
+    pub fn into_rc(this: Self) -> Rc<T, S> {
+        // the guard keeps it alive until the new reference exists
+        unsafe {
+            Rc::<T, S>::increment_strong_count(this.ptr);
+            Rc::from_raw(this.ptr)
+        }
+    }
+}
+
+impl<T, S: RcStub> Deref for LoadGuard<'_, T, S> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T, S: RcStub> Drop for LoadGuard<'_, T, S> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            if slot
+                .compare_exchange(self.ptr as *mut (), ptr::null_mut(), Release, Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+            // a writer paid the debt, so there is a reference to give up
+            slot.store(ptr::null_mut(), Relaxed);
+        }
+        unsafe { Rc::<T, S>::decrement_strong_count(self.ptr) }
+    }
+}
+
+impl<T: Debug, S: RcStub> Debug for LoadGuard<'_, T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        sync::Arc as StdArc,
+        thread,
+        vec::Vec,
+    };
+
+    /// counts how many are alive
+    struct Counted(usize, StdArc<AtomicUsize>);
+
+    impl Counted {
+        fn new(n: usize, live: &StdArc<AtomicUsize>) -> Rc<Self, AtRc> {
+            live.fetch_add(1, Ordering::Relaxed);
+            Rc::new(Self(n, live.clone()))
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.1.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn load_store() {
+        let a: Rc<i32, AtRc> = Rc::new(1);
+        let cell = AtomicRc::new(a.clone());
+        let guard = cell.load();
+        // borrowed, not counted
+        assert_eq!(Rc::strong_count(&a), 2);
+        let old = cell.swap(Rc::new(2));
+        // the guard got paid
+        assert_eq!(Rc::strong_count(&a), 3);
+        drop(old);
+        assert_eq!((*guard, *cell.load()), (1, 2));
+        drop(guard);
+        assert_eq!(Rc::strong_count(&a), 1);
+
+        let cur = cell.load_full();
+        assert!(cell.compare_and_swap(Rc::as_ptr(&a), Rc::new(3)).is_err());
+        let old = cell.compare_and_swap(Rc::as_ptr(&cur), a.clone()).unwrap();
+        assert!(Rc::ptr_eq(&old, &cur));
+        assert_eq!(Rc::strong_count(&cur), 2);
+        assert_eq!(*LoadGuard::into_rc(cell.load()), 1);
+        assert_eq!(Rc::strong_count(&Rc::clone(&cell.into_inner())), 3);
+        assert_eq!(Rc::strong_count(&a), 1);
+    }
+
+    #[test]
+    fn more_guards_than_slots() {
+        let a: Rc<i32, AtRc> = Rc::new(1);
+        let cell = AtomicRc::new(a.clone());
+        let guards: Vec<_> = (0..SLOTS * 3).map(|_| cell.load()).collect();
+        assert_eq!(Rc::strong_count(&a), 2 + 2 * SLOTS);
+        cell.store(Rc::new(2));
+        assert!(guards.iter().all(|g| **g == 1));
+        drop(guards);
+        assert_eq!(Rc::strong_count(&a), 1);
+    }
+
+    #[test]
+    fn concurrent() {
+        const WRITES: usize = 2000;
+        let live = StdArc::new(AtomicUsize::new(0));
+        let cell = StdArc::new(AtomicRc::new(Counted::new(0, &live)));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let cell = cell.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while last < WRITES {
+                        let held: Vec<_> = (0..SLOTS + 2).map(|_| cell.load()).collect();
+                        let n = held[0].0;
+                        assert!(n >= last);
+                        last = n;
+                        let full = cell.load_full();
+                        assert!(full.0 >= n);
+                    }
+                })
+            })
+            .collect();
+        let writers: Vec<_> = (0..2)
+            .map(|w| {
+                let (cell, live) = (cell.clone(), live.clone());
+                thread::spawn(move || {
+                    for i in 1..=WRITES / 2 {
+                        let n = if w == 0 { i * 2 - 1 } else { i * 2 };
+                        loop {
+                            let cur = cell.load();
+                            let next = Counted::new(n.max(cur.0), &live);
+                            if cell.compare_and_swap(LoadGuard::as_ptr(&cur), next).is_ok() {
+                                break;
+                            }
+                        }
+                    }
+                    cell.store(Counted::new(WRITES, &live));
+                })
+            })
+            .collect();
+        for t in writers.into_iter().chain(readers) {
+            t.join().unwrap();
+        }
+        drop(StdArc::try_unwrap(cell).ok().unwrap());
+        assert_eq!(live.load(Ordering::Relaxed), 0);
+    }
+}
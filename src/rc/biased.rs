@@ -0,0 +1,286 @@
+//! Biased reference counting.
+//!
+//! The thread that creates an allocation owns it and changes its own count without atomics,
+//! every other thread goes through an atomic "shared" count. When the shared count drops below
+//! zero the allocation is pushed onto the owner's queue, and the owner folds the two counts
+//! together the next time it releases a [`BiRc`](super::BiRc) (or calls
+//! [`BiRc::merge_queued`](super::BiRc::merge_queued)). Once the owner has let go of all of its
+//! references the counts are merged and the shared count alone decides when to free.
+
+use super::counts::{Counts, Destroy, ErasedFn};
+use alloc::boxed::Box;
+use core::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    sync::atomic::{self, AtomicIsize, AtomicPtr, Ordering::*},
+};
+
+/// the owner has released its last biased reference
+const MERGED: isize = 1;
+/// the allocation sits in the owner's queue
+const QUEUED: isize = 2;
+/// one reference in the shared count
+const ONE: isize = 4;
+
+#[inline(always)]
+fn count(shared: isize) -> isize {
+    shared >> 2
+}
+
+/// Every thread that ever created a biased allocation gets one of these, they are never freed.
+struct ThreadRecord {
+    queue: AtomicPtr<BiasedCounts>,
+}
+
+#[thread_local]
+static THREAD: Cell<*const ThreadRecord> = Cell::new(ptr::null());
+
+fn current_thread() -> NonNull<ThreadRecord> {
+    match NonNull::new(THREAD.get() as *mut ThreadRecord) {
+        Some(rec) => rec,
+        None => {
+            let rec = NonNull::from(Box::leak(Box::new(ThreadRecord {
+                queue: AtomicPtr::new(ptr::null_mut()),
+            })));
+            THREAD.set(rec.as_ptr());
+            rec
+        }
+    }
+}
+
+/// Counts for [`BiRc`](super::BiRc), see the [module docs](self).
+pub struct BiasedCounts {
+    owner: NonNull<ThreadRecord>,
+    /// only ever written by the owner, it's atomic so other threads can peek at it
+    biased: AtomicIsize,
+    shared: AtomicIsize,
+    /// the link in the owner's queue
+    next: AtomicPtr<BiasedCounts>,
+    /// the drop function of the `Destroy`, it changes in `retype`
+    destroy_fn: AtomicPtr<()>,
+    destroy_meta: usize,
+}
+
+unsafe impl Send for BiasedCounts {}
+unsafe impl Sync for BiasedCounts {}
+
+impl BiasedCounts {
+    /// # Safety
+    /// There can't be any strong reference left.
+    unsafe fn destroy(counts: *const Self) {
+        let f = (*counts).destroy_fn.load(Relaxed);
+        let f: ErasedFn = core::mem::transmute(f);
+        f(counts as *mut (), (*counts).destroy_meta)
+    }
+
+    #[inline]
+    fn is_owner(&self) -> bool {
+        THREAD.get() == self.owner.as_ptr() as *const _
+    }
+
+    /// the slow path for everyone but an owner with biased references left
+    fn dec_shared(&self) -> bool {
+        let mut cur = self.shared.load(Relaxed);
+        loop {
+            let mut new = cur - ONE;
+            let queue = new & (MERGED | QUEUED) == 0 && count(new) < 0;
+            if queue {
+                new |= QUEUED;
+            }
+            match self
+                .shared
+                .compare_exchange_weak(cur, new, Release, Relaxed)
+            {
+                Ok(_) => {
+                    if queue {
+                        // nobody frees a queued allocation but its owner, so `self` stays valid
+                        // until the push went through
+                        unsafe { self.push() };
+                        return false;
+                    }
+                    if new == MERGED {
+                        atomic::fence(Acquire);
+                        return true;
+                    }
+                    return false;
+                }
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// # Safety
+    /// `QUEUED` has to be set and `self` must not be used afterwards.
+    unsafe fn push(&self) {
+        let this = self as *const Self as *mut Self;
+        let queue = &self.owner.as_ref().queue;
+        let mut head = queue.load(Relaxed);
+        loop {
+            self.next.store(head, Relaxed);
+            match queue.compare_exchange_weak(head, this, Release, Relaxed) {
+                Ok(_) => return,
+                Err(old) => head = old,
+            }
+        }
+    }
+}
+
+/// Folds the queued allocations into their biased counts and frees the ones that reached zero.
+pub(crate) fn merge_queued() {
+    let rec = THREAD.get();
+    if rec.is_null() {
+        return;
+    }
+    let mut cur = unsafe { (*rec).queue.swap(ptr::null_mut(), Acquire) };
+    while !cur.is_null() {
+        unsafe {
+            let next = (*cur).next.load(Relaxed);
+            merge_one(cur);
+            cur = next;
+        }
+    }
+}
+
+/// # Safety
+/// `counts` has to come out of this thread's queue.
+unsafe fn merge_one(counts: *const BiasedCounts) {
+    let (biased, shared) = (&(*counts).biased, &(*counts).shared);
+    let b = biased.load(Relaxed);
+    if b == 0 {
+        // merged while it was queued, from now on the shared count is all there is
+        let prev = shared.fetch_and(!QUEUED, AcqRel);
+        if count(prev) == 0 {
+            BiasedCounts::destroy(counts);
+        }
+        return;
+    }
+    let mut cur = shared.load(Acquire);
+    loop {
+        let k = count(cur);
+        if k >= 0 {
+            // references were added again since it was queued, nothing to pay back
+            match shared.compare_exchange_weak(cur, cur & !QUEUED, AcqRel, Acquire) {
+                Ok(_) => return,
+                Err(old) => cur = old,
+            }
+            continue;
+        }
+        // move the debt over to the biased count, the total only ever reads too high
+        match shared.compare_exchange_weak(cur, 0, AcqRel, Acquire) {
+            Ok(_) => {
+                let b = b + k;
+                biased.store(b, Relaxed);
+                if b == 0 {
+                    BiasedCounts::destroy(counts);
+                }
+                return;
+            }
+            Err(old) => cur = old,
+        }
+    }
+}
+
+unsafe impl Counts for BiasedCounts {
+    fn new(destroy: Destroy) -> Self {
+        let (f, meta) = destroy.into_parts();
+        Self {
+            owner: current_thread(),
+            biased: AtomicIsize::new(1),
+            shared: AtomicIsize::new(0),
+            next: AtomicPtr::new(ptr::null_mut()),
+            destroy_fn: AtomicPtr::new(f as *mut ()),
+            destroy_meta: meta,
+        }
+    }
+
+    fn strong(&self) -> usize {
+        let total = self.biased.load(Relaxed) + count(self.shared.load(Acquire));
+        total.max(0) as usize
+    }
+
+    #[inline]
+    fn inc_strong(&self) {
+        let b = self.biased.load(Relaxed);
+        if b > 0 && self.is_owner() {
+            assert!(b < isize::MAX, "reference count overflow");
+            self.biased.store(b + 1, Relaxed);
+        } else {
+            let old = self.shared.fetch_add(ONE, Relaxed);
+            assert!(count(old) < isize::MAX >> 3, "reference count overflow");
+        }
+    }
+
+    #[inline]
+    fn dec_strong(&self) -> bool {
+        let b = self.biased.load(Relaxed);
+        if !(b > 0 && self.is_owner()) {
+            return self.dec_shared();
+        }
+        self.biased.store(b - 1, Release);
+        if b > 1 {
+            return false;
+        }
+        // that was the last biased reference, hand the allocation over to the shared count
+        let prev = self.shared.fetch_or(MERGED, AcqRel);
+        prev == 0
+    }
+
+    fn try_take(&self) -> bool {
+        if self.is_owner() && self.biased.load(Relaxed) > 0 {
+            // `is_unique` settles the queue, after that the one reference is the biased one
+            if !self.is_unique() {
+                return false;
+            }
+            self.biased.store(0, Relaxed);
+            return true;
+        }
+        let s = self.shared.load(Acquire);
+        if s & MERGED != 0 {
+            let taken = self
+                .shared
+                .compare_exchange(MERGED | ONE, MERGED, Relaxed, Relaxed)
+                .is_ok();
+            if taken {
+                atomic::fence(Acquire);
+            }
+            return taken;
+        }
+        // not merged, but the owner can have handed its last biased reference to the caller. then
+        // the biased count is 1, nothing is owed in the shared count and nobody else can change
+        // either of them
+        if s == 0 && self.biased.load(Acquire) == 1 && self.shared.load(Acquire) == 0 {
+            self.biased.store(0, Relaxed);
+            return true;
+        }
+        false
+    }
+
+    fn is_unique(&self) -> bool {
+        if self.is_owner() {
+            if self.shared.load(Relaxed) & QUEUED != 0 {
+                // the owner can't be freed out from under itself, so settle the debts first
+                merge_queued();
+            }
+            let s = self.shared.load(Acquire);
+            s & QUEUED == 0 && self.biased.load(Relaxed) + count(s) == 1
+        } else {
+            // the biased count might still change under us, so only trust merged counts
+            let s = self.shared.load(Acquire);
+            s & MERGED != 0 && s & QUEUED == 0 && count(s) == 1
+        }
+    }
+
+    fn retype(&self, destroy: Destroy) {
+        // the caller holds a reference, releasing it publishes this to whoever frees
+        self.destroy_fn
+            .store(destroy.into_parts().0 as *mut (), Relaxed);
+    }
+
+    #[inline]
+    fn poll() {
+        let rec = THREAD.get();
+        if !rec.is_null() && unsafe { !(*rec).queue.load(Relaxed).is_null() } {
+            merge_queued();
+        }
+    }
+}
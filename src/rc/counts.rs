@@ -0,0 +1,425 @@
+//! The reference counts stored at the start of every allocation.
+//!
+//! A [`RcStub`](super::RcStub) only picks one of these, everything else about
+//! [`Rc`](super::Rc) is shared between the flavors.
+
+use core::{
+    alloc::Allocator,
+    cell::Cell,
+    mem,
+    ptr::{self, Pointee},
+    sync::atomic::{self, AtomicUsize, Ordering::*},
+};
+
+/// Counts past this are treated as a leak.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// Drops the value and frees the allocation that some counts live in, without knowing its type.
+///
+/// Counts that can't always tell the last [`Rc`](super::Rc) that it has to clean up (like
+/// [`BiasedCounts`](super::BiasedCounts)) keep this around to do it later themselves.
+#[derive(Clone, Copy)]
+pub struct Destroy {
+    drop_slow: ErasedFn,
+    drop_value: ErasedFn,
+    dealloc: ErasedFn,
+    meta: usize,
+}
+
+/// gets the address of the allocation and the erased metadata of the value
+pub(crate) type ErasedFn = unsafe fn(*mut (), usize);
+
+impl Destroy {
+    pub(crate) fn new<T: ?Sized, C: Counts, A: Allocator>(meta: <T as Pointee>::Metadata) -> Self {
+        Self {
+            drop_slow: super::erased_drop_slow::<T, C, A>,
+            drop_value: super::erased_drop_value::<T, C, A>,
+            dealloc: super::erased_dealloc::<T, C, A>,
+            meta: erase_meta(meta),
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (ErasedFn, usize) {
+        (self.drop_slow, self.meta)
+    }
+
+    /// # Safety
+    /// `counts` has to point to the counts this was created for, and there can't be any strong
+    /// reference left.
+    pub unsafe fn run<C>(self, counts: *const C) {
+        (self.drop_slow)(counts as *mut (), self.meta)
+    }
+
+    /// Only drops the value and leaves the allocation alone.
+    ///
+    /// # Safety
+    /// Same as [`run`](Self::run), and the value must not be used again.
+    pub(crate) unsafe fn drop_value<C>(self, counts: *const C) {
+        (self.drop_value)(counts as *mut (), self.meta)
+    }
+
+    /// Only frees the allocation.
+    ///
+    /// # Safety
+    /// Same as [`run`](Self::run), and the value has to be dropped already.
+    pub(crate) unsafe fn dealloc<C>(self, counts: *const C) {
+        (self.dealloc)(counts as *mut (), self.meta)
+    }
+}
+
+/// stores pointer metadata (`()`, a length or a vtable) in a `usize`
+pub(crate) fn erase_meta<M: Copy>(meta: M) -> usize {
+    assert!(mem::size_of::<M>() <= mem::size_of::<usize>());
+    let mut out = 0usize;
+    unsafe { ptr::write_unaligned(&mut out as *mut usize as *mut M, meta) };
+    out
+}
+
+/// the inverse of `erase_meta`
+pub(crate) unsafe fn unerase_meta<M: Copy>(meta: usize) -> M {
+    ptr::read_unaligned(&meta as *const usize as *const M)
+}
+
+/// The strong (and maybe weak) count of an allocation.
+///
+/// All strong references share a single weak reference, which is released with
+/// [`release_weak`](Counts::release_weak) once the value has been dropped. Counts without weak
+/// references just return `true` there.
+///
+/// # Safety
+/// The methods have to keep the value alive while there are strong references and report the
+/// last reference exactly once.
+pub unsafe trait Counts: Sized {
+    /// Counts for a new allocation with a single strong reference.
+    fn new(destroy: Destroy) -> Self;
+    /// The number of strong references, this can be approximate while other threads are changing it.
+    fn strong(&self) -> usize;
+    fn inc_strong(&self);
+    /// Returns `true` if that was the last strong reference and the value has to be dropped.
+    fn dec_strong(&self) -> bool;
+    /// If the caller holds the only strong reference, sets the strong count to zero and returns
+    /// `true`. The value has to be moved out or dropped afterwards.
+    fn try_take(&self) -> bool;
+    /// Returns `true` if the caller's strong reference is the only reference of any kind.
+    fn is_unique(&self) -> bool;
+    /// Releases the weak reference held by the strong references or by a weak pointer. Returns
+    /// `true` if the allocation has to be freed.
+    fn release_weak(&self) -> bool {
+        true
+    }
+    /// Called when the value is reinterpreted as another type (like in `assume_init`), counts that
+    /// keep their [`Destroy`] have to switch to the new one.
+    fn retype(&self, _destroy: Destroy) {}
+    /// Called on the counts of a new allocation holding a clone of the value behind `original`
+    /// (like in `make_mut`), counts that keep something about the value have to copy it over.
+    fn cloned_from(&self, _original: &Self) {}
+    /// Called after every strong reference is released, lets lazy schemes catch up.
+    #[inline(always)]
+    fn poll() {}
+}
+
+/// Counts that support weak references.
+///
+/// # Safety
+/// Same as [`Counts`], and [`upgrade`](WeakCounts::upgrade) must never resurrect a dropped value.
+pub unsafe trait WeakCounts: Counts {
+    /// Counts for an allocation that is still being initialized: no strong references and the
+    /// shared weak reference.
+    fn new_cyclic(destroy: Destroy) -> Self;
+    /// Turns the counts from [`new_cyclic`](WeakCounts::new_cyclic) into a single strong reference.
+    fn init_strong(&self);
+    /// The number of weak references, not counting the one shared by the strong references.
+    fn weak(&self) -> usize;
+    fn inc_weak(&self);
+    /// Adds a strong reference if there is still one left.
+    fn upgrade(&self) -> bool;
+}
+
+/// Non atomic strong and weak counts.
+pub struct LocalCounts {
+    strong: Cell<usize>,
+    weak: Cell<usize>,
+}
+
+unsafe impl Counts for LocalCounts {
+    #[inline]
+    fn new(_: Destroy) -> Self {
+        Self {
+            strong: Cell::new(1),
+            weak: Cell::new(1),
+        }
+    }
+    #[inline]
+    fn strong(&self) -> usize {
+        self.strong.get()
+    }
+    #[inline]
+    fn inc_strong(&self) {
+        let n = self.strong.get();
+        assert!(n < MAX_REFCOUNT, "reference count overflow");
+        self.strong.set(n + 1);
+    }
+    #[inline]
+    fn dec_strong(&self) -> bool {
+        let n = self.strong.get() - 1;
+        self.strong.set(n);
+        n == 0
+    }
+    #[inline]
+    fn try_take(&self) -> bool {
+        let unique = self.strong.get() == 1;
+        if unique {
+            self.strong.set(0);
+        }
+        unique
+    }
+    #[inline]
+    fn is_unique(&self) -> bool {
+        self.strong.get() == 1 && self.weak.get() == 1
+    }
+    #[inline]
+    fn release_weak(&self) -> bool {
+        let n = self.weak.get() - 1;
+        self.weak.set(n);
+        n == 0
+    }
+}
+
+unsafe impl WeakCounts for LocalCounts {
+    #[inline]
+    fn new_cyclic(_: Destroy) -> Self {
+        Self {
+            strong: Cell::new(0),
+            weak: Cell::new(1),
+        }
+    }
+    #[inline]
+    fn init_strong(&self) {
+        self.strong.set(1);
+    }
+    #[inline]
+    fn weak(&self) -> usize {
+        self.weak.get() - 1
+    }
+    #[inline]
+    fn inc_weak(&self) {
+        let n = self.weak.get();
+        assert!(n < MAX_REFCOUNT, "reference count overflow");
+        self.weak.set(n + 1);
+    }
+    #[inline]
+    fn upgrade(&self) -> bool {
+        let n = self.strong.get();
+        if n == 0 {
+            return false;
+        }
+        self.inc_strong();
+        true
+    }
+}
+
+/// Atomic strong and weak counts, the same scheme as `alloc::sync::Arc`.
+pub struct AtomicCounts {
+    strong: AtomicUsize,
+    /// `usize::MAX` while `is_unique` has it locked
+    weak: AtomicUsize,
+}
+
+unsafe impl Counts for AtomicCounts {
+    #[inline]
+    fn new(_: Destroy) -> Self {
+        Self {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+        }
+    }
+    #[inline]
+    fn strong(&self) -> usize {
+        self.strong.load(Acquire)
+    }
+    #[inline]
+    fn inc_strong(&self) {
+        let old = self.strong.fetch_add(1, Relaxed);
+        assert!(old < MAX_REFCOUNT, "reference count overflow");
+    }
+    #[inline]
+    fn dec_strong(&self) -> bool {
+        if self.strong.fetch_sub(1, Release) != 1 {
+            return false;
+        }
+        // see every write made through the other references before dropping the value
+        atomic::fence(Acquire);
+        true
+    }
+    #[inline]
+    fn try_take(&self) -> bool {
+        let taken = self.strong.compare_exchange(1, 0, Relaxed, Relaxed).is_ok();
+        if taken {
+            atomic::fence(Acquire);
+        }
+        taken
+    }
+    fn is_unique(&self) -> bool {
+        // lock the weak count so nobody can downgrade while the strong count is checked
+        if self
+            .weak
+            .compare_exchange(1, usize::MAX, Acquire, Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        let unique = self.strong.load(Acquire) == 1;
+        self.weak.store(1, Release);
+        unique
+    }
+    #[inline]
+    fn release_weak(&self) -> bool {
+        if self.weak.fetch_sub(1, Release) != 1 {
+            return false;
+        }
+        atomic::fence(Acquire);
+        true
+    }
+}
+
+unsafe impl WeakCounts for AtomicCounts {
+    #[inline]
+    fn new_cyclic(_: Destroy) -> Self {
+        Self {
+            strong: AtomicUsize::new(0),
+            weak: AtomicUsize::new(1),
+        }
+    }
+    #[inline]
+    fn init_strong(&self) {
+        self.strong.store(1, Release);
+    }
+    fn weak(&self) -> usize {
+        let weak = self.weak.load(Acquire);
+        if weak == usize::MAX {
+            // locked by `is_unique`, which only happens when there are no weak pointers
+            0
+        } else {
+            weak - 1
+        }
+    }
+    fn inc_weak(&self) {
+        let mut cur = self.weak.load(Relaxed);
+        loop {
+            if cur == usize::MAX {
+                core::hint::spin_loop();
+                cur = self.weak.load(Relaxed);
+                continue;
+            }
+            assert!(cur < MAX_REFCOUNT, "reference count overflow");
+            match self
+                .weak
+                .compare_exchange_weak(cur, cur + 1, Acquire, Relaxed)
+            {
+                Ok(_) => return,
+                Err(old) => cur = old,
+            }
+        }
+    }
+    fn upgrade(&self) -> bool {
+        let mut cur = self.strong.load(Relaxed);
+        loop {
+            if cur == 0 {
+                return false;
+            }
+            assert!(cur < MAX_REFCOUNT, "reference count overflow");
+            match self
+                .strong
+                .compare_exchange_weak(cur, cur + 1, Acquire, Relaxed)
+            {
+                Ok(_) => return true,
+                Err(old) => cur = old,
+            }
+        }
+    }
+}
+
+/// A non atomic strong count without weak references.
+pub struct LocalStrongCount {
+    strong: Cell<usize>,
+}
+
+unsafe impl Counts for LocalStrongCount {
+    #[inline]
+    fn new(_: Destroy) -> Self {
+        Self {
+            strong: Cell::new(1),
+        }
+    }
+    #[inline]
+    fn strong(&self) -> usize {
+        self.strong.get()
+    }
+    #[inline]
+    fn inc_strong(&self) {
+        let n = self.strong.get();
+        assert!(n < MAX_REFCOUNT, "reference count overflow");
+        self.strong.set(n + 1);
+    }
+    #[inline]
+    fn dec_strong(&self) -> bool {
+        let n = self.strong.get() - 1;
+        self.strong.set(n);
+        n == 0
+    }
+    #[inline]
+    fn try_take(&self) -> bool {
+        let unique = self.strong.get() == 1;
+        if unique {
+            self.strong.set(0);
+        }
+        unique
+    }
+    #[inline]
+    fn is_unique(&self) -> bool {
+        self.strong.get() == 1
+    }
+}
+
+/// An atomic strong count without weak references.
+pub struct AtomicStrongCount {
+    strong: AtomicUsize,
+}
+
+unsafe impl Counts for AtomicStrongCount {
+    #[inline]
+    fn new(_: Destroy) -> Self {
+        Self {
+            strong: AtomicUsize::new(1),
+        }
+    }
+    #[inline]
+    fn strong(&self) -> usize {
+        self.strong.load(Acquire)
+    }
+    #[inline]
+    fn inc_strong(&self) {
+        let old = self.strong.fetch_add(1, Relaxed);
+        assert!(old < MAX_REFCOUNT, "reference count overflow");
+    }
+    #[inline]
+    fn dec_strong(&self) -> bool {
+        if self.strong.fetch_sub(1, Release) != 1 {
+            return false;
+        }
+        atomic::fence(Acquire);
+        true
+    }
+    #[inline]
+    fn try_take(&self) -> bool {
+        let taken = self.strong.compare_exchange(1, 0, Relaxed, Relaxed).is_ok();
+        if taken {
+            atomic::fence(Acquire);
+        }
+        taken
+    }
+    #[inline]
+    fn is_unique(&self) -> bool {
+        self.strong.load(Acquire) == 1
+    }
+}
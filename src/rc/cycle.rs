@@ -0,0 +1,591 @@
+//! Cycle collection for [`CcRc`], synchronous trial deletion after Bacon and Rajan.
+//!
+//! Every time a strong count drops without reaching zero the allocation might have become the
+//! only thing keeping a cycle alive, so it is remembered as a possible root. [`collect_cycles`]
+//! then subtracts the references the values hold among themselves: whatever ends up at zero is
+//! only reachable from garbage and gets dropped.
+//!
+//! The collector only sees references that [`Trace`] reports, and only for values created with
+//! [`Rc::new_traced`]. Everything else looks like it is referenced from outside, which keeps it
+//! (and whatever it points to) alive.
+
+use super::counts::{Counts, Destroy};
+use super::{CcRc, Rc, RcInner};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{
+    alloc::{AllocError, Allocator},
+    cell::{Cell, RefCell},
+    ptr::{self, NonNull},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Color {
+    /// in use
+    Black,
+    /// a possible root
+    Purple,
+    /// being checked
+    Gray,
+    /// garbage
+    White,
+    /// dropped by the collector, releasing it doesn't free anything anymore
+    Dead,
+}
+
+/// reports the children of the value in the allocation
+type TraceFn = unsafe fn(*const (), usize, &mut Tracer<'_>);
+
+/// Counts for [`CcRc`].
+pub struct CycleCounts {
+    strong: Cell<usize>,
+    color: Cell<Color>,
+    /// sitting in `ROOTS`, the collector frees it if the value goes away meanwhile
+    buffered: Cell<bool>,
+    trace: Cell<Option<TraceFn>>,
+    destroy: Cell<Destroy>,
+}
+
+#[thread_local]
+static ROOTS: RefCell<Vec<NonNull<CycleCounts>>> = RefCell::new(Vec::new());
+
+impl CycleCounts {
+    fn possible_root(&self) {
+        if self.trace.get().is_none() || self.color.get() == Color::Purple {
+            return;
+        }
+        self.color.set(Color::Purple);
+        if !self.buffered.get() {
+            self.buffered.set(true);
+            ROOTS.borrow_mut().push(NonNull::from(self));
+        }
+    }
+
+    /// calls `f` on every child of the value
+    unsafe fn children(this: NonNull<Self>, mut f: impl FnMut(NonNull<Self>)) {
+        let counts = this.as_ref();
+        if let Some(trace) = counts.trace.get() {
+            let (_, meta) = counts.destroy.get().into_parts();
+            trace(this.as_ptr() as *const (), meta, &mut Tracer { f: &mut f });
+        }
+    }
+}
+
+unsafe impl Counts for CycleCounts {
+    fn new(destroy: Destroy) -> Self {
+        Self {
+            strong: Cell::new(1),
+            color: Cell::new(Color::Black),
+            buffered: Cell::new(false),
+            trace: Cell::new(None),
+            destroy: Cell::new(destroy),
+        }
+    }
+    #[inline]
+    fn strong(&self) -> usize {
+        self.strong.get()
+    }
+    #[inline]
+    fn inc_strong(&self) {
+        let n = self.strong.get();
+        assert!(n < isize::MAX as usize, "reference count overflow");
+        self.strong.set(n + 1);
+        if self.color.get() != Color::Dead {
+            self.color.set(Color::Black);
+        }
+    }
+    fn dec_strong(&self) -> bool {
+        let n = self.strong.get() - 1;
+        self.strong.set(n);
+        match self.color.get() {
+            Color::Dead => false,
+            _ if n == 0 => {
+                self.color.set(Color::Black);
+                true
+            }
+            _ => {
+                self.possible_root();
+                false
+            }
+        }
+    }
+    #[inline]
+    fn try_take(&self) -> bool {
+        let unique = self.strong.get() == 1;
+        if unique {
+            self.strong.set(0);
+            self.color.set(Color::Black);
+        }
+        unique
+    }
+    #[inline]
+    fn is_unique(&self) -> bool {
+        self.strong.get() == 1
+    }
+    #[inline]
+    fn release_weak(&self) -> bool {
+        // a buffered allocation is freed by the collector once it gets to it
+        !self.buffered.get()
+    }
+    fn retype(&self, destroy: Destroy) {
+        self.destroy.set(destroy);
+    }
+    fn cloned_from(&self, original: &Self) {
+        // same value type and allocator, so the same trace function works
+        self.trace.set(original.trace.get());
+    }
+}
+
+/// Reports the [`Rc<_, CcRc>`](Rc) pointers a value owns to the cycle collector.
+///
+/// # Safety
+/// [`trace`](Trace::trace) has to visit the same pointers every time it's called, and only
+/// pointers the value owns, each at most once. Leaving some out is fine, it only keeps cycles
+/// through them alive.
+///
+/// When a cycle is collected the values in it are dropped one after the other, so their `Drop`
+/// impls must not look at the values behind the traced pointers.
+pub unsafe trait Trace {
+    fn trace(&self, tracer: &mut Tracer<'_>);
+}
+
+/// Collects the pointers reported by [`Trace`].
+pub struct Tracer<'a> {
+    f: &'a mut dyn FnMut(NonNull<CycleCounts>),
+}
+
+impl Tracer<'_> {
+    #[inline]
+    pub fn visit<T: ?Sized, A: Allocator>(&mut self, rc: &Rc<T, CcRc, A>) {
+        // the counts are at the start of the allocation
+        (self.f)(rc.ptr.cast())
+    }
+}
+
+unsafe fn trace_value<T: Trace + ?Sized, A>(data: *const (), meta: usize, tracer: &mut Tracer<'_>) {
+    let inner = ptr::from_raw_parts::<T>(data, super::counts::unerase_meta(meta))
+        as *const RcInner<CycleCounts, A, T>;
+    (*inner).value.trace(tracer)
+}
+
+impl<T: Trace + 'static> Rc<T, CcRc> {
+    /// Creates a value whose cycles [`collect_cycles`] can reclaim.
+    ///
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new_traced(value: T) -> Self {
+        Self::new_traced_in(value, alloc::alloc::Global).expect("failed to allocate")
+    }
+}
+
+impl<T: Trace + 'static, A: Allocator> Rc<T, CcRc, A> {
+    pub fn new_traced_in(value: T, alloc: A) -> Result<Self, AllocError> {
+        let rc = Self::new_in(value, alloc)?;
+        rc.counts().trace.set(Some(trace_value::<T, A>));
+        Ok(rc)
+    }
+}
+
+/// Frees every cycle of [`Rc<_, CcRc>`](Rc) on this thread that isn't referenced from outside.
+///
+/// Possible roots are remembered per thread, a cycle dropped on another thread has to be
+/// collected there.
+pub fn collect_cycles() {
+    let roots = core::mem::take(&mut *ROOTS.borrow_mut());
+    if roots.is_empty() {
+        return;
+    }
+    let mut stack = Vec::new();
+    let mut candidates = Vec::new();
+    unsafe {
+        for s in roots {
+            let c = s.as_ref();
+            if c.color.get() == Color::Purple && c.strong.get() > 0 {
+                mark_gray(s, &mut stack);
+                candidates.push(s);
+            } else {
+                c.buffered.set(false);
+                if c.color.get() == Color::Black && c.strong.get() == 0 {
+                    // the value went away while it was buffered
+                    c.destroy.get().dealloc(s.as_ptr());
+                }
+            }
+        }
+        for &s in &candidates {
+            scan(s, &mut stack);
+        }
+        let mut white = Vec::new();
+        for &s in &candidates {
+            s.as_ref().buffered.set(false);
+        }
+        for s in candidates {
+            collect_white(s, &mut stack, &mut white);
+        }
+        for &s in &white {
+            s.as_ref().color.set(Color::Dead);
+        }
+        // give back the references trial deletion took away, dropping the values releases them
+        // for real
+        for &s in &white {
+            CycleCounts::children(s, |c| {
+                let c = c.as_ref();
+                c.strong.set(c.strong.get() + 1);
+            });
+        }
+        for &s in &white {
+            s.as_ref().destroy.get().drop_value(s.as_ptr());
+        }
+        for s in white {
+            s.as_ref().destroy.get().dealloc(s.as_ptr());
+        }
+    }
+}
+
+/// takes away the references from everything reachable from `root`
+unsafe fn mark_gray(root: NonNull<CycleCounts>, stack: &mut Vec<NonNull<CycleCounts>>) {
+    stack.push(root);
+    while let Some(s) = stack.pop() {
+        let c = s.as_ref();
+        if c.color.get() == Color::Gray {
+            continue;
+        }
+        c.color.set(Color::Gray);
+        CycleCounts::children(s, |child| {
+            let child_counts = child.as_ref();
+            child_counts.strong.set(child_counts.strong.get() - 1);
+            stack.push(child);
+        });
+    }
+}
+
+/// whatever still has references left is alive, and so is everything it points to
+unsafe fn scan(root: NonNull<CycleCounts>, stack: &mut Vec<NonNull<CycleCounts>>) {
+    stack.push(root);
+    while let Some(s) = stack.pop() {
+        let c = s.as_ref();
+        if c.color.get() != Color::Gray {
+            continue;
+        }
+        if c.strong.get() > 0 {
+            scan_black(s);
+        } else {
+            c.color.set(Color::White);
+            CycleCounts::children(s, |child| stack.push(child));
+        }
+    }
+}
+
+unsafe fn scan_black(root: NonNull<CycleCounts>) {
+    root.as_ref().color.set(Color::Black);
+    let mut stack = Vec::from([root]);
+    while let Some(s) = stack.pop() {
+        CycleCounts::children(s, |child| {
+            let c = child.as_ref();
+            c.strong.set(c.strong.get() + 1);
+            if c.color.get() != Color::Black {
+                c.color.set(Color::Black);
+                stack.push(child);
+            }
+        });
+    }
+}
+
+unsafe fn collect_white(
+    root: NonNull<CycleCounts>,
+    stack: &mut Vec<NonNull<CycleCounts>>,
+    white: &mut Vec<NonNull<CycleCounts>>,
+) {
+    stack.push(root);
+    while let Some(s) = stack.pop() {
+        let c = s.as_ref();
+        if c.color.get() != Color::White || c.buffered.get() {
+            continue;
+        }
+        c.color.set(Color::Black);
+        white.push(s);
+        CycleCounts::children(s, |child| stack.push(child));
+    }
+}
+
+unsafe impl<T: ?Sized, A: Allocator> Trace for Rc<T, CcRc, A> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        tracer.visit(self)
+    }
+}
+
+macro_rules! trace_leaf {
+    ($($t:ty),*) => {
+        $(unsafe impl Trace for $t {
+            #[inline(always)]
+            fn trace(&self, _: &mut Tracer<'_>) {}
+        })*
+    };
+}
+
+trace_leaf!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    str,
+    String
+);
+
+unsafe impl<T: Trace> Trace for Option<T> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        if let Some(v) = self {
+            v.trace(tracer)
+        }
+    }
+}
+
+unsafe impl<T: Trace, E: Trace> Trace for Result<T, E> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        match self {
+            Ok(v) => v.trace(tracer),
+            Err(e) => e.trace(tracer),
+        }
+    }
+}
+
+unsafe impl<T: Trace> Trace for [T] {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.iter().for_each(|v| v.trace(tracer))
+    }
+}
+
+unsafe impl<T: Trace, const N: usize> Trace for [T; N] {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.as_slice().trace(tracer)
+    }
+}
+
+unsafe impl<T: Trace, A: Allocator> Trace for Vec<T, A> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.as_slice().trace(tracer)
+    }
+}
+
+unsafe impl<T: Trace + ?Sized, A: Allocator> Trace for Box<T, A> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        (**self).trace(tracer)
+    }
+}
+
+unsafe impl<T: Trace + ?Sized> Trace for RefCell<T> {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        // nothing runs while the collector traces, so a borrow can't come or go in between
+        if let Ok(v) = self.try_borrow() {
+            v.trace(tracer)
+        }
+    }
+}
+
+unsafe impl<A: Trace, B: Trace> Trace for (A, B) {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.0.trace(tracer);
+        self.1.trace(tracer);
+    }
+}
+
+unsafe impl<A: Trace, B: Trace, C: Trace> Trace for (A, B, C) {
+    fn trace(&self, tracer: &mut Tracer<'_>) {
+        self.0.trace(tracer);
+        self.1.trace(tracer);
+        self.2.trace(tracer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::FxState;
+    use crate::hash_table::SCHashTable;
+    use crate::linked_lists::{DoublyLinkedList, SinglyLinkedList};
+    use crate::queue::ArrayQueue;
+    use crate::traits::adt::Creatable;
+    use crate::traits::hash_table::HashTable;
+    use std::rc::Rc as StdRc;
+
+    /// bumps the counter when dropped
+    struct Dropper(StdRc<Cell<usize>>);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    unsafe impl Trace for Dropper {
+        fn trace(&self, _: &mut Tracer<'_>) {}
+    }
+
+    struct Node {
+        next: RefCell<Option<Rc<Node, CcRc>>>,
+        _drop: Dropper,
+    }
+
+    unsafe impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer<'_>) {
+            self.next.trace(tracer)
+        }
+    }
+
+    fn node(drops: &StdRc<Cell<usize>>) -> Rc<Node, CcRc> {
+        Rc::new_traced(Node {
+            next: RefCell::new(None),
+            _drop: Dropper(drops.clone()),
+        })
+    }
+
+    /// a ring of `n` nodes, returns one of them
+    fn ring(n: usize, drops: &StdRc<Cell<usize>>) -> Rc<Node, CcRc> {
+        let first = node(drops);
+        let mut last = first.clone();
+        for _ in 1..n {
+            let next = node(drops);
+            *last.next.borrow_mut() = Some(next.clone());
+            last = next;
+        }
+        *last.next.borrow_mut() = Some(first.clone());
+        first
+    }
+
+    #[test]
+    fn collects_rings() {
+        let drops = StdRc::new(Cell::new(0));
+        let r = ring(2, &drops);
+        let kept = ring(3, &drops);
+        drop(r);
+        collect_cycles();
+        assert_eq!(drops.get(), 2);
+        // still referenced from here
+        assert_eq!(Rc::strong_count(&kept), 2);
+        drop(kept);
+        collect_cycles();
+        assert_eq!(drops.get(), 5);
+
+        // long enough that recursion would blow the stack
+        drop(ring(100_000, &drops));
+        collect_cycles();
+        assert_eq!(drops.get(), 100_005);
+    }
+
+    #[test]
+    fn garbage_releases_live_values() {
+        let drops = StdRc::new(Cell::new(0));
+        let live = node(&drops);
+        let r = ring(2, &drops);
+        let side = node(&drops);
+        *side.next.borrow_mut() = Some(live.clone());
+        *r.next.borrow().as_ref().unwrap().next.borrow_mut() = Some(side);
+        // r -> b -> side -> live, the ring is broken now but `live` is shared
+        drop(r);
+        collect_cycles();
+        assert_eq!(drops.get(), 3);
+        assert_eq!(Rc::strong_count(&live), 1);
+        drop(live);
+        assert_eq!(drops.get(), 4);
+        // dropped values that were waiting as roots get freed too
+        collect_cycles();
+    }
+
+    #[test]
+    fn containers() {
+        struct Holder {
+            queue: RefCell<ArrayQueue<Rc<Holder, CcRc>>>,
+            list: RefCell<SinglyLinkedList<Rc<Holder, CcRc>>>,
+            dlist: RefCell<DoublyLinkedList<Rc<Holder, CcRc>>>,
+            table: RefCell<SCHashTable<u32, Rc<Holder, CcRc>, FxState>>,
+            _drop: Dropper,
+        }
+        unsafe impl Trace for Holder {
+            fn trace(&self, tracer: &mut Tracer<'_>) {
+                self.queue.trace(tracer);
+                self.list.trace(tracer);
+                self.dlist.trace(tracer);
+                self.table.trace(tracer);
+            }
+        }
+        let drops = StdRc::new(Cell::new(0));
+        let new = || {
+            Rc::new_traced(Holder {
+                queue: RefCell::new(ArrayQueue::new()),
+                list: RefCell::new(SinglyLinkedList::new()),
+                dlist: RefCell::new(DoublyLinkedList::new()),
+                table: RefCell::new(SCHashTable::new()),
+                _drop: Dropper(drops.clone()),
+            })
+        };
+        let (a, b, c, d) = (new(), new(), new(), new());
+        a.queue.borrow_mut().push_back(b.clone()).unwrap();
+        b.list.borrow_mut().push(c.clone());
+        c.dlist.borrow_mut().push_back(a.clone()).unwrap();
+        c.dlist.borrow_mut().push_back(c.clone()).unwrap();
+        // `d` is only reachable through hash buckets
+        c.table.borrow_mut().insert(0, d.clone()).unwrap();
+        d.table.borrow_mut().insert(1, a.clone()).unwrap();
+        drop((a, b, c, d));
+        assert_eq!(drops.get(), 0);
+        collect_cycles();
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn make_mut_keeps_tracing() {
+        #[derive(Clone)]
+        struct Link {
+            next: RefCell<Option<Rc<Link, CcRc>>>,
+            _drop: Dropper,
+        }
+        unsafe impl Trace for Link {
+            fn trace(&self, tracer: &mut Tracer<'_>) {
+                self.next.trace(tracer)
+            }
+        }
+        impl Clone for Dropper {
+            fn clone(&self) -> Self {
+                Dropper(self.0.clone())
+            }
+        }
+        let drops = StdRc::new(Cell::new(0));
+        let mut a = Rc::new_traced(Link {
+            next: RefCell::new(None),
+            _drop: Dropper(drops.clone()),
+        });
+        let b = a.clone();
+        // shared, so this clones into a new allocation that the collector still has to see into
+        Rc::make_mut(&mut a);
+        *a.next.borrow_mut() = Some(a.clone());
+        drop((a, b));
+        assert_eq!(drops.get(), 1);
+        collect_cycles();
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn untraced_values_stay() {
+        let drops = StdRc::new(Cell::new(0));
+        // made with the plain constructor, so the collector can't see inside
+        let a: Rc<Node, CcRc> = Rc::new(Node {
+            next: RefCell::new(None),
+            _drop: Dropper(drops.clone()),
+        });
+        *a.next.borrow_mut() = Some(a.clone());
+        drop(a);
+        collect_cycles();
+        assert_eq!(drops.get(), 0);
+    }
+}
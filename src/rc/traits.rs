@@ -0,0 +1,221 @@
+//! The std traits for [`Rc`] and [`WeakStub`].
+
+use super::*;
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    ffi::CString,
+    string::String,
+    vec::Vec,
+};
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    ffi::CStr,
+    fmt::{self, Debug, Display, Formatter, Pointer},
+    hash::{Hash, Hasher},
+    marker::Unsize,
+    ops::CoerceUnsized,
+};
+
+impl<T: ?Sized + Unsize<U>, U: ?Sized, S: RcStub, A: Allocator> CoerceUnsized<Rc<U, S, A>>
+    for Rc<T, S, A>
+{
+}
+impl<T: ?Sized + Unsize<U>, U: ?Sized, S: RcStub, A: Allocator> CoerceUnsized<WeakStub<U, S, A>>
+    for WeakStub<T, S, A>
+{
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> Unpin for Rc<T, S, A> {}
+
+impl<T: ?Sized + Debug, S: RcStub, A: Allocator> Debug for Rc<T, S, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + Display, S: RcStub, A: Allocator> Display for Rc<T, S, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> Pointer for Rc<T, S, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Pointer::fmt(&Rc::as_ptr(self), f)
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> Debug for WeakStub<T, S, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("(Weak)")
+    }
+}
+
+impl<T: Default, S: RcStub> Default for Rc<T, S> {
+    fn default() -> Self {
+        Rc::new(T::default())
+    }
+}
+
+impl<S: RcStub> Default for Rc<str, S> {
+    fn default() -> Self {
+        Rc::from("")
+    }
+}
+
+impl<T, S: RcStub> Default for Rc<[T], S> {
+    fn default() -> Self {
+        Rc::from_exact_iter([])
+    }
+}
+
+impl<T: ?Sized + Hash, S: RcStub, A: Allocator> Hash for Rc<T, S, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<T: ?Sized + PartialEq, S: RcStub, A: Allocator> PartialEq for Rc<T, S, A> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq, S: RcStub, A: Allocator> Eq for Rc<T, S, A> {}
+
+impl<T: ?Sized + PartialOrd, S: RcStub, A: Allocator> PartialOrd for Rc<T, S, A> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<T: ?Sized + Ord, S: RcStub, A: Allocator> Ord for Rc<T, S, A> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> AsRef<T> for Rc<T, S, A> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> Borrow<T> for Rc<T, S, A> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T, S: RcStub> From<T> for Rc<T, S> {
+    fn from(value: T) -> Self {
+        Rc::new(value)
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> From<Box<T, A>> for Rc<T, S, A> {
+    /// Moves the value out of the box into a new allocation from the same allocator.
+    fn from(value: Box<T, A>) -> Self {
+        let layout = Layout::for_value::<T>(&value);
+        // allocated before the box is taken apart, so a failure can still drop it
+        let mem = match Box::allocator(&value).allocate(inner_layout::<S::Counts, A>(layout).0) {
+            Ok(mem) => mem.cast(),
+            Err(_) => {
+                drop(value);
+                panic!("failed to allocate");
+            }
+        };
+        let (src, alloc) = Box::into_raw_with_allocator(value);
+        unsafe {
+            let ptr = write_header::<T, _, A>(mem, ptr::metadata(src), alloc, S::Counts::new);
+            let dst = ptr::addr_of_mut!((*ptr.as_ptr()).value);
+            ptr::copy_nonoverlapping(src as *const u8, dst as *mut u8, layout.size());
+            let rc = Self::from_inner(ptr);
+            // free the box without dropping the value that moved out of it
+            if layout.size() != 0 {
+                Rc::allocator(&rc).deallocate(NonNull::new_unchecked(src as *mut u8), layout);
+            }
+            rc
+        }
+    }
+}
+
+impl<T, S: RcStub> From<Vec<T>> for Rc<[T], S> {
+    fn from(value: Vec<T>) -> Self {
+        Rc::from_exact_iter(value)
+    }
+}
+
+impl<T: Clone, S: RcStub> From<&[T]> for Rc<[T], S> {
+    fn from(value: &[T]) -> Self {
+        Rc::from_exact_iter(value.iter().cloned())
+    }
+}
+
+impl<S: RcStub> From<&str> for Rc<str, S> {
+    fn from(value: &str) -> Self {
+        let bytes = Rc::<[u8], S>::from(value.as_bytes());
+        unsafe { Rc::from_raw(Rc::into_raw(bytes) as *const str) }
+    }
+}
+
+impl<S: RcStub> From<String> for Rc<str, S> {
+    fn from(value: String) -> Self {
+        Rc::from(value.as_str())
+    }
+}
+
+impl<S: RcStub, A: Allocator> From<Rc<str, S, A>> for Rc<[u8], S, A> {
+    fn from(value: Rc<str, S, A>) -> Self {
+        unsafe { Rc::from_raw(Rc::into_raw(value) as *const [u8]) }
+    }
+}
+
+impl<S: RcStub> From<&CStr> for Rc<CStr, S> {
+    fn from(value: &CStr) -> Self {
+        let bytes = Rc::<[u8], S>::from(value.to_bytes_with_nul());
+        unsafe { Rc::from_raw(Rc::into_raw(bytes) as *const CStr) }
+    }
+}
+
+impl<S: RcStub> From<CString> for Rc<CStr, S> {
+    fn from(value: CString) -> Self {
+        Rc::from(value.as_c_str())
+    }
+}
+
+impl<'a, B, S: RcStub> From<Cow<'a, B>> for Rc<B, S>
+where
+    B: ToOwned + ?Sized,
+    Rc<B, S>: From<&'a B> + From<B::Owned>,
+{
+    fn from(value: Cow<'a, B>) -> Self {
+        match value {
+            Cow::Borrowed(b) => Rc::from(b),
+            Cow::Owned(o) => Rc::from(o),
+        }
+    }
+}
+
+impl<T, S: RcStub, A: Allocator, const N: usize> TryFrom<Rc<[T], S, A>> for Rc<[T; N], S, A> {
+    type Error = Rc<[T], S, A>;
+
+    fn try_from(value: Rc<[T], S, A>) -> Result<Self, Self::Error> {
+        if value.len() == N {
+            Ok(unsafe { Rc::from_raw(Rc::into_raw(value) as *const [T; N]) })
+        } else {
+            Err(value)
+        }
+    }
+}
+
+impl<T, S: RcStub> FromIterator<T> for Rc<[T], S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Rc::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
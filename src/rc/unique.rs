@@ -0,0 +1,73 @@
+use super::{Rc, RcStub, StRc};
+use alloc::alloc::Global;
+use core::{
+    alloc::{AllocError, Allocator},
+    fmt::{self, Debug, Formatter},
+    ops::{Deref, DerefMut},
+};
+
+/// An [`Rc`] that nobody else can see yet, so it can be changed freely before it's handed out
+/// with [`into_rc`](UniqueRc::into_rc).
+///
+/// # Examples
+/// ```
+/// use hash_table::rc::{Rc, UniqueRc};
+/// let mut v: UniqueRc<Vec<i32>> = UniqueRc::new(vec![]);
+/// v.push(1);
+/// let shared: Rc<Vec<i32>> = UniqueRc::into_rc(v);
+/// assert_eq!(*shared, [1]);
+/// ```
+pub struct UniqueRc<T: ?Sized, S: RcStub = StRc, A: Allocator = Global>(Rc<T, S, A>);
+
+impl<T, S: RcStub> UniqueRc<T, S> {
+    /// # Panics
+    /// Panics if allocating fails.
+    pub fn new(value: T) -> Self {
+        Self(Rc::new(value))
+    }
+}
+
+impl<T, S: RcStub, A: Allocator> UniqueRc<T, S, A> {
+    pub fn new_in(value: T, alloc: A) -> Result<Self, AllocError> {
+        Rc::new_in(value, alloc).map(Self)
+    }
+
+    /// Works from any thread, the counts' `try_take` has to succeed for the only reference even
+    /// when it was moved away from the thread that made it.
+    pub fn into_inner(this: Self) -> T {
+        match Rc::try_unwrap(this.0) {
+            Ok(value) => value,
+            Err(_) => unreachable!("a UniqueRc was shared"),
+        }
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> UniqueRc<T, S, A> {
+    /// Gives up the uniqueness so the value can be shared.
+    #[inline]
+    pub fn into_rc(this: Self) -> Rc<T, S, A> {
+        this.0
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> Deref for UniqueRc<T, S, A> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized, S: RcStub, A: Allocator> DerefMut for UniqueRc<T, S, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // there is no other reference of any kind
+        unsafe { &mut self.0.ptr.as_mut().value }
+    }
+}
+
+impl<T: ?Sized + Debug, S: RcStub, A: Allocator> Debug for UniqueRc<T, S, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
@@ -1,24 +1,50 @@
 //#![recursion_limit = "1000"]
 
-/// Abstract Data Type Traits
-trait Creatable {
+//! Abstract Data Type Traits
+//!
+//! Lets algorithms be written against "any list" or "any priority queue" so the containers can be
+//! swapped out, in benchmarks for instance.
+
+use core::{alloc::AllocError, cmp::Ordering};
+
+/// Collections that can start out empty.
+pub trait Creatable {
     fn new() -> Self;
 }
 
-trait List<T> {
-    fn new() -> Self;
-    fn is_empty(&self) -> bool;
-    fn append(&mut self, item: T);
-    fn prepend(&mut self, item: T);
+/// A sequence with a front and a back.
+pub trait List<T> {
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// adds to the back
+    fn try_append(&mut self, item: T) -> Result<(), AllocError>;
+    /// adds to the front
+    fn try_prepend(&mut self, item: T) -> Result<(), AllocError>;
+    fn append(&mut self, item: T) {
+        self.try_append(item).expect("failed to allocate")
+    }
+    fn prepend(&mut self, item: T) {
+        self.try_prepend(item).expect("failed to allocate")
+    }
+    /// the first element
     fn head(&self) -> Option<&T>;
-    fn tail(&mut self); // simply drops the first element
-    fn get(&self, index: usize);
+    /// Removes the head so only the tail is left, and hands the head back.
+    fn tail(&mut self) -> Option<T>;
+    fn get(&self, index: usize) -> Option<&T>;
 }
 
-trait PriorityQueue<T, P: Ord> {
-    fn is_empty(&self) -> bool;
+/// The most urgent element is the one the heap's [`HeapKind`] puts on top, so the smallest
+/// priority for a [`MinHeap`].
+pub trait PriorityQueue<T, P: Ord> {
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn insert_with_priority(&mut self, item: T, priority: P);
-    fn pull_highest_priority_element(&mut self) -> (T, P);
+    fn peek_highest_priority_element(&self) -> Option<(&T, &P)>;
+    fn pull_highest_priority_element(&mut self) -> Option<(T, P)>;
 }
 
 /*
@@ -30,49 +56,60 @@ trait Tree<T> {
 }
 */
 
-trait HeapKind {}
-struct MinHeap;
-struct MaxHeap;
-impl HeapKind for MinHeap {}
-impl HeapKind for MaxHeap {}
-
-trait Heap<T, K>
-where
-    Self: Sized,
-    K: HeapKind,
-{
+/// Which end of the order a [`Heap`] hands out first.
+pub trait HeapKind {
+    /// `true` if `a` should come out before `b`
+    fn before<T: Ord>(a: &T, b: &T) -> bool;
+}
+pub struct MinHeap;
+pub struct MaxHeap;
+impl HeapKind for MinHeap {
+    fn before<T: Ord>(a: &T, b: &T) -> bool {
+        a < b
+    }
+}
+impl HeapKind for MaxHeap {
+    fn before<T: Ord>(a: &T, b: &T) -> bool {
+        a > b
+    }
+}
+
+pub trait Heap<T: Ord>: Creatable + Sized {
+    type Kind: HeapKind;
     //basic:
-    fn find_max(&self) -> T;
+    /// the top without removing it
+    fn peek(&self) -> Option<&T>;
     fn insert(&mut self, item: T);
-    fn extract_max(&mut self) -> Option<T>;
-    fn delete_max(&mut self);
-    fn replace(&mut self);
+    /// removes the top
+    fn extract(&mut self) -> Option<T>;
+    fn delete(&mut self) {
+        self.extract();
+    }
+    /// Removes the top and inserts `item`, cheaper than doing both when the heap can sift once.
+    fn replace(&mut self, item: T) -> Option<T> {
+        let top = self.extract();
+        self.insert(item);
+        top
+    }
     //creation:
-    fn new() -> Self;
     //fn heapify() impl from iter
-    /*
-    fn merge(&self, other: &Self) -> Self where Self: Clone + IntoIterator<Item = T> + FromIterator<T>, T: Clone {
-        Self::from_iter(self.clone().into_iter().chain(other.clone().into_iter()))
-    }*/
     fn merge(&self, other: &Self) -> Self
     where
         Self: Clone,
-        T: Clone,
     {
-        let mut new = Self::new();
-        new.meld(self.clone());
+        let mut new = self.clone();
         new.meld(other.clone());
         new
     }
     fn meld(&mut self, mut other: Self) {
-        while let Some(s) = other.extract_max() {
+        while let Some(s) = other.extract() {
             self.insert(s);
         }
     }
     //inspection:
-    fn size(&self) -> usize;
+    fn len(&self) -> usize;
     fn is_empty(&self) -> bool {
-        self.size() == 0
+        self.len() == 0
     }
     //internal:
     //fn increase_key()
@@ -85,20 +122,64 @@ where
     */
 }
 
-trait Set<T> {
-    fn union(s: Self, t: Self);
-    fn intersection(s: Self, t: Self);
-    fn difference(s: Self, t: Self);
-    fn subset(s: Self, t: Self);
+/// An item that's ordered by its priority alone, which makes any [`Heap`] of them a
+/// [`PriorityQueue`].
+#[derive(Clone, Copy, Debug)]
+pub struct Prioritized<T, P> {
+    pub priority: P,
+    pub item: T,
+}
+
+impl<T, P: PartialEq> PartialEq for Prioritized<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<T, P: Eq> Eq for Prioritized<T, P> {}
+
+impl<T, P: PartialOrd> PartialOrd for Prioritized<T, P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.priority.partial_cmp(&other.priority)
+    }
+}
+
+impl<T, P: Ord> Ord for Prioritized<T, P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority)
+    }
 }
 
-enum SetSize {
+impl<T, P: Ord, H: Heap<Prioritized<T, P>>> PriorityQueue<T, P> for H {
+    fn len(&self) -> usize {
+        Heap::len(self)
+    }
+    fn insert_with_priority(&mut self, item: T, priority: P) {
+        self.insert(Prioritized { priority, item })
+    }
+    fn peek_highest_priority_element(&self) -> Option<(&T, &P)> {
+        self.peek().map(|p| (&p.item, &p.priority))
+    }
+    fn pull_highest_priority_element(&mut self) -> Option<(T, P)> {
+        self.extract().map(|p| (p.item, p.priority))
+    }
+}
+
+pub trait Set<T> {
+    fn union(self, other: Self) -> Self;
+    fn intersection(self, other: Self) -> Self;
+    /// the elements of `self` that aren't in `other`
+    fn difference(self, other: Self) -> Self;
+    fn is_subset(&self, other: &Self) -> bool;
+}
+
+pub enum SetSize {
     Finite(usize),
     Infinite,
 }
 
-trait StaticSet<T>: Set<T> {
-    fn is_element_of(&self, elem: T) -> bool;
+pub trait StaticSet<T>: Set<T> {
+    fn is_element_of(&self, elem: &T) -> bool;
     fn is_empty(&self) -> bool;
     fn size(&self) -> SetSize;
     //fn iter(&self) ->
@@ -107,20 +188,92 @@ trait StaticSet<T>: Set<T> {
     //fn create_from() -> Self; impl FromIter
 }
 
-trait DynamicSet<T>: Set<T> {
-    fn create() -> Self;
-    fn with_capacity(capacity: usize) -> Self;
-    fn add(&mut self, elem: T);
-    fn remove(&mut self, elem: T);
+pub trait DynamicSet<T>: StaticSet<T> {
+    fn with_capacity(capacity: usize) -> Self
+    where
+        Self: Sized;
+    /// `false` if it was already there
+    fn add(&mut self, elem: T) -> Result<bool, AllocError>;
+    /// `false` if it wasn't there
+    fn remove(&mut self, elem: &T) -> bool;
     fn capacity(&self) -> usize;
 }
 
-trait MultiSetKind {}
+/// A set that counts its elements, `add` and `remove` change the count by one and the set
+/// operations work on counts (max for union, min for intersection).
+pub trait MultiSet<T>: DynamicSet<T> {
+    fn count(&self, elem: &T) -> usize;
+    /// removes every copy and returns how many there were
+    fn remove_all(&mut self, elem: &T) -> usize;
+}
+
+mod impls {
+    use super::*;
+    use alloc::collections::{BinaryHeap, VecDeque};
+
+    // std versions, to have something to compare against
+
+    impl<T> Creatable for VecDeque<T> {
+        fn new() -> Self {
+            VecDeque::new()
+        }
+    }
 
-trait MultiSet<T, K>: Set<T>
-where
-    K: MultiSetKind,
-{
+    impl<T> List<T> for VecDeque<T> {
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn try_append(&mut self, item: T) -> Result<(), AllocError> {
+            self.try_reserve(1).map_err(|_| AllocError)?;
+            self.push_back(item);
+            Ok(())
+        }
+        fn try_prepend(&mut self, item: T) -> Result<(), AllocError> {
+            self.try_reserve(1).map_err(|_| AllocError)?;
+            self.push_front(item);
+            Ok(())
+        }
+        fn head(&self) -> Option<&T> {
+            self.front()
+        }
+        fn tail(&mut self) -> Option<T> {
+            self.pop_front()
+        }
+        fn get(&self, index: usize) -> Option<&T> {
+            self.get(index)
+        }
+    }
+
+    impl<T: Ord> Creatable for BinaryHeap<T> {
+        fn new() -> Self {
+            BinaryHeap::new()
+        }
+    }
+
+    impl<T: Ord> Heap<T> for BinaryHeap<T> {
+        type Kind = MaxHeap;
+        fn peek(&self) -> Option<&T> {
+            self.peek()
+        }
+        fn insert(&mut self, item: T) {
+            self.push(item)
+        }
+        fn extract(&mut self) -> Option<T> {
+            self.pop()
+        }
+        fn replace(&mut self, item: T) -> Option<T> {
+            if self.is_empty() {
+                self.push(item);
+                return None;
+            }
+            // sifts down once the guard is dropped
+            let mut top = self.peek_mut().unwrap();
+            Some(core::mem::replace(&mut *top, item))
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+    }
 }
 
 /*
@@ -154,3 +307,152 @@ fn is_member<T: Eq>(arr: &[T], val: &T) -> bool {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_table::SCHashTable;
+    use crate::heap::binary_heap::BinaryHeap;
+    use crate::linked_lists::{DoublyLinkedList, SinglyLinkedList};
+    use crate::queue::ArrayQueue;
+    use alloc::{collections, vec::Vec};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::hash_map::RandomState;
+
+    /// runs the same ops on `L` and a `VecDeque`
+    fn list_matches_model<L: List<u32> + Creatable>(seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut list = L::new();
+        let mut model = collections::VecDeque::new();
+        for i in 0..500 {
+            match rng.gen_range(0..3) {
+                0 => {
+                    list.append(i);
+                    model.push_back(i);
+                }
+                1 => {
+                    list.prepend(i);
+                    model.push_front(i);
+                }
+                _ => assert_eq!(list.tail(), model.pop_front()),
+            }
+            assert_eq!(list.head(), model.front());
+            assert_eq!(list.len(), model.len());
+        }
+        assert!((0..model.len() + 1).all(|i| list.get(i) == model.get(i)));
+        while list.tail().is_some() {}
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn lists() {
+        list_matches_model::<SinglyLinkedList<u32>>(1);
+        list_matches_model::<DoublyLinkedList<u32>>(2);
+        list_matches_model::<ArrayQueue<u32>>(3);
+        list_matches_model::<collections::VecDeque<u32>>(4);
+    }
+
+    /// pops everything and checks it came out in the kind's order
+    fn drains_in_order<H: Heap<i32>>(seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut a = H::new();
+        let mut b = H::new();
+        for _ in 0..200 {
+            a.insert(rng.gen_range(-100..100));
+            b.insert(rng.gen_range(-100..100));
+        }
+        let top = *a.peek().unwrap();
+        assert_eq!(a.replace(1000), Some(top));
+        a.delete();
+        a.meld(b);
+        assert_eq!(a.len(), 399);
+        let mut out = Vec::new();
+        while let Some(x) = a.extract() {
+            out.push(x);
+        }
+        assert!(out.windows(2).all(|w| !H::Kind::before(&w[1], &w[0])));
+        assert!(a.is_empty());
+    }
+
+    #[test]
+    fn heaps() {
+        drains_in_order::<BinaryHeap<i32>>(5);
+        drains_in_order::<collections::BinaryHeap<i32>>(6);
+    }
+
+    #[test]
+    fn priority_queue() {
+        fn run<Q: PriorityQueue<&'static str, u8>>(mut q: Q) -> Vec<&'static str> {
+            q.insert_with_priority("b", 2);
+            q.insert_with_priority("c", 3);
+            q.insert_with_priority("a", 1);
+            assert_eq!(q.len(), 3);
+            core::iter::from_fn(|| q.pull_highest_priority_element().map(|(t, _)| t)).collect()
+        }
+        assert_eq!(run(BinaryHeap::new()), ["a", "b", "c"]);
+        assert_eq!(run(collections::BinaryHeap::new()), ["c", "b", "a"]);
+    }
+
+    type HashSet = SCHashTable<u32, (), RandomState>;
+    type HashBag = SCHashTable<u32, usize, RandomState>;
+
+    fn set(items: &[u32]) -> HashSet {
+        let mut s = HashSet::with_capacity(4);
+        for &i in items {
+            s.add(i).unwrap();
+        }
+        s
+    }
+
+    fn sorted(s: &HashSet) -> Vec<u32> {
+        let mut v: Vec<u32> = s.iter().map(|(k, _)| *k).collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn sets() {
+        let evens = set(&(0..100).step_by(2).collect::<Vec<_>>());
+        let threes = set(&(0..100).step_by(3).collect::<Vec<_>>());
+        let both = set(&evens.iter().map(|(k, _)| *k).collect::<Vec<_>>())
+            .intersection(set(&(0..100).step_by(3).collect::<Vec<_>>()));
+        assert_eq!(sorted(&both), (0..100).step_by(6).collect::<Vec<_>>());
+        assert!(both.is_subset(&threes));
+        assert!(!threes.is_subset(&both));
+        let either = set(&[1, 2, 3]).union(set(&[3, 4]));
+        assert_eq!(sorted(&either), [1, 2, 3, 4]);
+        let only = set(&[1, 2, 3]).difference(set(&[2, 5]));
+        assert_eq!(sorted(&only), [1, 3]);
+
+        let mut s = set(&[7]);
+        assert!(!s.add(7).unwrap());
+        assert!(s.is_element_of(&7) && s.remove(&7) && !s.remove(&7));
+        assert!(StaticSet::is_empty(&s));
+        assert!(matches!(evens.size(), SetSize::Finite(50)));
+    }
+
+    #[test]
+    fn multisets() {
+        let bag = |items: &[u32]| {
+            let mut b = HashBag::with_capacity(4);
+            for &i in items {
+                b.add(i).unwrap();
+            }
+            b
+        };
+        let mut a = bag(&[1, 1, 1, 2, 3]);
+        assert_eq!((a.count(&1), a.count(&4)), (3, 0));
+        assert!(matches!(a.size(), SetSize::Finite(5)));
+        assert!(a.remove(&1) && a.remove(&3) && !a.remove(&3));
+        assert_eq!((a.count(&1), a.is_element_of(&3)), (2, false));
+        assert!(bag(&[1, 2]).is_subset(&a) && !bag(&[2, 2]).is_subset(&a));
+
+        let u = bag(&[1, 2, 2]).union(bag(&[1, 1, 2]));
+        assert_eq!((u.count(&1), u.count(&2)), (2, 2));
+        let i = bag(&[1, 2, 2, 3]).intersection(bag(&[2, 3, 3]));
+        assert_eq!((i.count(&1), i.count(&2), i.count(&3)), (0, 1, 1));
+        let mut d = bag(&[1, 2, 2, 3]).difference(bag(&[2, 3, 3]));
+        assert_eq!((d.count(&1), d.count(&2), d.count(&3)), (1, 1, 0));
+        assert_eq!(d.remove_all(&2), 1);
+    }
+}
@@ -0,0 +1,415 @@
+//! The things every collection has, so generic code can be written once and run on anything from
+//! a `Vec` to a [`PersistentVec`](crate::persistent::PersistentVec).
+//!
+//! These aren't in the prelude since a lot of the types have inherent methods with the same
+//! names as well as [`List`](crate::traits::adt::List) ones.
+//!
+//! The std maps don't store `(K, V)` pairs to hand out references to, so a map is a collection of
+//! its values: that's its `Item` here and what [`Iterable`](super::iter::Iterable) goes over, like
+//! `values()`. The keys come out with [`Drainable`](super::iter::Drainable).
+
+pub trait Collection {
+    type Item;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub trait Clear: Collection {
+    /// Removes every element, keeping whatever memory is already allocated where it can.
+    fn clear(&mut self);
+}
+
+pub trait Contains: Collection {
+    fn contains(&self, item: &Self::Item) -> bool;
+}
+
+pub trait Capacity: Collection {
+    /// How many elements fit before the next allocation.
+    fn capacity(&self) -> usize;
+}
+
+mod impls {
+    use super::*;
+    use alloc::collections::*;
+    use alloc::vec::Vec;
+    use core::alloc::Allocator;
+
+    impl<T> Collection for [T] {
+        type Item = T;
+        fn len(&self) -> usize {
+            <[T]>::len(self)
+        }
+    }
+
+    impl<T: PartialEq> Contains for [T] {
+        fn contains(&self, item: &T) -> bool {
+            <[T]>::contains(self, item)
+        }
+    }
+
+    impl<T, const N: usize> Collection for [T; N] {
+        type Item = T;
+        fn len(&self) -> usize {
+            N
+        }
+    }
+
+    impl<T: PartialEq, const N: usize> Contains for [T; N] {
+        fn contains(&self, item: &T) -> bool {
+            self.as_slice().contains(item)
+        }
+    }
+
+    impl<T, A: Allocator> Collection for Vec<T, A> {
+        type Item = T;
+        fn len(&self) -> usize {
+            Vec::len(self)
+        }
+    }
+
+    impl<T, A: Allocator> Clear for Vec<T, A> {
+        fn clear(&mut self) {
+            Vec::clear(self)
+        }
+    }
+
+    impl<T: PartialEq, A: Allocator> Contains for Vec<T, A> {
+        fn contains(&self, item: &T) -> bool {
+            self.as_slice().contains(item)
+        }
+    }
+
+    impl<T, A: Allocator> Capacity for Vec<T, A> {
+        fn capacity(&self) -> usize {
+            Vec::capacity(self)
+        }
+    }
+
+    impl<T, A: Allocator> Collection for VecDeque<T, A> {
+        type Item = T;
+        fn len(&self) -> usize {
+            VecDeque::len(self)
+        }
+    }
+
+    impl<T, A: Allocator> Clear for VecDeque<T, A> {
+        fn clear(&mut self) {
+            VecDeque::clear(self)
+        }
+    }
+
+    impl<T: PartialEq, A: Allocator> Contains for VecDeque<T, A> {
+        fn contains(&self, item: &T) -> bool {
+            VecDeque::contains(self, item)
+        }
+    }
+
+    impl<T, A: Allocator> Capacity for VecDeque<T, A> {
+        fn capacity(&self) -> usize {
+            VecDeque::capacity(self)
+        }
+    }
+
+    impl<T> Collection for LinkedList<T> {
+        type Item = T;
+        fn len(&self) -> usize {
+            LinkedList::len(self)
+        }
+    }
+
+    impl<T> Clear for LinkedList<T> {
+        fn clear(&mut self) {
+            LinkedList::clear(self)
+        }
+    }
+
+    impl<T: PartialEq> Contains for LinkedList<T> {
+        fn contains(&self, item: &T) -> bool {
+            LinkedList::contains(self, item)
+        }
+    }
+
+    impl<T> Collection for BinaryHeap<T> {
+        type Item = T;
+        fn len(&self) -> usize {
+            BinaryHeap::len(self)
+        }
+    }
+
+    impl<T> Clear for BinaryHeap<T> {
+        fn clear(&mut self) {
+            BinaryHeap::clear(self)
+        }
+    }
+
+    impl<T: PartialEq> Contains for BinaryHeap<T> {
+        fn contains(&self, item: &T) -> bool {
+            self.iter().any(|x| x == item)
+        }
+    }
+
+    impl<T> Capacity for BinaryHeap<T> {
+        fn capacity(&self) -> usize {
+            BinaryHeap::capacity(self)
+        }
+    }
+
+    impl<T> Collection for BTreeSet<T> {
+        type Item = T;
+        fn len(&self) -> usize {
+            BTreeSet::len(self)
+        }
+    }
+
+    impl<T> Clear for BTreeSet<T> {
+        fn clear(&mut self) {
+            BTreeSet::clear(self)
+        }
+    }
+
+    impl<T: Ord> Contains for BTreeSet<T> {
+        fn contains(&self, item: &T) -> bool {
+            BTreeSet::contains(self, item)
+        }
+    }
+
+    impl<K, V> Collection for BTreeMap<K, V> {
+        type Item = V;
+        fn len(&self) -> usize {
+            BTreeMap::len(self)
+        }
+    }
+
+    impl<K, V> Clear for BTreeMap<K, V> {
+        fn clear(&mut self) {
+            BTreeMap::clear(self)
+        }
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+mod std_impls {
+    use super::*;
+    use core::hash::{BuildHasher, Hash};
+    use std::collections::{HashMap, HashSet};
+
+    impl<T, S> Collection for HashSet<T, S> {
+        type Item = T;
+        fn len(&self) -> usize {
+            HashSet::len(self)
+        }
+    }
+
+    impl<T, S> Clear for HashSet<T, S> {
+        fn clear(&mut self) {
+            HashSet::clear(self)
+        }
+    }
+
+    impl<T: Eq + Hash, S: BuildHasher> Contains for HashSet<T, S> {
+        fn contains(&self, item: &T) -> bool {
+            HashSet::contains(self, item)
+        }
+    }
+
+    impl<T, S> Capacity for HashSet<T, S> {
+        fn capacity(&self) -> usize {
+            HashSet::capacity(self)
+        }
+    }
+
+    impl<K, V, S> Collection for HashMap<K, V, S> {
+        type Item = V;
+        fn len(&self) -> usize {
+            HashMap::len(self)
+        }
+    }
+
+    impl<K, V, S> Clear for HashMap<K, V, S> {
+        fn clear(&mut self) {
+            HashMap::clear(self)
+        }
+    }
+
+    impl<K, V, S> Capacity for HashMap<K, V, S> {
+        fn capacity(&self) -> usize {
+            HashMap::capacity(self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitstring::BitString;
+    use crate::heap::Heap;
+    use crate::linked_lists::DoublyLinkedList;
+    use crate::persistent::{
+        ConsList, PersistentHashMap, PersistentOrdMap, PersistentOrdSet, PersistentVec,
+    };
+    use crate::traits::iter::{Drainable, Iterable, IterableMut};
+    use crate::{ArrayQueue, SCHashTable, SinglyLinkedList};
+    use std::collections::hash_map::RandomState;
+    use std::collections::*;
+
+    // all of these get written once and used on everything
+    fn check<C: Contains<Item = i32> + ?Sized>(c: &C, len: usize) {
+        assert_eq!(c.len(), len);
+        assert_eq!(c.is_empty(), len == 0);
+        assert!(len == 0 || c.contains(&0));
+        assert!(!c.contains(&-1));
+    }
+
+    fn emptied<C: Clear + Contains<Item = i32>>(mut c: C) {
+        c.clear();
+        check(&c, 0);
+    }
+
+    // the items it counts are the ones it iterates, maps included
+    fn total<C: Collection<Item = i32> + Iterable<Item = i32> + ?Sized>(c: &C) -> i32 {
+        assert_eq!(c.iter().count(), c.len());
+        c.iter().sum()
+    }
+
+    fn doubled<C: IterableMut<Item = i32> + Iterable<Item = i32> + ?Sized>(c: &mut C) -> i32 {
+        c.iter_mut().for_each(|x| *x *= 2);
+        c.iter().sum()
+    }
+
+    fn drained<C: Drainable<Item = i32> + Collection>(mut c: C) -> i32 {
+        let sum = c.drain().sum();
+        assert!(c.is_empty());
+        sum
+    }
+
+    #[test]
+    fn std_collections() {
+        let mut v: Vec<i32> = (0..10).collect();
+        check(&v, 10);
+        check(v.as_slice(), 10);
+        assert_eq!(total(v.as_slice()), 45);
+        assert_eq!(doubled(&mut v), 90);
+        assert!(v.capacity() >= 10);
+        emptied(v.clone());
+        assert_eq!(drained(v), 90);
+
+        let mut a = [0, 1, 2];
+        check(&a, 3);
+        assert_eq!(doubled(&mut a), 6);
+
+        let mut d: VecDeque<i32> = (0..10).collect();
+        check(&d, 10);
+        assert_eq!(doubled(&mut d), 90);
+        emptied(d.clone());
+        assert_eq!(drained(d), 90);
+
+        let l: LinkedList<i32> = (0..10).collect();
+        check(&l, 10);
+        assert_eq!(total(&l), 45);
+        emptied(l.clone());
+        assert_eq!(drained(l), 45);
+
+        let h: BinaryHeap<i32> = (0..10).collect();
+        check(&h, 10);
+        assert_eq!(total(&h), 45);
+        emptied(h.clone());
+        assert_eq!(drained(h), 45);
+
+        let s: BTreeSet<i32> = (0..10).collect();
+        check(&s, 10);
+        assert_eq!(total(&s), 45);
+        emptied(s.clone());
+        assert_eq!(drained(s), 45);
+
+        let s: HashSet<i32> = (0..10).collect();
+        check(&s, 10);
+        assert_eq!(total(&s), 45);
+        emptied(s.clone());
+        assert_eq!(drained(s), 45);
+
+        let mut m: BTreeMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(Collection::len(&m), 10);
+        assert_eq!(doubled(&mut m), 90);
+        assert_eq!(m.drain().map(|(k, v)| k + v).sum::<i32>(), 135);
+        assert!(Collection::is_empty(&m));
+
+        let mut m: HashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(doubled(&mut m), 90);
+        Clear::clear(&mut m);
+        assert!(Collection::is_empty(&m));
+    }
+
+    #[test]
+    fn crate_collections() {
+        let mut q: ArrayQueue<i32> = (0..10).collect();
+        check(&q, 10);
+        assert_eq!(doubled(&mut q), 90);
+        emptied(q.clone());
+        assert_eq!(drained(q), 90);
+
+        let mut s: SinglyLinkedList<i32> = (0..10).collect();
+        check(&s, 10);
+        assert_eq!(doubled(&mut s), 90);
+        emptied(s.clone());
+        assert_eq!(drained(s), 90);
+
+        let mut d: DoublyLinkedList<i32> = (0..10).collect();
+        check(&d, 10);
+        assert_eq!(doubled(&mut d), 90);
+        emptied(d.clone());
+        assert_eq!(drained(d), 90);
+
+        let mut h = crate::heap::binary_heap::BinaryHeap::new();
+        (0..10).for_each(|i| h.insert(i));
+        check(&h, 10);
+        assert_eq!(total(&h), 45);
+        emptied(h.clone());
+        assert_eq!(drained(h), 45);
+
+        let mut bits: BitString = [true, false, true].into_iter().collect();
+        assert_eq!(Collection::len(&bits), 3);
+        assert!(Contains::contains(&bits, &false));
+        Clear::clear(&mut bits);
+        assert!(!Contains::contains(&bits, &true));
+
+        let mut t: SCHashTable<i32, i32, RandomState> = crate::traits::adt::Creatable::new();
+        for i in 0..10 {
+            crate::traits::hash_table::HashTable::insert(&mut t, i, i).unwrap();
+        }
+        assert_eq!(Collection::len(&t), 10);
+        assert!(Capacity::capacity(&t) >= 10);
+        assert_eq!(doubled(&mut t), 90);
+        assert_eq!(t.drain().map(|(k, v)| k + v).sum::<i32>(), 135);
+        assert!(Collection::is_empty(&t));
+        for i in 0..10 {
+            crate::traits::hash_table::HashTable::insert(&mut t, i, i).unwrap();
+        }
+        Clear::clear(&mut t);
+        assert_eq!(t.iter().count(), 0);
+    }
+
+    #[test]
+    fn persistent_collections() {
+        let v: PersistentVec<i32> = (0..10).collect();
+        check(&v, 10);
+        assert_eq!(total(&v), 45);
+
+        let l: ConsList<i32> = (0..10).collect();
+        check(&l, 10);
+        assert_eq!(total(&l), 45);
+
+        let s: PersistentOrdSet<i32> = (0..10).collect();
+        check(&s, 10);
+        assert_eq!(total(&s), 45);
+
+        let m: PersistentOrdMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(Collection::len(&m), 10);
+        assert_eq!(total(&m), 45);
+
+        let m: PersistentHashMap<i32, i32, RandomState> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(Collection::len(&m), 10);
+        assert_eq!(total(&m), 45);
+    }
+}
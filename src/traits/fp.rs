@@ -1,3 +1,6 @@
+mod monoid;
+pub use monoid::*;
+
 pub trait Functor {
     type Unwrapped;
     type Wrapped<B>: Functor;
@@ -61,19 +64,58 @@ instance Monad m => Category (Kleisli m) where
 
 */
 
+/// `pure(id)` applied to `v` is `v`, `pure(x)` lifted with `pure(y)` is `pure(f(x, y))`.
 pub trait Applicative: Functor {
-    fn lift_a2<F, B, C>(self, b: Self::Wrapped<B>, f: F) -> Self::Wrapped<C>
+    /// `B: Clone` since the list instances pair every element of `self` with every element of `b`
+    fn lift_a2<F, B: Clone, C>(self, b: Self::Wrapped<B>, f: F) -> Self::Wrapped<C>
     where
         F: FnMut(Self::Unwrapped, B) -> C;
     fn pure<T>(t: T) -> Self::Wrapped<T>;
 }
 
+/// `pure(a).bind(f)` is `f(a)`, `m.bind(pure)` is `m` and `bind` is associative.
 pub trait Monad: Applicative {
     fn bind<B, F>(self, f: F) -> Self::Wrapped<B>
     where
         F: FnMut(Self::Unwrapped) -> Self::Wrapped<B>;
 }
 
+/// Folds the elements down to a single value.
+pub trait Foldable: Sized {
+    type Item;
+    /// from the front
+    fn foldl<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B;
+    /// from the back
+    fn foldr<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(Self::Item, B) -> B;
+}
+
+/// Maps with an effect and collects the effects, stopping at the first `None` or `Err`.
+pub trait Traversable: Functor + Sized {
+    fn traverse_option<B, F>(self, f: F) -> Option<Self::Wrapped<B>>
+    where
+        F: FnMut(Self::Unwrapped) -> Option<B>;
+    fn traverse_result<B, E, F>(self, f: F) -> Result<Self::Wrapped<B>, E>
+    where
+        F: FnMut(Self::Unwrapped) -> Result<B, E>;
+
+    fn sequence_option<B>(self) -> Option<Self::Wrapped<B>>
+    where
+        Self: Functor<Unwrapped = Option<B>>,
+    {
+        self.traverse_option(|x| x)
+    }
+    fn sequence_result<B, E>(self) -> Result<Self::Wrapped<B>, E>
+    where
+        Self: Functor<Unwrapped = Result<B, E>>,
+    {
+        self.traverse_result(|x| x)
+    }
+}
+
 pub trait MonadTrans {
     type Base: Monad;
 
@@ -84,6 +126,8 @@ pub trait Monoid: Semigroup {
     fn mempty() -> Self;
 }
 
+use crate::linked_lists::{DoublyLinkedList, SinglyLinkedList};
+use crate::queue::ArrayQueue;
 use alloc::vec::Vec;
 
 impl<T> Monoid for Vec<T> {
@@ -128,24 +172,6 @@ impl<T> Semigroup for Vec<T> {
     }
 }
 
-use core::ops::Add;
-
-struct Sum<T>(pub T);
-
-impl<T: Add<Output = T>> Semigroup for Sum<T> {
-    fn append(self, rhs: Self) -> Self {
-        let a = self.0 + rhs.0;
-        Sum(a)
-    }
-}
-/*
-impl<T: Add<Output = T> + Zero> Monoid for Sum<T> {
-    fn mempty() -> Self {
-        Sum(T::zero())
-    }
-}
-*/
-
 impl<M: Monad> MonadTrans for IdentityT<M> {
     type Base = M;
 
@@ -185,7 +211,7 @@ impl<M: Pointed> Pointed for IdentityT<M> {
  */
 
 impl<M: Applicative> Applicative for IdentityT<M> {
-    fn lift_a2<F, B, C>(self, b: Self::Wrapped<B>, f: F) -> Self::Wrapped<C>
+    fn lift_a2<F, B: Clone, C>(self, b: Self::Wrapped<B>, f: F) -> Self::Wrapped<C>
     where
         F: FnMut(Self::Unwrapped, B) -> C,
     {
@@ -245,7 +271,7 @@ impl<A, E> Pointed for Result<A, E> {
  */
 
 impl<A> Applicative for Option<A> {
-    fn lift_a2<F, B, C>(self, b: Self::Wrapped<B>, mut f: F) -> Self::Wrapped<C>
+    fn lift_a2<F, B: Clone, C>(self, b: Self::Wrapped<B>, mut f: F) -> Self::Wrapped<C>
     where
         F: FnMut(Self::Unwrapped, B) -> C,
     {
@@ -259,7 +285,7 @@ impl<A> Applicative for Option<A> {
 }
 
 impl<A, E> Applicative for Result<A, E> {
-    fn lift_a2<F, B, C>(self, b: Self::Wrapped<B>, mut f: F) -> Self::Wrapped<C>
+    fn lift_a2<F, B: Clone, C>(self, b: Self::Wrapped<B>, mut f: F) -> Self::Wrapped<C>
     where
         F: FnMut(Self::Unwrapped, B) -> C,
     {
@@ -281,9 +307,235 @@ impl<A> Monad for Option<A> {
     }
 }
 
+impl<A, E> Monad for Result<A, E> {
+    fn bind<B, F>(self, f: F) -> Result<B, E>
+    where
+        F: FnMut(A) -> Result<B, E>,
+    {
+        self.and_then(f)
+    }
+}
+
+impl<A> Foldable for Option<A> {
+    type Item = A;
+    fn foldl<B, F: FnMut(B, A) -> B>(self, init: B, f: F) -> B {
+        self.into_iter().fold(init, f)
+    }
+    fn foldr<B, F: FnMut(A, B) -> B>(self, init: B, mut f: F) -> B {
+        self.into_iter().fold(init, |b, a| f(a, b))
+    }
+}
+
+impl<A, E> Foldable for Result<A, E> {
+    type Item = A;
+    fn foldl<B, F: FnMut(B, A) -> B>(self, init: B, f: F) -> B {
+        self.into_iter().fold(init, f)
+    }
+    fn foldr<B, F: FnMut(A, B) -> B>(self, init: B, mut f: F) -> B {
+        self.into_iter().fold(init, |b, a| f(a, b))
+    }
+}
+
+impl<A> Traversable for Option<A> {
+    fn traverse_option<B, F: FnMut(A) -> Option<B>>(self, mut f: F) -> Option<Option<B>> {
+        match self {
+            Some(a) => f(a).map(Some),
+            None => Some(None),
+        }
+    }
+    fn traverse_result<B, E, F: FnMut(A) -> Result<B, E>>(self, mut f: F) -> Result<Option<B>, E> {
+        match self {
+            Some(a) => f(a).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<A, E0> Traversable for Result<A, E0> {
+    fn traverse_option<B, F>(self, mut f: F) -> Option<Result<B, E0>>
+    where
+        F: FnMut(A) -> Option<B>,
+    {
+        match self {
+            Ok(a) => f(a).map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+    fn traverse_result<B, E, F>(self, mut f: F) -> Result<Result<B, E0>, E>
+    where
+        F: FnMut(A) -> Result<B, E>,
+    {
+        match self {
+            Ok(a) => f(a).map(Ok),
+            Err(e) => Ok(Err(e)),
+        }
+    }
+}
+
+/// every `f(a, b)` with `a` as the outer loop, like a list comprehension
+fn cartesian<A, B, C, F, I>(a: impl IntoIterator<Item = A>, b: I, mut f: F) -> Vec<C>
+where
+    A: Clone,
+    B: Clone,
+    F: FnMut(A, B) -> C,
+    I: IntoIterator<Item = B>,
+{
+    let b: Vec<B> = b.into_iter().collect();
+    let mut out = Vec::new();
+    for x in a {
+        out.extend(b.iter().map(|y| f(x.clone(), y.clone())));
+    }
+    out
+}
+
+// sequences whose `FromIterator` keeps the order
+macro_rules! sequence_instances {
+    ($($ty:ident),*) => {$(
+        impl<A: Clone> Applicative for $ty<A> {
+            fn lift_a2<F, B: Clone, C>(self, b: $ty<B>, f: F) -> $ty<C>
+            where
+                F: FnMut(A, B) -> C,
+            {
+                cartesian(self, b, f).into_iter().collect()
+            }
+            fn pure<T>(t: T) -> $ty<T> {
+                core::iter::once(t).collect()
+            }
+        }
+
+        impl<A: Clone> Monad for $ty<A> {
+            fn bind<B, F>(self, f: F) -> $ty<B>
+            where
+                F: FnMut(A) -> $ty<B>,
+            {
+                self.into_iter().flat_map(f).collect()
+            }
+        }
+
+        impl<A> Foldable for $ty<A> {
+            type Item = A;
+            fn foldl<B, F: FnMut(B, A) -> B>(self, init: B, f: F) -> B {
+                self.into_iter().fold(init, f)
+            }
+            fn foldr<B, F: FnMut(A, B) -> B>(self, init: B, mut f: F) -> B {
+                self.into_iter().rev().fold(init, |b, a| f(a, b))
+            }
+        }
+
+        impl<A> Traversable for $ty<A> {
+            fn traverse_option<B, F>(self, f: F) -> Option<$ty<B>>
+            where
+                F: FnMut(A) -> Option<B>,
+            {
+                self.into_iter().map(f).collect()
+            }
+            fn traverse_result<B, E, F>(self, f: F) -> Result<$ty<B>, E>
+            where
+                F: FnMut(A) -> Result<B, E>,
+            {
+                self.into_iter().map(f).collect()
+            }
+        }
+    )*};
+}
+
+sequence_instances!(Vec, ArrayQueue, DoublyLinkedList);
+
+impl<A> Functor for ArrayQueue<A> {
+    type Unwrapped = A;
+    type Wrapped<B> = ArrayQueue<B>;
+
+    fn map<F: FnMut(A) -> B, B>(self, f: F) -> ArrayQueue<B> {
+        self.into_iter().map(f).collect()
+    }
+}
+
+impl<A> Functor for DoublyLinkedList<A> {
+    type Unwrapped = A;
+    type Wrapped<B> = DoublyLinkedList<B>;
+
+    fn map<F: FnMut(A) -> B, B>(self, f: F) -> DoublyLinkedList<B> {
+        self.into_iter().map(f).collect()
+    }
+}
+
+// collecting into a `SinglyLinkedList` pushes to the front, so everything goes through a
+// reversed iterator to keep the order
+
+fn singly<T>(iter: impl IntoIterator<Item = T>) -> SinglyLinkedList<T> {
+    iter.into_iter()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect()
+}
+
+impl<A> Functor for SinglyLinkedList<A> {
+    type Unwrapped = A;
+    type Wrapped<B> = SinglyLinkedList<B>;
+
+    fn map<F: FnMut(A) -> B, B>(self, f: F) -> SinglyLinkedList<B> {
+        singly(self.into_iter().map(f))
+    }
+}
+
+impl<A: Clone> Applicative for SinglyLinkedList<A> {
+    fn lift_a2<F, B: Clone, C>(self, b: SinglyLinkedList<B>, f: F) -> SinglyLinkedList<C>
+    where
+        F: FnMut(A, B) -> C,
+    {
+        singly(cartesian(self, b, f))
+    }
+    fn pure<T>(t: T) -> SinglyLinkedList<T> {
+        core::iter::once(t).collect()
+    }
+}
+
+impl<A: Clone> Monad for SinglyLinkedList<A> {
+    fn bind<B, F>(self, f: F) -> SinglyLinkedList<B>
+    where
+        F: FnMut(A) -> SinglyLinkedList<B>,
+    {
+        singly(self.into_iter().flat_map(f))
+    }
+}
+
+impl<A> Foldable for SinglyLinkedList<A> {
+    type Item = A;
+    fn foldl<B, F: FnMut(B, A) -> B>(self, init: B, f: F) -> B {
+        self.into_iter().fold(init, f)
+    }
+    fn foldr<B, F: FnMut(A, B) -> B>(self, init: B, mut f: F) -> B {
+        let items: Vec<A> = self.into_iter().collect();
+        items.into_iter().rev().fold(init, |b, a| f(a, b))
+    }
+}
+
+impl<A> Traversable for SinglyLinkedList<A> {
+    fn traverse_option<B, F>(self, f: F) -> Option<SinglyLinkedList<B>>
+    where
+        F: FnMut(A) -> Option<B>,
+    {
+        self.into_iter()
+            .map(f)
+            .collect::<Option<Vec<B>>>()
+            .map(singly)
+    }
+    fn traverse_result<B, E, F>(self, f: F) -> Result<SinglyLinkedList<B>, E>
+    where
+        F: FnMut(A) -> Result<B, E>,
+    {
+        self.into_iter()
+            .map(f)
+            .collect::<Result<Vec<B>, E>>()
+            .map(singly)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
     #[test]
     fn option_functor() {
         fn mapper<A: Functor<Unwrapped = i32>>(a: A) -> A::Wrapped<i32> {
@@ -302,6 +554,126 @@ mod tests {
         assert_eq!(b, Some(32));
         assert_eq!(i, Some(64));
     }
+    use core::fmt::Debug;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::format;
+
+    fn functor_laws<M>(m: M)
+    where
+        M: Functor<Unwrapped = i32, Wrapped<i32> = M> + Clone + PartialEq + Debug,
+    {
+        assert_eq!(m.clone().map(|x| x), m);
+        let (f, g) = (|x: i32| x.wrapping_mul(3), |x: i32| x ^ 5);
+        assert_eq!(m.clone().map(f).map(g), m.map(|x| g(f(x))));
+    }
+
+    fn monad_laws<M>(m: M, f: impl Fn(i32) -> M, g: impl Fn(i32) -> M)
+    where
+        M: Monad<Unwrapped = i32, Wrapped<i32> = M> + Clone + PartialEq + Debug,
+    {
+        // applicative identity and homomorphism
+        // the GATs don't normalize on their own, hence all the turbofish
+        let unit: M::Wrapped<()> = M::pure(());
+        assert_eq!(m.clone().lift_a2::<_, (), i32>(unit, |x, _| x), m);
+        let h = |x: i32, y: i32| x.wrapping_sub(y);
+        let (x, y): (M, M) = (M::pure(7), M::pure(2));
+        assert_eq!(x.lift_a2::<_, i32, i32>(y, h), M::pure(h(7, 2)));
+        // lift_a2 agrees with bind
+        let n = f(1);
+        assert_eq!(
+            m.clone().lift_a2::<_, i32, i32>(n.clone(), h),
+            m.clone()
+                .bind::<i32, _>(|x| n.clone().map::<_, i32>(move |y| h(x, y)))
+        );
+
+        let unit: M = M::pure(3);
+        assert_eq!(unit.bind::<i32, _>(&f), f(3));
+        assert_eq!(m.clone().bind::<i32, _>(M::pure), m);
+        let left = m.clone().bind::<i32, _>(&f).bind::<i32, _>(&g);
+        assert_eq!(left, m.bind::<i32, _>(|x| f(x).bind::<i32, _>(&g)));
+    }
+
+    fn traversable_laws<M>(m: M)
+    where
+        M: Traversable<Unwrapped = i32, Wrapped<i32> = M>
+            + Foldable<Item = i32>
+            + Clone
+            + PartialEq
+            + Debug,
+    {
+        assert_eq!(m.clone().traverse_option(Some), Some(m.clone()));
+        assert_eq!(m.clone().traverse_result(Ok::<_, ()>), Ok(m.clone()));
+        let items = m.clone().foldr(Vec::new(), |x, mut v| {
+            v.insert(0, x);
+            v
+        });
+        let first_neg = items.iter().copied().find(|x| *x < 0);
+        let r = m
+            .clone()
+            .traverse_result(|x| if x < 0 { Err(x) } else { Ok(x) });
+        assert_eq!(r.err(), first_neg);
+        let o = m.clone().traverse_option(|x| (x >= 0).then_some(x));
+        assert_eq!(o.is_some(), first_neg.is_none());
+        assert_eq!(m.clone().foldl(0i64, |a, x| a * 3 + x as i64), {
+            items.iter().fold(0i64, |a, x| a * 3 + *x as i64)
+        });
+        assert_eq!(m.foldr(0i64, |x, a| a * 3 + x as i64), {
+            items.iter().rev().fold(0i64, |a, x| a * 3 + *x as i64)
+        });
+    }
+
+    fn check_all<M>(rng: &mut StdRng, from: fn(Vec<i32>) -> M)
+    where
+        M: Monad<Unwrapped = i32, Wrapped<i32> = M>
+            + Traversable
+            + Foldable<Item = i32>
+            + Clone
+            + PartialEq
+            + Debug,
+    {
+        for _ in 0..50 {
+            let len = rng.gen_range(0..6);
+            let v: Vec<i32> = (0..len).map(|_| rng.gen_range(-3..20)).collect();
+            let m = from(v.clone());
+            assert_eq!(format!("{:?}", from(v.clone())), format!("{m:?}"));
+            functor_laws(m.clone());
+            traversable_laws(m.clone());
+            monad_laws(m, |x| from(vec![x, x + 1]), |x| from(vec![x * 2]));
+        }
+    }
+
+    #[test]
+    fn laws() {
+        let mut rng = StdRng::seed_from_u64(41);
+        check_all(&mut rng, |v| v);
+        check_all(&mut rng, |v| v.into_iter().collect::<ArrayQueue<_>>());
+        check_all(&mut rng, |v| v.into_iter().collect::<DoublyLinkedList<_>>());
+        check_all(&mut rng, singly);
+        // only the first element survives
+        check_all(&mut rng, |v| v.into_iter().next());
+        check_all(&mut rng, |v| v.into_iter().next().ok_or(()));
+    }
+
+    #[test]
+    fn cartesian_order() {
+        let v = vec![1, 2].lift_a2(vec![10, 20, 30], |a, b| a * b);
+        assert_eq!(v, [10, 20, 30, 20, 40, 60]);
+        let l = singly([1, 2]).lift_a2(singly([10, 20]), |a, b| a + b);
+        assert!(l.into_iter().eq([11, 21, 12, 22]));
+        assert_eq!(Some(4).lift_a2(None::<i32>, |a, b| a + b), None);
+    }
+
+    #[test]
+    fn sequence() {
+        let all: Vec<Result<i32, &str>> = vec![Ok(1), Ok(2)];
+        assert_eq!(all.sequence_result(), Ok(vec![1, 2]));
+        let bad: Vec<Result<i32, &str>> = vec![Ok(1), Err("a"), Err("b")];
+        assert_eq!(bad.sequence_result(), Err("a"));
+        let q: ArrayQueue<Option<i32>> = [Some(1), None].into_iter().collect();
+        assert_eq!(q.sequence_option(), None);
+        assert_eq!(Some(Ok::<_, ()>(3)).sequence_result(), Ok(Some(3)));
+    }
+
     #[test]
     fn vec_functor() {
         let list: Vec<u32> = (0..100).collect();
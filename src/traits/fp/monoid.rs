@@ -0,0 +1,432 @@
+//! Ready made [`Semigroup`]s and [`Monoid`]s, mostly newtypes picking one of the many monoids a
+//! type has, like in haskell's `Data.Monoid`.
+
+use super::{Monoid, Semigroup};
+use crate::hash_table::SCHashTable;
+use crate::prelude::*;
+use alloc::{alloc::Global, boxed::Box, string::String};
+use core::{
+    alloc::Allocator,
+    hash::{BuildHasher, Hash},
+    ops::{Add, Mul},
+};
+
+/// Numbers with an additive identity.
+pub trait Zero {
+    const ZERO: Self;
+}
+
+/// Numbers with a multiplicative identity.
+pub trait One {
+    const ONE: Self;
+}
+
+/// Types with a smallest and a largest value.
+pub trait Bounded {
+    const MIN: Self;
+    const MAX: Self;
+}
+
+macro_rules! numeric {
+    ($($ty:ty),*) => {$(
+        impl Zero for $ty {
+            const ZERO: Self = 0 as $ty;
+        }
+        impl One for $ty {
+            const ONE: Self = 1 as $ty;
+        }
+    )*};
+}
+
+numeric!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+macro_rules! bounded {
+    ($($ty:ty),*) => {$(
+        impl Bounded for $ty {
+            const MIN: Self = <$ty>::MIN;
+            const MAX: Self = <$ty>::MAX;
+        }
+    )*};
+}
+
+bounded!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl Bounded for char {
+    const MIN: Self = '\0';
+    const MAX: Self = char::MAX;
+}
+
+impl Bounded for bool {
+    const MIN: Self = false;
+    const MAX: Self = true;
+}
+
+// infinities so that they are actually the identity of `min` and `max`
+impl Bounded for f32 {
+    const MIN: Self = f32::NEG_INFINITY;
+    const MAX: Self = f32::INFINITY;
+}
+
+impl Bounded for f64 {
+    const MIN: Self = f64::NEG_INFINITY;
+    const MAX: Self = f64::INFINITY;
+}
+
+/// Combines with `+`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sum<T>(pub T);
+
+impl<T: Add<Output = T>> Semigroup for Sum<T> {
+    fn append(self, rhs: Self) -> Self {
+        Sum(self.0 + rhs.0)
+    }
+}
+
+impl<T: Add<Output = T> + Zero> Monoid for Sum<T> {
+    fn mempty() -> Self {
+        Sum(T::ZERO)
+    }
+}
+
+/// Combines with `*`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Product<T>(pub T);
+
+impl<T: Mul<Output = T>> Semigroup for Product<T> {
+    fn append(self, rhs: Self) -> Self {
+        Product(self.0 * rhs.0)
+    }
+}
+
+impl<T: Mul<Output = T> + One> Monoid for Product<T> {
+    fn mempty() -> Self {
+        Product(T::ONE)
+    }
+}
+
+/// Keeps the smaller one, the left one on ties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Min<T>(pub T);
+
+impl<T: PartialOrd> Semigroup for Min<T> {
+    fn append(self, rhs: Self) -> Self {
+        if rhs.0 < self.0 {
+            rhs
+        } else {
+            self
+        }
+    }
+}
+
+impl<T: PartialOrd + Bounded> Monoid for Min<T> {
+    fn mempty() -> Self {
+        Min(T::MAX)
+    }
+}
+
+/// Keeps the larger one, the left one on ties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Max<T>(pub T);
+
+impl<T: PartialOrd> Semigroup for Max<T> {
+    fn append(self, rhs: Self) -> Self {
+        if rhs.0 > self.0 {
+            rhs
+        } else {
+            self
+        }
+    }
+}
+
+impl<T: PartialOrd + Bounded> Monoid for Max<T> {
+    fn mempty() -> Self {
+        Max(T::MIN)
+    }
+}
+
+/// The leftmost `Some`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct First<T>(pub Option<T>);
+
+impl<T> Semigroup for First<T> {
+    fn append(self, rhs: Self) -> Self {
+        First(self.0.or(rhs.0))
+    }
+}
+
+impl<T> Monoid for First<T> {
+    fn mempty() -> Self {
+        First(None)
+    }
+}
+
+/// The rightmost `Some`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Last<T>(pub Option<T>);
+
+impl<T> Semigroup for Last<T> {
+    fn append(self, rhs: Self) -> Self {
+        Last(rhs.0.or(self.0))
+    }
+}
+
+impl<T> Monoid for Last<T> {
+    fn mempty() -> Self {
+        Last(None)
+    }
+}
+
+/// Combines with `&&`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct All(pub bool);
+
+impl Semigroup for All {
+    fn append(self, rhs: Self) -> Self {
+        All(self.0 && rhs.0)
+    }
+}
+
+impl Monoid for All {
+    fn mempty() -> Self {
+        All(true)
+    }
+}
+
+/// Combines with `||`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Any(pub bool);
+
+impl Semigroup for Any {
+    fn append(self, rhs: Self) -> Self {
+        Any(self.0 || rhs.0)
+    }
+}
+
+impl Monoid for Any {
+    fn mempty() -> Self {
+        Any(false)
+    }
+}
+
+/// Functions from a type to itself under composition, `f.append(g)` runs `g` first.
+pub struct Endo<'a, T>(pub Box<dyn Fn(T) -> T + 'a>);
+
+impl<'a, T> Endo<'a, T> {
+    pub fn new<F: Fn(T) -> T + 'a>(f: F) -> Self {
+        Endo(Box::new(f))
+    }
+
+    pub fn apply(&self, x: T) -> T {
+        (self.0)(x)
+    }
+}
+
+impl<'a, T: 'a> Semigroup for Endo<'a, T> {
+    fn append(self, rhs: Self) -> Self {
+        Endo(Box::new(move |x| (self.0)((rhs.0)(x))))
+    }
+}
+
+impl<'a, T: 'a> Monoid for Endo<'a, T> {
+    fn mempty() -> Self {
+        Endo(Box::new(|x| x))
+    }
+}
+
+impl Semigroup for () {
+    fn append(self, _: Self) -> Self {}
+}
+
+impl Monoid for () {
+    fn mempty() -> Self {}
+}
+
+macro_rules! tuple_monoid {
+    ($($name:ident $idx:tt),*) => {
+        impl<$($name: Semigroup),*> Semigroup for ($($name,)*) {
+            fn append(self, rhs: Self) -> Self {
+                ($(self.$idx.append(rhs.$idx),)*)
+            }
+        }
+
+        impl<$($name: Monoid),*> Monoid for ($($name,)*) {
+            fn mempty() -> Self {
+                ($($name::mempty(),)*)
+            }
+        }
+    };
+}
+
+tuple_monoid!(A 0);
+tuple_monoid!(A 0, B 1);
+tuple_monoid!(A 0, B 1, C 2);
+tuple_monoid!(A 0, B 1, C 2, D 3);
+
+/// `None` is the identity, which turns any semigroup into a monoid.
+impl<S: Semigroup> Semigroup for Option<S> {
+    fn append(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Some(a), Some(b)) => Some(a.append(b)),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+}
+
+impl<S: Semigroup> Monoid for Option<S> {
+    fn mempty() -> Self {
+        None
+    }
+}
+
+impl Semigroup for String {
+    fn append(mut self, rhs: Self) -> Self {
+        self.push_str(&rhs);
+        self
+    }
+}
+
+impl Monoid for String {
+    fn mempty() -> Self {
+        String::new()
+    }
+}
+
+/// Union, the values of keys in both maps are appended.
+impl<K, V, S, A> Semigroup for SCHashTable<K, V, S, A>
+where
+    K: Eq + Hash,
+    V: Semigroup,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn append(mut self, rhs: Self) -> Self {
+        self.union_with(rhs, V::append).expect("failed to allocate");
+        self
+    }
+}
+
+impl<K, V, S> Monoid for SCHashTable<K, V, S, Global>
+where
+    K: Eq + Hash,
+    V: Semigroup,
+    S: BuildHasher + Default,
+{
+    fn mempty() -> Self {
+        use crate::traits::hash_table::HashTable;
+        Self::with_capacity_and_hasher_in(0, S::default(), Global).expect("failed to allocate")
+    }
+}
+
+/// Appends everything together, left to right.
+pub fn mconcat<M: Monoid, I: IntoIterator<Item = M>>(iter: I) -> M {
+    iter.into_iter().fold(M::mempty(), M::append)
+}
+
+/// Maps every element of a collection into a monoid and appends the results.
+///
+/// # Examples
+/// ```
+/// use hash_table::traits::fp::{fold_map, Max, Sum};
+/// let v = vec![3, 1, 4, 1, 5];
+/// assert_eq!(fold_map(&v, |x| (Sum(*x), Max(*x))), (Sum(14), Max(5)));
+/// ```
+pub fn fold_map<'a, C, M, F>(collection: &'a C, f: F) -> M
+where
+    C: Iterable + ?Sized,
+    M: Monoid,
+    F: FnMut(&'a C::Item) -> M,
+{
+    mconcat(collection.iter().map(f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::ArrayQueue;
+    use crate::traits::hash_table::HashTable;
+    use alloc::{string::ToString, vec, vec::Vec};
+    use core::fmt::Debug;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::hash_map::RandomState;
+
+    fn laws<M: Monoid + Clone + PartialEq + Debug>(a: M, b: M, c: M) {
+        let left = a.clone().append(b.clone()).append(c.clone());
+        assert_eq!(left, a.clone().append(b.append(c)));
+        assert_eq!(M::mempty().append(a.clone()), a);
+        assert_eq!(a.clone().append(M::mempty()), a);
+    }
+
+    #[test]
+    fn instances() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..200 {
+            let [a, b, c]: [i64; 3] = core::array::from_fn(|_| rng.gen_range(-50..50));
+            let opt = |x: i64| (x > 0).then_some(x);
+            laws(Sum(a), Sum(b), Sum(c));
+            laws(Product(a), Product(b), Product(c));
+            laws(Min(a), Min(b), Min(c));
+            laws(Max(a), Max(b), Max(c));
+            laws(First(opt(a)), First(opt(b)), First(opt(c)));
+            laws(Last(opt(a)), Last(opt(b)), Last(opt(c)));
+            laws(All(a > 0), All(b > 0), All(c > 0));
+            laws(Any(a > 0), Any(b > 0), Any(c > 0));
+            laws((Sum(a), Max(a)), (Sum(b), Max(b)), (Sum(c), Max(c)));
+            laws(opt(a).map(Max), opt(b).map(Max), opt(c).map(Max));
+            laws(a.to_string(), b.to_string(), c.to_string());
+            laws(vec![a], vec![b, c], vec![]);
+        }
+        assert_eq!(Min::<f64>::mempty().append(Min(-1e300)), Min(-1e300));
+        assert_eq!(mconcat(Vec::<(Any, All)>::new()), (Any(false), All(true)));
+    }
+
+    #[test]
+    fn endo() {
+        let f = Endo::new(|x: i32| x * 2).append(Endo::new(|x| x + 1));
+        assert_eq!(f.apply(5), 12);
+        let g = mconcat((0..3).map(|i| Endo::new(move |s: String| s + &i.to_string())));
+        assert_eq!(g.apply(String::new()), "210");
+        assert_eq!(Endo::<u8>::mempty().apply(7), 7);
+    }
+
+    #[test]
+    fn folds() {
+        let q: ArrayQueue<i32> = (1..=10).collect();
+        assert_eq!(fold_map(&q, |x| Product(*x as u64)), Product(3628800));
+        assert_eq!(
+            fold_map(&q, |x| First(Some(*x).filter(|x| x % 4 == 0))),
+            First(Some(4))
+        );
+        assert_eq!(
+            fold_map(&q, |x| Last(Some(*x).filter(|x| x % 4 == 0))),
+            Last(Some(8))
+        );
+        let words = vec!["a", "bb", "ccc"];
+        assert_eq!(fold_map(&words, |w| Sum(w.len())), Sum(6));
+    }
+
+    #[test]
+    fn map_union() {
+        type Table = SCHashTable<u32, Sum<u32>, RandomState>;
+        let from = |pairs: &[(u32, u32)]| {
+            let mut t = Table::mempty();
+            for &(k, v) in pairs {
+                t.insert(k, Sum(v)).unwrap();
+            }
+            t
+        };
+        let a = from(&[(1, 1), (2, 2)]);
+        let b = from(&[(2, 10), (3, 3)]);
+        let c = a.append(b).append(Table::mempty());
+        assert_eq!(c.len(), 3);
+        assert_eq!(c.get(&1), Some(&Sum(1)));
+        assert_eq!(c.get(&2), Some(&Sum(12)));
+        assert_eq!(c.get(&3), Some(&Sum(3)));
+
+        // word counts over a bunch of tables, enough to make them grow
+        let tables = (0..10).map(|i| from(&(0..100).map(|k| (k, i)).collect::<Vec<_>>()));
+        let total = mconcat(tables);
+        assert_eq!(total.len(), 100);
+        assert!((0..100).all(|k| total.get(&k) == Some(&Sum(45))));
+    }
+}
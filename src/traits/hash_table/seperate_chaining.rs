@@ -17,6 +17,10 @@ where
     fn is_empty(&self) -> bool;
     fn get(&self, key: &K) -> Option<&V>;
     fn remove(&mut self, key: &K) -> Option<(K, V)>;
+    /// Moves every entry into `buckets[index(key)]` without allocating, for when the table grows.
+    fn move_into<F: Fn(&K) -> usize>(&mut self, buckets: &mut [Self], index: F)
+    where
+        Self: Sized;
 }
 
 pub trait BucketIters<'a, K, V, A>:
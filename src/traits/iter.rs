@@ -1,4 +1,11 @@
-use core::ops::RangeBounds;
+use crate::traits::adt::List;
+use crate::util::resolve_range;
+use alloc::alloc::Global;
+use alloc::vec::Vec;
+use core::alloc::Allocator;
+use core::marker::PhantomData;
+use core::ops::{Range, RangeBounds};
+use core::ptr;
 
 pub trait Iterable {
     type Item;
@@ -24,26 +31,35 @@ pub trait Drainable {
     fn drain(&mut self) -> Self::Drain<'_>;
 }
 
+/// Removes and yields the elements `pred` returns `true` for, in order.
+///
+/// Elements the iterator hasn't reached yet stay in the collection if it is dropped early, and
+/// if `pred` panics the collection is left holding every element that wasn't yielded.
 pub trait DrainableBy {
     type Item;
     type DrainBy<'a, F>: Iterator<Item = Self::Item> + 'a
     where
-        Self: 'a;
-    fn drain_by<F>(&mut self) -> Self::DrainBy<'_, F>
+        Self: 'a,
+        F: FnMut(&Self::Item) -> bool + 'a;
+    fn drain_by<'a, F>(&'a mut self, pred: F) -> Self::DrainBy<'a, F>
     where
-        F: FnMut(&Self::Item) -> bool;
+        F: FnMut(&Self::Item) -> bool + 'a;
 }
 
+/// Removes and yields the elements in `range`, the whole range is removed even if the iterator
+/// is dropped early.
 pub trait DrainableRange {
     type Item;
-    type DrainBy<'a>: Iterator<Item = Self::Item> + 'a
+    type DrainRange<'a>: Iterator<Item = Self::Item> + 'a
     where
         Self: 'a;
-    fn drain_range<R>(&mut self, range: R) -> Self::DrainBy<'_>
+    fn drain_range<R>(&mut self, range: R) -> Self::DrainRange<'_>
     where
         R: RangeBounds<usize>;
 }
 
+/// Keeps only the elements in `range` and yields everything else, the rest is removed even if the
+/// iterator is dropped early.
 pub trait RetainRange {
     type Item;
     type Retain<'a>: Iterator<Item = Self::Item> + 'a
@@ -54,27 +70,222 @@ pub trait RetainRange {
         R: RangeBounds<usize>;
 }
 
-mod impls {
-    use core::alloc::Allocator;
+/// Decides which elements a `drain_by` style iterator takes out.
+pub trait DrainFilter<T> {
+    /// keep going until the end when dropped instead of leaving the rest alone.
+    const FINISH_ON_DROP: bool = false;
+    fn matches(&mut self, item: &T) -> bool;
+    /// nothing after this point will match, so the iterator can stop walking.
+    fn done(&self) -> bool {
+        false
+    }
+}
+
+impl<T, F: FnMut(&T) -> bool> DrainFilter<T> for F {
+    fn matches(&mut self, item: &T) -> bool {
+        self(item)
+    }
+}
+
+/// Matches elements by their position, either the ones inside a range or the ones outside it.
+#[derive(Debug, Clone)]
+pub struct IndexFilter {
+    range: Range<usize>,
+    index: usize,
+    inside: bool,
+}
+
+impl IndexFilter {
+    /// matches the positions in `range`, panics if it doesn't fit in `len`.
+    pub fn inside<R: RangeBounds<usize>>(range: R, len: usize) -> Self {
+        Self {
+            range: resolve_range(range, len).expect("range out of bounds"),
+            index: 0,
+            inside: true,
+        }
+    }
+    /// matches the positions not in `range`, panics if it doesn't fit in `len`.
+    pub fn outside<R: RangeBounds<usize>>(range: R, len: usize) -> Self {
+        Self {
+            range: resolve_range(range, len).expect("range out of bounds"),
+            index: 0,
+            inside: false,
+        }
+    }
+}
+
+impl<T> DrainFilter<T> for IndexFilter {
+    const FINISH_ON_DROP: bool = true;
+    fn matches(&mut self, _: &T) -> bool {
+        let index = self.index;
+        self.index += 1;
+        self.range.contains(&index) == self.inside
+    }
+    fn done(&self) -> bool {
+        self.inside && self.index >= self.range.end
+    }
+}
+
+/// Drains from anything that can pop its front and push its back by rotating every element
+/// through once, the order is put back when it's dropped.
+pub struct RotateDrainBy<'a, L: List<T>, T, P: DrainFilter<T>> {
+    list: &'a mut L,
+    remaining: usize,
+    pred: P,
+    marker: PhantomData<T>,
+}
+
+impl<'a, L: List<T>, T, P: DrainFilter<T>> RotateDrainBy<'a, L, T, P> {
+    pub(crate) fn new(list: &'a mut L, pred: P) -> Self {
+        Self {
+            remaining: list.len(),
+            list,
+            pred,
+            marker: PhantomData,
+        }
+    }
+}
 
+impl<'a, L: List<T>, T, P: DrainFilter<T>> Iterator for RotateDrainBy<'a, L, T, P> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        while self.remaining > 0 && !self.pred.done() {
+            // test it in place so a panicking pred leaves it where it was
+            let matched = self.pred.matches(self.list.head()?);
+            self.remaining -= 1;
+            let item = self.list.tail()?;
+            if matched {
+                return Some(item);
+            }
+            // a slot was just freed so this never allocates
+            self.list.append(item);
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<'a, L: List<T>, T, P: DrainFilter<T>> Drop for RotateDrainBy<'a, L, T, P> {
+    fn drop(&mut self) {
+        if P::FINISH_ON_DROP {
+            self.for_each(drop);
+        }
+        for _ in 0..self.remaining {
+            if let Some(item) = self.list.tail() {
+                self.list.append(item);
+            }
+        }
+    }
+}
+
+/// Drains by popping until empty, for the collections that don't have a `drain` of their own.
+/// Whatever isn't popped stays put.
+pub struct PopDrain<'a, C, T> {
+    collection: &'a mut C,
+    pop: fn(&mut C) -> Option<T>,
+}
+
+impl<'a, C, T> PopDrain<'a, C, T> {
+    pub(crate) fn new(collection: &'a mut C, pop: fn(&mut C) -> Option<T>) -> Self {
+        Self { collection, pop }
+    }
+}
+
+impl<'a, C, T> Iterator for PopDrain<'a, C, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        (self.pop)(self.collection)
+    }
+}
+
+/// The `Vec` version of `drain_by`, shifts the kept elements down as it goes.
+pub struct VecDrainBy<'a, T, P: DrainFilter<T>, A: Allocator = Global> {
+    vec: &'a mut Vec<T, A>,
+    index: usize,
+    deleted: usize,
+    old_len: usize,
+    pred: P,
+}
+
+impl<'a, T, P: DrainFilter<T>, A: Allocator> VecDrainBy<'a, T, P, A> {
+    pub(crate) fn new(vec: &'a mut Vec<T, A>, pred: P) -> Self {
+        let old_len = vec.len();
+        // if we get leaked the elements are leaked with us instead of being dropped twice
+        unsafe { vec.set_len(0) };
+        Self {
+            vec,
+            index: 0,
+            deleted: 0,
+            old_len,
+            pred,
+        }
+    }
+}
+
+impl<'a, T, P: DrainFilter<T>, A: Allocator> Iterator for VecDrainBy<'a, T, P, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let ptr = self.vec.as_mut_ptr();
+        while self.index < self.old_len && !self.pred.done() {
+            unsafe {
+                let current = ptr.add(self.index);
+                let matched = self.pred.matches(&*current);
+                self.index += 1;
+                if matched {
+                    self.deleted += 1;
+                    return Some(ptr::read(current));
+                } else if self.deleted > 0 {
+                    ptr::copy_nonoverlapping(current, current.sub(self.deleted), 1);
+                }
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old_len - self.index))
+    }
+}
+
+impl<'a, T, P: DrainFilter<T>, A: Allocator> Drop for VecDrainBy<'a, T, P, A> {
+    fn drop(&mut self) {
+        if P::FINISH_ON_DROP {
+            self.for_each(drop);
+        }
+        unsafe {
+            let ptr = self.vec.as_mut_ptr();
+            if self.deleted > 0 && self.index < self.old_len {
+                ptr::copy(
+                    ptr.add(self.index),
+                    ptr.add(self.index - self.deleted),
+                    self.old_len - self.index,
+                );
+            }
+            self.vec.set_len(self.old_len - self.deleted);
+        }
+    }
+}
+
+mod impls {
     use super::*;
     use crate::prelude::*;
     use alloc::collections::*;
     use alloc::slice;
-    use alloc::vec::{self, Vec};
+    use alloc::vec;
 
     // vec
-    impl<T> Iterable for Vec<T> {
+    impl<T, A: Allocator> Iterable for Vec<T, A> {
         type Item = T;
-        type Iter<'a> = slice::Iter<'a, T> where T: 'a;
+        type Iter<'a> = slice::Iter<'a, T> where T: 'a, A: 'a;
         fn iter(&self) -> Self::Iter<'_> {
             self.deref().iter()
         }
     }
 
-    impl<T> IterableMut for Vec<T> {
+    impl<T, A: Allocator> IterableMut for Vec<T, A> {
         type Item = T;
-        type IterMut<'a> = slice::IterMut<'a ,T> where T: 'a;
+        type IterMut<'a> = slice::IterMut<'a ,T> where T: 'a, A: 'a;
         fn iter_mut(&mut self) -> Self::IterMut<'_> {
             self.deref_mut().iter_mut()
         }
@@ -88,17 +299,17 @@ mod impls {
         }
     }
 
-    impl<T> Iterable for VecDeque<T> {
+    impl<T, A: Allocator> Iterable for VecDeque<T, A> {
         type Item = T;
-        type Iter<'a> = vec_deque::Iter<'a, T> where T: 'a;
+        type Iter<'a> = vec_deque::Iter<'a, T> where T: 'a, A: 'a;
         fn iter(&self) -> Self::Iter<'_> {
             self.iter()
         }
     }
 
-    impl<T> IterableMut for VecDeque<T> {
+    impl<T, A: Allocator> IterableMut for VecDeque<T, A> {
         type Item = T;
-        type IterMut<'a> = vec_deque::IterMut<'a ,T> where T: 'a;
+        type IterMut<'a> = vec_deque::IterMut<'a ,T> where T: 'a, A: 'a;
         fn iter_mut(&mut self) -> Self::IterMut<'_> {
             self.iter_mut()
         }
@@ -112,6 +323,62 @@ mod impls {
         }
     }
 
+    impl<T, A: Allocator> DrainableBy for Vec<T, A> {
+        type Item = T;
+        type DrainBy<'a, F> = VecDrainBy<'a, T, F, A> where T: 'a, A: 'a, F: FnMut(&T) -> bool + 'a;
+        fn drain_by<'a, F>(&'a mut self, pred: F) -> Self::DrainBy<'a, F>
+        where
+            F: FnMut(&T) -> bool + 'a,
+        {
+            VecDrainBy::new(self, pred)
+        }
+    }
+
+    impl<T, A: Allocator> DrainableRange for Vec<T, A> {
+        type Item = T;
+        type DrainRange<'a> = vec::Drain<'a, T, A> where T: 'a, A: 'a;
+        fn drain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::DrainRange<'_> {
+            self.drain(range)
+        }
+    }
+
+    impl<T, A: Allocator> RetainRange for Vec<T, A> {
+        type Item = T;
+        type Retain<'a> = VecDrainBy<'a, T, IndexFilter, A> where T: 'a, A: 'a;
+        fn retain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::Retain<'_> {
+            let filter = IndexFilter::outside(range, self.len());
+            VecDrainBy::new(self, filter)
+        }
+    }
+
+    impl<T> DrainableBy for VecDeque<T> {
+        type Item = T;
+        type DrainBy<'a, F> = RotateDrainBy<'a, Self, T, F> where T: 'a, F: FnMut(&T) -> bool + 'a;
+        fn drain_by<'a, F>(&'a mut self, pred: F) -> Self::DrainBy<'a, F>
+        where
+            F: FnMut(&T) -> bool + 'a,
+        {
+            RotateDrainBy::new(self, pred)
+        }
+    }
+
+    impl<T, A: Allocator> DrainableRange for VecDeque<T, A> {
+        type Item = T;
+        type DrainRange<'a> = vec_deque::Drain<'a, T, A> where T: 'a, A: 'a;
+        fn drain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::DrainRange<'_> {
+            self.drain(range)
+        }
+    }
+
+    impl<T> RetainRange for VecDeque<T> {
+        type Item = T;
+        type Retain<'a> = RotateDrainBy<'a, Self, T, IndexFilter> where T: 'a;
+        fn retain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Self::Retain<'_> {
+            let filter = IndexFilter::outside(range, self.len());
+            RotateDrainBy::new(self, filter)
+        }
+    }
+
     impl<T> Iterable for LinkedList<T> {
         type Item = T;
         type Iter<'a> = linked_list::Iter<'a, T> where T: 'a;
@@ -128,6 +395,14 @@ mod impls {
         }
     }
 
+    impl<T> Drainable for LinkedList<T> {
+        type Item = T;
+        type Drain<'a> = PopDrain<'a, Self, T> where T: 'a;
+        fn drain(&mut self) -> Self::Drain<'_> {
+            PopDrain::new(self, LinkedList::pop_front)
+        }
+    }
+
     impl<T> Iterable for BTreeSet<T> {
         type Item = T;
         type Iter<'a> = btree_set::Iter<'a, T> where T: 'a;
@@ -135,4 +410,286 @@ mod impls {
             self.iter()
         }
     }
+
+    impl<T: Ord> Drainable for BTreeSet<T> {
+        type Item = T;
+        type Drain<'a> = PopDrain<'a, Self, T> where T: 'a;
+        fn drain(&mut self) -> Self::Drain<'_> {
+            PopDrain::new(self, BTreeSet::pop_first)
+        }
+    }
+
+    impl<K, V> Iterable for BTreeMap<K, V> {
+        type Item = V;
+        type Iter<'a> = btree_map::Values<'a, K, V> where K: 'a, V: 'a;
+        fn iter(&self) -> Self::Iter<'_> {
+            self.values()
+        }
+    }
+
+    impl<K, V> IterableMut for BTreeMap<K, V> {
+        type Item = V;
+        type IterMut<'a> = btree_map::ValuesMut<'a, K, V> where K: 'a, V: 'a;
+        fn iter_mut(&mut self) -> Self::IterMut<'_> {
+            self.values_mut()
+        }
+    }
+
+    impl<K: Ord, V> Drainable for BTreeMap<K, V> {
+        type Item = (K, V);
+        type Drain<'a> = PopDrain<'a, Self, (K, V)> where K: 'a, V: 'a;
+        fn drain(&mut self) -> Self::Drain<'_> {
+            PopDrain::new(self, BTreeMap::pop_first)
+        }
+    }
+
+    /// Storage order, not heap order.
+    impl<T> Iterable for BinaryHeap<T> {
+        type Item = T;
+        type Iter<'a> = binary_heap::Iter<'a, T> where T: 'a;
+        fn iter(&self) -> Self::Iter<'_> {
+            self.iter()
+        }
+    }
+
+    impl<T> Drainable for BinaryHeap<T> {
+        type Item = T;
+        type Drain<'a> = binary_heap::Drain<'a, T> where T: 'a;
+        fn drain(&mut self) -> Self::Drain<'_> {
+            self.drain()
+        }
+    }
+
+    impl<T> Iterable for [T] {
+        type Item = T;
+        type Iter<'a> = slice::Iter<'a, T> where T: 'a;
+        fn iter(&self) -> Self::Iter<'_> {
+            <[T]>::iter(self)
+        }
+    }
+
+    impl<T> IterableMut for [T] {
+        type Item = T;
+        type IterMut<'a> = slice::IterMut<'a, T> where T: 'a;
+        fn iter_mut(&mut self) -> Self::IterMut<'_> {
+            <[T]>::iter_mut(self)
+        }
+    }
+
+    impl<T, const N: usize> Iterable for [T; N] {
+        type Item = T;
+        type Iter<'a> = slice::Iter<'a, T> where T: 'a;
+        fn iter(&self) -> Self::Iter<'_> {
+            self.as_slice().iter()
+        }
+    }
+
+    impl<T, const N: usize> IterableMut for [T; N] {
+        type Item = T;
+        type IterMut<'a> = slice::IterMut<'a, T> where T: 'a;
+        fn iter_mut(&mut self) -> Self::IterMut<'_> {
+            self.as_mut_slice().iter_mut()
+        }
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+mod std_impls {
+    use super::*;
+    use std::collections::{hash_map, hash_set, HashMap, HashSet};
+
+    impl<T, S> Iterable for HashSet<T, S> {
+        type Item = T;
+        type Iter<'a> = hash_set::Iter<'a, T> where T: 'a, S: 'a;
+        fn iter(&self) -> Self::Iter<'_> {
+            self.iter()
+        }
+    }
+
+    impl<T, S> Drainable for HashSet<T, S> {
+        type Item = T;
+        type Drain<'a> = hash_set::Drain<'a, T> where T: 'a, S: 'a;
+        fn drain(&mut self) -> Self::Drain<'_> {
+            self.drain()
+        }
+    }
+
+    impl<K, V, S> Iterable for HashMap<K, V, S> {
+        type Item = V;
+        type Iter<'a> = hash_map::Values<'a, K, V> where K: 'a, V: 'a, S: 'a;
+        fn iter(&self) -> Self::Iter<'_> {
+            self.values()
+        }
+    }
+
+    impl<K, V, S> IterableMut for HashMap<K, V, S> {
+        type Item = V;
+        type IterMut<'a> = hash_map::ValuesMut<'a, K, V> where K: 'a, V: 'a, S: 'a;
+        fn iter_mut(&mut self) -> Self::IterMut<'_> {
+            self.values_mut()
+        }
+    }
+
+    impl<K, V, S> Drainable for HashMap<K, V, S> {
+        type Item = (K, V);
+        type Drain<'a> = hash_map::Drain<'a, K, V> where K: 'a, V: 'a, S: 'a;
+        fn drain(&mut self) -> Self::Drain<'_> {
+            self.drain()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linked_lists::DoublyLinkedList;
+    use crate::{ArrayQueue, SinglyLinkedList};
+    use alloc::collections::VecDeque;
+    use alloc::vec;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    fn check<C>(make: impl Fn(&[i32]) -> C, contents: impl Fn(&C) -> Vec<i32>)
+    where
+        C: DrainableBy<Item = i32> + DrainableRange<Item = i32> + RetainRange<Item = i32>,
+    {
+        let items: Vec<i32> = (0..10).collect();
+
+        let mut c = make(&items);
+        let evens: Vec<_> = c.drain_by(|x| x % 2 == 0).collect();
+        assert_eq!(evens, [0, 2, 4, 6, 8]);
+        assert_eq!(contents(&c), [1, 3, 5, 7, 9]);
+
+        // stopping early leaves everything it didn't get to
+        let mut c = make(&items);
+        assert_eq!(c.drain_by(|x| x % 3 == 0).take(2).collect::<Vec<_>>(), [0, 3]);
+        assert_eq!(contents(&c), [1, 2, 4, 5, 6, 7, 8, 9]);
+
+        // a panicking pred doesn't lose or duplicate anything
+        let mut c = make(&items);
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            c.drain_by(|&x| if x == 5 { panic!("boom") } else { x < 2 })
+                .for_each(drop)
+        }));
+        assert!(res.is_err());
+        assert_eq!(contents(&c), [2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let mut c = make(&items);
+        assert_eq!(c.drain_range(2..5).collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(contents(&c), [0, 1, 5, 6, 7, 8, 9]);
+
+        // the whole range goes even if it isn't all taken
+        let mut c = make(&items);
+        assert_eq!(c.drain_range(..=3).next(), Some(0));
+        assert_eq!(contents(&c), [4, 5, 6, 7, 8, 9]);
+
+        let mut c = make(&items);
+        assert_eq!(c.retain_range(3..6).collect::<Vec<_>>(), [0, 1, 2, 6, 7, 8, 9]);
+        assert_eq!(contents(&c), [3, 4, 5]);
+
+        let mut c = make(&items);
+        drop(c.retain_range(8..));
+        assert_eq!(contents(&c), [8, 9]);
+
+        let mut c = make(&[]);
+        assert_eq!(c.drain_by(|_| true).count(), 0);
+        assert_eq!(c.drain_range(..).count(), 0);
+    }
+
+    #[test]
+    fn vec() {
+        check(|s| s.to_vec(), |v| v.clone());
+    }
+
+    #[test]
+    fn vec_deque() {
+        check(
+            |s| s.iter().copied().collect::<VecDeque<_>>(),
+            |v| v.iter().copied().collect(),
+        );
+    }
+
+    #[test]
+    fn array_queue() {
+        check(
+            |s| s.iter().copied().collect::<ArrayQueue<_>>(),
+            |v| v.iter().copied().collect(),
+        );
+        // wrapped around the end of the buffer
+        check(
+            |s| {
+                let mut q = ArrayQueue::new();
+                for &i in s.iter().rev() {
+                    q.push_front(i).unwrap();
+                }
+                q
+            },
+            |v| v.iter().copied().collect(),
+        );
+    }
+
+    #[test]
+    fn singly_linked_list() {
+        check(
+            |s| s.iter().rev().copied().collect::<SinglyLinkedList<_>>(),
+            |v| v.iter().copied().collect(),
+        );
+    }
+
+    #[test]
+    fn doubly_linked_list() {
+        check(
+            |s| s.iter().copied().collect::<DoublyLinkedList<_>>(),
+            |v| v.iter().copied().collect(),
+        );
+        let mut list: DoublyLinkedList<_> = (0..5).collect();
+        list.drain_by(|&x| x == 0 || x == 4).for_each(drop);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(1));
+    }
+
+    #[test]
+    fn hash_table() {
+        use crate::traits::adt::Creatable;
+        use crate::traits::hash_table::HashTable;
+        use crate::SCHashTable;
+        use std::collections::hash_map::RandomState;
+
+        let mut table: SCHashTable<i32, i32, RandomState> = Creatable::new();
+        let mut drained: Vec<_> = table.drain_by(|_| true).collect();
+        assert!(drained.is_empty());
+        for i in 0..100 {
+            table.insert(i, i * 10).unwrap();
+        }
+        drained = table.drain_by(|(k, _)| k % 4 == 0).collect();
+        drained.sort();
+        assert_eq!(drained, (0..100).step_by(4).map(|i| (i, i * 10)).collect::<Vec<_>>());
+        assert_eq!(table.len(), 75);
+        assert_eq!(table.get(&4), None);
+        assert_eq!(table.get(&5), Some(&50));
+
+        assert_eq!(table.drain_by(|(_, v)| v % 20 == 0).take(3).count(), 3);
+        assert_eq!(table.len(), 72);
+        assert_eq!(table.iter().count(), 72);
+    }
+
+    #[test]
+    fn drops_once() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let mut v = vec![counter.clone(); 6];
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let mut seen = 0;
+            v.drain_by(|_| {
+                seen += 1;
+                assert!(seen < 4);
+                seen % 2 == 0
+            })
+            .for_each(drop)
+        }));
+        assert!(res.is_err());
+        assert_eq!(v.len(), 5);
+        drop(v);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
 }
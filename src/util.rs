@@ -1,12 +1,18 @@
+//! Iterator adapters that std doesn't have, or only has on slices or behind a feature.
+
+mod adapters;
+mod arrays;
+
+pub use adapters::{DedupByKey, Interleave, KWayMerge, MergeBy};
+pub use arrays::{ArrayChunks, ArrayWindows, PartialArray, PartialArrayIntoIter};
+
 pub trait IteratorExactExt: Iterator + ExactSizeIterator {
-    fn groups<const N: usize>(&mut self) -> Groups<'_, Self, N>
+    /// [`array_chunks`](IteratorExt::array_chunks) without giving up the iterator.
+    fn groups<const N: usize>(&mut self) -> ArrayChunks<&mut Self, N>
     where
         Self: Sized,
     {
-        Groups {
-            inner: self,
-            iterations: 0,
-        }
+        ArrayChunks::new(self)
     }
 
     fn collect_to_array<const N: usize>(mut self) -> Option<[Self::Item; N]>
@@ -23,91 +29,120 @@ pub trait IteratorExactExt: Iterator + ExactSizeIterator {
 
 impl<T: ExactSizeIterator + Iterator + Sized> IteratorExactExt for T {}
 
-pub struct Groups<'a, I: Iterator + ExactSizeIterator, const N: usize> {
-    inner: &'a mut I,
-    iterations: usize,
-}
-/*
-impl<'a, I: Iterator + ExactSizeIterator, const N: usize> Groups<'a, I, N> {
-    pub fn new(inner: &'a mut I) -> Groups<'a, I, N> {
-        Self {
-            inner,
-            iterations: 0,
-        }
+pub trait IteratorExt: Iterator {
+    /// Collects the first `N` items without checking there are enough.
+    ///
+    /// # Safety
+    /// The iterator has to have at least `N` items left.
+    unsafe fn collect_to_array_lossy_unchecked<const N: usize>(mut self) -> [Self::Item; N]
+    where
+        Self: Sized,
+        [Self::Item; N - 1]: Sized,
+    {
+        core::array::from_fn(|_| self.next().unwrap_unchecked())
     }
-}*/
 
-impl<'a, I: Iterator + ExactSizeIterator, const N: usize> Iterator for Groups<'a, I, N> {
-    type Item = [I::Item; N];
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.inner.len() < N {
-            return None;
-        }
-        self.iterations += 1;
-        Some(core::array::from_fn(|_| self.inner.next().unwrap()))
+    /// Collects the first `N` items, dropping them if there aren't enough. Use
+    /// [`try_collect_array`](IteratorExt::try_collect_array) to get them back.
+    fn collect_to_array_lossy<const N: usize>(self) -> Option<[Self::Item; N]>
+    where
+        Self: Sized,
+    {
+        self.try_collect_array().ok()
     }
-}
 
-impl<'a, I: Iterator + ExactSizeIterator, const N: usize> ExactSizeIterator for Groups<'a, I, N> {
-    fn len(&self) -> usize {
-        self.inner.len().div_floor(N)
+    /// Collects the first `N` items, or hands back everything it got if there weren't enough.
+    /// Nothing past the first `N` is pulled, so use `by_ref` to keep going afterwards.
+    fn try_collect_array<const N: usize>(
+        mut self,
+    ) -> Result<[Self::Item; N], PartialArray<Self::Item, N>>
+    where
+        Self: Sized,
+    {
+        PartialArray::fill(&mut self)
+    }
+
+    /// `[T; N]`s that don't overlap, anything left over at the end is in
+    /// [`remainder`](ArrayChunks::remainder). Panics if `N` is 0.
+    ///
+    /// The unstable `Iterator::array_chunks` has the same name, so call it as
+    /// `IteratorExt::array_chunks(iter)` to keep the compiler quiet.
+    fn array_chunks<const N: usize>(self) -> ArrayChunks<Self, N>
+    where
+        Self: Sized,
+    {
+        ArrayChunks::new(self)
     }
-}
 
-/*
-const fn type_eq<T, U, const N: usize>() -> bool {
-    size_of::<[T; N]>() == size_of::<[U; N]>()
-        && size_of::<T>() == size_of::<U>()
-        && align_of::<[T; N]>() == align_of::<[U; N]>()
-        && align_of::<T>() == align_of::<U>()
-}*/
+    /// Each run of `N` items in a row, like `slice::windows` but for any iterator. Panics if
+    /// `N` is 0.
+    fn array_windows<const N: usize>(self) -> ArrayWindows<Self, N>
+    where
+        Self: Sized,
+        Self::Item: Clone,
+    {
+        ArrayWindows::new(self)
+    }
 
-pub trait IteratorExt: Iterator {
-    /// tries to collect
-    unsafe fn collect_to_array_lossy_unchecked<const N: usize>(mut self) -> [Self::Item; N]
+    /// One from `self`, then one from `other`, and so on.
+    fn interleave<I>(self, other: I) -> Interleave<Self, I::IntoIter>
     where
         Self: Sized,
-        [Self::Item; N - 1]: Sized,
+        I: IntoIterator<Item = Self::Item>,
     {
-        core::array::from_fn(|_| self.next().unwrap_unchecked())
+        Interleave::new(self, other.into_iter())
     }
-    fn collect_to_array_lossy<const N: usize>(mut self) -> Option<[Self::Item; N]>
+
+    /// Merges two sorted iterators, `first(a, b)` says if `a` from `self` goes before `b` from
+    /// `other`.
+    fn merge_by<I, F>(self, other: I, first: F) -> MergeBy<Self, I::IntoIter, F>
     where
         Self: Sized,
-        [Self::Item; N - 1]: Sized,
-        /*
-        MaybeUninit<Self::Item>: Sized,
-        Self::Item: Sized,
-        [MaybeUninit<Self::Item>; N - 1]: Sized,
-        [(); type_eq::<Self::Item, MaybeUninit<Self::Item>, N>() as usize - 1]: Sized,*/
+        I: IntoIterator<Item = Self::Item>,
+        F: FnMut(&Self::Item, &Self::Item) -> bool,
     {
-        // i was hoping that this would work but oh well
-        /*
-        let mut data: [MaybeUninit<Self::Item>; N] = unsafe { MaybeUninit::uninit().assume_init() };
-        let mut i = 0;
-        while i < N {
-            let elem = if let Some(s) = self.next() {
-                s
-            } else {
-                break;
-            };
-            unsafe { *data.as_mut_ptr().add(i) = MaybeUninit::new(elem) };
-            i += 1;
-        }
+        MergeBy::new(self, other.into_iter(), first)
+    }
+
+    /// Drops the items whose key is the same as the one before.
+    fn dedup_by_key<K, F>(self, key: F) -> DedupByKey<Self, K, F>
+    where
+        Self: Sized,
+        K: PartialEq,
+        F: FnMut(&Self::Item) -> K,
+    {
+        DedupByKey::new(self, key)
+    }
 
-        if i != N {
-            let x = unsafe { transmute::<[MaybeUninit<Self::Item>; N], [Self::Item; N]>(data) };
-            Some(x)
-        } else {
-            for i2 in 0..i {
-                unsafe { data.get_unchecked_mut(i2).assume_init_drop() };
-            }
-            core::mem::forget(data);
-            None
-        }*/
-        // this is easy :/
-        core::array::try_from_fn(|_| self.next())
+    /// Merges an iterator of sorted iterators into one sorted iterator.
+    fn k_way_merge(self) -> KWayMerge<<Self::Item as IntoIterator>::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: IntoIterator,
+        <Self::Item as IntoIterator>::Item: Ord,
+    {
+        KWayMerge::new(self.map(IntoIterator::into_iter))
     }
 }
 
 impl<T: Iterator + Sized> IteratorExt for T {}
+
+/// Turns any `RangeBounds` into a `Range` over `0..len`. Returns `None` if the range is backwards
+/// or doesn't fit in `0..len`.
+pub(crate) fn resolve_range<R: core::ops::RangeBounds<usize>>(
+    range: R,
+    len: usize,
+) -> Option<core::ops::Range<usize>> {
+    use core::ops::Bound::*;
+    let start = match range.start_bound() {
+        Included(s) => *s,
+        Excluded(s) => s.checked_add(1)?,
+        Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Included(e) => e.checked_add(1)?,
+        Excluded(e) => *e,
+        Unbounded => len,
+    };
+    (start <= end && end <= len).then_some(start..end)
+}
@@ -0,0 +1,297 @@
+use crate::heap::binary_heap::BinaryHeap;
+use crate::heap::Heap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::iter::{Fuse, FusedIterator, Peekable};
+
+/// Takes turns between two iterators, once one runs out the rest of the other follows.
+pub struct Interleave<A, B> {
+    a: Fuse<A>,
+    b: Fuse<B>,
+    b_next: bool,
+}
+
+impl<A: Iterator, B: Iterator<Item = A::Item>> Interleave<A, B> {
+    pub(crate) fn new(a: A, b: B) -> Self {
+        Self {
+            a: a.fuse(),
+            b: b.fuse(),
+            b_next: false,
+        }
+    }
+}
+
+impl<A: Iterator, B: Iterator<Item = A::Item>> Iterator for Interleave<A, B> {
+    type Item = A::Item;
+    fn next(&mut self) -> Option<A::Item> {
+        self.b_next = !self.b_next;
+        if self.b_next {
+            self.a.next().or_else(|| self.b.next())
+        } else {
+            self.b.next().or_else(|| self.a.next())
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_lo, a_hi) = self.a.size_hint();
+        let (b_lo, b_hi) = self.b.size_hint();
+        let hi = match (a_hi, b_hi) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        (a_lo.saturating_add(b_lo), hi)
+    }
+}
+
+impl<A: Iterator, B: Iterator<Item = A::Item>> FusedIterator for Interleave<A, B> {}
+
+/// Merges two sorted iterators into one, `first(a, b)` says if `a` goes before `b`. On ties
+/// `first` should return `true` so the left side comes out first and the merge is stable.
+pub struct MergeBy<A: Iterator, B: Iterator, F> {
+    a: Peekable<Fuse<A>>,
+    b: Peekable<Fuse<B>>,
+    first: F,
+}
+
+impl<A, B, F> MergeBy<A, B, F>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+    F: FnMut(&A::Item, &A::Item) -> bool,
+{
+    pub(crate) fn new(a: A, b: B, first: F) -> Self {
+        Self {
+            a: a.fuse().peekable(),
+            b: b.fuse().peekable(),
+            first,
+        }
+    }
+}
+
+impl<A, B, F> Iterator for MergeBy<A, B, F>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+    F: FnMut(&A::Item, &A::Item) -> bool,
+{
+    type Item = A::Item;
+    fn next(&mut self) -> Option<A::Item> {
+        match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) if !(self.first)(a, b) => self.b.next(),
+            (Some(_), _) => self.a.next(),
+            (None, _) => self.b.next(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_lo, a_hi) = self.a.size_hint();
+        let (b_lo, b_hi) = self.b.size_hint();
+        let hi = match (a_hi, b_hi) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        (a_lo.saturating_add(b_lo), hi)
+    }
+}
+
+impl<A, B, F> FusedIterator for MergeBy<A, B, F>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+    F: FnMut(&A::Item, &A::Item) -> bool,
+{
+}
+
+/// Skips items whose key matches the one right before them.
+pub struct DedupByKey<I, K, F> {
+    iter: I,
+    last: Option<K>,
+    key: F,
+}
+
+impl<I: Iterator, K: PartialEq, F: FnMut(&I::Item) -> K> DedupByKey<I, K, F> {
+    pub(crate) fn new(iter: I, key: F) -> Self {
+        Self {
+            iter,
+            last: None,
+            key,
+        }
+    }
+}
+
+impl<I: Iterator, K: PartialEq, F: FnMut(&I::Item) -> K> Iterator for DedupByKey<I, K, F> {
+    type Item = I::Item;
+    fn next(&mut self) -> Option<I::Item> {
+        loop {
+            let item = self.iter.next()?;
+            let key = (self.key)(&item);
+            if self.last.as_ref() != Some(&key) {
+                self.last = Some(key);
+                return Some(item);
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        // only the very first item is sure to come out, after that everything left can be a repeat
+        let lo = if self.last.is_none() { lo.min(1) } else { 0 };
+        (lo, hi)
+    }
+}
+
+// the heap is a min heap, ties go to the iterator that came first so the merge is stable
+struct Head<T> {
+    item: T,
+    source: usize,
+}
+
+impl<T: Ord> Ord for Head<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item
+            .cmp(&other.item)
+            .then(self.source.cmp(&other.source))
+    }
+}
+
+impl<T: Ord> PartialOrd for Head<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> PartialEq for Head<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Head<T> {}
+
+/// Merges any number of sorted iterators, keeping the front of each one in a [`BinaryHeap`].
+pub struct KWayMerge<I: Iterator> {
+    sources: Vec<I>,
+    heads: BinaryHeap<Head<I::Item>>,
+}
+
+impl<I: Iterator> KWayMerge<I>
+where
+    I::Item: Ord,
+{
+    pub(crate) fn new(sources: impl IntoIterator<Item = I>) -> Self {
+        let mut sources: Vec<I> = sources.into_iter().collect();
+        let mut heads = BinaryHeap::new();
+        for (source, iter) in sources.iter_mut().enumerate() {
+            if let Some(item) = iter.next() {
+                heads.insert(Head { item, source });
+            }
+        }
+        Self { sources, heads }
+    }
+}
+
+impl<I: Iterator> Iterator for KWayMerge<I>
+where
+    I::Item: Ord,
+{
+    type Item = I::Item;
+    fn next(&mut self) -> Option<I::Item> {
+        let Head { item, source } = self.heads.extract()?;
+        if let Some(next) = self.sources[source].next() {
+            self.heads.insert(Head { item: next, source });
+        }
+        Some(item)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let queued = self.heads.len();
+        self.sources.iter().map(Iterator::size_hint).fold(
+            (queued, Some(queued)),
+            |(lo, hi), (s_lo, s_hi)| {
+                let hi = match (hi, s_hi) {
+                    (Some(a), Some(b)) => a.checked_add(b),
+                    _ => None,
+                };
+                (lo.saturating_add(s_lo), hi)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::IteratorExt;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn interleave() {
+        let v: Vec<_> = [1, 3, 5].into_iter().interleave([2, 4]).collect();
+        assert_eq!(v, [1, 2, 3, 4, 5]);
+        let v: Vec<_> = [1].into_iter().interleave([2, 3, 4]).collect();
+        assert_eq!(v, [1, 2, 3, 4]);
+        assert_eq!([1].into_iter().interleave([2, 3]).size_hint(), (3, Some(3)));
+    }
+
+    #[test]
+    fn merge_by() {
+        let v: Vec<_> = [1, 4, 6]
+            .into_iter()
+            .merge_by([2, 3, 7, 8], |a, b| a <= b)
+            .collect();
+        assert_eq!(v, [1, 2, 3, 4, 6, 7, 8]);
+        // stable, ties come from the left first
+        let v: Vec<_> = [(1, 'a'), (2, 'a')]
+            .into_iter()
+            .merge_by([(1, 'b'), (2, 'b')], |a, b| a.0 <= b.0)
+            .collect();
+        assert_eq!(v, [(1, 'a'), (1, 'b'), (2, 'a'), (2, 'b')]);
+        let v: Vec<i32> = [].into_iter().merge_by([1], |a, b| a <= b).collect();
+        assert_eq!(v, [1]);
+
+        // stays done even if what it merges doesn't
+        let mut flip = 0;
+        let flaky = core::iter::from_fn(move || {
+            flip += 1;
+            (flip % 2 == 0).then_some(flip)
+        });
+        let mut m = flaky.merge_by([], |a, b| a <= b);
+        assert_eq!(m.next(), None);
+        assert_eq!(m.next(), None);
+    }
+
+    #[test]
+    fn dedup_by_key() {
+        let v: Vec<_> = [1, 1, 2, 3, 3, 3, 1]
+            .into_iter()
+            .dedup_by_key(|x| *x)
+            .collect();
+        assert_eq!(v, [1, 2, 3, 1]);
+        let v: Vec<_> = ["a", "ab", "b", "bc", "abc"]
+            .into_iter()
+            .dedup_by_key(|s| s.as_bytes()[0])
+            .collect();
+        assert_eq!(v, ["a", "b", "abc"]);
+
+        let mut d = [1, 1].into_iter().dedup_by_key(|x| *x);
+        assert_eq!(d.size_hint(), (1, Some(2)));
+        d.next();
+        // the one left is a repeat
+        assert_eq!(d.size_hint(), (0, Some(1)));
+        assert_eq!(d.next(), None);
+    }
+
+    #[test]
+    fn k_way_merge() {
+        let merged: Vec<_> = vec![vec![1, 5, 9], vec![], vec![2, 3, 10], vec![4]]
+            .into_iter()
+            .map(Vec::into_iter)
+            .k_way_merge()
+            .collect();
+        assert_eq!(merged, [1, 2, 3, 4, 5, 9, 10]);
+
+        let iters = (0..5).map(|i| (0..20).map(move |x| x * 5 + i));
+        let merged = iters.k_way_merge();
+        assert_eq!(merged.size_hint(), (100, Some(100)));
+        assert!(merged.eq(0..100));
+
+        let empty = Vec::<vec::IntoIter<u8>>::new().into_iter().k_way_merge();
+        assert_eq!(empty.count(), 0);
+    }
+}
@@ -0,0 +1,333 @@
+use core::fmt::{self, Debug, Formatter};
+use core::iter::{Fuse, FusedIterator};
+use core::mem::{self, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+/// Up to `N` items pulled out of an iterator, what's left when it ran out before filling an
+/// array.
+pub struct PartialArray<T, const N: usize> {
+    data: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> PartialArray<T, N> {
+    fn new() -> Self {
+        Self {
+            data: core::array::from_fn(|_| MaybeUninit::uninit()),
+            len: 0,
+        }
+    }
+
+    /// Pulls from `iter` until the array is full, handing back what it got if `iter` runs out
+    /// first. If `next` panics the items already pulled are dropped with `partial`.
+    pub(crate) fn fill<I: Iterator<Item = T>>(iter: &mut I) -> Result<[T; N], Self> {
+        let mut partial = Self::new();
+        while partial.len < N {
+            match iter.next() {
+                Some(item) => {
+                    partial.data[partial.len].write(item);
+                    partial.len += 1;
+                }
+                None => return Err(partial),
+            }
+        }
+        // every slot is written, so the array can be moved out and the drop skipped
+        let partial = mem::ManuallyDrop::new(partial);
+        Ok(unsafe { ptr::read(partial.data.as_ptr() as *const [T; N]) })
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { &*(&self.data[..self.len] as *const [MaybeUninit<T>] as *const [T]) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { &mut *(&mut self.data[..self.len] as *mut [MaybeUninit<T>] as *mut [T]) }
+    }
+}
+
+impl<T, const N: usize> Deref for PartialArray<T, N> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for PartialArray<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const N: usize> Drop for PartialArray<T, N> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for PartialArray<T, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<T, const N: usize> IntoIterator for PartialArray<T, N> {
+    type Item = T;
+    type IntoIter = PartialArrayIntoIter<T, N>;
+    fn into_iter(self) -> Self::IntoIter {
+        let end = self.len;
+        PartialArrayIntoIter {
+            array: self,
+            start: 0,
+            end,
+        }
+    }
+}
+
+pub struct PartialArrayIntoIter<T, const N: usize> {
+    // `array.len` is left alone, `start..end` are the slots that still need dropping
+    array: PartialArray<T, N>,
+    start: usize,
+    end: usize,
+}
+
+impl<T, const N: usize> Iterator for PartialArrayIntoIter<T, N> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        (self.start < self.end).then(|| {
+            self.start += 1;
+            unsafe { self.array.data[self.start - 1].assume_init_read() }
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.end - self.start, Some(self.end - self.start))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for PartialArrayIntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        (self.start < self.end).then(|| {
+            self.end -= 1;
+            unsafe { self.array.data[self.end].assume_init_read() }
+        })
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for PartialArrayIntoIter<T, N> {}
+impl<T, const N: usize> FusedIterator for PartialArrayIntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for PartialArrayIntoIter<T, N> {
+    fn drop(&mut self) {
+        // the array's own drop would go over the taken ones again, and it still runs if one of
+        // the drops below panics
+        self.array.len = 0;
+        let rest = &mut self.array.data[self.start..self.end];
+        unsafe { ptr::drop_in_place(rest as *mut [MaybeUninit<T>] as *mut [T]) };
+    }
+}
+
+/// Non overlapping `[T; N]`s, whatever doesn't fill the last one is kept as the remainder.
+pub struct ArrayChunks<I: Iterator, const N: usize> {
+    iter: Fuse<I>,
+    remainder: Option<PartialArray<I::Item, N>>,
+}
+
+impl<I: Iterator, const N: usize> ArrayChunks<I, N> {
+    pub(crate) fn new(iter: I) -> Self {
+        assert!(N != 0, "chunks can't be empty");
+        Self {
+            iter: iter.fuse(),
+            remainder: None,
+        }
+    }
+
+    /// The items left over at the end, empty until the iterator has run out.
+    pub fn remainder(&self) -> &[I::Item] {
+        self.remainder.as_deref().unwrap_or(&[])
+    }
+
+    pub fn into_remainder(self) -> Option<PartialArray<I::Item, N>> {
+        self.remainder
+    }
+}
+
+impl<I: Iterator, const N: usize> Iterator for ArrayChunks<I, N> {
+    type Item = [I::Item; N];
+    fn next(&mut self) -> Option<Self::Item> {
+        match PartialArray::fill(&mut self.iter) {
+            Ok(chunk) => Some(chunk),
+            Err(rest) => {
+                if !rest.is_empty() {
+                    self.remainder = Some(rest);
+                }
+                None
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        (lo / N, hi.map(|hi| hi / N))
+    }
+}
+
+impl<I: ExactSizeIterator, const N: usize> ExactSizeIterator for ArrayChunks<I, N> {}
+impl<I: Iterator, const N: usize> FusedIterator for ArrayChunks<I, N> {}
+
+/// Every run of `N` items in a row, so consecutive windows overlap by `N - 1`.
+pub struct ArrayWindows<I: Iterator, const N: usize> {
+    iter: Fuse<I>,
+    window: Option<[I::Item; N]>,
+}
+
+impl<I: Iterator, const N: usize> ArrayWindows<I, N> {
+    pub(crate) fn new(iter: I) -> Self {
+        assert!(N != 0, "windows can't be empty");
+        Self {
+            iter: iter.fuse(),
+            window: None,
+        }
+    }
+}
+
+impl<I: Iterator, const N: usize> Iterator for ArrayWindows<I, N>
+where
+    I::Item: Clone,
+{
+    type Item = [I::Item; N];
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.window {
+            Some(window) => {
+                let item = self.iter.next()?;
+                window.rotate_left(1);
+                window[N - 1] = item;
+            }
+            None => self.window = Some(PartialArray::fill(&mut self.iter).ok()?),
+        }
+        self.window.clone()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        match self.window {
+            Some(_) => (lo, hi),
+            None => (
+                lo.saturating_add(1).saturating_sub(N),
+                hi.and_then(|hi| hi.checked_add(1))
+                    .map(|n| n.saturating_sub(N)),
+            ),
+        }
+    }
+}
+
+impl<I: Iterator, const N: usize> FusedIterator for ArrayWindows<I, N> where I::Item: Clone {}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::IteratorExt;
+    use core::cell::Cell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    #[test]
+    fn chunks() {
+        let mut chunks = IteratorExt::array_chunks::<3>(0..8);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.next(), Some([0, 1, 2]));
+        assert_eq!(chunks.next(), Some([3, 4, 5]));
+        assert_eq!(chunks.remainder(), &[]);
+        assert_eq!(chunks.next(), None);
+        assert_eq!(chunks.next(), None);
+        assert_eq!(chunks.remainder(), &[6, 7]);
+        let rest: Vec<_> = chunks.into_remainder().unwrap().into_iter().collect();
+        assert_eq!(rest, [6, 7]);
+
+        // no remainder when it divides evenly
+        let mut chunks = IteratorExt::array_chunks::<2>((0..6).filter(|_| true));
+        assert_eq!(chunks.by_ref().count(), 3);
+        assert!(chunks.into_remainder().is_none());
+    }
+
+    #[test]
+    fn groups() {
+        use crate::util::IteratorExactExt;
+        let mut iter = 0..7;
+        let mut groups = iter.groups::<2>();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups.by_ref().last(), Some([4, 5]));
+        assert_eq!(groups.remainder(), &[6]);
+    }
+
+    #[test]
+    fn windows() {
+        let windows: Vec<_> = (0..5).array_windows::<3>().collect();
+        assert_eq!(windows, [[0, 1, 2], [1, 2, 3], [2, 3, 4]]);
+        assert_eq!((0..5).array_windows::<3>().size_hint(), (3, Some(3)));
+        assert_eq!((0..2).array_windows::<3>().next(), None);
+        assert_eq!((0..1).array_windows::<1>().collect::<Vec<_>>(), [[0]]);
+    }
+
+    #[test]
+    fn try_collect() {
+        assert_eq!((0..3).try_collect_array::<3>().unwrap(), [0, 1, 2]);
+        let partial = (0..2).try_collect_array::<3>().unwrap_err();
+        assert_eq!(&*partial, &[0, 1]);
+        assert_eq!(partial.into_iter().rev().collect::<Vec<_>>(), [1, 0]);
+        // only takes what it needs
+        let mut iter = 0..5;
+        assert_eq!(iter.by_ref().try_collect_array::<2>().unwrap(), [0, 1]);
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!((0..2).collect_to_array_lossy::<3>(), None);
+    }
+
+    #[test]
+    fn partial_items_drop_once() {
+        let counter = Rc::new(());
+        let partial = core::iter::repeat(counter.clone())
+            .take(3)
+            .try_collect_array::<5>()
+            .unwrap_err();
+        assert_eq!(Rc::strong_count(&counter), 4);
+        let mut iter = partial.into_iter();
+        drop(iter.next());
+        assert_eq!(Rc::strong_count(&counter), 3);
+        drop(iter);
+        assert_eq!(Rc::strong_count(&counter), 1);
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let mut n = 0;
+            core::iter::from_fn(|| {
+                n += 1;
+                assert!(n < 3);
+                Some(counter.clone())
+            })
+            .try_collect_array::<4>()
+        }));
+        assert!(res.is_err());
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn panicking_drop_in_into_iter() {
+        #[derive(Debug)]
+        struct Loud<'a>(usize, &'a Cell<usize>);
+        impl Drop for Loud<'_> {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+                assert!(self.0 != 2, "dropping 2");
+            }
+        }
+        let drops = Cell::new(0);
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let partial = (0..4)
+                .map(|i| Loud(i, &drops))
+                .try_collect_array::<5>()
+                .unwrap_err();
+            let mut iter = partial.into_iter();
+            drop(iter.next());
+            drop(iter);
+        }));
+        assert!(res.is_err());
+        // 2 panics, the ones after it are still dropped and none of them twice
+        assert_eq!(drops.get(), 4);
+    }
+}
@@ -6,266 +6,173 @@
     allocator_api
 )]
 
-mod tester;
-
-use std::{
-    alloc::Global,
-    collections::{hash_map::RandomState, HashMap},
-    fmt::Debug,
-    hash::{BuildHasher, Hash},
-    marker::PhantomData,
-};
-
-use rand::{
-    distributions::{Distribution, Standard},
-    rngs::StdRng,
-    Rng, SeedableRng,
-};
-
-use hash_table::traits::hash_table::HashTable;
+pub mod tester;
 
 const MIN_KEYS: usize = 1;
 
-#[test]
-fn run_test() {
-    let seed = None;
+#[cfg(test)]
+mod tests {
+    use crate::tester::*;
+    use hash_table::{
+        hash::{FxState, LowEntropyState, Sip13State, Sip24State, WyState},
+        linked_lists::{DoublyLinkedList, SinglyLinkedList},
+        SCHashTable,
+    };
+    use rand::{rngs::StdRng, Rng};
+    use std::{alloc::Global, fmt::Debug, panic, path::Path};
+
     #[cfg(miri)]
-    let count = 10;
+    const RUNS: usize = 10;
     #[cfg(not(miri))]
-    let count = 1000;
-    for _ in 0..count {
-        //if let Err(report) = test_hashtable::<crate::HashTable<u8, i64, RandomState>, _, _, RandomState, StdRng>(seed, 4, Some(3)) {
-        if let Err(report) = test_hashtable::<
-            hash_table::hash_table::SCHashTable<u8, i64, RandomState, Global>,
-            _,
-            _,
-            RandomState,
-            StdRng,
-        >(seed, 1000, None)
-        {
-            println!("{:?}", report);
-            report.playback();
-            panic!("died");
+    const RUNS: usize = 1000;
+
+    // the hasher is seeded from the run's seed, so a saved case hashes the same way every replay
+    type TableOperation<S = Sip13State> =
+        HashTableOperation<SCHashTable<u8, i64, S, Global>, u8, i64, S>;
+
+    // failures are shrunk and saved here, `saved_cases` replays everything in it
+    const REGRESSIONS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/regressions");
+
+    /// runs `O` against its model `runs` times with fresh seeds, on the first failure it's
+    /// shrunk, played back and saved under `name` before panicking
+    fn check<O>(name: &str, runs: usize, ops: usize, starting_capacity: Option<usize>)
+    where
+        O: OperationGen<StdRng> + Shrink + Clone + Debug,
+        O::Result: Eq + Debug,
+        O::Target: Debug,
+    {
+        for _ in 0..runs {
+            let seed = rand::thread_rng().gen();
+            let case = match panic::catch_unwind(|| {
+                test_operations::<O, StdRng>(Some(seed), ops, starting_capacity)
+            }) {
+                Ok(Ok(())) => continue,
+                Ok(Err(report)) => {
+                    println!("failed at operation {} of seed {:?}", report.op_num, seed);
+                    report.case()
+                }
+                // the panic's message is already out, the case finds where it was
+                Err(_) => Case::generated(seed, starting_capacity.unwrap_or(DEFAULT_CAPACITY), ops),
+            };
+            let case = case.shrink();
+            case.playback();
+            let path = case.save(&Path::new(REGRESSIONS).join(name));
+            panic!(
+                "died, minimal case saved to {:?}:\n{}",
+                path,
+                case.to_text()
+            );
         }
     }
-}
-
-trait DeterministicHasher: BuildHasher {
-    type Seed;
-    fn from_seed(seed: Self::Seed) -> Self;
-}
 
-impl DeterministicHasher for RandomState {
-    type Seed = [u8; 32];
-    // I'm trying to make the test_hashtable function deterministic but randomstate is annoying :/
-    fn from_seed(seed: Self::Seed) -> Self {
-        // RandomState is `RandomState {k1: u64, k2: u64}` so its equal size to (u64, u64). transmuting should be fine.
-        unsafe {
-            use std::mem::{size_of, transmute};
-            #[allow(dead_code)]
-            const SIZE_CHECK: u8 = (size_of::<(u64, u64)>() == size_of::<RandomState>()) as u8 - 1;
-            let a: [u64; 4] = transmute(seed);
-            transmute((a[0] ^ a[1], a[2] ^ a[3]))
+    fn replay_saved<O>(name: &str)
+    where
+        O: OperationGen<StdRng> + OperationText,
+        O::Result: Eq + Debug,
+        O::Target: Debug,
+    {
+        let dir = Path::new(REGRESSIONS).join(name);
+        for (path, case) in Case::<O, StdRng>::load_dir(&dir).expect("couldn't read saved cases") {
+            if let Err(e) = case.run() {
+                case.playback();
+                panic!("{} failed: {:?}", path.display(), e);
+            }
         }
     }
-}
 
-fn test_hashtable<H, K, V, S, R>(
-    seed: Option<R::Seed>,
-    ops: usize,
-    starting_capacity: Option<usize>,
-) -> Result<(), HashTableFailure<H, K, V, S, R>>
-where
-    H: HashTable<K, V, S, Global> + Debug,
-    K: Hash + Eq + Copy + Debug,
-    V: Copy + Eq + Debug,
-    S: BuildHasher + DeterministicHasher<Seed = R::Seed>,
-    R: SeedableRng + Rng,
-    R::Seed: Clone,
-    Standard: Distribution<K>,
-    Standard: Distribution<V>,
-    Standard: Distribution<R::Seed>,
-{
-    let seed: R::Seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
-    let starting_capacity = starting_capacity.unwrap_or(50);
-    let hash_builder = S::from_seed(seed.clone());
-    let mut table =
-        H::with_capacity_and_hasher_in(starting_capacity, hash_builder, Global).unwrap();
-
-    let mut gen: OperationGenerator<K, V, R> = OperationGenerator::from_seed(seed.clone());
-    let mut op_num = 0;
-    for (op, res) in (&mut gen).take(ops) {
-        op_num += 1;
-        let r = op.apply(&mut table);
-        if r != res {
-            let mut op_gen = OperationGenerator::<K, V, R>::from_seed(seed.clone());
-            let operations: Vec<_> = (&mut op_gen).take(op_num).collect();
-            return Err(HashTableFailure {
-                seed,
-                starting_capacity,
-                table,
-                op_num,
-                operations,
-                data: op_gen.data,
-                marker: PhantomData,
-            });
-        }
+    #[test]
+    fn run_test() {
+        check::<TableOperation>("hash_table", RUNS, 1000, None);
     }
 
-    Ok(())
-}
-
-use std::iter::*;
-
-#[derive(Debug)]
-struct HashTableFailure<H, K, V, S, R>
-where
-    H: HashTable<K, V, S, Global> + Debug,
-    S: BuildHasher,
-    R: SeedableRng + Rng,
-    R::Seed: Clone,
-    K: Hash + Eq + Debug,
-    V: Debug,
-{
-    pub seed: R::Seed,
-    pub starting_capacity: usize,
-    pub table: H,
-    #[allow(dead_code)]
-    pub data: HashMap<K, V>,
-    #[allow(dead_code)]
-    pub operations: Vec<(Operation<K, V>, Option<V>)>,
-    pub op_num: usize,
-    marker: PhantomData<S>,
-}
-
-impl<H, K, V, S, R> HashTableFailure<H, K, V, S, R>
-where
-    H: HashTable<K, V, S, Global> + Debug,
-    S: BuildHasher + DeterministicHasher<Seed = R::Seed>,
-    R: SeedableRng + Rng,
-    R::Seed: Clone,
-    K: Hash + Eq + Debug + Copy,
-    V: Debug + Copy + Eq,
-    Standard: Distribution<R::Seed>,
-    Standard: Distribution<K>,
-    Standard: Distribution<V>,
-{
-    fn playback(&self) {
-        println!("running playback");
-        let mut table = H::with_capacity_and_hasher_in(
-            self.starting_capacity,
-            S::from_seed(self.seed.clone()),
-            Global,
-        )
-        .expect("failed alloc");
-
-        let mut gen: OperationGenerator<K, V, R> = OperationGenerator::from_seed(self.seed.clone());
-        let lower = self.op_num.saturating_sub(5);
-        for _ in 0..lower {
-            let (op, r) = gen.next().unwrap();
-            assert_eq!(r, op.apply(&mut table));
-        }
+    #[test]
+    fn hash_table_other_hashers() {
+        check::<TableOperation<Sip24State>>("hash_table_sip24", RUNS / 5, 1000, None);
+        check::<TableOperation<FxState>>("hash_table_fx", RUNS / 5, 1000, None);
+        check::<TableOperation<WyState>>("hash_table_wy", RUNS / 5, 1000, None);
+    }
 
-        for (ind, (op, re)) in gen
-            .enumerate()
-            .take(std::cmp::min(lower + 5, self.op_num) + 1)
-        {
-            let r = op.apply(&mut table);
-            println!("--------- operation {} ----------", (lower) + ind);
-            println!("operation: {:?}", op);
-            println!("table_state: {:?}", &self.table);
-            println!("expected / actual : {:?}, {:?}", re, r);
-        }
+    #[test]
+    fn hash_table_collisions() {
+        // seeded with DEFAULT_BITS = 2, so only 4 different hashes and every bucket that gets
+        // used has a long chain
+        check::<TableOperation<LowEntropyState>>("hash_table_low_entropy", RUNS / 5, 1000, Some(8));
     }
-}
 
-struct OperationGenerator<K, V, R = StdRng>
-where
-    R: Rng + SeedableRng,
-{
-    rng: R,
-    data: HashMap<K, V>,
-}
+    #[test]
+    fn array_queue() {
+        // a small starting capacity so it has to grow
+        check::<QueueOperation<u16>>("array_queue", RUNS / 5, 1000, Some(3));
+    }
 
-impl<K, V, R> OperationGenerator<K, V, R>
-where
-    R: Rng + SeedableRng,
-{
-    fn from_seed(seed: R::Seed) -> Self {
-        Self {
-            rng: R::from_seed(seed),
-            data: HashMap::new(),
-        }
+    #[test]
+    fn singly_linked_list() {
+        check::<ListOperation<SinglyLinkedList<u16>, u16>>(
+            "singly_linked_list",
+            RUNS / 5,
+            500,
+            None,
+        );
     }
-}
 
-impl<K, V, R> Iterator for OperationGenerator<K, V, R>
-where
-    K: Copy + Hash + Eq,
-    V: Copy,
-    R: SeedableRng + Rng,
-    Standard: Distribution<K>,
-    Standard: Distribution<V>,
-{
-    type Item = (Operation<K, V>, Option<V>);
+    #[test]
+    fn doubly_linked_list() {
+        check::<ListOperation<DoublyLinkedList<u16>, u16>>(
+            "doubly_linked_list",
+            RUNS / 5,
+            500,
+            None,
+        );
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        //  this prevents insert operations if there arent enough keys
-        let range = if self.data.len() > MIN_KEYS {
-            0..std::mem::variant_count::<Operation<K, V>>()
-        } else {
-            0..1
-        };
+    #[test]
+    fn binary_heap() {
+        check::<HeapOperation<u16>>("binary_heap", RUNS / 5, 1000, None);
+    }
 
-        match self.rng.gen_range(range) {
-            0 => {
-                let key: K = self.rng.gen();
-                let val: V = self.rng.gen();
-                let res: Option<V> = self.data.insert(key, val);
-                Some((Operation::Insert(key, val), res))
-            }
-            1 => {
-                let get_existing = self.rng.gen_bool(0.75);
-                let key: K = if get_existing {
-                    let ind = self.rng.gen_range(0..self.data.len());
-                    *self.data.keys().nth(ind).unwrap()
-                } else {
-                    self.rng.gen()
-                };
+    #[test]
+    fn bit_string() {
+        check::<BitStringOperation>("bit_string", RUNS / 5, 500, Some(0));
+    }
 
-                let res = self.data.get(&key);
+    #[test]
+    fn saved_cases() {
+        replay_saved::<TableOperation>("hash_table");
+        replay_saved::<TableOperation<Sip24State>>("hash_table_sip24");
+        replay_saved::<TableOperation<FxState>>("hash_table_fx");
+        replay_saved::<TableOperation<WyState>>("hash_table_wy");
+        replay_saved::<TableOperation<LowEntropyState>>("hash_table_low_entropy");
+        replay_saved::<QueueOperation<u16>>("array_queue");
+        replay_saved::<ListOperation<SinglyLinkedList<u16>, u16>>("singly_linked_list");
+        replay_saved::<ListOperation<DoublyLinkedList<u16>, u16>>("doubly_linked_list");
+        replay_saved::<HeapOperation<u16>>("binary_heap");
+        replay_saved::<BitStringOperation>("bit_string");
+    }
 
-                Some((Operation::Get(key), res.copied()))
-            }
-            2 => {
-                let key: K = {
-                    let ind = self.rng.gen_range(0..self.data.len());
-                    *self.data.keys().nth(ind).unwrap()
-                };
-                Some((Operation::Remove(key), self.data.remove(&key)))
-            }
-            _ => unreachable!(),
+    #[test]
+    fn same_seed_same_operations() {
+        // failure reports regenerate the operations from the seed, so they have to come out the same
+        fn ops<O>() -> String
+        where
+            O: OperationGen<StdRng> + Debug,
+            O::Result: Debug,
+        {
+            let ops: Vec<_> = O::gen_from_seed([7; 32]).take(200).collect();
+            format!("{:?}", ops)
         }
+        assert_eq!(ops::<TableOperation>(), ops::<TableOperation>());
+        assert_eq!(ops::<QueueOperation<u16>>(), ops::<QueueOperation<u16>>());
     }
-}
-
-#[derive(Clone, Copy, Debug)]
-enum Operation<K, V> {
-    Insert(K, V),
-    Remove(K),
-    Get(K),
-}
 
-impl<K, V> Operation<K, V>
-where
-    K: Hash + Eq + Copy + Eq + Debug,
-    V: Copy + Eq + Debug,
-{
-    fn apply<S: BuildHasher, H: HashTable<K, V, S, Global>>(&self, table: &mut H) -> Option<V> {
-        match self {
-            Self::Insert(key, value) => table.insert(*key, *value).expect("failed alloc"),
-            Self::Get(key) => table.get(key).copied(),
-            Self::Remove(key) => table.remove(key),
+    #[test]
+    fn same_seed_same_table() {
+        // the table's hasher comes from the seed too, so the layout has to match between runs
+        let mut a = <TableOperation as OperationGen<StdRng>>::new_target(DEFAULT_CAPACITY, [9; 32]);
+        let mut b = <TableOperation as OperationGen<StdRng>>::new_target(DEFAULT_CAPACITY, [9; 32]);
+        for key in 0..40 {
+            TableOperation::Insert(key, key as i64).apply(&mut a);
+            TableOperation::Insert(key, key as i64).apply(&mut b);
         }
+        assert!(a.iter().eq(b.iter()));
     }
 }
@@ -1,147 +1,139 @@
-use std::{
-    alloc::Global,
-    collections::{hash_map::RandomState, HashMap},
-    fmt::Debug,
-    hash::{BuildHasher, Hash},
-    marker::PhantomData,
-};
-
-use hash_table::traits::hash_table::HashTable;
+use std::fmt::{self, Debug, Formatter};
 
-use super::{DeterministicHasher, MIN_KEYS};
 use rand::{
     distributions::{Distribution, Standard},
-    rngs::StdRng,
     Rng, SeedableRng,
 };
 
+mod case;
+mod ops;
 mod traits;
-use traits::*;
+pub use case::*;
+pub use ops::*;
+pub use traits::*;
 
-/*
-impl<K, V, R> HashTableOperationGenerator<K, V, R>
-where
-    R: Rng + SeedableRng,
-{
-    fn from_seed(seed: R::Seed) -> Self {
-        Self {
-            rng: R::from_seed(seed),
-            data: HashMap::new(),
-        }
-    }
-}*/
-fn test_hashtable<T, O, K, V, S, R>(
+/// what targets start with when a run doesn't say
+pub const DEFAULT_CAPACITY: usize = 50;
+
+/// Runs `ops` generated operations on a fresh target and the generator's reference
+/// implementation, stopping at the first result or state that doesn't match.
+pub fn test_operations<O, R>(
     seed: Option<R::Seed>,
     ops: usize,
     starting_capacity: Option<usize>,
-) -> Result<(), OperationFailure<T, O, S, R>>
+) -> Result<(), OperationFailure<O, R>>
 where
-    O: OperationGen<R> + Debug,
+    O: OperationGen<R>,
     O::Result: Eq,
-    K: Hash + Eq + Copy + Debug,
-    V: Copy + Eq + Debug,
-    <O::Generator as OperationGenerator<R>>::ReferenceImpl: Debug + Default,
-    //<O::Generator as OperationGenerator<R>>::Operation::Target: HashTable<K, V, S, Global> + Debug,
-    <<<O as OperationGen<R>>::Generator as OperationGenerator<R>>::Operation as Operation>::Target:
-        HashTable<K, V, S, Global> + Debug,
-    S: BuildHasher + DeterministicHasher<Seed = R::Seed>,
     R: SeedableRng + Rng,
     R::Seed: Clone,
-    Standard: Distribution<K>,
-    Standard: Distribution<V>,
     Standard: Distribution<R::Seed>,
 {
-    /*
     let seed: R::Seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
-    let starting_capacity = starting_capacity.unwrap_or(50);
-    let hash_builder = S::from_seed(seed.clone());
-    let mut target =
-        T::with_capacity_and_hasher_in(starting_capacity, hash_builder, Global).unwrap();
+    let starting_capacity = starting_capacity.unwrap_or(DEFAULT_CAPACITY);
+    let mut target = O::new_target(starting_capacity, seed.clone());
 
-    let mut gen: O::Generator = O::gen_from_seed(seed.clone());
-    let mut op_num = 0;
-    for (op, res) in (&mut gen).take(ops) {
-        op_num += 1;
-        let r = op.apply(&mut target);
-        if r != res {
+    let mut gen = O::gen_from_seed(seed.clone());
+    for op_num in 1..=ops {
+        let (op, expected) = gen.next().expect("generators don't run out");
+        let actual = op.apply(&mut target);
+        if actual != expected || !gen.agrees_with(&target) {
             let mut op_gen = O::gen_from_seed(seed.clone());
             let operations: Vec<_> = (&mut op_gen).take(op_num).collect();
             return Err(OperationFailure {
                 seed,
                 starting_capacity,
                 target,
-                op_num,
-                operations,
                 data: op_gen.data(),
-                marker: PhantomData,
+                operations,
+                actual,
+                op_num,
             });
         }
     }
     Ok(())
-    */
-    todo!()
 }
 
-#[derive(Debug)]
-struct OperationFailure<T, O, S, R>
+pub struct OperationFailure<O, R>
 where
     O: OperationGen<R>,
-    S: BuildHasher + DeterministicHasher<Seed = R::Seed>,
     R: SeedableRng + Rng,
-    R::Seed: Clone,
-    <<O as OperationGen<R>>::Generator as OperationGenerator<R>>::ReferenceImpl: Debug,
 {
     pub seed: R::Seed,
     pub starting_capacity: usize,
-    pub target: T,
-    #[allow(dead_code)]
-    pub data: <<O as OperationGen<R>>::Generator as OperationGenerator<R>>::ReferenceImpl,
-    #[allow(dead_code)]
+    pub target: O::Target,
+    /// the reference implementation as it was after the failing operation
+    pub data: <O::Generator as OperationGenerator<R>>::ReferenceImpl,
+    /// everything up to and including the failing operation, with the expected results
     pub operations: Vec<(O, O::Result)>,
+    /// what the target returned for the failing operation
+    pub actual: O::Result,
     pub op_num: usize,
-    marker: PhantomData<S>,
 }
 
-impl<T, G, S, R> OperationFailure<T, G, S, R>
+impl<O, R> Debug for OperationFailure<O, R>
 where
-    G: OperationGenerator<R> + OperationGen<R>,
-    G::Operation: Debug,
-    G::Result: Debug,
-    S: BuildHasher + DeterministicHasher<Seed = R::Seed>,
+    O: OperationGen<R> + Debug,
+    O::Result: Debug,
+    O::Target: Debug,
+    <O::Generator as OperationGenerator<R>>::ReferenceImpl: Debug,
     R: SeedableRng + Rng,
-    <<G as OperationGen<R>>::Generator as OperationGenerator<R>>::ReferenceImpl: Debug,
-    R::Seed: Clone,
-    //K: Hash + Eq + Debug + Copy,
-    Standard: Distribution<R::Seed>,
-    //Standard: Distribution<K>,
-    //Standard: Distribution<<<G as tester::OperationGenerator<T, R>>::Operation as Trait>::Result>,
+    R::Seed: Debug,
 {
-    fn playback(&self) {
-        /*
-        println!("running playback");
-        let mut table = T::with_capacity_and_hasher_in(
-            self.starting_capacity,
-            S::from_seed(self.seed.clone()),
-            Global,
-        )
-        .expect("failed alloc");
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (op, expected) = &self.operations[self.op_num - 1];
+        f.debug_struct("OperationFailure")
+            .field("seed", &self.seed)
+            .field("starting_capacity", &self.starting_capacity)
+            .field("op_num", &self.op_num)
+            .field("operation", op)
+            .field("expected", expected)
+            .field("actual", &self.actual)
+            .field("target", &self.target)
+            .field("data", &self.data)
+            .finish()
+    }
+}
 
-        let mut gen: OperationGenerator<K, V, R> = OperationGenerator::from_seed(self.seed.clone());
-        let lower = self.op_num.saturating_sub(5);
-        for _ in 0..lower {
-            let (op, r) = gen.next().unwrap();
-            assert_eq!(r, op.apply(&mut table));
+impl<O, R> OperationFailure<O, R>
+where
+    O: OperationGen<R> + Clone,
+    R: SeedableRng + Rng,
+    R::Seed: Clone,
+{
+    /// the operations up to the failure as a [`Case`], ready to [`shrink`](Case::shrink)
+    pub fn case(&self) -> Case<O, R> {
+        Case {
+            seed: self.seed.clone(),
+            starting_capacity: self.starting_capacity,
+            operations: self.operations.iter().map(|(op, _)| op.clone()).collect(),
         }
+    }
+}
 
-        for (ind, (op, re)) in gen
-            .enumerate()
-            .take(std::cmp::min(lower + 5, self.op_num) + 1)
-        {
-            let r = op.apply(&mut table);
-            println!("--------- operation {} ----------", (lower) + ind);
+impl<O, R> OperationFailure<O, R>
+where
+    O: OperationGen<R> + Debug,
+    O::Result: Debug,
+    O::Target: Debug,
+    R: SeedableRng + Rng,
+    R::Seed: Clone,
+{
+    /// replays the run, printing the last few operations before the failure with the target's
+    /// state after each one
+    pub fn playback(&self) {
+        println!("running playback");
+        let mut target = O::new_target(self.starting_capacity, self.seed.clone());
+        let lower = self.op_num.saturating_sub(5);
+        for (ind, (op, expected)) in self.operations.iter().enumerate() {
+            let actual = op.apply(&mut target);
+            if ind < lower {
+                continue;
+            }
+            println!("--------- operation {} ----------", ind + 1);
             println!("operation: {:?}", op);
-            println!("table_state: {:?}", &self.table);
-            println!("expected / actual : {:?}, {:?}", re, r);
-        }*/
+            println!("target_state: {:?}", &target);
+            println!("expected / actual : {:?}, {:?}", expected, actual);
+        }
     }
 }
@@ -0,0 +1,497 @@
+use super::traits::*;
+use rand::{Rng, SeedableRng};
+use std::cell::Cell;
+use std::fmt::{Debug, Write};
+use std::fs;
+use std::io;
+use std::mem::{self, Discriminant};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+/// A run that can be replayed on its own. Every operation is written out, the seed is only
+/// there for targets that need randomness like a hasher.
+pub struct Case<O, R: SeedableRng> {
+    pub seed: R::Seed,
+    pub starting_capacity: usize,
+    pub operations: Vec<O>,
+}
+
+/// Where and why a [`Case`] stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaseError {
+    /// the operation at this index isn't valid for the reference at that point
+    Invalid(usize),
+    /// the target disagreed with the reference at this index
+    Mismatch(usize),
+    /// the target panicked at this index
+    Panicked(usize),
+}
+
+impl CaseError {
+    pub fn index(self) -> usize {
+        match self {
+            Self::Invalid(i) | Self::Mismatch(i) | Self::Panicked(i) => i,
+        }
+    }
+}
+
+impl<O, R> Case<O, R>
+where
+    O: OperationGen<R>,
+    O::Result: Eq,
+    R: SeedableRng + Rng,
+    R::Seed: Clone,
+{
+    /// The first `len` operations generated from `seed`.
+    pub fn generated(seed: R::Seed, starting_capacity: usize, len: usize) -> Self {
+        Self {
+            operations: O::gen_from_seed(seed.clone())
+                .take(len)
+                .map(|(op, _)| op)
+                .collect(),
+            seed,
+            starting_capacity,
+        }
+    }
+
+    /// Runs the operations on a fresh target and reference.
+    pub fn run(&self) -> Result<(), CaseError> {
+        let mut target = O::new_target(self.starting_capacity, self.seed.clone());
+        let mut reference = O::gen_from_seed(self.seed.clone());
+        for (i, op) in self.operations.iter().enumerate() {
+            let expected = reference.replay(op).ok_or(CaseError::Invalid(i))?;
+            let Some(actual) = apply_caught(op, &mut target) else {
+                mem::forget(target);
+                return Err(CaseError::Panicked(i));
+            };
+            if actual != expected || !reference.agrees_with(&target) {
+                return Err(CaseError::Mismatch(i));
+            }
+        }
+        Ok(())
+    }
+
+    /// prints every operation with the expected and actual results and the target afterwards
+    pub fn playback(&self)
+    where
+        O: Debug,
+        O::Result: Debug,
+        O::Target: Debug,
+    {
+        let mut target = O::new_target(self.starting_capacity, self.seed.clone());
+        let mut reference = O::gen_from_seed(self.seed.clone());
+        for (i, op) in self.operations.iter().enumerate() {
+            let expected = reference.replay(op);
+            println!("--------- operation {} ----------", i + 1);
+            println!("operation: {:?}", op);
+            let Some(actual) = apply_caught(op, &mut target) else {
+                println!("expected {:?} but it panicked", expected);
+                mem::forget(target);
+                return;
+            };
+            println!("target_state: {:?}", &target);
+            println!("expected / actual : {:?}, {:?}", expected, actual);
+        }
+    }
+}
+
+thread_local! {
+    static QUIET: Cell<bool> = Cell::new(false);
+}
+
+static QUIET_HOOK: Once = Once::new();
+
+/// Keeps panics on this thread from printing while it's alive. The hook is installed once and
+/// wraps whatever was there, other threads (like other tests) still print as usual.
+struct Quiet {
+    was: bool,
+}
+
+impl Quiet {
+    fn new() -> Self {
+        QUIET_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                if !QUIET.with(Cell::get) {
+                    previous(info)
+                }
+            }));
+        });
+        Self {
+            was: QUIET.with(|quiet| quiet.replace(true)),
+        }
+    }
+}
+
+// also runs when shrinking itself panics
+impl Drop for Quiet {
+    fn drop(&mut self) {
+        QUIET.with(|quiet| quiet.set(self.was))
+    }
+}
+
+/// `None` if the operation panicked, the target should be leaked then since it could be in any
+/// state and dropping it could double free
+fn apply_caught<O: Operation>(op: &O, target: &mut O::Target) -> Option<O::Result> {
+    panic::catch_unwind(AssertUnwindSafe(|| op.apply(target))).ok()
+}
+
+impl<O, R> Case<O, R>
+where
+    O: OperationGen<R> + Shrink + Clone,
+    O::Result: Eq,
+    R: SeedableRng + Rng,
+    R::Seed: Clone,
+{
+    /// Delta debugging: drops chunks of operations, halving the chunk size whenever a pass
+    /// can't drop anything, then tries simpler values for what's left, until neither helps. A
+    /// case that doesn't fail comes back as it was.
+    ///
+    /// Only candidates that fail the same way are kept, so a mismatch doesn't turn into some
+    /// other panic along the way. Panic messages are silenced while it works.
+    pub fn shrink(mut self) -> Self {
+        let failure = match self.run() {
+            Err(failure @ (CaseError::Mismatch(_) | CaseError::Panicked(_))) => failure,
+            _ => return self,
+        };
+        self.operations.truncate(failure.index() + 1);
+        let _quiet = Quiet::new();
+        let kind = mem::discriminant(&failure);
+        loop {
+            let len = self.operations.len();
+            self.remove_chunks(kind);
+            if !self.simplify(kind) && self.operations.len() == len {
+                break;
+            }
+        }
+        self
+    }
+
+    /// keeps `operations` if they still fail the same way, cut down to the one that fails
+    fn keep_if_failing(&mut self, operations: Vec<O>, kind: Discriminant<CaseError>) -> bool {
+        let candidate = Case::<O, R> {
+            seed: self.seed.clone(),
+            starting_capacity: self.starting_capacity,
+            operations,
+        };
+        match candidate.run() {
+            Err(failure) if mem::discriminant(&failure) == kind => {
+                self.operations = candidate.operations;
+                self.operations.truncate(failure.index() + 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remove_chunks(&mut self, kind: Discriminant<CaseError>) {
+        let mut chunk = self.operations.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start < self.operations.len() {
+                let end = (start + chunk).min(self.operations.len());
+                let mut operations = self.operations.clone();
+                operations.drain(start..end);
+                // on success the next chunk has moved down to `start`
+                if !self.keep_if_failing(operations, kind) {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+    }
+
+    fn simplify(&mut self, kind: Discriminant<CaseError>) -> bool {
+        let mut changed = false;
+        let mut i = 0;
+        // the length can drop when a simpler operation fails sooner
+        while i < self.operations.len() {
+            for simpler in self.operations[i].simpler() {
+                let mut operations = self.operations.clone();
+                operations[i] = simpler;
+                if self.keep_if_failing(operations, kind) {
+                    changed = true;
+                    break;
+                }
+            }
+            i += 1;
+        }
+        changed
+    }
+}
+
+impl<O, R: SeedableRng> Case<O, R>
+where
+    R::Seed: Clone,
+{
+    /// `seed <hex>` and `capacity <n>` lines then one operation per line, blank lines and lines
+    /// starting with `#` are skipped when reading it back
+    pub fn to_text(&self) -> String
+    where
+        O: Debug,
+    {
+        let mut seed = self.seed.clone();
+        let mut text = String::from("seed ");
+        for byte in seed.as_mut() {
+            write!(text, "{:02x}", byte).unwrap();
+        }
+        writeln!(text, "\ncapacity {}", self.starting_capacity).unwrap();
+        for op in &self.operations {
+            writeln!(text, "{:?}", op).unwrap();
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Option<Self>
+    where
+        O: OperationText,
+    {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let hex = lines.next()?.strip_prefix("seed ")?.trim();
+        let mut seed = R::Seed::default();
+        let bytes = seed.as_mut();
+        if hex.len() != bytes.len() * 2 {
+            return None;
+        }
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+        }
+        let starting_capacity = lines
+            .next()?
+            .strip_prefix("capacity ")?
+            .trim()
+            .parse()
+            .ok()?;
+        let operations = lines.map(O::from_text).collect::<Option<_>>()?;
+        Some(Self {
+            seed,
+            starting_capacity,
+            operations,
+        })
+    }
+
+    /// Writes the case into `dir`, named after its seed and length so re-saving the same case
+    /// doesn't pile up copies.
+    pub fn save(&self, dir: &Path) -> io::Result<PathBuf>
+    where
+        O: Debug,
+    {
+        fs::create_dir_all(dir)?;
+        let text = self.to_text();
+        let seed: String = text["seed ".len()..]
+            .chars()
+            .take_while(char::is_ascii_hexdigit)
+            .take(16)
+            .collect();
+        let path = dir.join(format!("{}-{}.case", seed, self.operations.len()));
+        fs::write(&path, text)?;
+        Ok(path)
+    }
+
+    /// Every `.case` file in `dir`, a missing directory just has none.
+    pub fn load_dir(dir: &Path) -> io::Result<Vec<(PathBuf, Self)>>
+    where
+        O: OperationText,
+    {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut cases = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != "case") {
+                continue;
+            }
+            let case = Self::from_text(&fs::read_to_string(&path)?).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("couldn't parse {}", path.display()),
+                )
+            })?;
+            cases.push((path, case));
+        }
+        // read_dir's order isn't stable
+        cases.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(cases)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    // a stack that loses anything over 100 pushed once it has two items
+    #[derive(Debug, Default)]
+    struct LossyStack(Vec<u8>);
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum StackOperation {
+        Push(u8),
+        Pop,
+    }
+
+    impl Operation for StackOperation {
+        type Result = Option<u8>;
+        type Target = LossyStack;
+        fn apply(&self, stack: &mut LossyStack) -> Option<u8> {
+            match *self {
+                Self::Push(item) if item > 100 && stack.0.len() >= 2 => None,
+                Self::Push(item) => {
+                    stack.0.push(item);
+                    None
+                }
+                Self::Pop => stack.0.pop(),
+            }
+        }
+    }
+
+    impl<R: Rng + SeedableRng> OperationGen<R> for StackOperation {
+        type Generator = ModelGenerator<Self, Vec<u8>, R>;
+        fn new_target(_capacity: usize, _seed: R::Seed) -> LossyStack {
+            LossyStack::default()
+        }
+    }
+
+    impl<R: Rng> Model<StackOperation, R> for Vec<u8> {
+        fn choose(&self, rng: &mut R) -> StackOperation {
+            if rng.gen_ratio(2, 3) {
+                StackOperation::Push(rng.gen())
+            } else {
+                StackOperation::Pop
+            }
+        }
+        fn apply(&mut self, op: &StackOperation) -> Option<Option<u8>> {
+            Some(match *op {
+                StackOperation::Push(item) => {
+                    self.push(item);
+                    None
+                }
+                StackOperation::Pop => self.pop(),
+            })
+        }
+        fn agrees_with(&self, stack: &LossyStack) -> bool {
+            *self == stack.0
+        }
+    }
+
+    impl Shrink for StackOperation {
+        fn simpler(&self) -> Vec<Self> {
+            match *self {
+                Self::Push(item) => item.simpler().into_iter().map(Self::Push).collect(),
+                Self::Pop => Vec::new(),
+            }
+        }
+    }
+
+    impl OperationText for StackOperation {
+        fn from_text(text: &str) -> Option<Self> {
+            match parse_call(text)? {
+                ("Push", args) if args.len() == 1 => Some(Self::Push(args[0].parse().ok()?)),
+                ("Pop", args) if args.is_empty() => Some(Self::Pop),
+                _ => None,
+            }
+        }
+    }
+
+    fn failing_case() -> Case<StackOperation, StdRng> {
+        let mut seed = 0;
+        loop {
+            let operations = <StackOperation as OperationGen<StdRng>>::gen_from_seed([seed; 32])
+                .take(200)
+                .map(|(op, _)| op)
+                .collect();
+            let case = Case {
+                seed: [seed; 32],
+                starting_capacity: 0,
+                operations,
+            };
+            if case.run().is_err() {
+                return case;
+            }
+            seed += 1;
+        }
+    }
+
+    #[test]
+    fn shrinks_to_the_smallest_failure() {
+        let case = failing_case().shrink();
+        assert_eq!(case.run(), Err(CaseError::Mismatch(2)));
+        use StackOperation::*;
+        assert_eq!(case.operations, [Push(0), Push(0), Push(101)]);
+    }
+
+    #[test]
+    fn passing_cases_dont_shrink() {
+        use StackOperation::*;
+        let case = Case::<_, StdRng> {
+            seed: [0; 32],
+            starting_capacity: 0,
+            operations: vec![Push(200), Push(5), Pop, Pop],
+        };
+        assert_eq!(case.run(), Ok(()));
+        assert_eq!(case.shrink().operations, [Push(200), Push(5), Pop, Pop]);
+    }
+
+    #[test]
+    fn quiet_is_undone_by_a_panic() {
+        let caught = panic::catch_unwind(|| {
+            let _quiet = Quiet::new();
+            assert!(QUIET.with(Cell::get));
+            panic!("while shrinking");
+        });
+        assert!(caught.is_err());
+        assert!(!QUIET.with(Cell::get));
+        // nested ones leave it on for the outer one
+        let outer = Quiet::new();
+        drop(Quiet::new());
+        assert!(QUIET.with(Cell::get));
+        drop(outer);
+        assert!(!QUIET.with(Cell::get));
+    }
+
+    #[test]
+    fn bad_text_is_rejected() {
+        let case = Case::<StackOperation, StdRng>::from_text(&format!(
+            "seed {}\ncapacity 0\nPush(1)\nPop\n",
+            "ab".repeat(32)
+        ))
+        .unwrap();
+        assert_eq!(case.run(), Ok(()));
+        assert!(Case::<StackOperation, StdRng>::from_text("seed 00\ncapacity 0\n").is_none());
+        assert!(Case::<StackOperation, StdRng>::from_text(&format!(
+            "seed {}\ncapacity 0\nPush(1000)\n",
+            "00".repeat(32)
+        ))
+        .is_none());
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let case = failing_case().shrink();
+        let text = case.to_text();
+        assert!(text.ends_with("Push(0)\nPush(0)\nPush(101)\n"));
+        let read =
+            Case::<StackOperation, StdRng>::from_text(&format!("# a comment\n\n{}", text)).unwrap();
+        assert_eq!(read.seed, case.seed);
+        assert_eq!(read.starting_capacity, case.starting_capacity);
+        assert_eq!(read.operations, case.operations);
+
+        let dir = std::env::temp_dir().join(format!("tester-cases-{}", std::process::id()));
+        let path = case.save(&dir).unwrap();
+        let loaded = Case::<StackOperation, StdRng>::load_dir(&dir).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].0, path);
+        assert_eq!(loaded[0].1.operations, case.operations);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(Case::<StackOperation, StdRng>::load_dir(&dir)
+            .unwrap()
+            .is_empty());
+    }
+}
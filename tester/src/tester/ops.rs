@@ -0,0 +1,14 @@
+//! Operations and reference models for each container, a model is the std collection that does
+//! the same job.
+
+mod bit_string;
+mod hash_table;
+mod heap;
+mod list;
+mod queue;
+
+pub use bit_string::BitStringOperation;
+pub use hash_table::HashTableOperation;
+pub use heap::HeapOperation;
+pub use list::{ListOp, ListOperation};
+pub use queue::QueueOperation;
@@ -0,0 +1,163 @@
+use crate::tester::traits::*;
+use hash_table::bitstring::BitString;
+use rand::{Rng, SeedableRng};
+
+#[derive(Clone, Copy, Debug)]
+pub enum BitStringOperation {
+    Push(bool),
+    Pop,
+    Get(usize),
+    Set(usize, bool),
+    Toggle(usize),
+    Resize(usize, bool),
+    Truncate(usize),
+    Clear,
+}
+
+impl Operation for BitStringOperation {
+    type Result = Option<bool>;
+    type Target = BitString;
+
+    fn apply(&self, bits: &mut BitString) -> Option<bool> {
+        match *self {
+            Self::Push(bit) => {
+                bits.try_push(bit).expect("failed alloc");
+                None
+            }
+            Self::Pop => bits.pop(),
+            Self::Get(index) => bits.get(index),
+            Self::Set(index, bit) => {
+                bits.set(index, bit);
+                None
+            }
+            Self::Toggle(index) => {
+                bits.toggle(index);
+                None
+            }
+            Self::Resize(len, bit) => {
+                bits.resize(len, bit);
+                None
+            }
+            Self::Truncate(len) => {
+                bits.truncate(len);
+                None
+            }
+            Self::Clear => {
+                bits.clear();
+                None
+            }
+        }
+    }
+}
+
+impl<R: Rng + SeedableRng> OperationGen<R> for BitStringOperation {
+    type Generator = ModelGenerator<Self, Vec<bool>, R>;
+    fn new_target(capacity: usize, _seed: R::Seed) -> BitString {
+        BitString::with_capacity(capacity)
+    }
+}
+
+impl<R: Rng> Model<BitStringOperation, R> for Vec<bool> {
+    fn choose(&self, rng: &mut R) -> BitStringOperation {
+        use BitStringOperation::*;
+        // set and toggle need a bit to work on
+        let range = if self.is_empty() { 0..2 } else { 0..10 };
+        match rng.gen_range(range) {
+            0 => Push(rng.gen()),
+            // grows by up to a couple of words so resizes cross word boundaries
+            1 => Resize(rng.gen_range(0..=self.len() + 130), rng.gen()),
+            2 => Pop,
+            3..=4 => Get(rng.gen_range(0..=self.len())),
+            5 => Set(rng.gen_range(0..self.len()), rng.gen()),
+            6..=7 => Toggle(rng.gen_range(0..self.len())),
+            8 => Truncate(rng.gen_range(0..=self.len())),
+            9 if rng.gen_ratio(1, 10) => Clear,
+            9 => Pop,
+            _ => unreachable!(),
+        }
+    }
+
+    fn apply(&mut self, op: &BitStringOperation) -> Option<Option<bool>> {
+        use BitStringOperation::*;
+        let result = match *op {
+            Push(bit) => {
+                self.push(bit);
+                None
+            }
+            Pop => self.pop(),
+            Get(index) => self.get(index).copied(),
+            Set(index, bit) => {
+                *self.get_mut(index)? = bit;
+                None
+            }
+            Toggle(index) => {
+                let bit = self.get_mut(index)?;
+                *bit = !*bit;
+                None
+            }
+            Resize(len, bit) => {
+                self.resize(len, bit);
+                None
+            }
+            Truncate(len) => {
+                self.truncate(len);
+                None
+            }
+            Clear => {
+                self.clear();
+                None
+            }
+        };
+        Some(result)
+    }
+
+    fn agrees_with(&self, bits: &BitString) -> bool {
+        bits.len() == self.len()
+            && bits.iter().eq(self.iter().copied())
+            && bits.count_ones() == self.iter().filter(|b| **b).count()
+    }
+}
+
+impl Shrink for BitStringOperation {
+    fn simpler(&self) -> Vec<Self> {
+        use BitStringOperation::*;
+        match *self {
+            Push(bit) => bit.simpler().into_iter().map(Push).collect(),
+            Get(index) => index.simpler().into_iter().map(Get).collect(),
+            Set(index, bit) => index
+                .simpler()
+                .into_iter()
+                .map(|index| Set(index, bit))
+                .chain(bit.simpler().into_iter().map(|bit| Set(index, bit)))
+                .collect(),
+            Toggle(index) => index.simpler().into_iter().map(Toggle).collect(),
+            Resize(len, bit) => len
+                .simpler()
+                .into_iter()
+                .map(|len| Resize(len, bit))
+                .chain(bit.simpler().into_iter().map(|bit| Resize(len, bit)))
+                .collect(),
+            Truncate(len) => len.simpler().into_iter().map(Truncate).collect(),
+            Pop | Clear => Vec::new(),
+        }
+    }
+}
+
+impl OperationText for BitStringOperation {
+    fn from_text(text: &str) -> Option<Self> {
+        use BitStringOperation::*;
+        Some(match parse_call(text)? {
+            ("Push", args) if args.len() == 1 => Push(args[0].parse().ok()?),
+            ("Pop", args) if args.is_empty() => Pop,
+            ("Get", args) if args.len() == 1 => Get(args[0].parse().ok()?),
+            ("Set", args) if args.len() == 2 => Set(args[0].parse().ok()?, args[1].parse().ok()?),
+            ("Toggle", args) if args.len() == 1 => Toggle(args[0].parse().ok()?),
+            ("Resize", args) if args.len() == 2 => {
+                Resize(args[0].parse().ok()?, args[1].parse().ok()?)
+            }
+            ("Truncate", args) if args.len() == 1 => Truncate(args[0].parse().ok()?),
+            ("Clear", args) if args.is_empty() => Clear,
+            _ => return None,
+        })
+    }
+}
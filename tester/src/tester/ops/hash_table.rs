@@ -0,0 +1,167 @@
+use crate::tester::traits::*;
+use crate::MIN_KEYS;
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+use hash_table::{hash::SeedableState, traits::hash_table::HashTable};
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng, SeedableRng,
+};
+use std::alloc::Global;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+// a fixed hasher so the model picks the same keys every time a seed is replayed
+type Reference<K, V> = HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
+
+#[derive(Debug)]
+pub enum HashTableOperation<I, K, V, S> {
+    Insert(K, V),
+    Remove(K),
+    Get(K),
+    #[allow(dead_code)]
+    Marker(PhantomData<(I, S)>),
+}
+
+// derived clones would want the table and hasher to be `Clone` too
+impl<I, K: Copy, V: Copy, S> Clone for HashTableOperation<I, K, V, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I, K: Copy, V: Copy, S> Copy for HashTableOperation<I, K, V, S> {}
+
+impl<I, K, V, S> Operation for HashTableOperation<I, K, V, S>
+where
+    I: HashTable<K, V, S, Global>,
+    K: Hash + Eq + Copy + Debug,
+    V: Copy + Eq + Debug,
+    S: BuildHasher,
+{
+    type Result = Option<V>;
+    type Target = I;
+
+    fn apply(&self, table: &mut Self::Target) -> Self::Result {
+        match self {
+            Self::Insert(key, value) => table.insert(*key, *value).expect("failed alloc"),
+            Self::Get(key) => table.get(key).copied(),
+            Self::Remove(key) => table.remove(key),
+            Self::Marker(_) => None,
+        }
+    }
+}
+
+impl<I, K, V, S, R> OperationGen<R> for HashTableOperation<I, K, V, S>
+where
+    I: HashTable<K, V, S, Global>,
+    K: Hash + Eq + Copy + Debug,
+    V: Copy + Eq + Debug,
+    S: SeedableState,
+    R: Rng + SeedableRng,
+    R::Seed: AsRef<[u8]>,
+    Standard: Distribution<K>,
+    Standard: Distribution<V>,
+{
+    type Generator = ModelGenerator<Self, Reference<K, V>, R>;
+    fn new_target(capacity: usize, seed: R::Seed) -> I {
+        // the hasher's seed is the run's seed folded in half, however long that is
+        let mut hasher_seed = [0; 16];
+        for (i, b) in seed.as_ref().iter().enumerate() {
+            hasher_seed[i % 16] ^= b;
+        }
+        I::with_capacity_and_hasher_in(capacity, S::from_seed(hasher_seed), Global)
+            .expect("failed alloc")
+    }
+}
+
+impl<I, K, V, S, R> Model<HashTableOperation<I, K, V, S>, R> for Reference<K, V>
+where
+    I: HashTable<K, V, S, Global>,
+    K: Hash + Eq + Copy + Debug,
+    V: Copy + Eq + Debug,
+    S: BuildHasher,
+    R: Rng,
+    Standard: Distribution<K>,
+    Standard: Distribution<V>,
+{
+    fn choose(&self, rng: &mut R) -> HashTableOperation<I, K, V, S> {
+        //  this prevents get and remove operations if there arent enough keys
+        let range = if self.len() > MIN_KEYS {
+            0..(std::mem::variant_count::<HashTableOperation<I, K, V, S>>() - 1)
+        } else {
+            0..1
+        };
+
+        match rng.gen_range(range) {
+            0 => HashTableOperation::Insert(rng.gen(), rng.gen()),
+            1 => {
+                let get_existing = rng.gen_bool(0.75);
+                let key: K = if get_existing {
+                    let ind = rng.gen_range(0..self.len());
+                    *self.keys().nth(ind).unwrap()
+                } else {
+                    rng.gen()
+                };
+                HashTableOperation::Get(key)
+            }
+            2 => {
+                let ind = rng.gen_range(0..self.len());
+                HashTableOperation::Remove(*self.keys().nth(ind).unwrap())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn apply(&mut self, op: &HashTableOperation<I, K, V, S>) -> Option<Option<V>> {
+        match op {
+            HashTableOperation::Insert(key, value) => Some(self.insert(*key, *value)),
+            HashTableOperation::Get(key) => Some(self.get(key).copied()),
+            HashTableOperation::Remove(key) => Some(self.remove(key)),
+            HashTableOperation::Marker(_) => None,
+        }
+    }
+
+    fn agrees_with(&self, table: &I) -> bool {
+        table.len() == self.len()
+    }
+}
+
+impl<I, K: Simplify + Copy, V: Simplify + Copy, S> Shrink for HashTableOperation<I, K, V, S> {
+    fn simpler(&self) -> Vec<Self> {
+        match *self {
+            Self::Insert(key, value) => key
+                .simpler()
+                .into_iter()
+                .map(|key| Self::Insert(key, value))
+                .chain(
+                    value
+                        .simpler()
+                        .into_iter()
+                        .map(|value| Self::Insert(key, value)),
+                )
+                .collect(),
+            Self::Get(key) => key.simpler().into_iter().map(Self::Get).collect(),
+            Self::Remove(key) => key.simpler().into_iter().map(Self::Remove).collect(),
+            Self::Marker(_) => Vec::new(),
+        }
+    }
+}
+
+impl<I: Debug, K: FromStr + Debug, V: FromStr + Debug, S: Debug> OperationText
+    for HashTableOperation<I, K, V, S>
+{
+    fn from_text(text: &str) -> Option<Self> {
+        match parse_call(text)? {
+            ("Insert", args) if args.len() == 2 => {
+                Some(Self::Insert(args[0].parse().ok()?, args[1].parse().ok()?))
+            }
+            ("Get", args) if args.len() == 1 => Some(Self::Get(args[0].parse().ok()?)),
+            ("Remove", args) if args.len() == 1 => Some(Self::Remove(args[0].parse().ok()?)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::tester::traits::*;
+use core::cmp::Reverse;
+use core::fmt::Debug;
+use hash_table::heap::{binary_heap::BinaryHeap, Heap};
+use hash_table::traits::iter::Iterable;
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng, SeedableRng,
+};
+use std::collections;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug)]
+pub enum HeapOperation<T> {
+    Insert(T),
+    Extract,
+    Peek,
+    Replace(T),
+}
+
+impl<T: Ord + Copy + Debug> Operation for HeapOperation<T> {
+    type Result = Option<T>;
+    type Target = BinaryHeap<T>;
+
+    fn apply(&self, heap: &mut BinaryHeap<T>) -> Option<T> {
+        match *self {
+            Self::Insert(item) => {
+                heap.insert(item);
+                None
+            }
+            Self::Extract => heap.extract(),
+            Self::Peek => heap.peek().copied(),
+            Self::Replace(item) => heap.replace(item),
+        }
+    }
+}
+
+impl<T, R> OperationGen<R> for HeapOperation<T>
+where
+    T: Ord + Copy + Debug,
+    R: Rng + SeedableRng,
+    Standard: Distribution<T>,
+{
+    type Generator = ModelGenerator<Self, collections::BinaryHeap<Reverse<T>>, R>;
+    fn new_target(_capacity: usize, _seed: R::Seed) -> BinaryHeap<T> {
+        BinaryHeap::new()
+    }
+}
+
+/// The crate's heap is a min heap and std's is a max heap, so the model's items are reversed.
+impl<T, R> Model<HeapOperation<T>, R> for collections::BinaryHeap<Reverse<T>>
+where
+    T: Ord + Copy + Debug,
+    R: Rng,
+    Standard: Distribution<T>,
+{
+    fn choose(&self, rng: &mut R) -> HeapOperation<T> {
+        use HeapOperation::*;
+        match rng.gen_range(0..6) {
+            0..=2 => Insert(rng.gen()),
+            3 => Extract,
+            4 => Peek,
+            5 => Replace(rng.gen()),
+            _ => unreachable!(),
+        }
+    }
+
+    fn apply(&mut self, op: &HeapOperation<T>) -> Option<Option<T>> {
+        use HeapOperation::*;
+        Some(match *op {
+            Insert(item) => {
+                self.push(Reverse(item));
+                None
+            }
+            Extract => self.pop().map(|Reverse(item)| item),
+            Peek => self.peek().map(|Reverse(item)| *item),
+            Replace(item) => {
+                let top = self.pop().map(|Reverse(item)| item);
+                self.push(Reverse(item));
+                top
+            }
+        })
+    }
+
+    fn agrees_with(&self, heap: &BinaryHeap<T>) -> bool {
+        let mut items: Vec<T> = heap.iter().copied().collect();
+        items.sort_unstable();
+        let mut expected: Vec<T> = self.iter().map(|Reverse(item)| *item).collect();
+        expected.sort_unstable();
+        items == expected
+    }
+}
+
+impl<T: Simplify + Copy> Shrink for HeapOperation<T> {
+    fn simpler(&self) -> Vec<Self> {
+        use HeapOperation::*;
+        match *self {
+            Insert(item) => item.simpler().into_iter().map(Insert).collect(),
+            Replace(item) => item.simpler().into_iter().map(Replace).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl<T: FromStr + Debug> OperationText for HeapOperation<T> {
+    fn from_text(text: &str) -> Option<Self> {
+        use HeapOperation::*;
+        Some(match parse_call(text)? {
+            ("Insert", args) if args.len() == 1 => Insert(args[0].parse().ok()?),
+            ("Extract", args) if args.is_empty() => Extract,
+            ("Peek", args) if args.is_empty() => Peek,
+            ("Replace", args) if args.len() == 1 => Replace(args[0].parse().ok()?),
+            _ => return None,
+        })
+    }
+}
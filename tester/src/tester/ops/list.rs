@@ -0,0 +1,158 @@
+use crate::tester::traits::*;
+use core::fmt::{self, Debug, Formatter};
+use hash_table::traits::adt::{Creatable, List};
+use hash_table::traits::iter::Iterable;
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng, SeedableRng,
+};
+use std::collections::LinkedList;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// The operations a [`ListOperation`] can do.
+#[derive(Clone, Copy, Debug)]
+pub enum ListOp<T> {
+    Append(T),
+    Prepend(T),
+    Head,
+    Tail,
+    Get(usize),
+}
+
+/// Works on anything that's a [`List`], so the same operations cover the singly and doubly
+/// linked lists. `L` only picks the target.
+pub struct ListOperation<L, T> {
+    pub op: ListOp<T>,
+    list: PhantomData<fn() -> L>,
+}
+
+impl<L, T> From<ListOp<T>> for ListOperation<L, T> {
+    fn from(op: ListOp<T>) -> Self {
+        Self {
+            op,
+            list: PhantomData,
+        }
+    }
+}
+
+// derived impls would want the list to be `Clone` and `Debug` too
+impl<L, T: Copy> Clone for ListOperation<L, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<L, T: Copy> Copy for ListOperation<L, T> {}
+
+// just the operation, that's what goes into saved cases
+impl<L, T: Debug> Debug for ListOperation<L, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.op.fmt(f)
+    }
+}
+
+impl<L: List<T>, T: Copy + Eq + Debug> Operation for ListOperation<L, T> {
+    type Result = Option<T>;
+    type Target = L;
+
+    fn apply(&self, list: &mut L) -> Option<T> {
+        match self.op {
+            ListOp::Append(item) => {
+                list.try_append(item).expect("failed alloc");
+                None
+            }
+            ListOp::Prepend(item) => {
+                list.try_prepend(item).expect("failed alloc");
+                None
+            }
+            ListOp::Head => list.head().copied(),
+            ListOp::Tail => list.tail(),
+            ListOp::Get(index) => list.get(index).copied(),
+        }
+    }
+}
+
+impl<L, T, R> OperationGen<R> for ListOperation<L, T>
+where
+    L: List<T> + Creatable + Iterable<Item = T>,
+    T: Copy + Eq + Debug,
+    R: Rng + SeedableRng,
+    Standard: Distribution<T>,
+{
+    type Generator = ModelGenerator<Self, LinkedList<T>, R>;
+    fn new_target(_capacity: usize, _seed: R::Seed) -> L {
+        L::new()
+    }
+}
+
+impl<L, T, R> Model<ListOperation<L, T>, R> for LinkedList<T>
+where
+    L: List<T> + Iterable<Item = T>,
+    T: Copy + Eq + Debug,
+    R: Rng,
+    Standard: Distribution<T>,
+{
+    fn choose(&self, rng: &mut R) -> ListOperation<L, T> {
+        use ListOp::*;
+        match rng.gen_range(0..6) {
+            0..=1 => Append(rng.gen()),
+            2 => Prepend(rng.gen()),
+            3 => Head,
+            4 => Tail,
+            5 => Get(rng.gen_range(0..=self.len())),
+            _ => unreachable!(),
+        }
+        .into()
+    }
+
+    fn apply(&mut self, op: &ListOperation<L, T>) -> Option<Option<T>> {
+        use ListOp::*;
+        let result = match op.op {
+            Append(item) => {
+                self.push_back(item);
+                None
+            }
+            Prepend(item) => {
+                self.push_front(item);
+                None
+            }
+            Head => self.front().copied(),
+            Tail => self.pop_front(),
+            Get(index) => self.iter().nth(index).copied(),
+        };
+        Some(result)
+    }
+
+    fn agrees_with(&self, list: &L) -> bool {
+        list.len() == self.len() && list.iter().eq(self.iter())
+    }
+}
+
+impl<L, T: Simplify + Copy> Shrink for ListOperation<L, T> {
+    fn simpler(&self) -> Vec<Self> {
+        use ListOp::*;
+        let simpler: Vec<ListOp<T>> = match self.op {
+            Append(item) => item.simpler().into_iter().map(Append).collect(),
+            Prepend(item) => item.simpler().into_iter().map(Prepend).collect(),
+            Get(index) => index.simpler().into_iter().map(Get).collect(),
+            _ => Vec::new(),
+        };
+        simpler.into_iter().map(Self::from).collect()
+    }
+}
+
+impl<L, T: FromStr + Debug> OperationText for ListOperation<L, T> {
+    fn from_text(text: &str) -> Option<Self> {
+        use ListOp::*;
+        let op = match parse_call(text)? {
+            ("Append", args) if args.len() == 1 => Append(args[0].parse().ok()?),
+            ("Prepend", args) if args.len() == 1 => Prepend(args[0].parse().ok()?),
+            ("Head", args) if args.is_empty() => Head,
+            ("Tail", args) if args.is_empty() => Tail,
+            ("Get", args) if args.len() == 1 => Get(args[0].parse().ok()?),
+            _ => return None,
+        };
+        Some(op.into())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::tester::traits::*;
+use core::fmt::Debug;
+use hash_table::queue::ArrayQueue;
+use hash_table::traits::iter::Iterable;
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng, SeedableRng,
+};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug)]
+pub enum QueueOperation<T> {
+    PushBack(T),
+    PushFront(T),
+    PopBack,
+    PopFront,
+    Front,
+    Back,
+    Get(usize),
+    Insert(usize, T),
+    Remove(usize),
+    Truncate(usize),
+    ShrinkToFit,
+    Clear,
+}
+
+impl<T: Copy + Eq + Debug> Operation for QueueOperation<T> {
+    type Result = Option<T>;
+    type Target = ArrayQueue<T>;
+
+    fn apply(&self, queue: &mut ArrayQueue<T>) -> Option<T> {
+        match *self {
+            Self::PushBack(item) => {
+                queue.push_back(item).expect("failed alloc");
+                None
+            }
+            Self::PushFront(item) => {
+                queue.push_front(item).expect("failed alloc");
+                None
+            }
+            Self::PopBack => queue.pop_back(),
+            Self::PopFront => queue.pop_front(),
+            Self::Front => queue.front().copied(),
+            Self::Back => queue.back().copied(),
+            Self::Get(index) => queue.get(index).copied(),
+            Self::Insert(index, item) => {
+                queue.insert(index, item).expect("failed alloc");
+                None
+            }
+            Self::Remove(index) => queue.remove(index),
+            Self::Truncate(len) => {
+                queue.truncate(len);
+                None
+            }
+            Self::ShrinkToFit => {
+                queue.shrink_to_fit().expect("failed alloc");
+                None
+            }
+            Self::Clear => {
+                queue.clear();
+                None
+            }
+        }
+    }
+}
+
+impl<T, R> OperationGen<R> for QueueOperation<T>
+where
+    T: Copy + Eq + Debug,
+    R: Rng + SeedableRng,
+    Standard: Distribution<T>,
+{
+    type Generator = ModelGenerator<Self, VecDeque<T>, R>;
+    fn new_target(capacity: usize, _seed: R::Seed) -> ArrayQueue<T> {
+        ArrayQueue::with_capacity(capacity)
+    }
+}
+
+impl<T, R> Model<QueueOperation<T>, R> for VecDeque<T>
+where
+    T: Copy + Eq + Debug,
+    R: Rng,
+    Standard: Distribution<T>,
+{
+    fn choose(&self, rng: &mut R) -> QueueOperation<T> {
+        use QueueOperation::*;
+        // pushes come up twice as often so the queue grows and wraps around
+        match rng.gen_range(0..14) {
+            0..=1 => PushBack(rng.gen()),
+            2..=3 => PushFront(rng.gen()),
+            4 => PopBack,
+            5 => PopFront,
+            6 => Front,
+            7 => Back,
+            // one past the end is fair game for the lookups
+            8 => Get(rng.gen_range(0..=self.len())),
+            9..=10 => Insert(rng.gen_range(0..=self.len()), rng.gen()),
+            11 => Remove(rng.gen_range(0..=self.len())),
+            12 => Truncate(rng.gen_range(self.len() / 2..=self.len())),
+            13 if rng.gen_ratio(1, 10) => Clear,
+            13 => ShrinkToFit,
+            _ => unreachable!(),
+        }
+    }
+
+    fn apply(&mut self, op: &QueueOperation<T>) -> Option<Option<T>> {
+        use QueueOperation::*;
+        let result = match *op {
+            PushBack(item) => {
+                self.push_back(item);
+                None
+            }
+            PushFront(item) => {
+                self.push_front(item);
+                None
+            }
+            PopBack => self.pop_back(),
+            PopFront => self.pop_front(),
+            Front => self.front().copied(),
+            Back => self.back().copied(),
+            Get(index) => self.get(index).copied(),
+            Insert(index, _) if index > self.len() => return None,
+            Insert(index, item) => {
+                self.insert(index, item);
+                None
+            }
+            Remove(index) => self.remove(index),
+            Truncate(len) => {
+                self.truncate(len);
+                None
+            }
+            ShrinkToFit => None,
+            Clear => {
+                self.clear();
+                None
+            }
+        };
+        Some(result)
+    }
+
+    fn agrees_with(&self, queue: &ArrayQueue<T>) -> bool {
+        queue.len() == self.len() && queue.iter().eq(self.iter())
+    }
+}
+
+impl<T: Simplify + Copy> Shrink for QueueOperation<T> {
+    fn simpler(&self) -> Vec<Self> {
+        use QueueOperation::*;
+        match *self {
+            PushBack(item) => item.simpler().into_iter().map(PushBack).collect(),
+            PushFront(item) => item.simpler().into_iter().map(PushFront).collect(),
+            Get(index) => index.simpler().into_iter().map(Get).collect(),
+            Insert(index, item) => index
+                .simpler()
+                .into_iter()
+                .map(|index| Insert(index, item))
+                .chain(item.simpler().into_iter().map(|item| Insert(index, item)))
+                .collect(),
+            Remove(index) => index.simpler().into_iter().map(Remove).collect(),
+            Truncate(len) => len.simpler().into_iter().map(Truncate).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl<T: FromStr + Debug> OperationText for QueueOperation<T> {
+    fn from_text(text: &str) -> Option<Self> {
+        use QueueOperation::*;
+        Some(match parse_call(text)? {
+            ("PushBack", args) if args.len() == 1 => PushBack(args[0].parse().ok()?),
+            ("PushFront", args) if args.len() == 1 => PushFront(args[0].parse().ok()?),
+            ("PopBack", args) if args.is_empty() => PopBack,
+            ("PopFront", args) if args.is_empty() => PopFront,
+            ("Front", args) if args.is_empty() => Front,
+            ("Back", args) if args.is_empty() => Back,
+            ("Get", args) if args.len() == 1 => Get(args[0].parse().ok()?),
+            ("Insert", args) if args.len() == 2 => {
+                Insert(args[0].parse().ok()?, args[1].parse().ok()?)
+            }
+            ("Remove", args) if args.len() == 1 => Remove(args[0].parse().ok()?),
+            ("Truncate", args) if args.len() == 1 => Truncate(args[0].parse().ok()?),
+            ("ShrinkToFit", args) if args.is_empty() => ShrinkToFit,
+            ("Clear", args) if args.is_empty() => Clear,
+            _ => return None,
+        })
+    }
+}
@@ -1,3 +1,5 @@
+use core::fmt::Debug;
+use core::marker::PhantomData;
 use rand::{
     distributions::{Distribution, Standard},
     Rng, SeedableRng,
@@ -15,6 +17,8 @@ pub trait OperationGen<R: Rng + SeedableRng>: Operation + Sized {
     fn gen_from_seed(seed: R::Seed) -> Self::Generator {
         Self::Generator::from_seed(seed)
     }
+    /// a fresh target for a run, the seed is there for anything that needs to be random like a hasher
+    fn new_target(capacity: usize, seed: R::Seed) -> Self::Target;
 }
 
 // deterministically generates a sequence of valid operations
@@ -27,6 +31,13 @@ where
     type ReferenceImpl;
     fn from_seed(seed: R::Seed) -> Self;
     fn data(self) -> Self::ReferenceImpl;
+    /// applies an operation that didn't come from this generator to the reference, `None` if it
+    /// isn't valid in the reference's current state. used to check shrunk and saved cases
+    fn replay(&mut self, op: &Self::Operation) -> Option<<Self::Operation as Operation>::Result>;
+    /// checked after every operation, for state the results alone wouldn't catch
+    fn agrees_with(&self, _target: &<Self::Operation as Operation>::Target) -> bool {
+        true
+    }
 }
 
 // extends operation generator so that it can be created from a random seed if the seed can be randomly generated.
@@ -47,133 +58,130 @@ where
 {
 }
 
-mod impls {
-    use super::*;
-    use crate::MIN_KEYS;
-    use core::fmt::Debug;
-    use core::hash::Hash;
-    use hash_table::traits::hash_table::HashTable;
-    use rand::rngs::StdRng;
-    use std::alloc::Global;
-    use std::collections::hash_map::RandomState;
-    use std::collections::HashMap;
-    use std::marker::PhantomData;
-
-    #[derive(Clone, Copy, Debug)]
-    enum HashTableOperation<I, K, V>
-    where
-        I:,
-    {
-        Insert(K, V),
-        Remove(K),
-        Get(K),
-        #[allow(dead_code)]
-        Marker(PhantomData<I>),
+/// a reference implementation that knows which operations are valid on it and what they should
+/// return, usually the matching std collection
+pub trait Model<O: Operation, R: Rng>: Default {
+    /// picks a valid operation for the model's current state
+    fn choose(&self, rng: &mut R) -> O;
+    /// `None` if `op` isn't valid right now, like an index that's out of bounds
+    fn apply(&mut self, op: &O) -> Option<O::Result>;
+    fn agrees_with(&self, _target: &O::Target) -> bool {
+        true
     }
+}
 
-    impl<T, K, V> Operation for HashTableOperation<T, K, V>
-    where
-        T: HashTable<K, V, RandomState, Global>,
-        K: Hash + Eq + Copy + Eq + Debug,
-        V: Copy + Eq + Debug,
-        //T: HashTable<K, V, RandomState, Global>,
-        Standard: Distribution<K>,
-        Standard: Distribution<V>,
-    {
-        type Result = Option<V>;
-        type Target = T;
-
-        fn apply(&self, table: &mut Self::Target) -> Self::Result {
-            match self {
-                Self::Insert(key, value) => table.insert(*key, *value).expect("failed alloc"),
-                Self::Get(key) => table.get(key).copied(),
-                Self::Remove(key) => table.remove(key),
-                _ => None,
-            }
+/// generates operations by stepping a [`Model`]
+pub struct ModelGenerator<O, M, R> {
+    rng: R,
+    model: M,
+    marker: PhantomData<O>,
+}
+
+impl<O, M, R> OperationGenerator<R> for ModelGenerator<O, M, R>
+where
+    O: Operation,
+    M: Model<O, R>,
+    R: Rng + SeedableRng,
+{
+    type Operation = O;
+    type ReferenceImpl = M;
+    fn from_seed(seed: R::Seed) -> Self {
+        Self {
+            rng: R::from_seed(seed),
+            model: M::default(),
+            marker: PhantomData,
         }
     }
+    fn data(self) -> M {
+        self.model
+    }
+    fn replay(&mut self, op: &O) -> Option<O::Result> {
+        self.model.apply(op)
+    }
+    fn agrees_with(&self, target: &O::Target) -> bool {
+        self.model.agrees_with(target)
+    }
+}
 
-    struct HashTableOperationGenerator<K, V, I, R = StdRng>
-    where
-        R: Rng + SeedableRng,
-        I: HashTable<K, V, RandomState, Global>,
-    {
-        rng: R,
-        data: HashMap<K, V>,
-        marker: PhantomData<I>,
+impl<O, M, R> Iterator for ModelGenerator<O, M, R>
+where
+    O: Operation,
+    M: Model<O, R>,
+    R: Rng,
+{
+    type Item = (O, O::Result);
+    fn next(&mut self) -> Option<Self::Item> {
+        let op = self.model.choose(&mut self.rng);
+        let result = self.model.apply(&op).expect("chose an invalid operation");
+        Some((op, result))
     }
+}
 
-    impl<K, V, I, R> OperationGenerator<R> for HashTableOperationGenerator<K, V, I, R>
-    where
-        R: Rng + SeedableRng,
-        I: HashTable<K, V, RandomState, Global>,
-        K: Copy + Hash + Eq + Debug,
-        V: Copy + Eq + Debug,
-        Standard: Distribution<K>,
-        Standard: Distribution<V>,
-    {
-        type Operation = HashTableOperation<I, K, V>;
-        type ReferenceImpl = HashMap<K, V>;
-        fn from_seed(seed: R::Seed) -> Self {
-            Self {
-                rng: R::from_seed(seed),
-                data: HashMap::new(),
-                marker: PhantomData,
+/// Smaller versions of an operation for shrinking, the simplest first.
+pub trait Shrink: Sized {
+    fn simpler(&self) -> Vec<Self> {
+        Vec::new()
+    }
+}
+
+/// Shrinks the values inside operations.
+pub trait Simplify: Sized {
+    fn simpler(&self) -> Vec<Self>;
+}
+
+macro_rules! simplify_int {
+    ($($t:ty),*) => {$(
+        impl Simplify for $t {
+            fn simpler(&self) -> Vec<Self> {
+                let mut simpler = vec![0, self / 2, self - self.signum()];
+                simpler.dedup();
+                simpler.retain(|x| x != self);
+                simpler
             }
         }
-        fn data(self) -> Self::ReferenceImpl {
-            self.data
+    )*};
+}
+
+macro_rules! simplify_uint {
+    ($($t:ty),*) => {$(
+        impl Simplify for $t {
+            fn simpler(&self) -> Vec<Self> {
+                let mut simpler = vec![0, self / 2, self.saturating_sub(1)];
+                simpler.dedup();
+                simpler.retain(|x| x != self);
+                simpler
+            }
+        }
+    )*};
+}
+
+simplify_int!(i8, i16, i32, i64);
+simplify_uint!(u8, u16, u32, u64, usize);
+
+impl Simplify for bool {
+    fn simpler(&self) -> Vec<Self> {
+        if *self {
+            vec![false]
+        } else {
+            Vec::new()
         }
     }
+}
 
-    impl<K, V, I, R> Iterator for HashTableOperationGenerator<K, V, I, R>
-    where
-        I: HashTable<K, V, RandomState, Global>,
-        K: Copy + Hash + Eq,
-        V: Copy,
-        R: SeedableRng + Rng,
-        Standard: Distribution<K>,
-        Standard: Distribution<V>,
-    {
-        type Item = (HashTableOperation<I, K, V>, Option<V>);
-
-        fn next(&mut self) -> Option<Self::Item> {
-            //  this prevents insert operations if there arent enough keys
-            let range = if self.data.len() > MIN_KEYS {
-                0..(std::mem::variant_count::<HashTableOperation<I, K, V>>() - 1)
-            } else {
-                0..1
-            };
-
-            match self.rng.gen_range(range) {
-                0 => {
-                    let key: K = self.rng.gen();
-                    let val: V = self.rng.gen();
-                    let res: Option<V> = self.data.insert(key, val);
-                    Some((HashTableOperation::Insert(key, val), res))
-                }
-                1 => {
-                    let get_existing = self.rng.gen_bool(0.75);
-                    let key: K = if get_existing {
-                        let ind = self.rng.gen_range(0..self.data.len());
-                        *self.data.keys().nth(ind).unwrap()
-                    } else {
-                        self.rng.gen()
-                    };
-
-                    let res = self.data.get(&key);
-
-                    Some((HashTableOperation::Get(key), res.copied()))
-                }
-                2 => {
-                    let key: K = {
-                        let ind = self.rng.gen_range(0..self.data.len());
-                        *self.data.keys().nth(ind).unwrap()
-                    };
-                    Some((HashTableOperation::Remove(key), self.data.remove(&key)))
-                }
-                _ => unreachable!(),
-            }
+/// The text form of an operation for reproduction files. Writing uses `Debug`, so reading has to
+/// take the same `Name(arg, arg)` shape back.
+pub trait OperationText: Debug + Sized {
+    fn from_text(text: &str) -> Option<Self>;
+}
+
+/// Splits `Name(a, b)` into `("Name", ["a", "b"])`, a bare `Name` has no arguments.
+pub fn parse_call(text: &str) -> Option<(&str, Vec<&str>)> {
+    let text = text.trim();
+    match text.split_once('(') {
+        None => Some((text, Vec::new())),
+        Some((name, rest)) => {
+            let args = rest.strip_suffix(')')?;
+            Some((name.trim(), args.split(',').map(str::trim).collect()))
         }
     }
 }